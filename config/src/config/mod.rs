@@ -235,6 +235,7 @@ impl NodeConfig {
         config.execution.load(&input_dir)?;
 
         let mut config = config.validate_network_configs()?;
+        config.storage.backup_service.validate()?;
         config.set_data_dir(config.data_dir().to_path_buf());
         Ok(config)
     }
@@ -1,9 +1,13 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::utils;
+use crate::{
+    config::{invariant, Error},
+    utils,
+};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
     net::{IpAddr, Ipv4Addr, SocketAddr},
     path::PathBuf,
 };
@@ -32,16 +36,70 @@ impl Default for RocksdbConfig {
     }
 }
 
+/// Access control of the backup service, for it to be exposed to backup workers on other hosts.
+/// Nothing is enforced by default, which is only safe with the service listening on localhost.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackupServiceConfig {
+    /// Client name to token. If not empty, a request must carry one of the tokens in an
+    /// `Authorization: Bearer <token>` header, and is logged and limited as the named client.
+    /// Otherwise, clients are told apart by their IP addresses.
+    pub auth_tokens: BTreeMap<String, String>,
+    pub tls_cert_path: Option<String>,
+    pub tls_key_path: Option<String>,
+    /// With TLS enabled, requires clients to present a certificate signed by this CA.
+    pub tls_client_ca_path: Option<String>,
+    /// Requests a client can have in flight, the ones beyond are rejected with 429.
+    pub max_concurrent_requests_per_client: Option<usize>,
+    /// Response bytes a client receives per second.
+    pub max_bytes_per_sec_per_client: Option<usize>,
+    /// A loopback address to also serve plain HTTP on, for the tools on the node itself to query
+    /// the service without a certificate. Tokens and limits apply the same.
+    pub local_address: Option<SocketAddr>,
+}
+
+impl BackupServiceConfig {
+    /// Checks the limits are positive, the TLS files come together and the local address is local.
+    pub fn validate(&self) -> Result<(), Error> {
+        invariant(
+            self.max_concurrent_requests_per_client != Some(0),
+            "backup_service.max_concurrent_requests_per_client must be greater than 0".into(),
+        )?;
+        invariant(
+            self.max_bytes_per_sec_per_client != Some(0),
+            "backup_service.max_bytes_per_sec_per_client must be greater than 0".into(),
+        )?;
+        invariant(
+            self.tls_cert_path.is_some() == self.tls_key_path.is_some(),
+            "backup_service.tls_cert_path and tls_key_path must be set together".into(),
+        )?;
+        invariant(
+            self.tls_client_ca_path.is_none() || self.tls_cert_path.is_some(),
+            "backup_service.tls_client_ca_path requires tls_cert_path".into(),
+        )?;
+        invariant(
+            self.local_address
+                .map_or(true, |address| address.ip().is_loopback()),
+            "backup_service.local_address must be a loopback address".into(),
+        )
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(default, deny_unknown_fields)]
 pub struct StorageConfig {
     pub address: SocketAddr,
     pub backup_service_address: SocketAddr,
+    pub backup_service: BackupServiceConfig,
     pub dir: PathBuf,
     pub grpc_max_receive_len: Option<i32>,
     /// None disables pruning. The windows is in number of versions, consider system tps
     /// (transaction per second) when calculating proper window.
     pub prune_window: Option<u64>,
+    /// None disables pruning of the ledger history: transactions, transaction infos and events.
+    /// The window is in number of versions, and must outlive the requests of RPC clients and of
+    /// peers syncing from this node.
+    pub ledger_prune_window: Option<u64>,
     #[serde(skip)]
     data_dir: PathBuf,
     /// Read, Write, Connect timeout for network operations in milliseconds
@@ -55,6 +113,7 @@ impl Default for StorageConfig {
         StorageConfig {
             address: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 6666),
             backup_service_address: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 6186),
+            backup_service: BackupServiceConfig::default(),
             dir: PathBuf::from("db"),
             grpc_max_receive_len: Some(100_000_000),
             // The prune window must at least out live a RPC request because its sub requests are
@@ -66,6 +125,7 @@ impl Default for StorageConfig {
             //////// 0L ////////
             // ~50GB state tree history (about 1 day at 100 tps)
             prune_window: Some(10_000_000), 
+            ledger_prune_window: None,
             data_dir: PathBuf::from("/opt/diem/data"),
             // Default read/write/connection timeout, in milliseconds
             timeout_ms: 30_000,
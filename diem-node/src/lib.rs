@@ -1,7 +1,7 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use backup_service::start_backup_service;
+use backup_service::start_backup_service_with_config;
 use consensus::{consensus_provider::start_consensus, gen_consensus_reconfig_subscription};
 use debug_interface::node_debug_service::NodeDebugService;
 use diem_config::{
@@ -264,17 +264,19 @@ pub fn setup_environment(node_config: &NodeConfig, logger: Option<Arc<Logger>>)
 
     let mut instant = Instant::now();
     let (diem_db, db_rw) = DbReaderWriter::wrap(
-        DiemDB::open(
+        DiemDB::open_with_ledger_pruner(
             &node_config.storage.dir(),
             false, /* readonly */
             node_config.storage.prune_window,
+            node_config.storage.ledger_prune_window,
             node_config.storage.rocksdb_config,
         )
         .expect("DB should open."),
     );
     let _simple_storage_service = start_storage_service_with_db(&node_config, Arc::clone(&diem_db));
-    let backup_service = start_backup_service(
+    let backup_service = start_backup_service_with_config(
         node_config.storage.backup_service_address,
+        &node_config.storage.backup_service,
         Arc::clone(&diem_db),
     );
 
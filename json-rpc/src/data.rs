@@ -18,7 +18,29 @@ use std::{
     cmp::min,
     convert::{TryFrom, TryInto},
 };
-use storage_interface::{DbReader, Order};
+use storage_interface::{DbReader, Order, PrunedError};
+
+/// Errors of the DB, ledger history removed by the ledger pruner is reported as such
+fn db_error(err: anyhow::Error) -> JsonRpcError {
+    match err.downcast_ref::<PrunedError>() {
+        Some(pruned) => JsonRpcError::ledger_pruned(pruned.to_string()),
+        None => err.into(),
+    }
+}
+
+/// A transaction below the sequence number of the account is missing if it was pruned
+fn account_txn_missing(db: &dyn DbReader, account: AccountAddress, seq: u64) -> JsonRpcError {
+    match db.get_first_txn_version() {
+        Ok(first_version) if first_version > 0 => db_error(
+            PrunedError {
+                what: format!("Transaction {} of account {}", seq, account),
+                first_version,
+            }
+            .into(),
+        ),
+        _ => format_err!("Can not find transaction for seq {}!", seq).into(),
+    }
+}
 
 pub fn get_account_state(
     db: &dyn DbReader,
@@ -104,7 +126,9 @@ pub fn get_transactions(
     if start_version > ledger_version || limit == 0 {
         return Ok(TransactionListView::empty());
     }
-    let txs = db.get_transactions(start_version, limit, ledger_version, include_events)?;
+    let txs = db
+        .get_transactions(start_version, limit, ledger_version, include_events)
+        .map_err(db_error)?;
     Ok(TransactionListView::try_from(txs)?)
 }
 
@@ -119,7 +143,9 @@ pub fn get_transactions_with_proofs(
     if start_version > ledger_version || limit == 0 {
         return Ok(None);
     }
-    let txs = db.get_transactions(start_version, limit, ledger_version, include_events)?;
+    let txs = db
+        .get_transactions(start_version, limit, ledger_version, include_events)
+        .map_err(db_error)?;
     Ok(Some(TransactionsWithProofsView::try_from(&txs)?))
 }
 
@@ -131,7 +157,9 @@ pub fn get_account_transaction(
     sequence_number: u64,
     include_events: bool,
 ) -> Result<Option<TransactionView>, JsonRpcError> {
-    let tx = db.get_txn_by_account(account, sequence_number, ledger_version, include_events)?;
+    let tx = db
+        .get_txn_by_account(account, sequence_number, ledger_version, include_events)
+        .map_err(db_error)?;
 
     if let Some(tx) = tx {
         Ok(Some(TransactionView::try_from_tx_and_events(
@@ -141,6 +169,15 @@ pub fn get_account_transaction(
             tx.events.unwrap_or_default(),
         )?))
     } else {
+        // committed, but missing from a pruned ledger
+        if db.get_first_txn_version()? > 0 {
+            let account_seq = get_account_state(db, account, ledger_version)?
+                .and_then(|state| state.get_account_resource().ok().flatten())
+                .map(|resource| resource.sequence_number());
+            if matches!(account_seq, Some(seq) if sequence_number < seq) {
+                return Err(account_txn_missing(db, account, sequence_number));
+            }
+        }
         Ok(None)
     }
 }
@@ -176,8 +213,9 @@ pub fn get_account_transactions(
 
     for seq in start..end {
         let tx = db
-            .get_txn_by_account(account, seq, ledger_version, include_events)?
-            .ok_or_else(|| format_err!("Can not find transaction for seq {}!", seq))?;
+            .get_txn_by_account(account, seq, ledger_version, include_events)
+            .map_err(db_error)?
+            .ok_or_else(|| account_txn_missing(db, account, seq))?;
 
         let tx_view = TransactionView::try_from_tx_and_events(
             tx.version,
@@ -199,7 +237,9 @@ pub fn get_events(
     start: u64,
     limit: u64,
 ) -> Result<Vec<EventView>, JsonRpcError> {
-    let events_raw = db.get_events(&event_key, start, Order::Ascending, limit)?;
+    let events_raw = db
+        .get_events(&event_key, start, Order::Ascending, limit)
+        .map_err(db_error)?;
 
     let events = events_raw
         .into_iter()
@@ -224,7 +264,8 @@ pub fn get_events_with_proofs(
         Order::Ascending,
         limit,
         Some(ledger_version),
-    )?;
+    )
+    .map_err(db_error)?;
 
     let views = events_with_proofs
         .iter()
@@ -51,6 +51,9 @@ pub enum ServerCode {
     MempoolInvalidUpdate = -32010,
     MempoolVmError = -32011,
     MempoolUnknownError = -32012,
+
+    // Storage errors
+    LedgerPruned = -32013,
 }
 
 /// JSON RPC server error codes for invalid request
@@ -181,6 +184,14 @@ impl JsonRpcError {
         }
     }
 
+    pub fn ledger_pruned(message: String) -> Self {
+        Self {
+            code: ServerCode::LedgerPruned as i16,
+            message,
+            data: None,
+        }
+    }
+
     pub fn mempool_error(error: MempoolStatus) -> Result<Self> {
         let code = match error.code {
             MempoolStatusCode::InvalidSeqNumber => ServerCode::MempoolInvalidSeqNumber,
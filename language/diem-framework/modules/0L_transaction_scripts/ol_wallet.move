@@ -3,6 +3,10 @@ module WalletScripts {
 
     use 0x1::Wallet;
     use 0x1::DiemAccount;
+    use 0x1::Errors;
+
+    /// The transfer is not pending, it was already paid or rejected, or there is no such uid
+    const ETRANSFER_NOT_PROPOSED: u64 = 0231011;
 
     public(script) fun set_wallet_type(sender: signer, type_of: u8) {
       if (type_of == 0) {
@@ -13,5 +17,17 @@ module WalletScripts {
           Wallet::set_comm(&sender);
       };
     }
+
+    // A validator vetoes a proposed community wallet transfer, by its uid.
+    // Each veto delays the payment by one epoch, the transfer is rejected
+    // once the vetos are above 2/3 of the validator set's voting power.
+    public(script) fun veto(sender: signer, uid: u64) {
+      // only pending transfers can be vetoed
+      assert(
+        Wallet::transfer_is_proposed(uid),
+        Errors::invalid_state(ETRANSFER_NOT_PROPOSED)
+      );
+      Wallet::veto(&sender, uid);
+    }
+}
 }
-}
\ No newline at end of file
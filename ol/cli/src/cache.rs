@@ -2,7 +2,10 @@
 use crate::{
     check::items::Items,
     mgmt::management::HostProcess,
-    node::{account::OwnerAccountView, chain_view::ChainView, states::HostState},
+    node::{
+        account::OwnerAccountView, chain_view::ChainView, fullnode_view::FullnodeView,
+        net_health::NetHealth, states::HostState,
+    },
 };
 use anyhow::Error;
 use serde::{Deserialize, Serialize};
@@ -40,6 +43,11 @@ pub struct Vitals {
     pub monitor_proc: Option<HostProcess>,
     /// state of the host for state machine
     pub host_state: HostState,
+    /// latest samples of the node metrics
+    pub net_health: Option<NetHealth>,
+    /// proofs and subsidy of the owner account as a fullnode
+    #[serde(default)]
+    pub fullnode_view: Option<FullnodeView>,
 }
 
 impl Vitals {
@@ -38,6 +38,20 @@ pub struct Items {
     pub has_operator_set: bool,
     /// has operator positive balance
     pub has_operator_positive_balance: bool,
+    /// the node's metrics could be read, the network items are unknown otherwise
+    pub net_health_known: bool,
+    /// connected validator peers
+    pub validator_peers: u64,
+    /// enough peers are connected
+    pub peers_connected: bool,
+    /// transactions in the mempool
+    pub mempool_size: u64,
+    /// mempool is not growing without commits
+    pub mempool_flowing: bool,
+    /// consensus rounds are advancing
+    pub consensus_progressing: bool,
+    /// state sync lag is shrinking or within tolerance
+    pub sync_catching_up: bool,
 }
 
 impl Default for Items {
@@ -58,6 +72,13 @@ impl Default for Items {
             has_autopay: false,
             has_operator_set: false,
             has_operator_positive_balance: false,
+            net_health_known: false,
+            validator_peers: 0,
+            peers_connected: false,
+            mempool_size: 0,
+            mempool_flowing: false,
+            consensus_progressing: false,
+            sync_catching_up: false,
         }
     }
 }
@@ -1,6 +1,7 @@
 //! `pilot` module
 
 #![allow(clippy::never_loop)]
+use crate::node::net_health::RESTART_AFTER_UNHEALTHY_CHECKS;
 use crate::node::node::Node;
 use crate::node::states::*;
 use std::{thread, time::Duration};
@@ -71,6 +72,62 @@ pub fn run_once(mut node: &mut Node, verbose: bool) -> &mut Node {
         }
     }
 
+    //////// NETWORK RULES ///////
+    if node.vitals.items.node_running {
+        if verbose && !node.vitals.items.net_health_known {
+            println!("Network: WARN: node metrics are not reachable, network health is unknown");
+        }
+        if verbose && node.vitals.items.net_health_known {
+            if !node.vitals.items.peers_connected {
+                println!(
+                    "Network: WARN: only {} validator peers connected",
+                    node.vitals.items.validator_peers
+                );
+            }
+            if !node.vitals.items.mempool_flowing {
+                println!(
+                    "Network: WARN: mempool growing without commits, {} txs pending",
+                    node.vitals.items.mempool_size
+                );
+            }
+            if !node.vitals.items.consensus_progressing {
+                println!("Network: WARN: consensus round is stuck");
+            }
+            if !node.vitals.items.sync_catching_up {
+                println!("Network: WARN: state sync is falling behind");
+            }
+        }
+
+        // only the pilot ticks count towards a restart, not every refresh of the checks
+        let node_running = node.vitals.items.node_running;
+        let unhealthy = match &mut node.vitals.net_health {
+            Some(h) => {
+                h.count_check(node_running, is_in_val_set);
+                h.consecutive_unhealthy
+            }
+            None => 0,
+        };
+        if unhealthy >= RESTART_AFTER_UNHEALTHY_CHECKS {
+            if verbose {
+                println!(
+                    ".. Network: WARN: unhealthy for {} checks, restarting node",
+                    unhealthy
+                );
+            }
+            node.stop_node();
+            node.vitals.host_state.node_state = match node.start_node(verbose) {
+                Ok(_) => NodeState::ValidatorOutOfSet,
+                Err(_) => {
+                    println!(".. Node: WARN: could not restart node");
+                    NodeState::Stopped
+                }
+            };
+            if let Some(h) = &mut node.vitals.net_health {
+                h.consecutive_unhealthy = 0;
+            }
+        }
+    }
+
     //////// MINER RULES ///////
     if node.vitals.items.miner_running {
         node.vitals.host_state.miner_state = TowerState::Mining;
@@ -116,6 +173,32 @@ pub fn run_once(mut node: &mut Node, verbose: bool) -> &mut Node {
             }
         }
     }
+    //////// FULLNODE RULES ///////
+    if verbose && !is_in_val_set {
+        if let Some(f) = &node.vitals.fullnode_view {
+            println!(
+                "Fullnode: {} proofs in epoch {}, {} paid, {} more accepted",
+                f.proofs_in_epoch,
+                f.epoch,
+                f.proofs_paid_in_epoch,
+                f.proofs_remaining()
+            );
+            if f.proofs_in_epoch <= f.thres_lower {
+                println!(
+                    ".. Fullnode: WARN: proofs are only paid above {} in the epoch",
+                    f.thres_lower
+                );
+            }
+            match f.projected_subsidy {
+                Some(s) => println!(
+                    ".. Fullnode: projected subsidy {}, total paid {}",
+                    s, f.total_payouts
+                ),
+                None => println!(".. Fullnode: run `ol index` to project the subsidy"),
+            }
+        }
+    }
+
     thread::sleep(Duration::from_millis(10_000));
     node
 }
@@ -34,7 +34,9 @@ impl Node {
 
         &self.refresh_onchain_state();
         &self.refresh_chain_info();
+        &self.refresh_validator_analytics();
         &self.refresh_account_info();
+        &self.refresh_fullnode_view();
         &self.refresh_checks();
         &self.vitals.write_json(&home_path);
         if verbose {
@@ -60,6 +62,11 @@ Node running: {node}
 Tower running: {miner}
 Account on chain: {account}
 In validator set: {in_set}
+Node metrics reachable: {net_known}
+Validator peers: {peers} (connected: {peers_ok})
+Mempool size: {mempool} (flowing: {mempool_ok})
+Consensus progressing: {consensus}
+State sync catching up: {catching_up}
 \n",
         now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
         configs = node.vitals.items.configs_exist,
@@ -72,5 +79,12 @@ In validator set: {in_set}
         miner = node.vitals.items.miner_running,
         account = node.vitals.items.account_created,
         in_set = node.vitals.items.validator_set,
+        net_known = node.vitals.items.net_health_known,
+        peers = node.vitals.items.validator_peers,
+        peers_ok = node.vitals.items.peers_connected,
+        mempool = node.vitals.items.mempool_size,
+        mempool_ok = node.vitals.items.mempool_flowing,
+        consensus = node.vitals.items.consensus_progressing,
+        catching_up = node.vitals.items.sync_catching_up,
     );
 }
@@ -10,7 +10,10 @@
 //! See the `impl Configurable` below for how to specify the path to the
 //! application's configuration file.
 
+mod attest_cmd;
+mod explain_validator_cmd;
 mod health_cmd;
+mod index_cmd;
 pub mod init_cmd;
 mod mgmt_cmd;
 mod pilot_cmd;
@@ -18,13 +21,15 @@ pub mod query_cmd;
 mod restore_cmd;
 mod serve_cmd;
 mod start_cmd;
+mod swarm_cmd;
 mod version;
 mod whoami_cmd;
 
 use self::{
-    health_cmd::HealthCmd, init_cmd::InitCmd, mgmt_cmd::MgmtCmd, pilot_cmd::PilotCmd,
+    attest_cmd::AttestCmd, explain_validator_cmd::ExplainValidatorCmd, health_cmd::HealthCmd,
+    index_cmd::IndexCmd, init_cmd::InitCmd, mgmt_cmd::MgmtCmd, pilot_cmd::PilotCmd,
     query_cmd::QueryCmd, restore_cmd::RestoreCmd, serve_cmd::ServeCmd, start_cmd::StartCmd,
-    version::VersionCmd, whoami_cmd::WhoamiCmd,
+    swarm_cmd::SwarmCmd, version::VersionCmd, whoami_cmd::WhoamiCmd,
 };
 
 use crate::config::AppCfg;
@@ -68,12 +73,28 @@ pub enum OlCliCmd {
     #[options(help = "run simple queries through subcommands, prints the value to stdout")]
     Query(QueryCmd),
 
+    /// The `attest` subcommand
+    #[options(
+        help = "build the stdlib from source and compare its hash with the upgrade oracle votes"
+    )]
+    Attest(AttestCmd),
+
+    /// The `explain-validator` subcommand
+    #[options(
+        help = "explain which epoch boundary criteria a validator meets, and which keep it out of the set"
+    )]
+    ExplainValidator(ExplainValidatorCmd),
+
     /// The `health` subcommand
     #[options(
         help = "run healthcheck on the account, node, and displays some network information"
     )]
     Health(HealthCmd),
 
+    /// The `index` subcommand
+    #[options(help = "index payments, memos and receipts for `ol query --payments`")]
+    Index(IndexCmd),
+
     /// The `pilot` subcommand, for explorer
     #[options(help = "run pilot command, which triggers needed services")]
     Pilot(PilotCmd),
@@ -82,6 +103,10 @@ pub enum OlCliCmd {
     #[options(help = "start 0L services")]
     Start(StartCmd),
 
+    /// The `swarm` subcommand
+    #[options(help = "start a local swarm of personas and run scenarios against it")]
+    Swarm(SwarmCmd),
+
     /// The `whoami` subcommand
     #[options(help = "show public keys and network protocols")]
     Whoami(WhoamiCmd),
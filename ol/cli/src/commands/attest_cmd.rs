@@ -0,0 +1,112 @@
+//! `attest` subcommand
+
+use crate::{
+    entrypoint,
+    mgmt::stdlib_attest::{attest_stdlib, proposals_by_hash},
+    node::client,
+    prelude::app_config,
+};
+use abscissa_core::{Command, Options, Runnable};
+use std::{path::PathBuf, process::exit};
+
+/// `attest` subcommand
+#[derive(Command, Debug, Default, Options)]
+pub struct AttestCmd {
+    #[options(
+        short = "s",
+        help = "source tree to build the stdlib from, defaults to the configured source path"
+    )]
+    source_path: Option<PathBuf>,
+    #[options(
+        no_short,
+        help = "build even if the source tree has uncommitted changes"
+    )]
+    allow_dirty: bool,
+    #[options(short = "o", help = "write the attestation as json")]
+    output: Option<PathBuf>,
+    #[options(
+        no_short,
+        help = "only build and hash, do not compare with the upgrade oracle"
+    )]
+    offline: bool,
+}
+
+impl Runnable for AttestCmd {
+    fn run(&self) {
+        let args = entrypoint::get_args();
+        let mut cfg = app_config().clone();
+        let source_path = match self
+            .source_path
+            .clone()
+            .or(cfg.workspace.source_path.clone())
+        {
+            Some(p) => p,
+            None => {
+                println!("ERROR: no source path given, pass one with --source-path");
+                exit(1);
+            }
+        };
+
+        let attestation = attest_stdlib(&source_path, self.allow_dirty).unwrap_or_else(|e| {
+            println!("ERROR: could not build the stdlib, message: {:?}", e);
+            exit(1);
+        });
+        attestation.print();
+        if let Some(p) = &self.output {
+            attestation.write_json(p).unwrap_or_else(|e| {
+                println!("ERROR: could not write the attestation, message: {:?}", e);
+                exit(1);
+            });
+        }
+        if self.offline {
+            return;
+        }
+
+        let client = client::pick_client(args.swarm_path, &mut cfg).unwrap();
+        let oracle = match client.get_oracle_upgrade_state() {
+            Ok(Some(state)) => state.upgrade,
+            Ok(None) => {
+                println!("No upgrade oracle state on chain.");
+                return;
+            }
+            Err(e) => {
+                println!(
+                    "ERROR: could not get the upgrade oracle state, message: {:?}",
+                    e
+                );
+                exit(1);
+            }
+        };
+
+        let proposals = proposals_by_hash(&oracle, &attestation.hash);
+        if proposals.is_empty() {
+            println!("No upgrade proposals in the current vote window.");
+            return;
+        }
+        println!(
+            "\nProposals in the vote window ending at height {}:",
+            oracle.vote_window
+        );
+        for p in proposals.iter() {
+            println!(
+                "{} weight {}{}",
+                p.hash,
+                p.total_weight,
+                if p.matches_build {
+                    " <- matches this build"
+                } else {
+                    ""
+                }
+            );
+            for v in p.validators.iter() {
+                println!("  {}", v);
+            }
+        }
+        if !proposals.iter().any(|p| p.matches_build) {
+            println!(
+                "\nWARN: no proposal matches the stdlib built from commit {}",
+                attestation.commit
+            );
+        }
+    }
+}
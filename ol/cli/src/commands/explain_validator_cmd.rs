@@ -0,0 +1,71 @@
+//! `explain-validator` subcommand
+
+use crate::{
+    entrypoint,
+    node::{client, explain_validator::explain, node::Node},
+    prelude::app_config,
+};
+use abscissa_core::{Command, Options, Runnable};
+use diem_types::account_address::AccountAddress;
+use std::process::exit;
+
+/// `explain-validator` subcommand
+#[derive(Command, Debug, Default, Options)]
+pub struct ExplainValidatorCmd {
+    #[options(
+        free,
+        help = "validator address, defaults to --account or the configured account"
+    )]
+    address: Vec<AccountAddress>,
+    #[options(no_short, help = "print the resources read, and the criteria, as json")]
+    json: bool,
+}
+
+impl Runnable for ExplainValidatorCmd {
+    fn run(&self) {
+        let args = entrypoint::get_args();
+        let is_swarm = args.swarm_path.is_some();
+        let mut cfg = app_config().clone();
+        let account = self
+            .address
+            .first()
+            .cloned()
+            .or(args.account)
+            .unwrap_or(cfg.profile.account);
+
+        let client = client::pick_client(args.swarm_path.clone(), &mut cfg).unwrap_or_else(|e| {
+            println!("ERROR: Cannot connect to a client. Message: {}", e);
+            exit(1);
+        });
+        let node = Node::new(client, &cfg, is_swarm);
+        let thresholds = node.get_compliance_thresholds().unwrap_or_else(|e| {
+            println!(
+                "ERROR: could not read the chain thresholds, message: {:?}",
+                e
+            );
+            exit(1);
+        });
+
+        let facts = node
+            .validator_facts(account, &thresholds)
+            .unwrap_or_else(|e| {
+                println!(
+                    "ERROR: could not read the validator state of {}, message: {:?}",
+                    account, e
+                );
+                exit(1);
+            });
+        let explanation = explain(&facts, &thresholds);
+
+        if self.json {
+            let out = serde_json::json!({
+              "facts": facts,
+              "thresholds": thresholds,
+              "explanation": explanation,
+            });
+            println!("{}", serde_json::to_string_pretty(&out).unwrap());
+        } else {
+            explanation.print();
+        }
+    }
+}
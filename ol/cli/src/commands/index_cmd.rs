@@ -0,0 +1,62 @@
+//! `index` subcommand
+
+use crate::{
+    entrypoint,
+    node::{client, node::Node, payment_index::PaymentIndex},
+    prelude::app_config,
+};
+use abscissa_core::{Command, Options, Runnable};
+use std::{process::exit, thread, time::Duration};
+
+/// seconds between polls for new transactions
+pub const INDEX_INTERVAL_SECS: u64 = 10;
+
+/// `index` subcommand
+#[derive(Command, Debug, Default, Options)]
+pub struct IndexCmd {
+    #[options(
+        no_short,
+        help = "index up to the chain tip and exit, instead of following it"
+    )]
+    once: bool,
+    #[options(
+        no_short,
+        help = "seconds between polls for new transactions, defaults to 10"
+    )]
+    interval: Option<u64>,
+}
+
+impl Runnable for IndexCmd {
+    fn run(&self) {
+        let args = entrypoint::get_args();
+        let is_swarm = args.swarm_path.is_some();
+        let mut cfg = app_config().clone();
+        let client = client::pick_client(args.swarm_path.clone(), &mut cfg).unwrap_or_else(|e| {
+            println!("ERROR: Cannot connect to a client. Message: {}", e);
+            exit(1);
+        });
+        let node = Node::new(client, &cfg, is_swarm);
+        let path = node.payment_index_path();
+        let index = PaymentIndex::open(&path).unwrap_or_else(|e| {
+            println!(
+                "ERROR: could not open the payment index at {:?}, message: {:?}",
+                path, e
+            );
+            exit(1);
+        });
+
+        let interval = Duration::from_secs(self.interval.unwrap_or(INDEX_INTERVAL_SECS));
+        loop {
+            match node.sync_payment_index(&index) {
+                Ok(Some(v)) => println!("payments indexed up to version {}", v),
+                Ok(None) => println!("no transactions to index"),
+                // the node may be restarting, try again on the next poll
+                Err(e) => println!("WARN: could not index payments, message: {:?}", e),
+            }
+            if self.once {
+                break;
+            }
+            thread::sleep(interval);
+        }
+    }
+}
@@ -4,6 +4,7 @@ use crate::{
     entrypoint,
     node::client,
     node::node::Node,
+    node::payment_index::PaymentFilter,
     node::query::{is_community_wallet, is_slow_wallet, QueryType, WalletType},
     prelude::app_config,
 };
@@ -47,10 +48,12 @@ pub struct QueryCmd {
     #[options(help = "get last payment events RECEIVED, defaults to last 100")]
     events_received: bool,
 
-    #[options(help = "height to start txs query from, defaults to -100_000 blocks")]
+    #[options(
+        help = "height to start txs or payments query from, defaults to -100_000 blocks, or the latest payments"
+    )]
     txs_height: Option<u64>,
 
-    #[options(help = "number of txs to return, defaults to 100 txs")]
+    #[options(help = "number of txs or payments to return, defaults to 100")]
     txs_count: Option<u64>,
 
     #[options(help = "filter by type of transaction, e.g. 'ol_miner_state_commit'")]
@@ -70,6 +73,24 @@ pub struct QueryCmd {
 
     #[options(help = "Get a validator's on-chain config")]
     val_config: bool,
+
+    #[options(help = "validator set churn, jailing risk and vouches")]
+    val_analytics: bool,
+
+    #[options(no_short, help = "payments in the local index, see `ol index`")]
+    payments: bool,
+
+    #[options(no_short, help = "receipts of the account in the local index")]
+    receipts: bool,
+
+    #[options(no_short, help = "filter payments by sender")]
+    sender: Option<AccountAddress>,
+
+    #[options(no_short, help = "filter payments by receiver")]
+    receiver: Option<AccountAddress>,
+
+    #[options(no_short, help = "filter payments by a regex on the memo")]
+    memo: Option<String>,
 }
 
 impl Runnable for QueryCmd {
@@ -141,6 +162,23 @@ impl Runnable for QueryCmd {
         } else if self.val_config {
             query_type = QueryType::ValConfig { account };
             display = "VALIDATOR CONFIGS";
+        } else if self.val_analytics {
+            query_type = QueryType::ValidatorAnalytics;
+            display = "VALIDATOR ANALYTICS";
+        } else if self.payments {
+            query_type = QueryType::Payments {
+                filter: PaymentFilter {
+                    sender: self.sender,
+                    receiver: self.receiver,
+                    memo: self.memo.clone(),
+                    from_version: self.txs_height,
+                    limit: self.txs_count.map(|c| c as usize),
+                },
+            };
+            display = "PAYMENTS";
+        } else if self.receipts {
+            query_type = QueryType::Receipts { account };
+            display = "RECEIPTS";
         }
 
         match node.query(query_type) {
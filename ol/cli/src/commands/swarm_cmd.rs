@@ -0,0 +1,89 @@
+//! `swarm` subcommand
+
+use crate::{
+    entrypoint,
+    prelude::app_config,
+    swarm::{harness::SwarmHarness, scenario::Scenario},
+};
+use abscissa_core::{Command, Options, Runnable};
+use std::{path::PathBuf, process::exit, thread, time::Duration};
+
+/// `swarm` subcommand
+#[derive(Command, Debug, Default, Options)]
+pub struct SwarmCmd {
+    #[options(
+        short = "n",
+        help = "number of validators, personas from alice to dave"
+    )]
+    num_nodes: Option<usize>,
+
+    #[options(short = "s", help = "scenario yaml file to run against the swarm")]
+    scenario: Option<PathBuf>,
+
+    #[options(help = "path of the libra source, with the binaries in target/debug")]
+    source_path: Option<PathBuf>,
+
+    #[options(help = "start the towers of all personas")]
+    mine: bool,
+}
+
+impl Runnable for SwarmCmd {
+    fn run(&self) {
+        let args = entrypoint::get_args();
+        let home = dirs::home_dir().unwrap();
+        let swarm_path = args
+            .swarm_path
+            .unwrap_or_else(|| home.join(".0L/swarm_temp"));
+        let source_path = self
+            .source_path
+            .clone()
+            .or_else(|| app_config().workspace.source_path.clone())
+            .unwrap_or_else(|| home.join("libra"));
+
+        let scenario = self.scenario.as_ref().map(|p| {
+            Scenario::read_yaml(p).unwrap_or_else(|e| {
+                println!("could not read scenario {:?}, exiting. Message: {:?}", p, e);
+                exit(1);
+            })
+        });
+        // the scenario knows how many nodes it needs
+        let num_nodes = self
+            .num_nodes
+            .or_else(|| scenario.as_ref().map(|s| s.num_nodes))
+            .unwrap_or(1);
+
+        let mut harness =
+            SwarmHarness::start(source_path, swarm_path, num_nodes).unwrap_or_else(|e| {
+                println!("could not start swarm, exiting. Message: {:?}", e);
+                exit(1);
+            });
+
+        if self.mine {
+            for p in harness.personas() {
+                harness.start_tower(&p).unwrap_or_else(|e| {
+                    println!("could not start tower for {}. Message: {:?}", p, e);
+                });
+            }
+        }
+
+        match scenario {
+            Some(s) => {
+                if let Err(e) = s.run(&mut harness) {
+                    println!("{}", e);
+                    // drop the harness so the nodes and towers are stopped
+                    drop(harness);
+                    exit(1);
+                }
+            }
+            None => {
+                println!(
+                    "swarm running in {:?}, stop with: killall ol diem-node tower",
+                    &harness.swarm_path
+                );
+                loop {
+                    thread::sleep(Duration::from_secs(60));
+                }
+            }
+        }
+    }
+}
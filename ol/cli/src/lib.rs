@@ -19,4 +19,5 @@ pub mod mgmt;
 pub mod node;
 pub mod prelude;
 pub mod server;
+pub mod swarm;
 // pub mod migrate;
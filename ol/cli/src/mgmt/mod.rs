@@ -1,3 +1,4 @@
 //! `mgmt` tools for management of host
 pub mod management;
 pub mod restore;
+pub mod stdlib_attest;
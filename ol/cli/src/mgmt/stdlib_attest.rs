@@ -0,0 +1,231 @@
+//! `stdlib_attest` rebuild the stdlib upgrade payload from source and match its hash to the oracle votes
+
+use anyhow::{bail, Error};
+use diem_temppath::TempPath;
+use diem_types::{account_address::AccountAddress, ol_oracle_upgrade_state::UpgradeOracle};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// where `diem-framework --create-upgrade-payload` writes the payload, relative to the source
+pub const STAGED_STDLIB_PATH: &str = "language/diem-framework/staged/stdlib.mv";
+
+/// Hash of a stdlib payload built from a source commit
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StdlibAttestation {
+    /// source tree the payload was built from
+    pub source_path: PathBuf,
+    /// git commit of the source tree
+    pub commit: String,
+    /// the tree had uncommitted changes
+    pub dirty: bool,
+    /// the built stdlib.mv
+    pub payload_path: PathBuf,
+    /// sha2_256 of the payload, hex. This is what `Oracle.move` tallies and `oracle-upgrade --hash` votes for.
+    pub hash: String,
+}
+
+/// An upgrade proposal of the oracle and its voters
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ProposalVotes {
+    /// sha2_256 of the proposed payload, hex
+    pub hash: String,
+    /// voting weight for the proposal
+    pub total_weight: u64,
+    /// validators who voted for it
+    pub validators: Vec<AccountAddress>,
+    /// the proposal is the payload built from source
+    pub matches_build: bool,
+}
+
+impl StdlibAttestation {
+    /// print the attestation
+    pub fn print(&self) {
+        println!("source:  {:?}", self.source_path);
+        println!(
+            "commit:  {}{}",
+            self.commit,
+            if self.dirty { " (dirty)" } else { "" }
+        );
+        println!("payload: {:?}", self.payload_path);
+        println!("hash:    {}", self.hash);
+    }
+
+    /// write the attestation as json
+    pub fn write_json(&self, path: &Path) -> Result<(), Error> {
+        let mut file = File::create(path)?;
+        file.write_all(&serde_json::to_vec_pretty(&self)?)?;
+        Ok(())
+    }
+}
+
+/// build the upgrade payload from a source tree and hash it
+pub fn attest_stdlib(source_path: &Path, allow_dirty: bool) -> Result<StdlibAttestation, Error> {
+    let source_path = fs::canonicalize(source_path)?;
+    let (commit, dirty) = git_commit(&source_path)?;
+    if dirty && !allow_dirty {
+        bail!(
+            "source tree {:?} has uncommitted changes, the hash would not match commit {}",
+            source_path,
+            commit
+        );
+    }
+
+    let payload_path = build_upgrade_payload(&source_path)?;
+    let hash = hex::encode(payload_hash(&fs::read(&payload_path)?));
+    Ok(StdlibAttestation {
+        source_path,
+        commit,
+        dirty,
+        payload_path,
+        hash,
+    })
+}
+
+/// compile the stdlib modules only, with the locked dependencies, and write the payload.
+/// The release artifacts go to a temp dir, the build must not change any other file of the tree.
+pub fn build_upgrade_payload(source_path: &Path) -> Result<PathBuf, Error> {
+    let changed_before = changed_files(source_path)?;
+    let artifacts = TempPath::new();
+    artifacts.create_as_dir()?;
+    let payload_path = source_path.join(STAGED_STDLIB_PATH);
+    // a stale payload must not be mistaken for the new build
+    if payload_path.exists() {
+        fs::remove_file(&payload_path)?;
+    }
+    fs::create_dir_all(payload_path.parent().unwrap())?;
+
+    println!("building the stdlib upgrade payload in {:?}", source_path);
+    let status = Command::new("cargo")
+        .current_dir(source_path)
+        .args(&[
+            "run",
+            "--locked",
+            "--release",
+            "-p",
+            "diem-framework",
+            "--",
+            "--create-upgrade-payload",
+            "--no-check-linking-layout-compatibility",
+            "--no-doc",
+            "--no-script-builder",
+            "--no-errmap",
+            "--output",
+        ])
+        .arg(artifacts.path())
+        .env("CARGO_INCREMENTAL", "0")
+        .status()?;
+    if !status.success() {
+        bail!("stdlib build failed with {}", status);
+    }
+    if !payload_path.exists() {
+        bail!("stdlib build did not write {:?}", payload_path);
+    }
+
+    let changed_by_build: Vec<_> = changed_files(source_path)?
+        .difference(&changed_before)
+        .filter(|f| f.as_str() != STAGED_STDLIB_PATH)
+        .cloned()
+        .collect();
+    if !changed_by_build.is_empty() {
+        bail!(
+            "stdlib build changed files of the source tree: {}",
+            changed_by_build.join(", ")
+        );
+    }
+    Ok(payload_path)
+}
+
+/// the hash `Oracle.move` computes for a payload
+pub fn payload_hash(payload: &[u8]) -> Vec<u8> {
+    Sha256::digest(payload).to_vec()
+}
+
+/// current commit of the tree, and whether it has uncommitted changes
+pub fn git_commit(source_path: &Path) -> Result<(String, bool), Error> {
+    let out = Command::new("git")
+        .current_dir(source_path)
+        .args(&["rev-parse", "HEAD"])
+        .output()?;
+    if !out.status.success() {
+        bail!("{:?} is not a git repository", source_path);
+    }
+    let commit = String::from_utf8(out.stdout)?.trim().to_owned();
+    Ok((commit, !changed_files(source_path)?.is_empty()))
+}
+
+/// tracked files with uncommitted changes, relative to the root of the tree
+pub fn changed_files(source_path: &Path) -> Result<BTreeSet<String>, Error> {
+    let out = Command::new("git")
+        .current_dir(source_path)
+        .args(&["status", "--porcelain", "--untracked-files=no"])
+        .output()?;
+    if !out.status.success() {
+        bail!("cannot get the status of {:?}", source_path);
+    }
+    // each line is `XY path`
+    Ok(String::from_utf8(out.stdout)?
+        .lines()
+        .filter_map(|l| l.get(3..))
+        .map(|f| f.to_owned())
+        .collect())
+}
+
+/// the proposals of the current vote window, with the one matching the hash flagged
+pub fn proposals_by_hash(oracle: &UpgradeOracle, hash: &str) -> Vec<ProposalVotes> {
+    oracle
+        .vote_counts
+        .iter()
+        .map(|vc| {
+            let h = hex::encode(&vc.hash);
+            ProposalVotes {
+                matches_build: h.eq_ignore_ascii_case(hash),
+                hash: h,
+                total_weight: vc.total_weight,
+                validators: vc.validators.clone(),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_proposals_by_hash() {
+    use diem_types::ol_oracle_upgrade_state::VoteCount;
+
+    let built = payload_hash(b"stdlib");
+    let other = payload_hash(b"other");
+    let vote_count = |hash: &Vec<u8>, validators: Vec<AccountAddress>, total_weight| VoteCount {
+        data: hash.clone(),
+        validators,
+        hash: hash.clone(),
+        total_weight,
+    };
+    let oracle = UpgradeOracle {
+        id: 1,
+        validators_voted: vec![],
+        vote_counts: vec![
+            vote_count(&built, vec![AccountAddress::ZERO], 10),
+            vote_count(&other, vec![AccountAddress::random()], 3),
+        ],
+        votes: vec![],
+        vote_window: 0,
+        version_id: 0,
+        consensus: vote_count(&vec![], vec![], 0),
+    };
+
+    let proposals = proposals_by_hash(&oracle, &hex::encode(&built));
+    assert_eq!(proposals.len(), 2);
+    assert!(proposals[0].matches_build);
+    assert_eq!(proposals[0].validators, vec![AccountAddress::ZERO]);
+    assert!(!proposals[1].matches_build);
+    assert_eq!(
+        hex::encode(payload_hash(b"abc")),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+}
@@ -9,7 +9,10 @@ use diem_types::{
 };
 use ol_types::{autopay::AutoPayView, validator_config::ValidatorConfigView};
 
-use super::{autopay_view::PayeeStats, dictionary::AccountDictionary, node::Node, query};
+use super::{
+    autopay_view::PayeeStats, dictionary::AccountDictionary, node::Node, query,
+    validator_analytics::ValidatorAnalytics,
+};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
@@ -51,6 +54,8 @@ pub struct ChainView {
     pub vals_config_stats: Option<ValsConfigStats>,
     /// autopay payees percentage recurring stats
     pub autopay_watch_list: Option<Vec<PayeeStats>>,
+    /// validator set churn, jailing risk and vouches
+    pub validator_analytics: Option<ValidatorAnalytics>,
 }
 
 #[derive(Default, Debug, Deserialize, Serialize, Clone)]
@@ -144,20 +149,8 @@ impl Node {
             ////////////// CHAIN METADATA //////////////
             cs.epoch = conf_resource.epoch();
 
-            // Calculate Epoch Progress
-            let time_start = conf_resource.last_reconfiguration_time() as i64 / 1000000;
-
-            let now = Utc::now().timestamp();
-
-            match meta.chain_id {
-                // testnet has faster epochs
-                4 => cs.epoch_progress = (now - time_start) as f64 / 61f64, // 1 minute
-                // for main net
-                _ => cs.epoch_progress = (now - time_start) as f64 / 86401f64, // 24 hours
-            }
-            if cs.epoch_progress > 1f64 {
-                cs.epoch_progress = 0f64;
-            };
+            cs.epoch_progress =
+                epoch_progress(conf_resource.last_reconfiguration_time(), meta.chain_id);
 
             if let Some(first) = account_state
                 .get_registered_currency_info_resources()?
@@ -301,6 +294,25 @@ impl Node {
     }
 }
 
+/// fraction of the epoch elapsed, from the time of the last reconfiguration in microseconds
+pub fn epoch_progress(last_reconfiguration_time: u64, chain_id: u8) -> f64 {
+    let time_start = last_reconfiguration_time as i64 / 1000000;
+
+    let now = Utc::now().timestamp();
+
+    let progress = match chain_id {
+        // testnet has faster epochs
+        4 => (now - time_start) as f64 / 61f64, // 1 minute
+        // for main net
+        _ => (now - time_start) as f64 / 86401f64, // 24 hours
+    };
+    if progress > 1f64 {
+        0f64
+    } else {
+        progress
+    }
+}
+
 fn calc_config_stats(vals: Vec<ValidatorView>) -> Result<ValsConfigStats, Error> {
     let mut count_autopay = 0;
     let mut count_operators = 0;
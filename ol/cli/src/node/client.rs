@@ -1,6 +1,9 @@
 //! `bal` subcommand
 
-use crate::{config::AppCfg, entrypoint, node::node::Node, prelude::app_config};
+use crate::{
+    config::AppCfg, entrypoint, node::node::Node, node::upstream_quorum::UpstreamRecord,
+    prelude::app_config,
+};
 use anyhow::Error;
 use anyhow::Result;
 use cli::diem_client::DiemClient;
@@ -57,7 +60,20 @@ pub fn get_client() -> Option<DiemClient> {
 /// get client type with defaults from toml for remote node
 pub fn find_a_remote_jsonrpc(config: &AppCfg, waypoint: Waypoint) -> Result<DiemClient, Error> {
     let mut rng = thread_rng();
-    let list = &config.profile.upstream_nodes;
+    // skip upstreams which keep disagreeing with the others, unless there is nothing else
+    let record = UpstreamRecord::read_json(&config.workspace.node_home);
+    let trusted: Vec<Url> = config
+        .profile
+        .upstream_nodes
+        .iter()
+        .filter(|u| !record.is_flagged(u))
+        .cloned()
+        .collect();
+    let list = if trusted.is_empty() {
+        &config.profile.upstream_nodes
+    } else {
+        &trusted
+    };
     let len = list.len();
     let url =
         list.choose_multiple(&mut rng, len)
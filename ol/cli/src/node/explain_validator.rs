@@ -0,0 +1,484 @@
+//! `explain_validator` why a validator is jailed, or would not be admitted at the next epoch boundary
+
+use super::{
+    node::Node,
+    query::{find_value_from_state, is_slow_wallet},
+    validator_analytics::{addresses_from_value, unrelated_buddies, ComplianceThresholds},
+};
+use anyhow::{bail, Error};
+use diem_types::{account_address::AccountAddress, account_state::AccountState};
+use resource_viewer::{AnnotatedAccountStateBlob, AnnotatedMoveValue};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, convert::TryFrom};
+
+/// On-chain state of a validator candidate, as the epoch boundary sees it
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ValidatorFacts {
+    /// account address
+    pub account: AccountAddress,
+    /// current epoch
+    pub epoch: u64,
+    /// is in the current validator set
+    pub in_set: bool,
+    /// is in the ValidatorUniverse
+    pub in_universe: bool,
+    /// is jailed
+    pub is_jailed: bool,
+    /// times dropped from the set
+    pub lifetime_jailed: u64,
+    /// times a validator it vouched for was jailed
+    pub lifetime_vouchees_jailed: u64,
+    /// failures to complete an epoch after rejoining
+    pub consecutive_failure_to_rejoin: u64,
+    /// has a TowerState
+    pub has_tower: bool,
+    /// verified tower height, the node weight
+    pub tower_height: u64,
+    /// proofs submitted in the epoch
+    pub proofs_in_epoch: u64,
+    /// votes in the epoch
+    pub vote_count: u64,
+    /// blocks since the epoch started
+    pub epoch_blocks: u64,
+    /// validator config is set
+    pub has_valid_config: bool,
+    /// operator account of the validator config
+    pub operator: Option<AccountAddress>,
+    /// is a slow wallet
+    pub is_slow_wallet: bool,
+    /// validators which vouched for this account
+    pub buddies: Vec<AccountAddress>,
+    /// buddies in the current set
+    pub buddies_in_set: Vec<AccountAddress>,
+    /// buddies in the set which are not family of each other
+    pub unrelated_buddies: Vec<AccountAddress>,
+    /// rank by tower height in the universe, starting at 1. 0 if not in the universe.
+    pub weight_rank: usize,
+    /// accounts in the universe
+    pub universe_size: usize,
+    /// set members signing above the threshold, which decides how much the set can grow
+    pub proven_set_members: u64,
+}
+
+/// One admission criterion of the epoch boundary
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Criterion {
+    /// what is checked
+    pub name: String,
+    /// must pass to be in the next set. Otherwise it is only reported.
+    pub required: bool,
+    /// is met
+    pub passed: bool,
+    /// the value on chain
+    pub observed: String,
+    /// what the epoch boundary needs
+    pub needed: String,
+    /// how much is missing, for counted criteria
+    pub shortfall: u64,
+}
+
+/// Where a validator stands
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum ValidatorStatus {
+    /// in the current set
+    InSet,
+    /// jailed, out of the set
+    Jailed,
+    /// in the universe, but not in the set
+    Candidate,
+    /// never joined the universe
+    NotInUniverse,
+}
+
+/// The admission criteria evaluated for a validator
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Explanation {
+    /// account address
+    pub account: AccountAddress,
+    /// current epoch
+    pub epoch: u64,
+    /// where the validator stands now
+    pub status: ValidatorStatus,
+    /// criteria, in the order the epoch boundary checks them
+    pub criteria: Vec<Criterion>,
+}
+
+impl Explanation {
+    /// required criteria which are not met
+    pub fn unmet(&self) -> Vec<&Criterion> {
+        self.criteria
+            .iter()
+            .filter(|c| c.required && !c.passed)
+            .collect()
+    }
+
+    /// print the explanation
+    pub fn print(&self) {
+        println!("validator: {}", self.account);
+        println!("epoch:     {}", self.epoch);
+        println!("status:    {:?}\n", self.status);
+        for c in self.criteria.iter() {
+            let mark = match (c.passed, c.required) {
+                (true, _) => "ok  ",
+                (false, true) => "FAIL",
+                (false, false) => "warn",
+            };
+            print!("[{}] {}: {}, needs {}", mark, c.name, c.observed, c.needed);
+            if !c.passed && c.shortfall > 0 {
+                print!(" ({} short)", c.shortfall);
+            }
+            println!();
+        }
+
+        let unmet = self.unmet();
+        if unmet.is_empty() {
+            println!("\nMeets the admission criteria for the next epoch.");
+        } else {
+            println!(
+                "\nWould not be in the next set, {} criteria unmet.",
+                unmet.len()
+            );
+        }
+    }
+}
+
+/// votes needed to be above the signing threshold, like `Stats::node_above_thresh`
+pub fn signing_votes_needed(epoch_blocks: u64, thresholds: &ComplianceThresholds) -> u64 {
+    epoch_blocks * thresholds.signing_threshold_pct / 100
+}
+
+/// seats open in the next set, like `EpochBoundary::propose_new_set`
+pub fn seats_in_next_set(proven_set_members: u64, thresholds: &ComplianceThresholds) -> u64 {
+    let seats = proven_set_members + proven_set_members / 6;
+    seats.min(thresholds.max_validators_per_set)
+}
+
+/// evaluate the epoch boundary admission criteria
+pub fn explain(facts: &ValidatorFacts, thresholds: &ComplianceThresholds) -> Explanation {
+    let status = if !facts.in_universe && !facts.in_set {
+        ValidatorStatus::NotInUniverse
+    } else if facts.in_set {
+        ValidatorStatus::InSet
+    } else if facts.is_jailed {
+        ValidatorStatus::Jailed
+    } else {
+        ValidatorStatus::Candidate
+    };
+
+    let yes_no = |b: bool| (if b { "yes" } else { "no" }).to_owned();
+    let flag = |name: &str, passed: bool| Criterion {
+        name: name.to_owned(),
+        required: true,
+        passed,
+        observed: yes_no(passed),
+        needed: "yes".to_owned(),
+        shortfall: 0,
+    };
+    let count = |name: &str, required: bool, observed: u64, needed: u64| Criterion {
+        name: name.to_owned(),
+        required,
+        passed: observed >= needed,
+        observed: observed.to_string(),
+        needed: format!("at least {}", needed),
+        shortfall: needed.saturating_sub(observed),
+    };
+
+    let mut criteria = vec![flag("in the validator universe", facts.in_universe)];
+    criteria.push(Criterion {
+        observed: if facts.is_jailed {
+            format!(
+                "jailed, {} times in its lifetime, {} failures to rejoin",
+                facts.lifetime_jailed, facts.consecutive_failure_to_rejoin
+            )
+        } else {
+            "not jailed".to_owned()
+        },
+        needed: "not jailed, unjailed by itself or a voucher".to_owned(),
+        ..flag("not jailed", !facts.is_jailed)
+    });
+
+    // validators in the set keep their seat by signing, the others rejoin by mining
+    let votes_needed = signing_votes_needed(facts.epoch_blocks, thresholds);
+    criteria.push(Criterion {
+        needed: format!(
+            "more than {} ({}% of {} blocks)",
+            votes_needed, thresholds.signing_threshold_pct, facts.epoch_blocks
+        ),
+        passed: facts.vote_count > votes_needed,
+        shortfall: (votes_needed + 1).saturating_sub(facts.vote_count),
+        ..count("votes in epoch", facts.in_set, facts.vote_count, 0)
+    });
+    criteria.push(count(
+        "proofs in epoch",
+        !facts.in_set,
+        facts.proofs_in_epoch,
+        thresholds.epoch_mining_thres_lower,
+    ));
+
+    // Audit::val_audit_passing
+    criteria.push(flag("validator config set", facts.has_valid_config));
+    criteria.push(Criterion {
+        observed: match facts.operator {
+            Some(o) => o.to_string(),
+            None => "none".to_owned(),
+        },
+        needed: "an operator account other than the owner".to_owned(),
+        ..flag(
+            "operator account",
+            facts.operator.map_or(false, |o| o != facts.account),
+        )
+    });
+    criteria.push(flag("tower initialized", facts.has_tower));
+    criteria.push(flag("slow wallet", facts.is_slow_wallet));
+
+    criteria.push(Criterion {
+        observed: format!(
+            "{} unrelated, of {} buddies in the set and {} in total",
+            facts.unrelated_buddies.len(),
+            facts.buddies_in_set.len(),
+            facts.buddies.len()
+        ),
+        ..count(
+            "unrelated vouches in set",
+            true,
+            facts.unrelated_buddies.len() as u64,
+            thresholds.vouch_threshold,
+        )
+    });
+
+    // the set only grows by a sixth of its proven members, the heaviest towers get the seats
+    let seats = seats_in_next_set(facts.proven_set_members, thresholds);
+    criteria.push(Criterion {
+        name: "seat by tower weight".to_owned(),
+        required: false,
+        passed: facts.weight_rank > 0 && facts.weight_rank as u64 <= seats,
+        observed: format!(
+            "rank {} of {} by tower height {}",
+            facts.weight_rank, facts.universe_size, facts.tower_height
+        ),
+        needed: format!("rank {} or better", seats),
+        shortfall: (facts.weight_rank as u64).saturating_sub(seats),
+    });
+
+    Explanation {
+        account: facts.account,
+        epoch: facts.epoch,
+        status,
+        criteria,
+    }
+}
+
+impl Node {
+    /// read the resources the epoch boundary uses to admit a validator
+    pub fn validator_facts(
+        &self,
+        account: AccountAddress,
+        thresholds: &ComplianceThresholds,
+    ) -> Result<ValidatorFacts, Error> {
+        let system = match self.get_annotate_account_blob(AccountAddress::ZERO)? {
+            (Some(r), _) => r,
+            (None, _) => bail!("cannot get state of system account"),
+        };
+        let system_state = self.get_account_state(AccountAddress::ZERO)?;
+        let epoch = match system_state.get_configuration_resource()? {
+            Some(cr) => cr.epoch(),
+            None => bail!("cannot get configuration resource from chain"),
+        };
+        let set: Vec<AccountAddress> = match system_state.get_validator_set()? {
+            Some(vs) => vs.payload().iter().map(|v| *v.account_address()).collect(),
+            None => bail!("cannot get validator set resource from chain"),
+        };
+        let stats = match system_state.get_validators_stats()? {
+            Some(s) => s,
+            None => bail!("could not get validators stats"),
+        };
+        let universe = addresses_from_value(find_value_from_state(
+            &system,
+            "ValidatorUniverse".to_string(),
+            "ValidatorUniverse".to_string(),
+            "validators".to_string(),
+        ));
+
+        let height_now = get_u64(&system, "DiemBlock", "BlockMetadata", "height");
+        let height_start = get_u64(&system, "Epoch", "Timer", "height_start");
+        let epoch_blocks = height_now.saturating_sub(height_start);
+        let votes_needed = signing_votes_needed(epoch_blocks, thresholds);
+        let votes_of = |a: AccountAddress| {
+            stats
+                .get_validator_current_stats(a)
+                .map(|s| s.vote_count)
+                .unwrap_or(0)
+        };
+        let proven_set_members = set.iter().filter(|a| votes_of(**a) > votes_needed).count() as u64;
+
+        let blob = match self.get_annotate_account_blob(account)? {
+            (Some(r), _) => r,
+            (None, _) => bail!("account {} does not exist on chain", account),
+        };
+        let is_jailed = match find_value_from_state(
+            &blob,
+            "Jail".to_string(),
+            "Jail".to_string(),
+            "is_jailed".to_string(),
+        ) {
+            Some(AnnotatedMoveValue::Bool(b)) => *b,
+            _ => false,
+        };
+
+        let buddies = addresses_from_value(find_value_from_state(
+            &blob,
+            "Vouch".to_string(),
+            "Vouch".to_string(),
+            "vals".to_string(),
+        ));
+        let buddies_in_set: Vec<AccountAddress> = buddies
+            .iter()
+            .filter(|b| set.contains(b))
+            .cloned()
+            .collect();
+        let mut trees = BTreeMap::new();
+        for b in buddies_in_set.iter() {
+            if let Ok((Some(r), _)) = self.get_annotate_account_blob(*b) {
+                let tree = addresses_from_value(find_value_from_state(
+                    &r,
+                    "Ancestry".to_string(),
+                    "Ancestry".to_string(),
+                    "tree".to_string(),
+                ));
+                trees.insert(*b, tree);
+            }
+        }
+
+        let validator_config = match self.client.get_account_state_blob(&account)?.0 {
+            Some(b) => AccountState::try_from(&b)?.get_validator_config_resource()?,
+            None => None,
+        };
+        let tower = self.client.get_miner_state(&account)?;
+        let tower_height = tower.as_ref().map_or(0, |t| t.verified_tower_height);
+
+        // NodeWeight::proof_of_weight is the tower height
+        let heavier = universe
+            .iter()
+            .filter(|a| **a != account)
+            .filter(|a| match self.client.get_miner_state(a) {
+                Ok(Some(t)) => t.verified_tower_height > tower_height,
+                _ => false,
+            })
+            .count();
+        let in_universe = universe.contains(&account);
+
+        Ok(ValidatorFacts {
+            account,
+            epoch,
+            in_set: set.contains(&account),
+            in_universe,
+            is_jailed,
+            lifetime_jailed: get_u64(&blob, "Jail", "Jail", "lifetime_jailed"),
+            lifetime_vouchees_jailed: get_u64(&blob, "Jail", "Jail", "lifetime_vouchees_jailed"),
+            consecutive_failure_to_rejoin: get_u64(
+                &blob,
+                "Jail",
+                "Jail",
+                "consecutive_failure_to_rejoin",
+            ),
+            has_tower: tower.is_some(),
+            tower_height,
+            proofs_in_epoch: tower.as_ref().map_or(0, |t| t.actual_count_proofs_in_epoch),
+            vote_count: votes_of(account),
+            epoch_blocks,
+            has_valid_config: validator_config
+                .as_ref()
+                .map_or(false, |c| c.validator_config.is_some()),
+            operator: validator_config.and_then(|c| c.delegated_account),
+            is_slow_wallet: is_slow_wallet(&blob),
+            unrelated_buddies: unrelated_buddies(&buddies_in_set, &trees),
+            buddies,
+            buddies_in_set,
+            weight_rank: if in_universe { heavier + 1 } else { 0 },
+            universe_size: universe.len(),
+            proven_set_members,
+        })
+    }
+}
+
+/// a u64 field of a resource, 0 if it is missing
+fn get_u64(blob: &AnnotatedAccountStateBlob, module: &str, resource: &str, field: &str) -> u64 {
+    match find_value_from_state(
+        blob,
+        module.to_string(),
+        resource.to_string(),
+        field.to_string(),
+    ) {
+        Some(AnnotatedMoveValue::U64(v)) => *v,
+        _ => 0,
+    }
+}
+
+#[test]
+fn test_explain_candidate() {
+    let account = AccountAddress::random();
+    let alice = AccountAddress::random();
+    let bob = AccountAddress::random();
+    let carol = AccountAddress::random();
+
+    // bob descends from alice, carol is unrelated to both
+    let mut trees = BTreeMap::new();
+    trees.insert(bob, vec![alice]);
+    assert_eq!(
+        unrelated_buddies(&[alice, bob], &trees),
+        Vec::<AccountAddress>::new()
+    );
+    assert_eq!(
+        unrelated_buddies(&[alice, bob, carol], &trees),
+        vec![alice, bob, carol]
+    );
+
+    let facts = ValidatorFacts {
+        account,
+        epoch: 10,
+        in_set: false,
+        in_universe: true,
+        is_jailed: true,
+        lifetime_jailed: 1,
+        lifetime_vouchees_jailed: 0,
+        consecutive_failure_to_rejoin: 0,
+        has_tower: true,
+        tower_height: 50,
+        proofs_in_epoch: 3,
+        vote_count: 0,
+        epoch_blocks: 1000,
+        has_valid_config: true,
+        operator: Some(AccountAddress::random()),
+        is_slow_wallet: true,
+        buddies: vec![alice, bob],
+        buddies_in_set: vec![alice, bob],
+        unrelated_buddies: unrelated_buddies(&[alice, bob], &trees),
+        weight_rank: 20,
+        universe_size: 30,
+        proven_set_members: 12,
+    };
+    let explanation = explain(&facts, &ComplianceThresholds::mainnet());
+    assert_eq!(explanation.status, ValidatorStatus::Jailed);
+
+    let unmet: Vec<&str> = explanation
+        .unmet()
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect();
+    assert_eq!(
+        unmet,
+        vec!["not jailed", "proofs in epoch", "unrelated vouches in set"]
+    );
+    let proofs = explanation
+        .criteria
+        .iter()
+        .find(|c| c.name == "proofs in epoch")
+        .unwrap();
+    assert_eq!(proofs.shortfall, 4);
+    // out of the set, votes are only reported
+    assert!(!explanation.criteria[2].required);
+    // 12 proven members open 14 seats
+    let seat = explanation.criteria.last().unwrap();
+    assert!(!seat.passed);
+    assert_eq!(seat.shortfall, 6);
+}
@@ -0,0 +1,189 @@
+//! `fullnode_view` proofs, projected subsidy and payouts of a fullnode operator
+
+use super::{
+    chain_view::epoch_progress,
+    node::Node,
+    payment_index::{PaymentFilter, PaymentIndex, PaymentRecord},
+};
+use anyhow::{bail, Error};
+use diem_types::{account_address::AccountAddress, account_state::AccountState};
+use ol_types::fullnode_counter::TowerCounterResource;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// metadata of the fullnode subsidy payments, see FullnodeSubsidy.move
+pub const FULLNODE_SUBSIDY_MEMO: &str = "fullnode_subsidy";
+
+/// metadata of the validator subsidy payments, see Subsidy.move
+pub const VALIDATOR_SUBSIDY_MEMO: &str = "validator subsidy";
+
+/// how many of the latest payouts are kept in the view
+pub const RECENT_PAYOUTS: usize = 10;
+
+/// how many of the latest payouts are summed, decades of daily epochs
+pub const MAX_PAYOUTS_SUMMED: usize = 10_000;
+
+/// Proofs and subsidy of a fullnode in the current epoch
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct FullnodeView {
+    /// account of the fullnode operator
+    pub account: AccountAddress,
+    /// validators are not paid the fullnode subsidy
+    pub is_validator: bool,
+    /// current epoch
+    pub epoch: u64,
+    /// fraction of the epoch elapsed
+    pub epoch_progress: f64,
+    /// proofs submitted in the epoch
+    pub proofs_in_epoch: u64,
+    /// proofs which must be submitted before any is paid
+    pub thres_lower: u64,
+    /// proofs accepted in the epoch
+    pub thres_upper: u64,
+    /// proofs of this account the subsidy will be paid for
+    pub proofs_paid_in_epoch: u64,
+    /// proofs of all fullnodes the subsidy is split by
+    pub global_proofs_paid_in_epoch: u64,
+    /// subsidy of one validator at the last epoch boundary, from the payment index
+    pub nominal_subsidy: Option<u64>,
+    /// price of a proof if the epoch ended now
+    pub proof_price: Option<u64>,
+    /// subsidy of this account if the epoch ended now
+    pub projected_subsidy: Option<u64>,
+    /// latest fullnode subsidy payouts, from the payment index
+    pub recent_payouts: Vec<PaymentRecord>,
+    /// sum of the latest fullnode subsidy payouts in the payment index, at most `MAX_PAYOUTS_SUMMED`
+    pub total_payouts: u64,
+}
+
+impl FullnodeView {
+    /// proofs which can still be submitted in the epoch
+    pub fn proofs_remaining(&self) -> u64 {
+        self.thres_upper.saturating_sub(self.proofs_in_epoch)
+    }
+
+    /// proofs to submit now to keep pace with the epoch.
+    /// The threshold is passed first, then the cap is spread over the epoch.
+    pub fn paced_allowance(&self) -> u64 {
+        let target = (self.thres_upper as f64 * self.epoch_progress).ceil() as u64;
+        let target = target.max(self.thres_lower + 1).min(self.thres_upper);
+        target.saturating_sub(self.proofs_in_epoch)
+    }
+}
+
+/// proofs paid by the epoch boundary, like `TowerState::get_count_above_thresh_in_epoch`
+pub fn proofs_paid(proofs_in_epoch: u64, thres_lower: u64) -> u64 {
+    proofs_in_epoch.saturating_sub(thres_lower)
+}
+
+/// price of a proof, like `FullnodeSubsidy::get_proof_price`
+pub fn proof_price(nominal_subsidy: u64, global_proofs_paid: u64) -> u64 {
+    if global_proofs_paid > 0 {
+        nominal_subsidy / global_proofs_paid
+    } else {
+        0
+    }
+}
+
+impl Node {
+    /// fullnode view of the owner account, kept in the vitals
+    pub fn refresh_fullnode_view(&mut self) -> Result<FullnodeView, Error> {
+        let view = self.get_fullnode_view(self.app_conf.profile.account)?;
+        self.vitals.fullnode_view = Some(view.clone());
+        Ok(view)
+    }
+
+    /// fullnode view of an account, with payouts if there is a payment index
+    pub fn get_fullnode_view(&self, account: AccountAddress) -> Result<FullnodeView, Error> {
+        let (blob, _version) = self.client.get_account_state_blob(&AccountAddress::ZERO)?;
+        let state = match blob {
+            Some(b) => AccountState::try_from(&b)?,
+            None => bail!("cannot get state of system account"),
+        };
+        let conf = match state.get_configuration_resource()? {
+            Some(c) => c,
+            None => bail!("cannot get configuration resource from chain"),
+        };
+        let chain_id = self.client.get_metadata()?.chain_id;
+        let thresholds = self.get_compliance_thresholds()?;
+        let counter = state
+            .get_resource::<TowerCounterResource>()?
+            .unwrap_or_default();
+        let is_validator = match state.get_validator_set()? {
+            Some(vs) => vs.payload().iter().any(|v| v.account_address() == &account),
+            None => false,
+        };
+        let proofs_in_epoch = match self.client.get_miner_state(&account)? {
+            Some(m) => m.actual_count_proofs_in_epoch,
+            None => 0,
+        };
+
+        let mut view = FullnodeView {
+            account,
+            is_validator,
+            epoch: conf.epoch(),
+            epoch_progress: epoch_progress(conf.last_reconfiguration_time(), chain_id),
+            proofs_in_epoch,
+            thres_lower: thresholds.epoch_mining_thres_lower,
+            thres_upper: thresholds.epoch_mining_thres_upper,
+            proofs_paid_in_epoch: proofs_paid(proofs_in_epoch, thresholds.epoch_mining_thres_lower),
+            global_proofs_paid_in_epoch: counter.fullnode_proofs_in_epoch_above_thresh,
+            ..FullnodeView::default()
+        };
+
+        // the index may be missing, or locked by `ol index`
+        if let Ok(index) = PaymentIndex::open_read_only(&self.payment_index_path()) {
+            view.nominal_subsidy = index
+                .payments(&PaymentFilter {
+                    memo: Some(format!("^{}$", VALIDATOR_SUBSIDY_MEMO)),
+                    limit: Some(1),
+                    ..PaymentFilter::default()
+                })?
+                .last()
+                .map(|p| p.amount);
+
+            let mut payouts = index.payments(&PaymentFilter {
+                receiver: Some(account),
+                memo: Some(format!("^{}$", FULLNODE_SUBSIDY_MEMO)),
+                limit: Some(MAX_PAYOUTS_SUMMED),
+                ..PaymentFilter::default()
+            })?;
+            view.total_payouts = payouts.iter().map(|p| p.amount).sum();
+            if payouts.len() > RECENT_PAYOUTS {
+                payouts.drain(..payouts.len() - RECENT_PAYOUTS);
+            }
+            view.recent_payouts = payouts;
+        }
+
+        if let Some(nominal) = view.nominal_subsidy {
+            let price = proof_price(nominal, view.global_proofs_paid_in_epoch);
+            view.proof_price = Some(price);
+            view.projected_subsidy = match view.is_validator {
+                true => Some(0),
+                false => Some(view.proofs_paid_in_epoch * price),
+            };
+        }
+        Ok(view)
+    }
+}
+
+#[test]
+fn test_paced_allowance() {
+    let mut view = FullnodeView {
+        thres_lower: 7,
+        thres_upper: 72,
+        ..FullnodeView::default()
+    };
+    // pass the threshold as soon as the epoch starts
+    assert_eq!(view.paced_allowance(), 8);
+
+    view.proofs_in_epoch = 8;
+    view.epoch_progress = 0.5;
+    assert_eq!(view.paced_allowance(), 28);
+    assert_eq!(proofs_paid(view.proofs_in_epoch, view.thres_lower), 1);
+
+    view.proofs_in_epoch = 72;
+    view.epoch_progress = 0.99;
+    assert_eq!(view.paced_allowance(), 0);
+    assert_eq!(view.proofs_remaining(), 0);
+}
@@ -4,9 +4,16 @@ pub mod autopay_view;
 pub mod chain_view;
 pub mod client;
 pub mod dictionary;
+pub mod explain_validator;
+pub mod fullnode_view;
+pub mod net_health;
 pub mod node;
+pub mod payment_index;
 pub mod query;
 pub mod refresh_peers;
+pub mod slow_wallet;
 pub mod states;
 pub mod sync;
+pub mod upstream_quorum;
+pub mod validator_analytics;
 // mod transitions;
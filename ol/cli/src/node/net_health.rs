@@ -0,0 +1,286 @@
+//! `net_health` peers, mempool, consensus and state sync health from the node's metrics
+
+use super::node::Node;
+use anyhow::Error;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// default port of the diem-metrics server, see `DebugInterfaceConfig`
+pub const DEFAULT_METRICS_PORT: u16 = 9101;
+
+/// minimum connected validator peers for a validator in the set
+pub const MIN_VALIDATOR_PEERS: u64 = 2;
+
+/// lag in versions under which state sync is considered caught up
+pub const SYNC_LAG_TOLERANCE: u64 = 1000;
+
+/// consecutive unhealthy checks before the pilot restarts the node
+pub const RESTART_AFTER_UNHEALTHY_CHECKS: u64 = 10;
+
+/// A sample of the node's Prometheus metrics
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct NodeMetrics {
+    /// unix timestamp of the sample
+    pub taken_at: i64,
+    /// connections on the validator network
+    pub validator_peers: u64,
+    /// connections on all networks
+    pub total_peers: u64,
+    /// transactions in the mempool
+    pub mempool_size: u64,
+    /// consensus round
+    pub current_round: u64,
+    /// last round committed by consensus
+    pub last_committed_round: u64,
+    /// last version committed by consensus
+    pub last_committed_version: u64,
+    /// version synced by state sync
+    pub sync_synced_version: u64,
+    /// highest version known to state sync
+    pub sync_highest_version: u64,
+}
+
+impl NodeMetrics {
+    /// parse the Prometheus text format served on `/metrics`
+    pub fn from_prometheus_text(text: &str) -> Self {
+        let mut m = NodeMetrics {
+            taken_at: Utc::now().timestamp(),
+            ..NodeMetrics::default()
+        };
+
+        for line in text.lines().filter(|l| !l.starts_with('#')) {
+            let (name, labels, value) = match parse_line(line) {
+                Some(p) => p,
+                None => continue,
+            };
+            match name {
+                "diem_connections" => {
+                    m.total_peers += value;
+                    if labels.contains("network_id=\"Validator\"") {
+                        m.validator_peers += value;
+                    }
+                }
+                // the priority index holds every transaction ready for consensus
+                "diem_core_mempool_index_size" if labels.contains("index=\"priority\"") => {
+                    m.mempool_size = value
+                }
+                "diem_consensus_current_round" => m.current_round = value,
+                "diem_consensus_last_committed_round" => m.last_committed_round = value,
+                "diem_consensus_last_committed_version" => m.last_committed_version = value,
+                "diem_state_sync_version" if labels.contains("type=\"synced\"") => {
+                    m.sync_synced_version = value
+                }
+                "diem_state_sync_version" if labels.contains("type=\"highest\"") => {
+                    m.sync_highest_version = value
+                }
+                _ => {}
+            }
+        }
+        m
+    }
+
+    /// versions state sync is behind the highest known version
+    pub fn sync_lag(&self) -> u64 {
+        self.sync_highest_version
+            .saturating_sub(self.sync_synced_version)
+    }
+}
+
+/// split `name{labels} value` into its parts
+fn parse_line(line: &str) -> Option<(&str, &str, u64)> {
+    let mut parts = line.rsplitn(2, ' ');
+    let value = parts.next()?.parse::<f64>().ok()?;
+    let key = parts.next()?;
+    let (name, labels) = match key.find('{') {
+        Some(i) => (&key[..i], &key[i..]),
+        None => (key, ""),
+    };
+    Some((name, labels, value as u64))
+}
+
+/// Latest and previous metrics samples, to evaluate trends
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct NetHealth {
+    /// latest sample
+    pub current: NodeMetrics,
+    /// previous sample
+    pub previous: Option<NodeMetrics>,
+    /// whether the latest attempt to sample the metrics succeeded, the samples are stale otherwise
+    pub metrics_available: bool,
+    /// how many pilot checks in a row were unhealthy
+    pub consecutive_unhealthy: u64,
+}
+
+impl NetHealth {
+    /// enough validator peers, only required when in the validator set
+    pub fn peers_connected(&self, in_validator_set: bool) -> bool {
+        if in_validator_set {
+            self.current.validator_peers >= MIN_VALIDATOR_PEERS
+        } else {
+            self.current.total_peers > 0
+        }
+    }
+
+    /// mempool is not growing while nothing gets committed
+    pub fn mempool_flowing(&self) -> bool {
+        match &self.previous {
+            Some(p) => {
+                !(self.current.mempool_size > p.mempool_size
+                    && self.current.last_committed_version <= p.last_committed_version)
+            }
+            None => true,
+        }
+    }
+
+    /// consensus rounds advance, only meaningful in the validator set
+    pub fn consensus_progressing(&self, in_validator_set: bool) -> bool {
+        if !in_validator_set {
+            return true;
+        }
+        match &self.previous {
+            Some(p) => self.current.current_round > p.current_round,
+            None => true,
+        }
+    }
+
+    /// state sync is caught up, or the lag is shrinking
+    pub fn sync_catching_up(&self) -> bool {
+        let lag = self.current.sync_lag();
+        if lag < SYNC_LAG_TOLERANCE {
+            return true;
+        }
+        match &self.previous {
+            Some(p) => lag < p.sync_lag(),
+            None => true,
+        }
+    }
+
+    /// all network checks pass
+    pub fn is_healthy(&self, in_validator_set: bool) -> bool {
+        self.peers_connected(in_validator_set)
+            && self.mempool_flowing()
+            && self.consensus_progressing(in_validator_set)
+            && self.sync_catching_up()
+    }
+
+    /// count a pilot check towards a restart. Health is unknown without metrics, which never
+    /// counts as unhealthy.
+    pub fn count_check(&mut self, node_running: bool, in_validator_set: bool) {
+        if !self.metrics_available {
+            return;
+        }
+        if !node_running || self.is_healthy(in_validator_set) {
+            self.consecutive_unhealthy = 0;
+        } else {
+            self.consecutive_unhealthy += 1;
+        }
+    }
+}
+
+impl Node {
+    /// fetch the node's Prometheus metrics
+    pub fn get_node_metrics(&self) -> Result<NodeMetrics, Error> {
+        let port = match &self.node_conf {
+            Some(c) => c.debug_interface.metrics_server_port,
+            None => DEFAULT_METRICS_PORT,
+        };
+        let url = format!("http://127.0.0.1:{}/metrics", port);
+        let text = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()?
+            .get(&url)
+            .send()?
+            .text()?;
+        Ok(NodeMetrics::from_prometheus_text(&text))
+    }
+
+    /// sample the metrics and update the network health items. Only the metrics of the local node
+    /// are used, so the items are unknown if they can't be read.
+    pub fn refresh_net_health(&mut self) -> &mut Self {
+        let in_set = self.vitals.items.validator_set;
+
+        let mut health = self.vitals.net_health.take().unwrap_or_default();
+        match self.get_node_metrics() {
+            Ok(current) => {
+                health.previous = Some(std::mem::replace(&mut health.current, current));
+                health.metrics_available = true;
+            }
+            Err(_) => health.metrics_available = false,
+        }
+
+        let known = health.metrics_available;
+        self.vitals.items.net_health_known = known;
+        self.vitals.items.validator_peers = health.current.validator_peers;
+        self.vitals.items.peers_connected = known && health.peers_connected(in_set);
+        self.vitals.items.mempool_size = health.current.mempool_size;
+        self.vitals.items.mempool_flowing = known && health.mempool_flowing();
+        self.vitals.items.consensus_progressing = known && health.consensus_progressing(in_set);
+        self.vitals.items.sync_catching_up = known && health.sync_catching_up();
+        self.vitals.net_health = Some(health);
+        self
+    }
+}
+
+#[test]
+fn test_parse_prometheus_text() {
+    let text = r#"
+# HELP diem_connections Number of current connections and their direction
+# TYPE diem_connections gauge
+diem_connections{direction="inbound",network_id="Validator",peer_id="a",role_type="validator"} 2
+diem_connections{direction="outbound",network_id="Validator",peer_id="a",role_type="validator"} 1
+diem_connections{direction="inbound",network_id="Public",peer_id="b",role_type="validator"} 4
+diem_core_mempool_index_size{index="priority"} 12
+diem_core_mempool_index_size{index="system_ttl"} 30
+diem_consensus_current_round 1234
+diem_consensus_last_committed_version 5678
+diem_state_sync_version{type="highest"} 9000
+diem_state_sync_version{type="synced"} 6000
+"#;
+    let m = NodeMetrics::from_prometheus_text(text);
+    assert_eq!(m.validator_peers, 3);
+    assert_eq!(m.total_peers, 7);
+    assert_eq!(m.mempool_size, 12);
+    assert_eq!(m.current_round, 1234);
+    assert_eq!(m.last_committed_version, 5678);
+    assert_eq!(m.sync_lag(), 3000);
+}
+
+#[test]
+fn test_mempool_growing_without_commits() {
+    let previous = NodeMetrics {
+        mempool_size: 10,
+        last_committed_version: 100,
+        current_round: 5,
+        ..NodeMetrics::default()
+    };
+    let health = NetHealth {
+        current: NodeMetrics {
+            mempool_size: 20,
+            ..previous.clone()
+        },
+        previous: Some(previous),
+        metrics_available: true,
+        consecutive_unhealthy: 0,
+    };
+    assert!(!health.mempool_flowing());
+    assert!(!health.consensus_progressing(true));
+    assert!(health.consensus_progressing(false));
+}
+
+#[test]
+fn test_unknown_health_is_not_counted() {
+    let mut health = NetHealth::default();
+    for _ in 0..RESTART_AFTER_UNHEALTHY_CHECKS {
+        health.count_check(true, true);
+    }
+    assert_eq!(health.consecutive_unhealthy, 0);
+
+    // no validator peers in the set
+    health.metrics_available = true;
+    health.count_check(true, true);
+    assert_eq!(health.consecutive_unhealthy, 1);
+    health.metrics_available = false;
+    health.count_check(true, true);
+    assert_eq!(health.consecutive_unhealthy, 1);
+}
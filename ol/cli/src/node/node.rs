@@ -81,6 +81,8 @@ impl Node {
                 node_proc: None,
                 miner_proc: None,
                 monitor_proc: None,
+                net_health: None,
+                fullnode_view: None,
             },
             miner_state: None,
             chain_state: None,
@@ -120,6 +122,7 @@ impl Node {
         self.vitals.items.has_operator_set = self.vitals.account_view.has_operator();
         self.vitals.items.has_operator_positive_balance =
             self.vitals.account_view.has_operator_positive_balance();
+        self.refresh_net_health();
         self
     }
 
@@ -0,0 +1,413 @@
+//! `payment_index` local index of payment events, memos and receipts, for filtered queries
+
+use super::{node::Node, query::find_value_from_state, validator_analytics::addresses_from_value};
+use anyhow::{bail, Error};
+use diem_json_rpc_client::views::{BytesView, EventDataView, TransactionView};
+use diem_types::account_address::AccountAddress;
+use regex::Regex;
+use resource_viewer::{AnnotatedAccountStateBlob, AnnotatedMoveValue};
+use rocksdb::{Direction, IteratorMode, Options, WriteBatch, DB};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    convert::TryInto,
+    path::{Path, PathBuf},
+};
+
+/// directory of the index, in node_home
+pub const PAYMENT_INDEX_DIR: &str = "payment_index";
+
+/// transactions fetched per request while indexing
+pub const INDEX_BATCH: u64 = 500;
+
+/// payments returned by a query without a limit
+pub const DEFAULT_QUERY_LIMIT: usize = 100;
+
+const LAST_VERSION_KEY: &[u8] = b"m/last_version";
+const PAYMENT_PREFIX: &[u8] = b"p/";
+const ACCOUNT_PREFIX: &[u8] = b"a/";
+const RECEIPT_PREFIX: &[u8] = b"r/";
+
+/// A payment, from its ReceivedPayment event
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct PaymentRecord {
+    /// version of the transaction
+    pub version: u64,
+    /// account which paid
+    pub sender: AccountAddress,
+    /// account which was paid
+    pub receiver: AccountAddress,
+    /// amount, unscaled
+    pub amount: u64,
+    /// currency code
+    pub currency: String,
+    /// metadata as text, e.g. the memo of a community wallet payment
+    pub memo: String,
+    /// metadata as hex
+    pub metadata: String,
+}
+
+/// The last payment from a payer to a destination, from `Receipts::UserReceipts`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ReceiptRecord {
+    /// account which paid
+    pub payer: AccountAddress,
+    /// account which was paid
+    pub destination: AccountAddress,
+    /// sum of all payments with a receipt
+    pub cumulative: u64,
+    /// time of the last payment, in seconds
+    pub last_payment_timestamp: u64,
+    /// value of the last payment
+    pub last_payment_value: u64,
+    /// version the receipt was read at
+    pub version: u64,
+}
+
+/// Filters of a payment query, all optional
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct PaymentFilter {
+    /// account which paid
+    pub sender: Option<AccountAddress>,
+    /// account which was paid
+    pub receiver: Option<AccountAddress>,
+    /// regex the memo must match
+    pub memo: Option<String>,
+    /// earliest version, the latest payments are returned without one
+    pub from_version: Option<u64>,
+    /// max payments returned
+    pub limit: Option<usize>,
+}
+
+impl PaymentFilter {
+    /// does the payment pass the filter, with the memo regex compiled
+    pub fn matches(&self, p: &PaymentRecord, memo: Option<&Regex>) -> bool {
+        self.sender.map_or(true, |s| s == p.sender)
+            && self.receiver.map_or(true, |r| r == p.receiver)
+            && self.from_version.map_or(true, |v| p.version >= v)
+            && memo.map_or(true, |re| re.is_match(&p.memo))
+    }
+}
+
+/// Payments, memos and receipts in an embedded rocksdb
+pub struct PaymentIndex {
+    db: DB,
+}
+
+impl PaymentIndex {
+    /// open the index for writing, creating it if missing
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        Ok(PaymentIndex {
+            db: DB::open(&opts, path)?,
+        })
+    }
+
+    /// open the index for queries, while an indexer may be writing to it
+    pub fn open_read_only(path: &Path) -> Result<Self, Error> {
+        if !path.exists() {
+            bail!("no payment index at {:?}, run `ol index` first", path);
+        }
+        Ok(PaymentIndex {
+            db: DB::open_for_read_only(&Options::default(), path, false)?,
+        })
+    }
+
+    /// last version indexed, if any
+    pub fn last_version(&self) -> Result<Option<u64>, Error> {
+        Ok(self.db.get(LAST_VERSION_KEY)?.map(|b| decode_u64(&b)))
+    }
+
+    /// store the payments of a batch of transactions, and the last version indexed
+    pub fn put_payments(&self, payments: &[PaymentRecord], last_version: u64) -> Result<(), Error> {
+        let mut batch = WriteBatch::default();
+        for (i, p) in payments.iter().enumerate() {
+            let key = payment_key(p.version, i as u32);
+            batch.put(&key, serde_json::to_vec(p)?);
+            batch.put(account_key(&p.sender, &key), &key);
+            if p.receiver != p.sender {
+                batch.put(account_key(&p.receiver, &key), &key);
+            }
+        }
+        batch.put(LAST_VERSION_KEY, last_version.to_be_bytes());
+        self.db.write(batch)?;
+        Ok(())
+    }
+
+    /// store the receipts of a payer, replacing older ones
+    pub fn put_receipts(&self, receipts: &[ReceiptRecord]) -> Result<(), Error> {
+        let mut batch = WriteBatch::default();
+        for r in receipts.iter() {
+            let mut key = receipt_prefix(&r.payer);
+            key.extend_from_slice(r.destination.as_ref());
+            batch.put(key, serde_json::to_vec(r)?);
+        }
+        self.db.write(batch)?;
+        Ok(())
+    }
+
+    /// payments matching the filter, in version order. Without a starting version, the latest
+    /// ones, found walking back from the end of the index.
+    pub fn payments(&self, filter: &PaymentFilter) -> Result<Vec<PaymentRecord>, Error> {
+        let memo = match &filter.memo {
+            Some(m) => Some(Regex::new(m)?),
+            None => None,
+        };
+        let limit = filter.limit.unwrap_or(DEFAULT_QUERY_LIMIT);
+
+        // with an account, walk only that account's payments
+        let account = filter.sender.or(filter.receiver);
+        let bound = |version, i| match account {
+            Some(a) => account_key(&a, &payment_key(version, i)),
+            None => payment_key(version, i),
+        };
+        let prefix = match account {
+            Some(a) => account_prefix(&a),
+            None => PAYMENT_PREFIX.to_vec(),
+        };
+        let (start, direction) = match filter.from_version {
+            Some(from) => (bound(from, 0), Direction::Forward),
+            None => (bound(u64::MAX, u32::MAX), Direction::Reverse),
+        };
+
+        let mut found = vec![];
+        for (key, value) in self.db.iterator(IteratorMode::From(&start, direction)) {
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            let bytes = match account {
+                Some(_) => match self.db.get(&value)? {
+                    Some(b) => b,
+                    None => continue,
+                },
+                None => value.to_vec(),
+            };
+            let p: PaymentRecord = serde_json::from_slice(&bytes)?;
+            if filter.matches(&p, memo.as_ref()) {
+                found.push(p);
+                if found.len() >= limit {
+                    break;
+                }
+            }
+        }
+        if filter.from_version.is_none() {
+            found.reverse();
+        }
+        Ok(found)
+    }
+
+    /// receipts of a payer, by destination
+    pub fn receipts(&self, payer: &AccountAddress) -> Result<Vec<ReceiptRecord>, Error> {
+        let prefix = receipt_prefix(payer);
+        let mut receipts = vec![];
+        for (key, value) in self
+            .db
+            .iterator(IteratorMode::From(&prefix, Direction::Forward))
+        {
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            receipts.push(serde_json::from_slice(&value)?);
+        }
+        Ok(receipts)
+    }
+}
+
+/// the payments of a transaction, from its ReceivedPayment events
+pub fn payments_from_tx(tx: &TransactionView) -> Vec<PaymentRecord> {
+    tx.events
+        .iter()
+        .filter_map(|e| match &e.data {
+            // each payment emits a sent and a received event, keep one
+            EventDataView::ReceivedPayment {
+                amount,
+                sender,
+                receiver,
+                metadata: BytesView(m),
+            } => {
+                let bytes = hex::decode(m).unwrap_or_default();
+                Some(PaymentRecord {
+                    version: tx.version,
+                    sender: *sender,
+                    receiver: *receiver,
+                    amount: amount.amount,
+                    currency: amount.currency.clone(),
+                    memo: String::from_utf8_lossy(&bytes).to_string(),
+                    metadata: m.clone(),
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// the receipts in a payer's `Receipts::UserReceipts`
+pub fn receipts_from_state(
+    blob: &AnnotatedAccountStateBlob,
+    payer: AccountAddress,
+    version: u64,
+) -> Vec<ReceiptRecord> {
+    let field = |name: &str| {
+        find_value_from_state(
+            blob,
+            "Receipts".to_string(),
+            "UserReceipts".to_string(),
+            name.to_string(),
+        )
+    };
+    let destinations = addresses_from_value(field("destination"));
+    let cumulative = u64s_from_value(field("cumulative"));
+    let timestamps = u64s_from_value(field("last_payment_timestamp"));
+    let values = u64s_from_value(field("last_payment_value"));
+
+    destinations
+        .into_iter()
+        .enumerate()
+        .map(|(i, destination)| ReceiptRecord {
+            payer,
+            destination,
+            cumulative: cumulative.get(i).cloned().unwrap_or_default(),
+            last_payment_timestamp: timestamps.get(i).cloned().unwrap_or_default(),
+            last_payment_value: values.get(i).cloned().unwrap_or_default(),
+            version,
+        })
+        .collect()
+}
+
+impl Node {
+    /// path of the payment index
+    pub fn payment_index_path(&self) -> PathBuf {
+        self.app_conf.workspace.node_home.join(PAYMENT_INDEX_DIR)
+    }
+
+    /// index the transactions after the last indexed version, up to the chain tip.
+    /// Returns the last version indexed.
+    pub fn sync_payment_index(&self, index: &PaymentIndex) -> Result<Option<u64>, Error> {
+        let mut last = index.last_version()?;
+        loop {
+            let start = last.map_or(0, |v| v + 1);
+            let txs = self.client.get_txn_by_range(start, INDEX_BATCH, true)?;
+            let tip = match txs.last() {
+                Some(t) => t.version,
+                None => break,
+            };
+
+            let payments: Vec<PaymentRecord> = txs.iter().flat_map(payments_from_tx).collect();
+            index.put_payments(&payments, tip)?;
+
+            // receipts are state, read them again for the payers of the batch
+            let payers: BTreeSet<AccountAddress> = payments.iter().map(|p| p.sender).collect();
+            for payer in payers {
+                if let (Some(blob), version) = self.get_annotate_account_blob(payer)? {
+                    index.put_receipts(&receipts_from_state(&blob, payer, version))?;
+                }
+            }
+
+            last = Some(tip);
+            if (txs.len() as u64) < INDEX_BATCH {
+                break;
+            }
+        }
+        Ok(last)
+    }
+}
+
+fn payment_key(version: u64, i: u32) -> Vec<u8> {
+    let mut key = PAYMENT_PREFIX.to_vec();
+    key.extend_from_slice(&version.to_be_bytes());
+    key.extend_from_slice(&i.to_be_bytes());
+    key
+}
+
+fn account_prefix(account: &AccountAddress) -> Vec<u8> {
+    let mut key = ACCOUNT_PREFIX.to_vec();
+    key.extend_from_slice(account.as_ref());
+    key
+}
+
+fn account_key(account: &AccountAddress, payment_key: &[u8]) -> Vec<u8> {
+    let mut key = account_prefix(account);
+    key.extend_from_slice(payment_key);
+    key
+}
+
+fn receipt_prefix(payer: &AccountAddress) -> Vec<u8> {
+    let mut key = RECEIPT_PREFIX.to_vec();
+    key.extend_from_slice(payer.as_ref());
+    key
+}
+
+fn decode_u64(bytes: &[u8]) -> u64 {
+    u64::from_be_bytes(bytes.try_into().unwrap_or_default())
+}
+
+fn u64s_from_value(value: Option<&AnnotatedMoveValue>) -> Vec<u64> {
+    match value {
+        Some(AnnotatedMoveValue::Vector(_, vec)) => vec
+            .iter()
+            .filter_map(|v| match v {
+                AnnotatedMoveValue::U64(n) => Some(*n),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+#[test]
+fn test_payment_filter() {
+    let dir = diem_temppath::TempPath::new();
+    dir.create_as_dir().unwrap();
+    let index = PaymentIndex::open(dir.path()).unwrap();
+    let alice = AccountAddress::random();
+    let bob = AccountAddress::random();
+    let pay = |version, sender, receiver, memo: &str| PaymentRecord {
+        version,
+        sender,
+        receiver,
+        amount: 1,
+        currency: "GAS".to_owned(),
+        memo: memo.to_owned(),
+        metadata: hex::encode(memo),
+    };
+    index
+        .put_payments(
+            &[
+                pay(1, alice, bob, "rent"),
+                pay(2, bob, alice, "refund"),
+                pay(3, alice, bob, "grant: docs"),
+            ],
+            3,
+        )
+        .unwrap();
+    assert_eq!(index.last_version().unwrap(), Some(3));
+
+    let mut filter = PaymentFilter {
+        sender: Some(alice),
+        receiver: Some(bob),
+        ..Default::default()
+    };
+    assert_eq!(index.payments(&filter).unwrap().len(), 2);
+    filter.memo = Some("^grant".to_owned());
+    let found = index.payments(&filter).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].version, 3);
+
+    // the latest payments, when there is no starting version
+    let latest = PaymentFilter {
+        limit: Some(1),
+        ..Default::default()
+    };
+    assert_eq!(index.payments(&latest).unwrap()[0].version, 3);
+    let latest_of_alice = PaymentFilter {
+        sender: Some(alice),
+        limit: Some(2),
+        ..Default::default()
+    };
+    let found = index.payments(&latest_of_alice).unwrap();
+    assert_eq!(
+        found.iter().map(|p| p.version).collect::<Vec<_>>(),
+        vec![1, 3]
+    );
+}
@@ -1,7 +1,10 @@
 //! 'query'
 use std::collections::BTreeMap;
 
-use super::node::Node;
+use super::{
+    node::Node,
+    payment_index::{PaymentFilter, PaymentIndex},
+};
 use anyhow::Error;
 use diem_json_rpc_client::{
     views::{BytesView, EventView, TransactionView},
@@ -79,6 +82,18 @@ pub enum QueryType {
         /// the account of the validator
         account: AccountAddress,
     },
+    /// Validator set churn, jailing risk and vouches, from the epoch snapshots
+    ValidatorAnalytics,
+    /// Payments in the local payment index
+    Payments {
+        /// sender, receiver and memo filters
+        filter: PaymentFilter,
+    },
+    /// Receipts of a payer in the local payment index
+    Receipts {
+        /// the payer
+        account: AccountAddress,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -148,10 +163,23 @@ impl Node {
                 )
             }
             SyncDelay => match self.check_sync() {
-                Ok(sync) => format!(
-                    "is synced: {}, local height: {}, upstream delay: {}",
-                    sync.is_synced, sync.sync_height, sync.sync_delay
-                ),
+                Ok(sync) => {
+                    let mut print = format!(
+                        "is synced: {}, local height: {}, upstream delay: {}",
+                        sync.is_synced, sync.sync_height, sync.sync_delay
+                    );
+                    if let Some(q) = sync.upstream_quorum {
+                        print.push_str(&format!(
+                            "\nupstream quorum height: {}, agreeing: {}, disagreeing: {:?}, failed: {:?}, flagged: {:?}",
+                            q.height,
+                            q.agreeing.len(),
+                            q.disagreeing,
+                            q.failed,
+                            q.flagged
+                        ));
+                    }
+                    print
+                }
                 Err(e) => e.to_string(),
             },
             Resources { account } => {
@@ -292,6 +320,19 @@ impl Node {
                     Err(_) => format!("No validator configs cound at: {}", account),
                 }
             }
+            ValidatorAnalytics => {
+                self.refresh_chain_info()?;
+                let analytics = self.refresh_validator_analytics()?;
+                serde_json::to_string_pretty(&analytics)?
+            }
+            Payments { filter } => {
+                let index = PaymentIndex::open_read_only(&self.payment_index_path())?;
+                serde_json::to_string_pretty(&index.payments(&filter)?)?
+            }
+            Receipts { account } => {
+                let index = PaymentIndex::open_read_only(&self.payment_index_path())?;
+                serde_json::to_string_pretty(&index.receipts(&account)?)?
+            }
         };
         Ok(print)
     }
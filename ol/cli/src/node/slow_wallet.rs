@@ -0,0 +1,193 @@
+//! `slow_wallet` unlocked balance and unlock schedule of slow wallets
+
+use super::{node::Node, query};
+use anyhow::{bail, Error};
+use diem_types::account_address::AccountAddress;
+use resource_viewer::AnnotatedMoveValue;
+use serde::{Deserialize, Serialize};
+
+/// coins on chain are scaled by this factor
+pub const COIN_SCALING_FACTOR: u64 = 1_000_000;
+
+/// most epochs a split transfer may be spread over
+pub const MAX_SPLIT_EPOCHS: u64 = 365;
+
+/// Slow wallet state of an account, all values scaled
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SlowWalletView {
+    /// amount which can be transferred now
+    pub unlocked: u64,
+    /// amount transferred so far
+    pub transferred: u64,
+    /// GAS balance of the account
+    pub balance: u64,
+}
+
+/// Amount unlocked at a future epoch
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct UnlockStep {
+    /// epoch
+    pub epoch: u64,
+    /// unlocked amount spendable in that epoch, scaled
+    pub unlocked: u64,
+}
+
+/// Part of a transfer to send in an epoch
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct TransferTranche {
+    /// epoch in which the tranche can be sent
+    pub epoch: u64,
+    /// coins, unscaled like the transfer script expects
+    pub coins: u64,
+}
+
+impl SlowWalletView {
+    /// can the coins be sent now. `pay_from` requires the amount to be strictly below the unlocked amount.
+    pub fn can_transfer(&self, coins: u64) -> bool {
+        let scaled = coins.saturating_mul(COIN_SCALING_FACTOR);
+        scaled < self.unlocked && scaled <= self.balance
+    }
+
+    /// unlocked amount for the next epochs, with the drip of each epoch boundary.
+    /// The unlocked amount can exceed the balance, so it is capped.
+    pub fn unlock_schedule(
+        &self,
+        current_epoch: u64,
+        per_epoch: u64,
+        epochs: u64,
+    ) -> Vec<UnlockStep> {
+        (0..=epochs)
+            .map(|k| UnlockStep {
+                epoch: current_epoch + k,
+                unlocked: self
+                    .unlocked
+                    .saturating_add(per_epoch.saturating_mul(k))
+                    .min(self.balance),
+            })
+            .collect()
+    }
+
+    /// split the coins into the largest tranches allowed by each epoch's unlocked amount
+    pub fn plan_split(
+        &self,
+        coins: u64,
+        current_epoch: u64,
+        per_epoch: u64,
+    ) -> Result<Vec<TransferTranche>, Error> {
+        if coins.saturating_mul(COIN_SCALING_FACTOR) > self.balance {
+            bail!(
+                "cannot transfer {} coins, the balance is only {}",
+                coins,
+                self.balance / COIN_SCALING_FACTOR
+            );
+        }
+
+        let mut tranches = vec![];
+        let mut remaining = coins;
+        let mut available = self.unlocked;
+        for k in 0..=MAX_SPLIT_EPOCHS {
+            if k > 0 {
+                available = available.saturating_add(per_epoch);
+            }
+            // strictly below the unlocked amount, in whole coins
+            let max_coins = available.saturating_sub(1) / COIN_SCALING_FACTOR;
+            let c = max_coins.min(remaining);
+            if c > 0 {
+                tranches.push(TransferTranche {
+                    epoch: current_epoch + k,
+                    coins: c,
+                });
+                available -= c * COIN_SCALING_FACTOR;
+                remaining -= c;
+            }
+            if remaining == 0 {
+                return Ok(tranches);
+            }
+        }
+        bail!(
+            "{} coins would take more than {} epochs to unlock",
+            coins,
+            MAX_SPLIT_EPOCHS
+        )
+    }
+}
+
+impl Node {
+    /// amount a slow wallet unlocks each epoch, `Globals::get_unlock` of the chain, scaled.
+    /// Testnet, staging and mainnet are told apart like the other thresholds.
+    pub fn get_epoch_unlock(&self) -> Result<u64, Error> {
+        Ok(self.get_compliance_thresholds()?.epoch_slow_wallet_unlock)
+    }
+
+    /// slow wallet state of an account, None if it is not a slow wallet
+    pub fn get_slow_wallet(
+        &self,
+        address: AccountAddress,
+    ) -> Result<Option<SlowWalletView>, Error> {
+        let blob = match self.get_annotate_account_blob(address)? {
+            (Some(b), _) => b,
+            _ => bail!("cannot find account state for {}", address),
+        };
+        if !query::is_slow_wallet(&blob) {
+            return Ok(None);
+        }
+
+        let get = |key: &str| match query::find_value_from_state(
+            &blob,
+            "DiemAccount".to_string(),
+            "SlowWallet".to_string(),
+            key.to_string(),
+        ) {
+            Some(AnnotatedMoveValue::U64(v)) => *v,
+            _ => 0,
+        };
+        let balance = match self.client.get_account(&address)? {
+            Some(a) => a
+                .balances
+                .iter()
+                .find(|b| b.currency == "GAS")
+                .map(|b| b.amount)
+                .unwrap_or(0),
+            None => 0,
+        };
+
+        Ok(Some(SlowWalletView {
+            unlocked: get("unlocked"),
+            transferred: get("transferred"),
+            balance,
+        }))
+    }
+}
+
+#[test]
+fn test_plan_split() {
+    let wallet = SlowWalletView {
+        unlocked: 1500 * COIN_SCALING_FACTOR,
+        transferred: 0,
+        balance: 5000 * COIN_SCALING_FACTOR,
+    };
+    assert!(wallet.can_transfer(1499));
+    assert!(!wallet.can_transfer(1500));
+
+    let plan = wallet
+        .plan_split(3000, 10, 1000 * COIN_SCALING_FACTOR)
+        .unwrap();
+    assert_eq!(
+        plan,
+        vec![
+            TransferTranche {
+                epoch: 10,
+                coins: 1499
+            },
+            TransferTranche {
+                epoch: 11,
+                coins: 1000
+            },
+            TransferTranche {
+                epoch: 12,
+                coins: 501
+            },
+        ]
+    );
+    assert!(wallet.plan_split(6000, 10, COIN_SCALING_FACTOR).is_err());
+}
@@ -1,7 +1,9 @@
 //! `sync` subcommand
 
-use super::node::Node;
-use crate::node::client::*;
+use super::{
+    node::Node,
+    upstream_quorum::{upstream_quorum, UpstreamQuorum},
+};
 use anyhow::{bail, Error};
 use backup_cli::utils::backup_service_client::{BackupServiceClient, BackupServiceClientOpt};
 use diemdb::backup::backup_handler::DbState;
@@ -18,6 +20,8 @@ pub struct SyncState {
     pub remote_height: u64,
     /// delay in blocks between remote and local
     pub sync_delay: i64,
+    /// upstreams used to find the remote height
+    pub upstream_quorum: Option<UpstreamQuorum>,
 }
 
 impl Default for SyncState {
@@ -27,6 +31,7 @@ impl Default for SyncState {
             sync_height: 0,
             remote_height: 0,
             sync_delay: 0,
+            upstream_quorum: None,
         }
     }
 }
@@ -42,21 +47,27 @@ impl Node {
                 sync_height: 0,
                 remote_height: 0,
                 sync_delay: 404,
+                upstream_quorum: None,
             });
         }
         // let config = &self.app_conf;
         let waypoint = &self.waypoint()?;
 
-        let remote_client = find_a_remote_jsonrpc(&self.app_conf, *waypoint).map_err(|e| {
-            println!("cannot connect to upstream node");
+        // a single upstream may be stale or lying, use the verified median of all of them
+        let quorum = upstream_quorum(&self.app_conf, *waypoint).map_err(|e| {
+            println!("cannot get a verified height from upstream nodes");
             e
         })?;
+        if !quorum.flagged.is_empty() {
+            println!(
+                "WARN: upstream nodes repeatedly disagree with the others: {:?}",
+                quorum.flagged
+            );
+        }
 
         let local_db = self.get_db_state()?;
-        s.remote_height = match remote_client.get_metadata() {
-            Ok(m) => m.version,
-            Err(_) => 404,
-        };
+        s.remote_height = quorum.height;
+        s.upstream_quorum = Some(quorum);
         s.sync_height = local_db.synced_version;
         s.sync_delay = s.remote_height as i64 - s.sync_height as i64;
         s.is_synced = s.sync_delay < 1000;
@@ -71,11 +82,25 @@ impl Node {
     pub fn get_db_state(&self) -> Result<DbState, Error> {
         // if is swarm need to get the backup_service_address: "127.0.0.1:44867" from the NodeConfig in swarm_temp/0/node.yaml
         if let Some(cfg) = &self.node_conf {
-            let url_string = format!("http://{}", cfg.storage.backup_service_address);
+            let service_config = &cfg.storage.backup_service;
+            // The certificate of the TLS listener doesn't name the IP address the node would
+            // query it by, so the node uses the plain local listener instead.
+            let address = match service_config.local_address {
+                Some(local_address) => local_address,
+                None if service_config.tls_cert_path.is_some() => bail!(
+                    "backup service is TLS only, set storage.backup_service.local_address in node.yaml"
+                ),
+                None => cfg.storage.backup_service_address,
+            };
             let bk = BackupServiceClientOpt {
-                address: url_string,
+                address: format!("http://{}", address),
+                // Any configured client will do for the node to query itself.
+                token: service_config.auth_tokens.values().next().cloned(),
+                ca_cert: None,
+                client_cert: None,
+                client_key: None,
             };
-            let client = BackupServiceClient::new_with_opt(bk);
+            let client = BackupServiceClient::new_with_opt(bk)?;
 
             let rt = Runtime::new().unwrap();
             match rt.block_on(client.get_db_state())? {
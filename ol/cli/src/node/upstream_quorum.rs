@@ -0,0 +1,261 @@
+//! `upstream_quorum` verified height from several upstream nodes
+
+use super::client::make_client;
+use crate::config::AppCfg;
+use anyhow::{bail, Error};
+use diem_types::waypoint::Waypoint;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::Write,
+    path::PathBuf,
+    thread,
+};
+
+/// filename for the record of upstreams disagreeing with the quorum
+pub const UPSTREAM_RECORD_NAME: &str = "upstream_peers.json";
+
+/// versions an upstream may differ from the median before it is an outlier
+pub const OUTLIER_TOLERANCE: u64 = 1000;
+
+/// consecutive disagreements before an upstream is flagged
+pub const FLAG_AFTER_DISAGREEMENTS: u64 = 3;
+
+/// Response of one upstream node
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UpstreamResponse {
+    /// url of the upstream
+    pub url: Url,
+    /// ledger version verified with the state proof, None if it failed
+    pub verified_version: Option<u64>,
+    /// error returned by the upstream or the verification
+    pub error: Option<String>,
+}
+
+/// Result of querying all upstream nodes
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UpstreamQuorum {
+    /// median of the verified versions, outliers excluded
+    pub height: u64,
+    /// upstreams within the tolerance of the median
+    pub agreeing: Vec<Url>,
+    /// upstreams which verified but are outliers
+    pub disagreeing: Vec<Url>,
+    /// upstreams which failed verification or did not answer
+    pub failed: Vec<Url>,
+    /// upstreams which repeatedly disagree with the quorum
+    pub flagged: Vec<Url>,
+}
+
+/// Consecutive disagreements of each upstream, persisted in node home
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct UpstreamRecord {
+    /// consecutive disagreements by url
+    pub disagreements: BTreeMap<String, u64>,
+}
+
+impl UpstreamRecord {
+    /// read the record, or start an empty one
+    pub fn read_json(node_home: &PathBuf) -> UpstreamRecord {
+        match fs::File::open(node_home.join(UPSTREAM_RECORD_NAME)) {
+            Ok(file) => serde_json::from_reader(file).unwrap_or_default(),
+            Err(_) => UpstreamRecord::default(),
+        }
+    }
+
+    /// write the record to a temporary file and rename it, so a crash never leaves it truncated
+    pub fn write_json(&self, node_home: &PathBuf) -> Result<(), Error> {
+        let path = node_home.join(UPSTREAM_RECORD_NAME);
+        let tmp_path = path.with_extension("json.tmp");
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&serde_json::to_vec(&self)?)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    /// count a disagreement, or reset the count when the upstream agrees
+    pub fn update(&mut self, url: &Url, agrees: bool) {
+        let count = self.disagreements.entry(url.to_string()).or_insert(0);
+        if agrees {
+            *count = 0;
+        } else {
+            *count += 1;
+        }
+    }
+
+    /// has the upstream disagreed too many times in a row
+    pub fn is_flagged(&self, url: &Url) -> bool {
+        match self.disagreements.get(&url.to_string()) {
+            Some(c) => *c >= FLAG_AFTER_DISAGREEMENTS,
+            None => false,
+        }
+    }
+}
+
+/// query every upstream concurrently, verifying each state proof against the waypoint
+pub fn query_upstreams(urls: &[Url], waypoint: Waypoint) -> Vec<UpstreamResponse> {
+    let handles: Vec<_> = urls
+        .iter()
+        .cloned()
+        .map(|url| {
+            thread::spawn(move || {
+                let verified = make_client(Some(url.clone()), waypoint).and_then(|mut c| {
+                    c.update_and_verify_state_proof()?;
+                    Ok(c.verified_version())
+                });
+                match verified {
+                    Ok(v) => UpstreamResponse {
+                        url,
+                        verified_version: Some(v),
+                        error: None,
+                    },
+                    Err(e) => UpstreamResponse {
+                        url,
+                        verified_version: None,
+                        error: Some(e.to_string()),
+                    },
+                }
+            })
+        })
+        .collect();
+
+    handles
+        .into_iter()
+        .zip(urls.iter())
+        .map(|(h, url)| {
+            h.join().unwrap_or_else(|_| UpstreamResponse {
+                url: url.clone(),
+                verified_version: None,
+                error: Some("upstream query panicked".to_owned()),
+            })
+        })
+        .collect()
+}
+
+/// lower median of sorted versions, so an upstream ahead of the others can't raise it on its own
+fn lower_median(sorted: &[(&Url, u64)]) -> u64 {
+    sorted[(sorted.len() - 1) / 2].1
+}
+
+/// median of the verified versions, after discarding the outliers
+pub fn calc_quorum(responses: &[UpstreamResponse]) -> Result<UpstreamQuorum, Error> {
+    let mut verified: Vec<(&Url, u64)> = responses
+        .iter()
+        .filter_map(|r| r.verified_version.map(|v| (&r.url, v)))
+        .collect();
+    if verified.is_empty() {
+        bail!("no upstream node returned a verified state proof");
+    }
+    verified.sort_by_key(|(_, v)| *v);
+    let median = lower_median(&verified);
+
+    let (agreeing, disagreeing): (Vec<_>, Vec<_>) = verified.into_iter().partition(|(_, v)| {
+        let diff = if *v > median {
+            *v - median
+        } else {
+            median - *v
+        };
+        diff <= OUTLIER_TOLERANCE
+    });
+
+    // agreeing is sorted and never empty, the median itself always agrees
+    let height = lower_median(&agreeing);
+
+    Ok(UpstreamQuorum {
+        height,
+        agreeing: agreeing.into_iter().map(|(u, _)| u.clone()).collect(),
+        disagreeing: disagreeing.into_iter().map(|(u, _)| u.clone()).collect(),
+        failed: responses
+            .iter()
+            .filter(|r| r.verified_version.is_none())
+            .map(|r| r.url.clone())
+            .collect(),
+        flagged: vec![],
+    })
+}
+
+/// quorum height of the configured upstream nodes, recording upstreams that disagree
+pub fn upstream_quorum(config: &AppCfg, waypoint: Waypoint) -> Result<UpstreamQuorum, Error> {
+    let node_home = &config.workspace.node_home;
+    let responses = query_upstreams(&config.profile.upstream_nodes, waypoint);
+    let mut quorum = calc_quorum(&responses)?;
+
+    let mut record = UpstreamRecord::read_json(node_home);
+    for url in quorum.agreeing.iter() {
+        record.update(url, true);
+    }
+    for url in quorum.disagreeing.iter().chain(quorum.failed.iter()) {
+        record.update(url, false);
+    }
+    quorum.flagged = config
+        .profile
+        .upstream_nodes
+        .iter()
+        .filter(|u| record.is_flagged(u))
+        .cloned()
+        .collect();
+    record.write_json(node_home)?;
+
+    Ok(quorum)
+}
+
+#[cfg(test)]
+fn test_response(url: &str, version: Option<u64>) -> UpstreamResponse {
+    UpstreamResponse {
+        url: url.parse().unwrap(),
+        verified_version: version,
+        error: None,
+    }
+}
+
+#[test]
+fn test_quorum_discards_outliers() {
+    let responses = vec![
+        test_response("http://a.com:8080", Some(10_000)),
+        test_response("http://b.com:8080", Some(10_100)),
+        test_response("http://c.com:8080", Some(10_050)),
+        test_response("http://d.com:8080", Some(900_000)),
+        test_response("http://e.com:8080", None),
+    ];
+    let q = calc_quorum(&responses).unwrap();
+    assert_eq!(q.height, 10_050);
+    assert_eq!(q.agreeing.len(), 3);
+    assert_eq!(
+        q.disagreeing,
+        vec!["http://d.com:8080".parse::<Url>().unwrap()]
+    );
+    assert_eq!(q.failed, vec!["http://e.com:8080".parse::<Url>().unwrap()]);
+}
+
+#[test]
+fn test_quorum_takes_lower_median() {
+    let responses = vec![
+        test_response("http://a.com:8080", Some(10_000)),
+        test_response("http://b.com:8080", Some(10_900)),
+    ];
+    let q = calc_quorum(&responses).unwrap();
+    assert_eq!(q.height, 10_000);
+    assert_eq!(q.agreeing.len(), 2);
+}
+
+#[test]
+fn test_quorum_fails_without_verified_upstreams() {
+    let responses = vec![test_response("http://a.com:8080", None)];
+    assert!(calc_quorum(&responses).is_err());
+}
+
+#[test]
+fn test_record_flags_repeated_disagreement() {
+    let url: Url = "http://a.com:8080".parse().unwrap();
+    let mut record = UpstreamRecord::default();
+    for _ in 0..FLAG_AFTER_DISAGREEMENTS {
+        assert!(!record.is_flagged(&url));
+        record.update(&url, false);
+    }
+    assert!(record.is_flagged(&url));
+    record.update(&url, true);
+    assert!(!record.is_flagged(&url));
+}
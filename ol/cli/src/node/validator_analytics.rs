@@ -0,0 +1,685 @@
+//! `validator_analytics` per-epoch snapshots of the validator set
+
+use super::{node::Node, query, slow_wallet::COIN_SCALING_FACTOR};
+use anyhow::{bail, Error};
+use chrono::Utc;
+use diem_types::{
+    account_address::AccountAddress, account_state::AccountState,
+    ol_validators_stats::ValidatorsStatsResource,
+};
+use ol_types::fullnode_counter::FullnodeCounterResource;
+use resource_viewer::{AnnotatedAccountStateBlob, AnnotatedMoveValue};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    fs::{self, rename, File},
+    io::Write,
+    path::PathBuf,
+};
+
+/// filename for the validator analytics history
+pub const ANALYTICS_JSON_NAME: &str = "validator_analytics.json";
+
+/// filename for temp validator analytics history
+pub const ANALYTICS_TEMP_NAME: &str = "validator_analytics.temp";
+
+/// how many epochs of snapshots are kept on disk
+pub const MAX_EPOCHS_KEPT: usize = 90;
+
+/// Mirrors of the `Globals.move` thresholds used at the epoch boundary.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ComplianceThresholds {
+    /// proofs a validator must submit in the epoch
+    pub epoch_mining_thres_lower: u64,
+    /// proofs accepted in the epoch, the cap of the fullnode subsidy
+    pub epoch_mining_thres_upper: u64,
+    /// percentage of the epoch's blocks a validator must sign
+    pub signing_threshold_pct: u64,
+    /// unrelated buddies needed in the set
+    pub vouch_threshold: u64,
+    /// seats in the validator set
+    pub max_validators_per_set: u64,
+    /// amount a slow wallet unlocks each epoch, scaled
+    pub epoch_slow_wallet_unlock: u64,
+}
+
+impl ComplianceThresholds {
+    /// `Globals::get_constants` on a chain with the `Testnet::IsTestnet` marker
+    pub fn testnet() -> Self {
+        ComplianceThresholds {
+            epoch_mining_thres_lower: 2,
+            epoch_mining_thres_upper: 1000,
+            signing_threshold_pct: 3,
+            vouch_threshold: 0,
+            max_validators_per_set: 100,
+            epoch_slow_wallet_unlock: 10,
+        }
+    }
+
+    /// `Globals::get_constants` on a chain with the `StagingNet::IsStagingNet` marker
+    pub fn staging() -> Self {
+        ComplianceThresholds {
+            epoch_mining_thres_lower: 1,
+            epoch_mining_thres_upper: 72,
+            signing_threshold_pct: 3,
+            vouch_threshold: 0,
+            max_validators_per_set: 100,
+            epoch_slow_wallet_unlock: 10 * COIN_SCALING_FACTOR,
+        }
+    }
+
+    /// `Globals::get_constants` on mainnet
+    pub fn mainnet() -> Self {
+        ComplianceThresholds {
+            epoch_mining_thres_lower: 7,
+            epoch_mining_thres_upper: 72,
+            signing_threshold_pct: 3,
+            vouch_threshold: 2,
+            max_validators_per_set: 100,
+            epoch_slow_wallet_unlock: 1000 * COIN_SCALING_FACTOR,
+        }
+    }
+
+    /// thresholds of the chain, picked like `Globals::get_constants` does, by the marker
+    /// resources on the root account rather than by the chain id
+    pub fn from_root_account(root: &AnnotatedAccountStateBlob) -> Self {
+        let has_marker = |module: &str, name: &str| {
+            root.0
+                .keys()
+                .any(|tag| tag.module.as_str() == module && tag.name.as_str() == name)
+        };
+        if has_marker("Testnet", "IsTestnet") {
+            Self::testnet()
+        } else if has_marker("StagingNet", "IsStagingNet") {
+            Self::staging()
+        } else {
+            Self::mainnet()
+        }
+    }
+}
+
+/// State of one validator candidate at the time of the snapshot
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ValidatorSnapshot {
+    /// account address
+    pub account_address: AccountAddress,
+    /// note from the accounts dictionary
+    pub note: String,
+    /// is in the current validator set
+    pub in_set: bool,
+    /// is jailed
+    pub is_jailed: bool,
+    /// block propositions in the epoch
+    pub prop_count: u64,
+    /// votes in the epoch
+    pub vote_count: u64,
+    /// verified tower height
+    pub tower_height: u64,
+    /// tower height gained since the previous epoch snapshot
+    pub tower_height_delta: u64,
+    /// proofs submitted in the epoch
+    pub proofs_in_epoch: u64,
+    /// validators which vouched for this account
+    pub vouches_received: Vec<AccountAddress>,
+    /// ancestors, from the Ancestry resource
+    #[serde(default)]
+    pub ancestry: Vec<AccountAddress>,
+    /// fullnode counter
+    pub fullnode_counter: Option<FullnodeCounterResource>,
+}
+
+/// Snapshot of all validator candidates in an epoch
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EpochSnapshot {
+    /// epoch
+    pub epoch: u64,
+    /// height/version when the snapshot was taken
+    pub height: u64,
+    /// unix timestamp when the snapshot was taken
+    pub taken_at: i64,
+    /// total propositions in the epoch
+    pub total_props: u64,
+    /// total votes in the epoch
+    pub total_votes: u64,
+    /// all accounts in the validator universe
+    pub validators: Vec<ValidatorSnapshot>,
+}
+
+impl EpochSnapshot {
+    /// addresses of the validators in the set
+    pub fn set_members(&self) -> BTreeSet<AccountAddress> {
+        self.validators
+            .iter()
+            .filter(|v| v.in_set)
+            .map(|v| v.account_address)
+            .collect()
+    }
+
+    /// find a validator in the snapshot
+    pub fn get(&self, address: &AccountAddress) -> Option<&ValidatorSnapshot> {
+        self.validators
+            .iter()
+            .find(|v| &v.account_address == address)
+    }
+}
+
+/// History of epoch snapshots, persisted in node home
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct AnalyticsHistory {
+    /// snapshots by epoch, the latest snapshot taken in an epoch wins
+    pub snapshots: BTreeMap<u64, EpochSnapshot>,
+}
+
+impl AnalyticsHistory {
+    /// read the history file, or start an empty history
+    pub fn read_json(node_home: &PathBuf) -> AnalyticsHistory {
+        let path = node_home.join(ANALYTICS_JSON_NAME);
+        match fs::File::open(path) {
+            Ok(file) => serde_json::from_reader(file).unwrap_or_default(),
+            Err(_) => AnalyticsHistory::default(),
+        }
+    }
+
+    /// write the history file
+    pub fn write_json(&self, node_home: &PathBuf) -> Result<(), Error> {
+        let serialized = serde_json::to_vec(&self)?;
+
+        // uses temporary file to avoid listeners reading partial content
+        let temp_path = node_home.join(ANALYTICS_TEMP_NAME);
+        let mut file = File::create(&temp_path)?;
+        file.write_all(&serialized)?;
+
+        rename(temp_path, node_home.join(ANALYTICS_JSON_NAME))?;
+        Ok(())
+    }
+
+    /// add or replace the snapshot for its epoch, dropping the oldest epochs
+    pub fn insert(&mut self, snapshot: EpochSnapshot) {
+        self.snapshots.insert(snapshot.epoch, snapshot);
+        while self.snapshots.len() > MAX_EPOCHS_KEPT {
+            let oldest = *self.snapshots.keys().next().unwrap();
+            self.snapshots.remove(&oldest);
+        }
+    }
+
+    /// latest snapshot before an epoch
+    pub fn previous(&self, epoch: u64) -> Option<&EpochSnapshot> {
+        self.snapshots.range(..epoch).next_back().map(|(_, s)| s)
+    }
+
+    /// latest snapshot
+    pub fn latest(&self) -> Option<&EpochSnapshot> {
+        self.snapshots.values().next_back()
+    }
+}
+
+/// Changes to the validator set between two consecutive snapshots
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EpochChurn {
+    /// epoch
+    pub epoch: u64,
+    /// validators entering the set
+    pub joined: Vec<AccountAddress>,
+    /// validators leaving the set
+    pub left: Vec<AccountAddress>,
+    /// (joined + left) / size of the previous set
+    pub churn_rate: f64,
+}
+
+/// How likely a validator is to be jailed at the next epoch boundary
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum RiskLevel {
+    /// meets every threshold
+    Low,
+    /// meets the thresholds with little margin
+    Medium,
+    /// does not meet a threshold yet
+    High,
+    /// already jailed
+    Jailed,
+}
+
+/// Jailing risk of a validator in the set
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JailRisk {
+    /// account address
+    pub account_address: AccountAddress,
+    /// note from the accounts dictionary
+    pub note: String,
+    /// risk level
+    pub risk: RiskLevel,
+    /// proofs submitted in the epoch
+    pub proofs_in_epoch: u64,
+    /// proofs needed in the epoch
+    pub proofs_needed: u64,
+    /// votes in the epoch
+    pub vote_count: u64,
+    /// votes needed so far in the epoch
+    pub votes_needed: u64,
+    /// vouches from validators in the set which are unrelated, like `Vouch::unrelated_buddies`
+    pub unrelated_vouches_in_set: u64,
+}
+
+/// A vouch from one validator to another
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct VouchEdge {
+    /// the validator vouching
+    pub voucher: AccountAddress,
+    /// the validator being vouched for
+    pub vouchee: AccountAddress,
+}
+
+/// Analytics computed from the snapshot history
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ValidatorAnalytics {
+    /// latest epoch in the history
+    pub epoch: u64,
+    /// set changes, epoch over epoch
+    pub churn: Vec<EpochChurn>,
+    /// jailing risk of the current set
+    pub jail_risk: Vec<JailRisk>,
+    /// who vouches for whom
+    pub vouch_graph: Vec<VouchEdge>,
+}
+
+impl ValidatorAnalytics {
+    /// compute the analytics from a history
+    pub fn from_history(history: &AnalyticsHistory, thresholds: &ComplianceThresholds) -> Self {
+        let latest = match history.latest() {
+            Some(s) => s,
+            None => return ValidatorAnalytics::default(),
+        };
+        ValidatorAnalytics {
+            epoch: latest.epoch,
+            churn: calc_churn(history),
+            jail_risk: calc_jail_risk(latest, thresholds),
+            vouch_graph: calc_vouch_graph(latest),
+        }
+    }
+}
+
+/// set churn between each pair of consecutive snapshots
+pub fn calc_churn(history: &AnalyticsHistory) -> Vec<EpochChurn> {
+    let snapshots: Vec<&EpochSnapshot> = history.snapshots.values().collect();
+    snapshots
+        .windows(2)
+        .map(|pair| {
+            let before = pair[0].set_members();
+            let after = pair[1].set_members();
+            let joined: Vec<AccountAddress> = after.difference(&before).cloned().collect();
+            let left: Vec<AccountAddress> = before.difference(&after).cloned().collect();
+            let churn_rate = if before.is_empty() {
+                0f64
+            } else {
+                (joined.len() + left.len()) as f64 / before.len() as f64
+            };
+            EpochChurn {
+                epoch: pair[1].epoch,
+                joined,
+                left,
+                churn_rate,
+            }
+        })
+        .collect()
+}
+
+/// evaluate the epoch boundary thresholds for every validator in the set
+pub fn calc_jail_risk(
+    snapshot: &EpochSnapshot,
+    thresholds: &ComplianceThresholds,
+) -> Vec<JailRisk> {
+    let members = snapshot.set_members();
+    let trees: BTreeMap<AccountAddress, Vec<AccountAddress>> = snapshot
+        .validators
+        .iter()
+        .map(|v| (v.account_address, v.ancestry.clone()))
+        .collect();
+    // every block has one proposer, so the propositions approximate the
+    // blocks produced so far in the epoch.
+    let votes_needed = snapshot.total_props * thresholds.signing_threshold_pct / 100;
+
+    snapshot
+        .validators
+        .iter()
+        .filter(|v| v.in_set || v.is_jailed)
+        .map(|v| {
+            let buddies_in_set: Vec<AccountAddress> = v
+                .vouches_received
+                .iter()
+                .filter(|a| members.contains(a))
+                .cloned()
+                .collect();
+            let unrelated_vouches_in_set = unrelated_buddies(&buddies_in_set, &trees).len() as u64;
+            let proofs_needed = thresholds.epoch_mining_thres_lower;
+
+            let risk = if v.is_jailed {
+                RiskLevel::Jailed
+            } else if v.proofs_in_epoch < proofs_needed
+                || v.vote_count <= votes_needed
+                || unrelated_vouches_in_set < thresholds.vouch_threshold
+            {
+                RiskLevel::High
+            } else if v.proofs_in_epoch < proofs_needed + 2
+                || v.vote_count <= votes_needed * 2
+                || unrelated_vouches_in_set == thresholds.vouch_threshold
+            {
+                RiskLevel::Medium
+            } else {
+                RiskLevel::Low
+            };
+
+            JailRisk {
+                account_address: v.account_address,
+                note: v.note.clone(),
+                risk,
+                proofs_in_epoch: v.proofs_in_epoch,
+                proofs_needed,
+                vote_count: v.vote_count,
+                votes_needed,
+                unrelated_vouches_in_set,
+            }
+        })
+        .collect()
+}
+
+/// buddies with at least one other buddy they are not family of, like `Vouch::unrelated_buddies`
+pub fn unrelated_buddies(
+    buddies_in_set: &[AccountAddress],
+    trees: &BTreeMap<AccountAddress, Vec<AccountAddress>>,
+) -> Vec<AccountAddress> {
+    buddies_in_set
+        .iter()
+        .filter(|target| {
+            buddies_in_set
+                .iter()
+                .any(|other| other != *target && !is_family(other, target, trees))
+        })
+        .cloned()
+        .collect()
+}
+
+/// like `Ancestry::is_family`, one is in the other's tree, or the trees overlap
+fn is_family(
+    left: &AccountAddress,
+    right: &AccountAddress,
+    trees: &BTreeMap<AccountAddress, Vec<AccountAddress>>,
+) -> bool {
+    let empty = vec![];
+    let left_tree = trees.get(left).unwrap_or(&empty);
+    let right_tree = trees.get(right).unwrap_or(&empty);
+    left_tree.contains(right)
+        || right_tree.contains(left)
+        || left_tree.iter().any(|a| right_tree.contains(a))
+}
+
+/// list all vouches between validator candidates
+pub fn calc_vouch_graph(snapshot: &EpochSnapshot) -> Vec<VouchEdge> {
+    snapshot
+        .validators
+        .iter()
+        .flat_map(|v| {
+            v.vouches_received.iter().map(move |voucher| VouchEdge {
+                voucher: *voucher,
+                vouchee: v.account_address,
+            })
+        })
+        .collect()
+}
+
+impl Node {
+    /// take a snapshot of the validator universe, add it to the history, and compute analytics
+    pub fn refresh_validator_analytics(&mut self) -> Result<ValidatorAnalytics, Error> {
+        let node_home = self.app_conf.workspace.node_home.clone();
+        let mut history = AnalyticsHistory::read_json(&node_home);
+
+        let snapshot = self.take_epoch_snapshot(&history)?;
+        history.insert(snapshot);
+        history.write_json(&node_home)?;
+
+        let analytics =
+            ValidatorAnalytics::from_history(&history, &self.get_compliance_thresholds()?);
+        if let Some(cv) = &mut self.vitals.chain_view {
+            cv.validator_analytics = Some(analytics.clone());
+        }
+        Ok(analytics)
+    }
+
+    /// snapshot of every account in the validator universe
+    pub fn take_epoch_snapshot(&self, history: &AnalyticsHistory) -> Result<EpochSnapshot, Error> {
+        let (blob, height) = self.client.get_account_state_blob(&AccountAddress::ZERO)?;
+        let account_state = match blob {
+            Some(b) => AccountState::try_from(&b)?,
+            None => bail!("cannot get state of system account"),
+        };
+
+        let epoch = match account_state.get_configuration_resource()? {
+            Some(cr) => cr.epoch(),
+            None => bail!("cannot get configuration resource from chain"),
+        };
+        let set: BTreeSet<AccountAddress> = match account_state.get_validator_set()? {
+            Some(vs) => vs.payload().iter().map(|v| *v.account_address()).collect(),
+            None => bail!("cannot get validator set resource from chain"),
+        };
+        let stats = match account_state.get_validators_stats()? {
+            Some(s) => s,
+            None => bail!("could not get validators stats"),
+        };
+
+        // candidates are everyone in the universe, plus the current set
+        let mut candidates = self.get_validator_universe().unwrap_or_default();
+        for a in set.iter() {
+            if !candidates.contains(a) {
+                candidates.push(*a);
+            }
+        }
+
+        let dict = self.load_account_dictionary();
+        let previous = history.previous(epoch);
+        let validators = candidates
+            .iter()
+            .map(|a| {
+                let mut v = self.snapshot_validator(*a, &stats);
+                v.in_set = set.contains(a);
+                v.note = dict.get_note_for_address(*a);
+                v.tower_height_delta = match previous.and_then(|p| p.get(a)) {
+                    Some(p) => v.tower_height.saturating_sub(p.tower_height),
+                    None => 0,
+                };
+                v
+            })
+            .collect();
+
+        Ok(EpochSnapshot {
+            epoch,
+            height,
+            taken_at: Utc::now().timestamp(),
+            total_props: stats.current.total_props,
+            total_votes: stats.current.total_votes,
+            validators,
+        })
+    }
+
+    /// thresholds the epoch boundary of the chain uses
+    pub fn get_compliance_thresholds(&self) -> Result<ComplianceThresholds, Error> {
+        match self.get_annotate_account_blob(AccountAddress::ZERO)? {
+            (Some(r), _) => Ok(ComplianceThresholds::from_root_account(&r)),
+            (None, _) => bail!("cannot get state of system account"),
+        }
+    }
+
+    /// addresses in the ValidatorUniverse resource
+    pub fn get_validator_universe(&self) -> Result<Vec<AccountAddress>, Error> {
+        match self.get_annotate_account_blob(AccountAddress::ZERO)? {
+            (Some(r), _) => Ok(addresses_from_value(query::find_value_from_state(
+                &r,
+                "ValidatorUniverse".to_string(),
+                "ValidatorUniverse".to_string(),
+                "validators".to_string(),
+            ))),
+            (None, _) => bail!("cannot get state of system account"),
+        }
+    }
+
+    fn snapshot_validator(
+        &self,
+        address: AccountAddress,
+        stats: &ValidatorsStatsResource,
+    ) -> ValidatorSnapshot {
+        let (prop_count, vote_count) = match stats.get_validator_current_stats(address) {
+            Ok(s) => (s.prop_count, s.vote_count),
+            Err(_) => (0, 0),
+        };
+
+        let (tower_height, proofs_in_epoch) = match self.client.get_miner_state(&address) {
+            Ok(Some(ms)) => (ms.verified_tower_height, ms.actual_count_proofs_in_epoch),
+            _ => (0, 0),
+        };
+
+        let (is_jailed, vouches_received, ancestry) = match self.get_annotate_account_blob(address)
+        {
+            Ok((Some(r), _)) => {
+                let jailed = match query::find_value_from_state(
+                    &r,
+                    "Jail".to_string(),
+                    "Jail".to_string(),
+                    "is_jailed".to_string(),
+                ) {
+                    Some(AnnotatedMoveValue::Bool(b)) => *b,
+                    _ => false,
+                };
+                let vouches = addresses_from_value(query::find_value_from_state(
+                    &r,
+                    "Vouch".to_string(),
+                    "Vouch".to_string(),
+                    "vals".to_string(),
+                ));
+                let ancestry = addresses_from_value(query::find_value_from_state(
+                    &r,
+                    "Ancestry".to_string(),
+                    "Ancestry".to_string(),
+                    "tree".to_string(),
+                ));
+                (jailed, vouches, ancestry)
+            }
+            _ => (false, vec![], vec![]),
+        };
+
+        let fullnode_counter = self.get_account_state(address).ok().and_then(|s| {
+            s.get_resource_impl::<FullnodeCounterResource>(
+                FullnodeCounterResource::resource_path().as_slice(),
+            )
+            .ok()
+            .flatten()
+        });
+
+        ValidatorSnapshot {
+            account_address: address,
+            note: String::new(),
+            in_set: false,
+            is_jailed,
+            prop_count,
+            vote_count,
+            tower_height,
+            tower_height_delta: 0,
+            proofs_in_epoch,
+            vouches_received,
+            ancestry,
+            fullnode_counter,
+        }
+    }
+}
+
+/// unwrap a Move `vector<address>`
+pub(crate) fn addresses_from_value(value: Option<&AnnotatedMoveValue>) -> Vec<AccountAddress> {
+    match value {
+        Some(AnnotatedMoveValue::Vector(_, vec)) => vec
+            .iter()
+            .filter_map(|v| match v {
+                AnnotatedMoveValue::Address(a) => Some(*a),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+fn test_snapshot(epoch: u64, set: &[AccountAddress]) -> EpochSnapshot {
+    EpochSnapshot {
+        epoch,
+        height: epoch * 100,
+        taken_at: 0,
+        total_props: 1000,
+        total_votes: 4000,
+        validators: set
+            .iter()
+            .map(|a| ValidatorSnapshot {
+                account_address: *a,
+                note: String::new(),
+                in_set: true,
+                is_jailed: false,
+                prop_count: 250,
+                vote_count: 1000,
+                tower_height: 100,
+                tower_height_delta: 10,
+                proofs_in_epoch: 20,
+                vouches_received: set.iter().filter(|b| *b != a).cloned().collect(),
+                ancestry: vec![],
+                fullnode_counter: None,
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn test_calc_churn() {
+    let alice = AccountAddress::random();
+    let bob = AccountAddress::random();
+    let carol = AccountAddress::random();
+    let mut history = AnalyticsHistory::default();
+    history.insert(test_snapshot(1, &[alice, bob]));
+    history.insert(test_snapshot(2, &[alice, carol]));
+
+    let churn = calc_churn(&history);
+    assert_eq!(churn.len(), 1);
+    assert_eq!(churn[0].epoch, 2);
+    assert_eq!(churn[0].joined, vec![carol]);
+    assert_eq!(churn[0].left, vec![bob]);
+    assert_eq!(churn[0].churn_rate, 1f64);
+}
+
+#[test]
+fn test_calc_jail_risk() {
+    let alice = AccountAddress::random();
+    let bob = AccountAddress::random();
+    let carol = AccountAddress::random();
+    let mut snapshot = test_snapshot(1, &[alice, bob, carol]);
+    snapshot.validators[0].proofs_in_epoch = 1;
+    snapshot.validators[1].is_jailed = true;
+
+    let thresholds = ComplianceThresholds::mainnet();
+    let risk = calc_jail_risk(&snapshot, &thresholds);
+    assert_eq!(risk[0].risk, RiskLevel::High);
+    assert_eq!(risk[1].risk, RiskLevel::Jailed);
+    // carol has two unrelated vouches in set, which is exactly at the threshold
+    assert_eq!(risk[2].risk, RiskLevel::Medium);
+    assert_eq!(calc_vouch_graph(&snapshot).len(), 6);
+
+    // once bob descends from alice, carol's vouches are from one family and none count
+    snapshot.validators[1].ancestry = vec![alice];
+    let risk = calc_jail_risk(&snapshot, &thresholds);
+    assert_eq!(risk[2].unrelated_vouches_in_set, 0);
+    assert_eq!(risk[2].risk, RiskLevel::High);
+}
+
+#[test]
+fn test_history_keeps_max_epochs() {
+    let mut history = AnalyticsHistory::default();
+    for e in 0..(MAX_EPOCHS_KEPT as u64 + 5) {
+        history.insert(test_snapshot(e, &[]));
+    }
+    assert_eq!(history.snapshots.len(), MAX_EPOCHS_KEPT);
+    assert_eq!(*history.snapshots.keys().next().unwrap(), 5);
+    assert_eq!(history.previous(10).unwrap().epoch, 9);
+}
@@ -11,7 +11,14 @@ use tokio_stream::wrappers::IntervalStream;
 use warp::{sse::Event, Filter};
 use std::process::exit;
 
-use crate::{cache::Vitals, check::runner, node::node::Node};
+use crate::{
+    cache::Vitals,
+    check::runner,
+    node::{
+        node::Node,
+        payment_index::{PaymentFilter, PaymentIndex, PAYMENT_INDEX_DIR},
+    },
+};
 
 #[tokio::main]
 /// starts the web server
@@ -63,6 +70,19 @@ pub async fn start_server(mut node: Node, _run_checks: bool) {
         json.to_string()
     });
 
+    let node_home = cfg.clone().workspace.node_home.clone();
+    let payments_route = warp::path("payments.json")
+        .and(warp::get())
+        .and(warp::query::<PaymentFilter>())
+        .map(move |filter: PaymentFilter| {
+            // opened per request, `ol index` holds the write lock
+            let path = node_home.join(PAYMENT_INDEX_DIR);
+            match PaymentIndex::open_read_only(&path).and_then(|i| i.payments(&filter)) {
+                Ok(payments) => json!(payments).to_string(),
+                Err(e) => json!({ "error": e.to_string() }).to_string(),
+            }
+        });
+
     let node_home = cfg.clone().workspace.node_home.clone();
     let web_files = if *IS_PROD {
         node_home.join("web-monitor/")
@@ -80,7 +100,8 @@ pub async fn start_server(mut node: Node, _run_checks: bool) {
         landing
             .or(account_template)
             .or(vitals_route)
-            .or(epoch_route),
+            .or(epoch_route)
+            .or(payments_route),
     )
     .run(([0, 0, 0, 0], 3030))
     .await;
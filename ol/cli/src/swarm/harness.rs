@@ -0,0 +1,276 @@
+//! `harness` boots a local swarm of fixture personas and drives their tools
+
+use crate::{
+    commands::init_cmd::initialize_host_swarm,
+    node::{account::get_balance, client::make_client},
+};
+use anyhow::{bail, Error};
+use cli::diem_client::DiemClient;
+use diem_swarm::swarm::DiemSwarm;
+use diem_types::{account_address::AccountAddress, account_state::AccountState};
+use ol_types::{config::get_swarm_rpc_url, fixtures};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    fs::{self, File, OpenOptions},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// personas in the order of the swarm node directories
+pub const SWARM_PERSONAS: [&str; 4] = ["alice", "bob", "carol", "dave"];
+
+/// seconds to wait for one epoch to pass on a test swarm
+pub const EPOCH_TIMEOUT_SECS: u64 = 300;
+
+/// seconds between polls of the swarm
+pub const POLL_INTERVAL_SECS: u64 = 5;
+
+/// A running swarm, its personas and their tower miners
+pub struct SwarmHarness {
+    /// the validator nodes, killed when dropped
+    swarm: DiemSwarm,
+    /// the swarm_temp directory
+    pub swarm_path: PathBuf,
+    /// the libra source, binaries are taken from target/debug
+    pub source_path: PathBuf,
+    /// tower miners by persona
+    towers: HashMap<String, Child>,
+}
+
+impl SwarmHarness {
+    /// start `num_nodes` validators and initialize the configs of their personas
+    pub fn start(
+        source_path: PathBuf,
+        swarm_path: PathBuf,
+        num_nodes: usize,
+    ) -> Result<SwarmHarness, Error> {
+        if num_nodes == 0 || num_nodes > SWARM_PERSONAS.len() {
+            bail!(
+                "swarm can have between 1 and {} nodes",
+                SWARM_PERSONAS.len()
+            );
+        }
+        let node_bin = debug_bin(&source_path, "diem-node")?;
+
+        println!("starting swarm of {} nodes in {:?}", num_nodes, &swarm_path);
+        let mut swarm = DiemSwarm::configure_validator_swarm(
+            &node_bin,
+            num_nodes,
+            Some(swarm_path.to_str().unwrap().to_owned()),
+            None,
+        )?;
+        swarm.launch_attempt()?;
+
+        // the swarm dir was recreated, so resolve it only now
+        let swarm_path = fs::canonicalize(swarm_path)?;
+        for (idx, persona) in SWARM_PERSONAS.iter().take(num_nodes).enumerate() {
+            initialize_host_swarm(
+                swarm_path.clone(),
+                swarm_path.join(idx.to_string()),
+                Some(persona.to_string()),
+                &Some(source_path.clone()),
+            )?;
+        }
+
+        Ok(SwarmHarness {
+            swarm,
+            swarm_path,
+            source_path,
+            towers: HashMap::new(),
+        })
+    }
+
+    /// number of validators in the swarm
+    pub fn num_nodes(&self) -> usize {
+        self.swarm.config.config_files.len()
+    }
+
+    /// personas with a node in the swarm
+    pub fn personas(&self) -> Vec<String> {
+        SWARM_PERSONAS
+            .iter()
+            .take(self.num_nodes())
+            .map(|p| p.to_string())
+            .collect()
+    }
+
+    /// client connected to the first node of the swarm
+    pub fn client(&self) -> Result<DiemClient, Error> {
+        let (url, waypoint) = get_swarm_rpc_url(self.swarm_path.clone());
+        make_client(Some(url), waypoint)
+    }
+
+    /// start the tower miner of a persona, at test difficulty
+    pub fn start_tower(&mut self, persona: &str) -> Result<(), Error> {
+        persona_index(persona, self.num_nodes())?;
+        if self.towers.contains_key(persona) {
+            return Ok(());
+        }
+
+        let log = self.log_file(&format!("tower-{}", persona))?;
+        let child = Command::new(debug_bin(&self.source_path, "tower")?)
+            .args(&self.persona_args(persona))
+            .arg("start")
+            .env("NODE_ENV", "test")
+            .env("TEST", "y")
+            .stdout(Stdio::from(log.try_clone()?))
+            .stderr(Stdio::from(log))
+            .spawn()?;
+        println!("started tower for {} with PID {}", persona, child.id());
+        self.towers.insert(persona.to_owned(), child);
+        Ok(())
+    }
+
+    /// stop the tower miner of a persona
+    pub fn stop_tower(&mut self, persona: &str) {
+        if let Some(mut child) = self.towers.remove(persona) {
+            child.kill().ok();
+            child.wait().ok();
+        }
+    }
+
+    /// run a `txs` subcommand signed by a persona, fails if the tx fails
+    pub fn run_txs(&self, persona: &str, args: &[&str]) -> Result<(), Error> {
+        persona_index(persona, self.num_nodes())?;
+        let log = self.log_file(&format!("txs-{}", persona))?;
+        let status = Command::new(debug_bin(&self.source_path, "txs")?)
+            .args(&self.persona_args(persona))
+            .args(args)
+            .env("NODE_ENV", "test")
+            .env("TEST", "y")
+            .stdout(Stdio::from(log.try_clone()?))
+            .stderr(Stdio::from(log))
+            .status()?;
+        if !status.success() {
+            bail!(
+                "txs {} by {} failed with {}, see the logs in {:?}",
+                args.join(" "),
+                persona,
+                status,
+                self.swarm_path.join("logs")
+            );
+        }
+        Ok(())
+    }
+
+    /// current epoch of the swarm
+    pub fn epoch(&self) -> Result<u64, Error> {
+        let (blob, _) = self
+            .client()?
+            .get_account_state_blob(&AccountAddress::ZERO)?;
+        let blob = match blob {
+            Some(b) => b,
+            None => bail!("cannot get the system account state"),
+        };
+        match AccountState::try_from(&blob)?.get_configuration_resource()? {
+            Some(c) => Ok(c.epoch()),
+            None => bail!("cannot get configuration resource from chain"),
+        }
+    }
+
+    /// wait until the swarm reaches the epoch
+    pub fn wait_for_epoch(&self, epoch: u64) -> Result<(), Error> {
+        let current = self.epoch()?;
+        let timeout = Duration::from_secs(EPOCH_TIMEOUT_SECS * epoch.saturating_sub(current));
+        let started = Instant::now();
+        loop {
+            // the nodes may be busy at an epoch boundary, only give up on timeout
+            if let Ok(e) = self.epoch() {
+                if e >= epoch {
+                    return Ok(());
+                }
+            }
+            if started.elapsed() > timeout {
+                bail!("swarm did not reach epoch {} in {:?}", epoch, timeout);
+            }
+            thread::sleep(Duration::from_secs(POLL_INTERVAL_SECS));
+        }
+    }
+
+    /// GAS balance of an account, None if the account does not exist
+    pub fn balance(&self, address: AccountAddress) -> Result<Option<f64>, Error> {
+        Ok(self.client()?.get_account(&address)?.map(get_balance))
+    }
+
+    /// verified tower height of an account
+    pub fn tower_height(&self, address: AccountAddress) -> Result<u64, Error> {
+        match self.client()?.get_miner_state(&address)? {
+            Some(ms) => Ok(ms.verified_tower_height),
+            None => Ok(0),
+        }
+    }
+
+    fn persona_args(&self, persona: &str) -> Vec<String> {
+        vec![
+            "--swarm-path".to_owned(),
+            self.swarm_path.to_str().unwrap().to_owned(),
+            "--swarm-persona".to_owned(),
+            persona.to_owned(),
+        ]
+    }
+
+    fn log_file(&self, name: &str) -> Result<File, Error> {
+        let logs_dir = self.swarm_path.join("logs");
+        fs::create_dir_all(&logs_dir)?;
+        Ok(OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(logs_dir.join(format!("{}.log", name)))?)
+    }
+}
+
+impl Drop for SwarmHarness {
+    fn drop(&mut self) {
+        // the nodes are killed by the swarm itself
+        let personas: Vec<String> = self.towers.keys().cloned().collect();
+        for p in personas {
+            self.stop_tower(&p);
+        }
+    }
+}
+
+/// index of the persona's node directory, if it is part of the swarm
+pub fn persona_index(persona: &str, num_nodes: usize) -> Result<usize, Error> {
+    match SWARM_PERSONAS
+        .iter()
+        .take(num_nodes)
+        .position(|&p| p == persona)
+    {
+        Some(i) => Ok(i),
+        None => bail!("{} is not a persona of this swarm", persona),
+    }
+}
+
+/// address of a persona from the fixtures, or a hex address
+pub fn resolve_address(account: &str) -> Result<AccountAddress, Error> {
+    if let Ok(a) = account.parse::<AccountAddress>() {
+        return Ok(a);
+    }
+    // eve has fixtures but no node, she is the one usually onboarded
+    if !SWARM_PERSONAS.contains(&account) && account != "eve" {
+        bail!("{} is neither an address nor a fixture persona", account);
+    }
+    Ok(fixtures::get_persona_toml_configs(account).profile.account)
+}
+
+/// binary built in the source's target/debug
+fn debug_bin(source_path: &Path, name: &str) -> Result<PathBuf, Error> {
+    let bin = source_path.join("target/debug").join(name);
+    if !bin.exists() {
+        bail!(
+            "could not find {:?}, build it with: cargo build -p diem-node -p tower -p txs",
+            bin
+        );
+    }
+    Ok(bin)
+}
+
+#[test]
+fn test_persona_index() {
+    assert_eq!(persona_index("carol", 4).unwrap(), 2);
+    assert!(persona_index("carol", 2).is_err());
+    assert!(persona_index("eve", 4).is_err());
+}
@@ -0,0 +1,3 @@
+//! `swarm` local swarm orchestration for scripted integration tests
+pub mod harness;
+pub mod scenario;
@@ -0,0 +1,212 @@
+//! `scenario` steps to script 0L flows against a swarm
+
+use super::harness::{resolve_address, SwarmHarness, EPOCH_TIMEOUT_SECS, POLL_INTERVAL_SECS};
+use anyhow::{bail, Error};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// A scenario file, e.g.
+/// ```yaml
+/// name: onboard eve
+/// num_nodes: 2
+/// steps:
+///   - step: onboard
+///     by: alice
+///     account_file: ol/fixtures/account/eve.account.json
+///   - step: advance_epochs
+///     count: 1
+///   - step: assert_balance
+///     account: eve
+///     min: 1
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Scenario {
+    /// name printed in the logs
+    pub name: String,
+    /// validators to start, personas are taken in order from alice
+    pub num_nodes: usize,
+    /// steps run in order, the scenario stops at the first failure
+    pub steps: Vec<Step>,
+}
+
+/// One step of a scenario. Accounts are persona names or hex addresses.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum Step {
+    /// onboard a validator with an account.json, paid by a persona
+    Onboard {
+        /// persona sending the onboarding tx
+        by: String,
+        /// account.json, relative to the source path
+        account_file: PathBuf,
+    },
+    /// vouch for an account
+    Vouch {
+        /// persona vouching
+        from: String,
+        /// account vouched for
+        to: String,
+    },
+    /// start the tower of a persona, optionally wait for its height
+    Mine {
+        /// persona mining
+        persona: String,
+        /// wait until the verified tower height reaches this
+        until_height: Option<u64>,
+    },
+    /// stop the tower of a persona
+    StopMining {
+        /// persona mining
+        persona: String,
+    },
+    /// wait for epochs to pass
+    AdvanceEpochs {
+        /// number of epochs
+        count: u64,
+    },
+    /// check an account balance is within bounds
+    AssertBalance {
+        /// account to check
+        account: String,
+        /// inclusive lower bound
+        min: Option<f64>,
+        /// inclusive upper bound
+        max: Option<f64>,
+    },
+}
+
+impl Scenario {
+    /// read a scenario from a yaml file
+    pub fn read_yaml(path: &PathBuf) -> Result<Scenario, Error> {
+        let scenario: Scenario = serde_yaml::from_str(&fs::read_to_string(path)?)?;
+        Ok(scenario)
+    }
+
+    /// run all the steps, stopping at the first failure
+    pub fn run(&self, harness: &mut SwarmHarness) -> Result<(), Error> {
+        println!("running scenario: {}", self.name);
+        for (i, step) in self.steps.iter().enumerate() {
+            println!("step {}: {:?}", i + 1, step);
+            if let Err(e) = step.run(harness) {
+                bail!("scenario {} failed at step {}: {}", self.name, i + 1, e);
+            }
+        }
+        println!("scenario {} passed", self.name);
+        Ok(())
+    }
+}
+
+impl Step {
+    /// run the step against the swarm
+    pub fn run(&self, harness: &mut SwarmHarness) -> Result<(), Error> {
+        match self {
+            Step::Onboard { by, account_file } => {
+                let file = harness.source_path.join(account_file);
+                harness.run_txs(by, &["create-validator", "-f", file.to_str().unwrap()])
+            }
+            Step::Vouch { from, to } => {
+                let address = resolve_address(to)?.to_hex_literal();
+                harness.run_txs(from, &["vouch", "--address", &address])
+            }
+            Step::Mine {
+                persona,
+                until_height,
+            } => {
+                harness.start_tower(persona)?;
+                match until_height {
+                    Some(h) => wait_for_height(harness, persona, *h),
+                    None => Ok(()),
+                }
+            }
+            Step::StopMining { persona } => {
+                harness.stop_tower(persona);
+                Ok(())
+            }
+            Step::AdvanceEpochs { count } => {
+                let target = harness.epoch()? + count;
+                harness.wait_for_epoch(target)
+            }
+            Step::AssertBalance { account, min, max } => {
+                let balance = match harness.balance(resolve_address(account)?)? {
+                    Some(b) => b,
+                    None => bail!("account {} does not exist", account),
+                };
+                check_bounds(balance, *min, *max)
+                    .map_err(|e| Error::msg(format!("balance of {}: {}", account, e)))
+            }
+        }
+    }
+}
+
+/// wait until a persona's tower reaches the height
+fn wait_for_height(harness: &SwarmHarness, persona: &str, height: u64) -> Result<(), Error> {
+    let address = resolve_address(persona)?;
+    let timeout = Duration::from_secs(EPOCH_TIMEOUT_SECS);
+    let started = Instant::now();
+    loop {
+        if let Ok(h) = harness.tower_height(address) {
+            if h >= height {
+                return Ok(());
+            }
+        }
+        if started.elapsed() > timeout {
+            bail!("tower of {} did not reach height {}", persona, height);
+        }
+        thread::sleep(Duration::from_secs(POLL_INTERVAL_SECS));
+    }
+}
+
+/// check a value is within the optional inclusive bounds
+fn check_bounds(value: f64, min: Option<f64>, max: Option<f64>) -> Result<(), Error> {
+    if let Some(m) = min {
+        if value < m {
+            bail!("{} is below the minimum {}", value, m);
+        }
+    }
+    if let Some(m) = max {
+        if value > m {
+            bail!("{} is above the maximum {}", value, m);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parse_scenario() {
+    let yaml = r#"
+name: onboard and vouch
+num_nodes: 2
+steps:
+  - step: onboard
+    by: alice
+    account_file: ol/fixtures/account/eve.account.json
+  - step: vouch
+    from: bob
+    to: eve
+  - step: mine
+    persona: alice
+    until_height: 2
+  - step: advance_epochs
+    count: 1
+  - step: assert_balance
+    account: 3DC18D1CF61FAAC6AC70E3A63F062E4B
+    min: 1
+"#;
+    let s: Scenario = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(s.num_nodes, 2);
+    assert_eq!(s.steps.len(), 5);
+    match &s.steps[4] {
+        Step::AssertBalance { min, max, .. } => {
+            assert_eq!(*min, Some(1.0));
+            assert!(max.is_none());
+        }
+        _ => panic!("expected assert_balance"),
+    }
+    assert!(check_bounds(1.5, Some(1.0), None).is_ok());
+    assert!(check_bounds(0.5, Some(1.0), Some(2.0)).is_err());
+}
@@ -0,0 +1,335 @@
+//! `epoch_preview` run the epoch boundary on a copy of the chain state, and report the next validator set and payouts
+
+use anyhow::{anyhow, bail, Context, Error};
+use diem_transaction_replay::DiemDebugger;
+use diem_types::{
+    account_address::AccountAddress,
+    account_config::{diem_root_address, ReceivedPaymentEvent, SentPaymentEvent},
+    contract_event::ContractEvent,
+    transaction::Version,
+};
+use move_core_types::{
+    identifier::Identifier,
+    value::{serialize_values, MoveValue},
+};
+use move_vm_runtime::logging::NoContextLog;
+use move_vm_types::gas_schedule::GasStatus;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::upgrade_rehearsal::module_id;
+
+/// Payments made by the epoch boundary to or from one account, unscaled
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Payouts {
+    /// validator subsidy minted to the account
+    pub subsidy: u64,
+    /// share of the epoch's transaction fees
+    pub fees: u64,
+    /// refund of the operator's mining tx fees
+    pub fee_refund: u64,
+    /// subsidy for the proofs of a fullnode
+    pub fullnode_subsidy: u64,
+    /// coins burned from the account
+    pub burn: u64,
+    /// coins sent to community wallets instead of burned
+    pub sent_to_community: u64,
+}
+
+/// Projected outcome of the next epoch boundary
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EpochPreview {
+    /// version of the state the boundary ran on
+    pub version: Version,
+    /// epoch which is ending
+    pub epoch: u64,
+    /// block height passed to the epoch boundary
+    pub height: u64,
+    /// validators of the ending epoch
+    pub current_set: Vec<AccountAddress>,
+    /// validators of the ending epoch which get paid
+    pub compliant_set: Vec<AccountAddress>,
+    /// validators of the next epoch
+    pub projected_set: Vec<AccountAddress>,
+    /// subsidy of each compliant validator, as calculated by the chain
+    pub nominal_subsidy_per_validator: u64,
+    /// fullnode subsidy per proof above threshold
+    pub proof_price: u64,
+    /// payments of every account the boundary paid or charged
+    pub payouts: BTreeMap<AccountAddress, Payouts>,
+}
+
+impl EpochPreview {
+    /// validators entering the set
+    pub fn joining(&self) -> Vec<AccountAddress> {
+        self.projected_set
+            .iter()
+            .filter(|a| !self.current_set.contains(a))
+            .cloned()
+            .collect()
+    }
+
+    /// validators leaving the set
+    pub fn leaving(&self) -> Vec<AccountAddress> {
+        self.current_set
+            .iter()
+            .filter(|a| !self.projected_set.contains(a))
+            .cloned()
+            .collect()
+    }
+
+    /// sum of the payouts of all accounts
+    pub fn totals(&self) -> Payouts {
+        self.payouts.values().fold(Payouts::default(), |mut t, p| {
+            t.subsidy += p.subsidy;
+            t.fees += p.fees;
+            t.fee_refund += p.fee_refund;
+            t.fullnode_subsidy += p.fullnode_subsidy;
+            t.burn += p.burn;
+            t.sent_to_community += p.sent_to_community;
+            t
+        })
+    }
+
+    /// print a summary of the preview
+    pub fn print(&self) {
+        println!(
+            "Epoch boundary preview at version {}, epoch {} ending at height {}",
+            self.version, self.epoch, self.height
+        );
+        println!(
+            "validator set: {} now, {} next, {} compliant",
+            self.current_set.len(),
+            self.projected_set.len(),
+            self.compliant_set.len()
+        );
+        for a in self.joining() {
+            println!("  + {}", a);
+        }
+        for a in self.leaving() {
+            println!("  - {}", a);
+        }
+        println!(
+            "nominal subsidy per validator: {}, fullnode proof price: {}",
+            self.nominal_subsidy_per_validator, self.proof_price
+        );
+        for (a, p) in self.payouts.iter() {
+            println!(
+                "{}: subsidy {}, fees {}, refund {}, fullnode {}, burn {}, to community {}",
+                a, p.subsidy, p.fees, p.fee_refund, p.fullnode_subsidy, p.burn, p.sent_to_community
+            );
+        }
+        let t = self.totals();
+        println!(
+            "total minted {}, burned {}, sent to community {}",
+            t.subsidy + t.fullnode_subsidy,
+            t.burn,
+            t.sent_to_community
+        );
+    }
+
+    /// write the preview as json
+    pub fn write_json(&self, path: &Path) -> Result<(), Error> {
+        let mut file = File::create(path)?;
+        file.write_all(&serde_json::to_vec_pretty(&self)?)?;
+        Ok(())
+    }
+}
+
+/// Preview the next epoch boundary from a db, or from the state served by a node's JSON-RPC
+pub fn preview_epoch_boundary(
+    db_path: Option<PathBuf>,
+    rpc_url: Option<String>,
+) -> Result<EpochPreview, Error> {
+    let db = match (db_path, rpc_url) {
+        (Some(p), _) => DiemDebugger::db(p)?,
+        (None, Some(u)) => DiemDebugger::json_rpc(&u)?,
+        (None, None) => bail!("must provide a db or a JSON-RPC url to preview the epoch boundary"),
+    };
+    let version = db.get_latest_version()?;
+
+    let mut preview = EpochPreview {
+        version,
+        epoch: 0,
+        height: 0,
+        current_set: vec![],
+        compliant_set: vec![],
+        projected_set: vec![],
+        nominal_subsidy_per_validator: 0,
+        proof_price: 0,
+        payouts: BTreeMap::new(),
+    };
+
+    // decoding errors are not VM errors, the first one stops the session and is returned after it
+    let mut decode_error = None;
+    // the change set is only read, it is never committed to the db
+    let change_set = db.run_session_at_version(version, None, |session| {
+        macro_rules! decode {
+            ($ret:expr, $index:expr, $function:expr) => {
+                match decode_return(&$ret, $index, $function) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        decode_error = Some(e);
+                        return Ok(());
+                    }
+                }
+            };
+        }
+
+        let mut gas_status = GasStatus::new_unmetered();
+        let log_context = NoContextLog::new();
+        let mut call = |module: &str, function: &str, args: Vec<MoveValue>| {
+            session.execute_function(
+                &module_id(module),
+                &Identifier::new(function).unwrap(),
+                vec![],
+                serialize_values(&args),
+                &mut gas_status,
+                &log_context,
+            )
+        };
+        let vm = || MoveValue::Signer(diem_root_address());
+
+        let ret = call("DiemConfig", "get_current_epoch", vec![])?;
+        preview.epoch = decode!(ret, 0, "DiemConfig::get_current_epoch");
+        let ret = call("DiemBlock", "get_current_block_height", vec![])?;
+        preview.height = decode!(ret, 0, "DiemBlock::get_current_block_height");
+        let ret = call("DiemSystem", "get_val_set_addr", vec![])?;
+        preview.current_set = decode!(ret, 0, "DiemSystem::get_val_set_addr");
+
+        // the same inputs reconfigure uses for the subsidy
+        let ret = call("Epoch", "get_timer_height_start", vec![vm()])?;
+        let height_start: u64 = decode!(ret, 0, "Epoch::get_timer_height_start");
+        let ret = call(
+            "DiemSystem",
+            "get_fee_ratio",
+            vec![
+                vm(),
+                MoveValue::U64(height_start),
+                MoveValue::U64(preview.height),
+            ],
+        )?;
+        preview.compliant_set = decode!(ret, 0, "DiemSystem::get_fee_ratio");
+        let ret = call(
+            "Subsidy",
+            "calculate_subsidy",
+            vec![vm(), MoveValue::U64(preview.compliant_set.len() as u64)],
+        )?;
+        preview.nominal_subsidy_per_validator = decode!(ret, 1, "Subsidy::calculate_subsidy");
+        let ret = call(
+            "FullnodeSubsidy",
+            "get_proof_price",
+            vec![MoveValue::U64(preview.nominal_subsidy_per_validator)],
+        )?;
+        preview.proof_price = decode!(ret, 0, "FullnodeSubsidy::get_proof_price");
+
+        call(
+            "EpochBoundary",
+            "reconfigure",
+            vec![vm(), MoveValue::U64(preview.height)],
+        )?;
+
+        let ret = call("DiemSystem", "get_val_set_addr", vec![])?;
+        preview.projected_set = decode!(ret, 0, "DiemSystem::get_val_set_addr");
+        Ok(())
+    })?;
+    if let Some(e) = decode_error {
+        return Err(e);
+    }
+
+    preview.payouts = tally_payouts(change_set.events());
+    Ok(preview)
+}
+
+/// decode a value returned by a Move function
+fn decode_return<T: DeserializeOwned>(
+    ret: &[Vec<u8>],
+    index: usize,
+    function: &str,
+) -> Result<T, Error> {
+    let bytes = ret
+        .get(index)
+        .ok_or_else(|| anyhow!("{} returned no value at {}", function, index))?;
+    bcs::from_bytes(bytes).with_context(|| {
+        format!(
+            "cannot decode the value at {} returned by {}",
+            index, function
+        )
+    })
+}
+
+/// sort the payment events of the epoch boundary by account and kind, using their metadata
+pub fn tally_payouts(events: &[ContractEvent]) -> BTreeMap<AccountAddress, Payouts> {
+    let mut payouts: BTreeMap<AccountAddress, Payouts> = BTreeMap::new();
+    for e in events.iter() {
+        // the event handle belongs to the account which received or sent
+        let account = e.key().get_creator_address();
+        if let Ok(r) = ReceivedPaymentEvent::try_from(e) {
+            let p = payouts.entry(account).or_default();
+            match r.metadata() {
+                b"validator subsidy" => p.subsidy += r.amount(),
+                b"transaction fees" => p.fees += r.amount(),
+                b"tx fee refund" => p.fee_refund += r.amount(),
+                b"fullnode_subsidy" => p.fullnode_subsidy += r.amount(),
+                _ => {}
+            }
+        } else if let Ok(s) = SentPaymentEvent::try_from(e) {
+            let p = payouts.entry(account).or_default();
+            match s.metadata() {
+                b"burn" => p.burn += s.amount(),
+                b"epoch start send" => p.sent_to_community += s.amount(),
+                _ => {}
+            }
+        }
+    }
+    // accounts which only moved coins for other reasons, e.g. community wallets
+    payouts.retain(|_, p| *p != Payouts::default());
+    payouts
+}
+
+#[test]
+fn test_tally_payouts() {
+    use diem_types::event::EventKey;
+    use move_core_types::{language_storage::TypeTag, move_resource::MoveStructType};
+
+    let val = AccountAddress::random();
+    let miner = AccountAddress::random();
+    let root = diem_root_address();
+    let received = |to: AccountAddress, amount: u64, metadata: &[u8]| {
+        ContractEvent::new(
+            EventKey::new_from_address(&to, 0),
+            0,
+            TypeTag::Struct(ReceivedPaymentEvent::struct_tag()),
+            bcs::to_bytes(&(amount, "GAS", root, metadata.to_vec())).unwrap(),
+        )
+    };
+    let sent = |from: AccountAddress, amount: u64, metadata: &[u8]| {
+        ContractEvent::new(
+            EventKey::new_from_address(&from, 1),
+            0,
+            TypeTag::Struct(SentPaymentEvent::struct_tag()),
+            bcs::to_bytes(&(amount, "GAS", root, metadata.to_vec())).unwrap(),
+        )
+    };
+    let events = vec![
+        received(val, 100, b"validator subsidy"),
+        received(val, 7, b"transaction fees"),
+        sent(val, 50, b"burn"),
+        received(miner, 3, b"fullnode_subsidy"),
+        received(miner, 9, b"community wallet"),
+    ];
+
+    let payouts = tally_payouts(&events);
+    assert_eq!(payouts.len(), 2);
+    assert_eq!(payouts[&val].subsidy, 100);
+    assert_eq!(payouts[&val].fees, 7);
+    assert_eq!(payouts[&val].burn, 50);
+    assert_eq!(payouts[&miner].fullnode_subsidy, 3);
+    assert_eq!(payouts[&miner].subsidy, 0);
+}
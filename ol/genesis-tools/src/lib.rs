@@ -8,6 +8,7 @@
     unused_qualifications
 )]
 
+pub mod epoch_preview;
 pub mod fetch_archive;
 pub mod fork_daemon;
 pub mod fork_genesis;
@@ -15,3 +16,4 @@ pub mod process_snapshot;
 pub mod read_snapshot;
 pub mod recover;
 pub mod swarm_genesis;
+pub mod upgrade_rehearsal;
@@ -2,7 +2,10 @@ use anyhow::Result;
 use std::{path::PathBuf, process::exit};
 
 use gumdrop::Options;
-use ol_genesis_tools::{fork_genesis::make_recovery_genesis, swarm_genesis::make_swarm_genesis};
+use ol_genesis_tools::{
+    epoch_preview::preview_epoch_boundary, fork_genesis::make_recovery_genesis,
+    swarm_genesis::make_swarm_genesis, upgrade_rehearsal::rehearse_upgrade,
+};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -26,6 +29,22 @@ async fn main() -> Result<()> {
         daemon: bool,
         #[options(help = "swarm simulation mode")]
         swarm: bool,
+        #[options(help = "rehearse a stdlib upgrade on a db, or on a backup with --snapshot-path")]
+        rehearse_upgrade: bool,
+        #[options(help = "stdlib.mv upgrade payload to rehearse")]
+        stdlib_path: Option<PathBuf>,
+        #[options(
+            help = "db to rehearse the upgrade or preview the epoch boundary on, opened read-only"
+        )]
+        db_path: Option<PathBuf>,
+        #[options(help = "optional, proof file for the tower commit of the rehearsal")]
+        tower_proof: Option<PathBuf>,
+        #[options(help = "optional, write the rehearsal or preview report as json")]
+        report_path: Option<PathBuf>,
+        #[options(help = "preview the next epoch boundary on a db, or on a node with --rpc-url")]
+        epoch_preview: bool,
+        #[options(help = "JSON-RPC url of a node, to read the state of the preview from")]
+        rpc_url: Option<String>,
     }
 
     let opts = Args::parse_args_default_or_exit();
@@ -76,6 +95,54 @@ async fn main() -> Result<()> {
             println!("ERROR: must provide a path with --snapshot, exiting.");
             exit(1);
         }
+    } else if opts.rehearse_upgrade {
+        // apply the upgrade to a copy of the state and compare smoke transactions
+        let stdlib_path = match opts.stdlib_path {
+            Some(p) => p,
+            None => {
+                println!("ERROR: must provide the upgrade payload with --stdlib-path, exiting.");
+                exit(1);
+            }
+        };
+        match rehearse_upgrade(
+            stdlib_path,
+            opts.db_path,
+            opts.snapshot_path,
+            opts.tower_proof,
+        ) {
+            Ok(report) => {
+                report.print();
+                if let Some(p) = opts.report_path {
+                    report.write_json(&p)?;
+                }
+                if report.has_regressions() {
+                    exit(1);
+                }
+                return Ok(());
+            }
+            Err(e) => {
+                println!("ERROR: could not rehearse the upgrade, message: {:?}", e);
+                exit(1);
+            }
+        }
+    } else if opts.epoch_preview {
+        // run the epoch boundary on a copy of the state, nothing is committed
+        match preview_epoch_boundary(opts.db_path, opts.rpc_url) {
+            Ok(preview) => {
+                preview.print();
+                if let Some(p) = opts.report_path {
+                    preview.write_json(&p)?;
+                }
+                return Ok(());
+            }
+            Err(e) => {
+                println!(
+                    "ERROR: could not preview the epoch boundary, message: {:?}",
+                    e
+                );
+                exit(1);
+            }
+        }
     } else {
         println!("ERROR: no options provided, exiting.");
         exit(1);
@@ -0,0 +1,559 @@
+//! `upgrade_rehearsal` apply a stdlib upgrade payload to a copy of the chain state and run smoke transactions against it
+
+use anyhow::{bail, Error};
+use diem_framework_releases::import_stdlib;
+use diem_temppath::TempPath;
+use diem_transaction_replay::DiemDebugger;
+use diem_types::{
+    account_address::AccountAddress,
+    account_config::{diem_root_address, CORE_CODE_ADDRESS},
+    transaction::Version,
+    write_set::WriteOp,
+};
+use move_core_types::{
+    gas_schedule::{GasAlgebra, GasUnits},
+    identifier::Identifier,
+    language_storage::{ModuleId, CODE_TAG},
+    value::{serialize_values, MoveValue},
+};
+use move_vm_runtime::logging::NoContextLog;
+use move_vm_types::gas_schedule::GasStatus;
+use ol::mgmt::restore::{restore_epoch, restore_snapshot, restore_transaction};
+use ol_types::block::VDFProof;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+use vm_genesis::genesis_gas_schedule::INITIAL_GAS_SCHEDULE;
+
+/// gas units each smoke transaction may use
+pub const REHEARSAL_MAX_GAS: u64 = 1_000_000;
+
+/// coins sent by the transfer smoke transaction, unscaled
+pub const REHEARSAL_TRANSFER_COINS: u64 = 1;
+
+/// Transactions run before and after the upgrade
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmokeStep {
+    /// `TransferScripts::balance_transfer` between two validators
+    Transfer,
+    /// `TowerStateScripts::minerstate_commit` with a proof file
+    TowerCommit,
+    /// `AutoPay::process_autopay`, as in the block prologue
+    AutopayTick,
+    /// `EpochBoundary::reconfigure` at the current height
+    EpochBoundary,
+}
+
+/// Accounts and inputs of the smoke transactions
+#[derive(Clone, Debug)]
+pub struct SmokeContext {
+    /// signer of the transfer and the tower commit
+    pub sender: AccountAddress,
+    /// recipient of the transfer
+    pub receiver: AccountAddress,
+    /// block height passed to the epoch boundary
+    pub height: u64,
+    /// proof for the tower commit, the step is skipped without one
+    pub proof: Option<VDFProof>,
+}
+
+/// Result of one smoke transaction against one stdlib
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct StepOutcome {
+    /// did the transaction execute without abort
+    pub success: bool,
+    /// gas units used
+    pub gas_used: u64,
+    /// the VM error, if any
+    pub error: Option<String>,
+    /// resources written, by access path, hex values. None is a deletion.
+    pub writes: BTreeMap<String, Option<String>>,
+}
+
+/// Resources written differently by the two stdlibs
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct WriteDiff {
+    /// written only with the current stdlib
+    pub only_baseline: Vec<String>,
+    /// written only with the upgraded stdlib
+    pub only_upgraded: Vec<String>,
+    /// written by both, with different values
+    pub changed: Vec<String>,
+}
+
+impl WriteDiff {
+    /// both stdlibs wrote the same state
+    pub fn is_empty(&self) -> bool {
+        self.only_baseline.is_empty() && self.only_upgraded.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A smoke transaction run with the current and the upgraded stdlib
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StepComparison {
+    /// the transaction
+    pub step: SmokeStep,
+    /// with the stdlib on chain
+    pub baseline: StepOutcome,
+    /// with the upgrade applied
+    pub upgraded: StepOutcome,
+    /// state written differently
+    pub diff: WriteDiff,
+}
+
+/// Modules changed by the upgrade payload
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ModuleDiff {
+    /// new modules
+    pub added: Vec<String>,
+    /// modules with different bytecode
+    pub changed: Vec<String>,
+    /// modules on chain missing from the payload, they are left published
+    pub removed: Vec<String>,
+    /// modules the VM refused to publish, with the error
+    pub publish_failed: BTreeMap<String, String>,
+}
+
+/// Full rehearsal report
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RehearsalReport {
+    /// version of the state the upgrade was applied to
+    pub version: Version,
+    /// modules of the payload compared to the chain
+    pub modules: ModuleDiff,
+    /// smoke transactions, skipped ones are not listed
+    pub steps: Vec<StepComparison>,
+}
+
+impl RehearsalReport {
+    /// the upgrade can't be published, or breaks a transaction which works today
+    pub fn has_regressions(&self) -> bool {
+        !self.modules.publish_failed.is_empty()
+            || self
+                .steps
+                .iter()
+                .any(|s| s.baseline.success && !s.upgraded.success)
+    }
+
+    /// print a summary of the report
+    pub fn print(&self) {
+        println!("Upgrade rehearsal at version {}", self.version);
+        println!(
+            "modules: {} added, {} changed, {} not in payload",
+            self.modules.added.len(),
+            self.modules.changed.len(),
+            self.modules.removed.len()
+        );
+        for (name, e) in self.modules.publish_failed.iter() {
+            println!("  cannot publish {}: {}", name, e);
+        }
+        for s in self.steps.iter() {
+            println!(
+                "{:?}: baseline {} ({} gas), upgraded {} ({} gas)",
+                s.step,
+                status(&s.baseline),
+                s.baseline.gas_used,
+                status(&s.upgraded),
+                s.upgraded.gas_used,
+            );
+            if let Some(e) = &s.upgraded.error {
+                println!("  upgraded error: {}", e);
+            }
+            for p in s.diff.only_baseline.iter() {
+                println!("  - {}", p);
+            }
+            for p in s.diff.only_upgraded.iter() {
+                println!("  + {}", p);
+            }
+            for p in s.diff.changed.iter() {
+                println!("  ~ {}", p);
+            }
+        }
+        if !self.modules.publish_failed.is_empty() {
+            println!(
+                "WARN: {} modules of the upgrade cannot be published, the steps ran without them",
+                self.modules.publish_failed.len()
+            );
+        }
+        if self
+            .steps
+            .iter()
+            .any(|s| s.baseline.success && !s.upgraded.success)
+        {
+            println!("WARN: the upgrade aborts transactions which succeed today");
+        }
+    }
+
+    /// write the report as json
+    pub fn write_json(&self, path: &Path) -> Result<(), Error> {
+        let mut file = File::create(path)?;
+        file.write_all(&serde_json::to_vec_pretty(&self)?)?;
+        Ok(())
+    }
+}
+
+/// Rehearse a stdlib upgrade payload (stdlib.mv) against a db, or a backup restored to a scratch db
+pub fn rehearse_upgrade(
+    stdlib_path: PathBuf,
+    db_path: Option<PathBuf>,
+    backup_path: Option<PathBuf>,
+    proof_path: Option<PathBuf>,
+) -> Result<RehearsalReport, Error> {
+    let payload = fs::read(&stdlib_path)?;
+    let new_modules = import_stdlib(&payload)?;
+    if new_modules.is_empty() {
+        bail!("no modules could be read from {:?}", stdlib_path);
+    }
+
+    // the scratch db is deleted when dropped
+    let scratch = TempPath::new();
+    let db_path = match (db_path, backup_path) {
+        (Some(p), _) => p,
+        (None, Some(b)) => {
+            scratch.create_as_dir()?;
+            restore_scratch_db(&b, scratch.path())?;
+            scratch.path().to_owned()
+        }
+        (None, None) => bail!("must provide a db or a backup to rehearse the upgrade on"),
+    };
+
+    let db = DiemDebugger::db(db_path)?;
+    let version = db.get_latest_version()?;
+
+    let current = db.get_diem_framework_modules_at_version(version, false)?;
+    let mut current_bytes = BTreeMap::new();
+    for m in current {
+        let mut bytes = vec![];
+        m.serialize(&mut bytes)?;
+        current_bytes.insert(m.self_id().name().to_string(), bytes);
+    }
+    let mut upgrade_bytes = BTreeMap::new();
+    for m in new_modules {
+        let mut bytes = vec![];
+        m.serialize(&mut bytes)?;
+        upgrade_bytes.insert(m.self_id().name().to_string(), bytes);
+    }
+    let mut modules = diff_modules(&current_bytes, &upgrade_bytes);
+    modules.publish_failed = check_publish(&db, version, &upgrade_bytes)?;
+    // modules which can't be published are left out, the smoke steps run with the others
+    let upgrade: Vec<Vec<u8>> = upgrade_bytes
+        .into_iter()
+        .filter(|(name, _)| !modules.publish_failed.contains_key(name))
+        .map(|(_, b)| b)
+        .collect();
+
+    let proof = proof_path.map(VDFProof::parse_block_file);
+    let ctx = smoke_context(&db, version, proof)?;
+
+    let mut steps = vec![];
+    for step in [
+        SmokeStep::Transfer,
+        SmokeStep::TowerCommit,
+        SmokeStep::AutopayTick,
+        SmokeStep::EpochBoundary,
+    ]
+    .iter()
+    {
+        if *step == SmokeStep::TowerCommit && ctx.proof.is_none() {
+            println!("no tower proof provided, skipping the tower commit");
+            continue;
+        }
+        let baseline = run_step(&db, version, None, *step, &ctx)?;
+        let upgraded = run_step(&db, version, Some(&upgrade), *step, &ctx)?;
+        let diff = diff_writes(&baseline.writes, &upgraded.writes);
+        steps.push(StepComparison {
+            step: *step,
+            baseline,
+            upgraded,
+            diff,
+        });
+    }
+
+    Ok(RehearsalReport {
+        version,
+        modules,
+        steps,
+    })
+}
+
+/// restore the epoch, transactions and state snapshot of a backup into a db
+fn restore_scratch_db(backup_path: &Path, db_path: &Path) -> Result<(), Error> {
+    let restore_path = match backup_path.to_str() {
+        Some(p) => p,
+        None => bail!("backup path is not valid utf8: {:?}", backup_path),
+    };
+    let version = backup_waypoint_version(backup_path)?;
+    let db_path = db_path.to_owned();
+    println!(
+        "restoring backup at version {} into {:?}",
+        version, &db_path
+    );
+    restore_epoch(&db_path, restore_path, false)?;
+    restore_transaction(&db_path, restore_path, false)?;
+    restore_snapshot(&db_path, restore_path, &version, false)?;
+    Ok(())
+}
+
+/// version of the waypoint in the backup's epoch ending manifest
+fn backup_waypoint_version(backup_path: &Path) -> Result<Version, Error> {
+    for entry in fs::read_dir(backup_path)? {
+        let path = entry?.path();
+        let manifest = path.join("epoch_ending.manifest");
+        if manifest.exists() {
+            let m: serde_json::Value = serde_json::from_str(&fs::read_to_string(&manifest)?)?;
+            // waypoints are serialized as "version:hash"
+            if let Some(w) = m["waypoints"][0].as_str() {
+                if let Some(Ok(v)) = w.split(':').next().map(|v| v.parse::<Version>()) {
+                    return Ok(v);
+                }
+            }
+            bail!("cannot read the waypoint in {:?}", manifest);
+        }
+    }
+    bail!("no epoch_ending.manifest found in {:?}", backup_path)
+}
+
+/// transfer between the first two validators, at the current height
+fn smoke_context(
+    db: &DiemDebugger,
+    version: Version,
+    proof: Option<VDFProof>,
+) -> Result<SmokeContext, Error> {
+    let mut vals = vec![];
+    let mut height = 0;
+    db.run_session_at_version(version, None, |session| {
+        let mut gas_status = GasStatus::new_unmetered();
+        let log_context = NoContextLog::new();
+        let ret = session.execute_function(
+            &module_id("DiemSystem"),
+            &Identifier::new("get_val_set_addr").unwrap(),
+            vec![],
+            vec![],
+            &mut gas_status,
+            &log_context,
+        )?;
+        vals = bcs::from_bytes::<Vec<AccountAddress>>(&ret[0]).unwrap_or_default();
+        let ret = session.execute_function(
+            &module_id("DiemBlock"),
+            &Identifier::new("get_current_block_height").unwrap(),
+            vec![],
+            vec![],
+            &mut gas_status,
+            &log_context,
+        )?;
+        height = bcs::from_bytes::<u64>(&ret[0]).unwrap_or_default();
+        Ok(())
+    })?;
+
+    if vals.len() < 2 {
+        bail!("need at least two validators in the set to rehearse a transfer");
+    }
+    Ok(SmokeContext {
+        sender: vals[0],
+        receiver: vals[1],
+        height,
+        proof,
+    })
+}
+
+/// publish every module of the upgrade, collecting the failures instead of stopping at the first
+fn check_publish(
+    db: &DiemDebugger,
+    version: Version,
+    upgrade: &BTreeMap<String, Vec<u8>>,
+) -> Result<BTreeMap<String, String>, Error> {
+    let mut failed = BTreeMap::new();
+    db.run_session_at_version(version, None, |session| {
+        let log_context = NoContextLog::new();
+        let mut gas_status = GasStatus::new_unmetered();
+        for (name, bytes) in upgrade {
+            if let Err(e) = session.revise_module(
+                bytes.clone(),
+                CORE_CODE_ADDRESS,
+                &mut gas_status,
+                &log_context,
+            ) {
+                failed.insert(name.clone(), format!("{:?}", e));
+            }
+        }
+        Ok(())
+    })?;
+    Ok(failed)
+}
+
+/// run a smoke transaction in its own session, with the upgrade applied first if given
+fn run_step(
+    db: &DiemDebugger,
+    version: Version,
+    upgrade: Option<&[Vec<u8>]>,
+    step: SmokeStep,
+    ctx: &SmokeContext,
+) -> Result<StepOutcome, Error> {
+    let mut outcome = StepOutcome::default();
+    let change_set = db.run_session_at_version(version, None, |session| {
+        let log_context = NoContextLog::new();
+        if let Some(modules) = upgrade {
+            let mut gas_status = GasStatus::new_unmetered();
+            for bytes in modules {
+                session.revise_module(
+                    bytes.clone(),
+                    CORE_CODE_ADDRESS,
+                    &mut gas_status,
+                    &log_context,
+                )?;
+            }
+        }
+
+        let mut gas_status =
+            GasStatus::new(&INITIAL_GAS_SCHEDULE, GasUnits::new(REHEARSAL_MAX_GAS));
+        let vm = MoveValue::Signer(diem_root_address());
+        let res = match step {
+            SmokeStep::Transfer => session.execute_script_function(
+                &module_id("TransferScripts"),
+                &Identifier::new("balance_transfer").unwrap(),
+                vec![],
+                serialize_values(&vec![
+                    MoveValue::Address(ctx.receiver),
+                    MoveValue::U64(REHEARSAL_TRANSFER_COINS),
+                ]),
+                vec![ctx.sender],
+                &mut gas_status,
+                &log_context,
+            ),
+            SmokeStep::TowerCommit => {
+                let proof = ctx.proof.as_ref().unwrap();
+                session.execute_script_function(
+                    &module_id("TowerStateScripts"),
+                    &Identifier::new("minerstate_commit").unwrap(),
+                    vec![],
+                    serialize_values(&vec![
+                        MoveValue::vector_u8(proof.preimage.clone()),
+                        MoveValue::vector_u8(proof.proof.clone()),
+                        MoveValue::U64(proof.difficulty()),
+                        MoveValue::U64(proof.security()),
+                    ]),
+                    vec![ctx.sender],
+                    &mut gas_status,
+                    &log_context,
+                )
+            }
+            SmokeStep::AutopayTick => session
+                .execute_function(
+                    &module_id("AutoPay"),
+                    &Identifier::new("process_autopay").unwrap(),
+                    vec![],
+                    serialize_values(&vec![vm]),
+                    &mut gas_status,
+                    &log_context,
+                )
+                .map(|_| ()),
+            SmokeStep::EpochBoundary => session
+                .execute_function(
+                    &module_id("EpochBoundary"),
+                    &Identifier::new("reconfigure").unwrap(),
+                    vec![],
+                    serialize_values(&vec![vm, MoveValue::U64(ctx.height)]),
+                    &mut gas_status,
+                    &log_context,
+                )
+                .map(|_| ()),
+        };
+        outcome.gas_used = REHEARSAL_MAX_GAS - gas_status.remaining_gas().get();
+        outcome.success = res.is_ok();
+        outcome.error = res.err().map(|e| format!("{:?}", e));
+        Ok(())
+    })?;
+
+    // an aborted transaction leaves no writes
+    if outcome.success {
+        for (ap, op) in change_set.write_set().iter() {
+            // the upgrade's own module writes are reported in the module diff
+            if ap.path.first() == Some(&CODE_TAG) {
+                continue;
+            }
+            let value = match op {
+                WriteOp::Value(v) => Some(hex::encode(v)),
+                WriteOp::Deletion => None,
+            };
+            outcome.writes.insert(ap.to_string(), value);
+        }
+    }
+    Ok(outcome)
+}
+
+/// compare the resources written by the two runs
+pub fn diff_writes(
+    baseline: &BTreeMap<String, Option<String>>,
+    upgraded: &BTreeMap<String, Option<String>>,
+) -> WriteDiff {
+    let mut diff = WriteDiff::default();
+    for (path, value) in baseline.iter() {
+        match upgraded.get(path) {
+            None => diff.only_baseline.push(path.clone()),
+            Some(v) if v != value => diff.changed.push(path.clone()),
+            _ => {}
+        }
+    }
+    for path in upgraded.keys() {
+        if !baseline.contains_key(path) {
+            diff.only_upgraded.push(path.clone());
+        }
+    }
+    diff
+}
+
+/// compare the modules on chain to the payload, by name
+pub fn diff_modules(
+    current: &BTreeMap<String, Vec<u8>>,
+    upgrade: &BTreeMap<String, Vec<u8>>,
+) -> ModuleDiff {
+    let mut diff = ModuleDiff::default();
+    for (name, bytes) in upgrade.iter() {
+        match current.get(name) {
+            None => diff.added.push(name.clone()),
+            Some(b) if b != bytes => diff.changed.push(name.clone()),
+            _ => {}
+        }
+    }
+    for name in current.keys() {
+        if !upgrade.contains_key(name) {
+            diff.removed.push(name.clone());
+        }
+    }
+    diff
+}
+
+pub(crate) fn module_id(name: &str) -> ModuleId {
+    ModuleId::new(CORE_CODE_ADDRESS, Identifier::new(name).unwrap())
+}
+
+fn status(o: &StepOutcome) -> &'static str {
+    if o.success {
+        "ok"
+    } else {
+        "aborted"
+    }
+}
+
+#[test]
+fn test_diff_writes() {
+    let mut baseline = BTreeMap::new();
+    baseline.insert("a".to_owned(), Some("01".to_owned()));
+    baseline.insert("b".to_owned(), Some("02".to_owned()));
+    baseline.insert("c".to_owned(), None);
+    let mut upgraded = BTreeMap::new();
+    upgraded.insert("a".to_owned(), Some("01".to_owned()));
+    upgraded.insert("b".to_owned(), Some("03".to_owned()));
+    upgraded.insert("d".to_owned(), Some("04".to_owned()));
+
+    let diff = diff_writes(&baseline, &upgraded);
+    assert_eq!(diff.only_baseline, vec!["c".to_owned()]);
+    assert_eq!(diff.only_upgraded, vec!["d".to_owned()]);
+    assert_eq!(diff.changed, vec!["b".to_owned()]);
+    assert!(diff_writes(&baseline, &baseline).is_empty());
+}
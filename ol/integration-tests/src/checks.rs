@@ -0,0 +1,95 @@
+//! `checks` on-chain state of the swarm, read through JSON-RPC
+
+use anyhow::Error;
+use cli::diem_client::DiemClient;
+use diem_types::{account_address::AccountAddress, account_state::AccountState};
+use ol::node::query::{find_value_from_state, is_community_wallet};
+use ol_types::autopay::AutoPayResource;
+use resource_viewer::{
+    AnnotatedAccountStateBlob, AnnotatedMoveValue, MoveValueAnnotator, NullStateView,
+};
+use std::convert::TryFrom;
+
+/// account state, None if the account does not exist
+pub fn account_state(
+    client: &DiemClient,
+    address: AccountAddress,
+) -> Result<Option<AccountState>, Error> {
+    match client.get_account_state_blob(&address)?.0 {
+        Some(blob) => Ok(Some(AccountState::try_from(&blob)?)),
+        None => Ok(None),
+    }
+}
+
+/// Move-annotated resources of an account, None if the account does not exist
+pub fn annotated_state(
+    client: &DiemClient,
+    address: AccountAddress,
+) -> Result<Option<AnnotatedAccountStateBlob>, Error> {
+    match account_state(client, address)? {
+        Some(state) => {
+            let state_view = NullStateView::default();
+            let annotator = MoveValueAnnotator::new(&state_view);
+            Ok(Some(annotator.view_account_state(&state)?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// the account has a tower, i.e. was onboarded with a proof
+pub fn has_tower(client: &DiemClient, address: AccountAddress) -> Result<bool, Error> {
+    Ok(client.get_miner_state(&address)?.is_some())
+}
+
+/// payees of the account's autopay instructions
+pub fn autopay_payees(
+    client: &DiemClient,
+    address: AccountAddress,
+) -> Result<Vec<AccountAddress>, Error> {
+    let autopay = match account_state(client, address)? {
+        Some(s) => s.get_resource_impl::<AutoPayResource>(&AutoPayResource::resource_path())?,
+        None => None,
+    };
+    Ok(autopay
+        .map(|a| a.payment.iter().map(|p| p.payee).collect())
+        .unwrap_or_default())
+}
+
+/// the account is an unfrozen community wallet
+pub fn is_community(client: &DiemClient, address: AccountAddress) -> Result<bool, Error> {
+    Ok(annotated_state(client, address)?
+        .map(|blob| is_community_wallet(&blob))
+        .unwrap_or(false))
+}
+
+/// community wallet transfers proposed and waiting for the veto period
+pub fn proposed_community_transfers(client: &DiemClient) -> Result<usize, Error> {
+    system_vector_len(client, "Wallet", "CommunityTransfers", "proposed")
+}
+
+/// stdlib upgrades recorded on chain
+pub fn upgrade_records(client: &DiemClient) -> Result<usize, Error> {
+    system_vector_len(client, "Upgrade", "UpgradeHistory", "records")
+}
+
+/// length of a vector in a resource of the system account
+fn system_vector_len(
+    client: &DiemClient,
+    module: &str,
+    resource: &str,
+    field: &str,
+) -> Result<usize, Error> {
+    let blob = match annotated_state(client, AccountAddress::ZERO)? {
+        Some(b) => b,
+        None => return Ok(0),
+    };
+    match find_value_from_state(
+        &blob,
+        module.to_owned(),
+        resource.to_owned(),
+        field.to_owned(),
+    ) {
+        Some(AnnotatedMoveValue::Vector(_, v)) => Ok(v.len()),
+        _ => Ok(0),
+    }
+}
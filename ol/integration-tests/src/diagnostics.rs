@@ -0,0 +1,90 @@
+//! `diagnostics` what a failed check saw, and what the swarm was doing at the time
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// lines of each log kept in a failure
+pub const LOG_TAIL_LINES: usize = 20;
+
+/// A check that did not pass
+#[derive(Debug)]
+pub struct CheckFailure {
+    /// what was being checked
+    pub check: String,
+    /// time spent before giving up
+    pub elapsed: Duration,
+    /// the last state or error the check observed
+    pub last_observed: String,
+    /// epoch of the swarm when the check failed, if it could be read
+    pub epoch: Option<u64>,
+    /// Move abort code, if a transaction failed
+    pub abort_code: Option<u64>,
+    /// Move module or script of the abort
+    pub location: Option<String>,
+    /// last lines of the swarm logs
+    pub log_tails: Vec<(PathBuf, Vec<String>)>,
+}
+
+impl fmt::Display for CheckFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "check failed: {}", self.check)?;
+        writeln!(f, "  elapsed:       {:?}", self.elapsed)?;
+        writeln!(f, "  last observed: {}", self.last_observed)?;
+        if let Some(e) = self.epoch {
+            writeln!(f, "  epoch:         {}", e)?;
+        }
+        if let Some(c) = self.abort_code {
+            writeln!(f, "  abort code:    {}", c)?;
+        }
+        if let Some(l) = &self.location {
+            writeln!(f, "  location:      {}", l)?;
+        }
+        for (path, lines) in self.log_tails.iter() {
+            writeln!(f, "  --- {:?}", path)?;
+            for l in lines.iter() {
+                writeln!(f, "  {}", l)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CheckFailure {}
+
+/// last lines of every log in the swarm's logs directory
+pub fn tail_logs(swarm_path: &Path, lines: usize) -> Vec<(PathBuf, Vec<String>)> {
+    let mut logs: Vec<PathBuf> = match fs::read_dir(swarm_path.join("logs")) {
+        Ok(dir) => dir
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().map_or(false, |ext| ext == "log"))
+            .collect(),
+        Err(_) => return vec![],
+    };
+    logs.sort();
+    logs.into_iter()
+        .filter_map(|p| {
+            let text = fs::read_to_string(&p).ok()?;
+            Some((p, tail(&text, lines)))
+        })
+        .collect()
+}
+
+/// the last n lines of a text
+fn tail(text: &str, n: usize) -> Vec<String> {
+    let all: Vec<&str> = text.lines().collect();
+    all[all.len().saturating_sub(n)..]
+        .iter()
+        .map(|l| l.to_string())
+        .collect()
+}
+
+#[test]
+fn test_tail() {
+    assert_eq!(tail("a\nb\nc", 2), vec!["b", "c"]);
+    assert_eq!(tail("a", 5), vec!["a"]);
+    assert!(tail("", 5).is_empty());
+}
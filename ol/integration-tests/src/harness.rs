@@ -0,0 +1,208 @@
+//! `harness` a swarm under test, driven through the onboard, tower and txs libraries
+
+use crate::diagnostics::{tail_logs, CheckFailure, LOG_TAIL_LINES};
+use anyhow::{bail, Error};
+use diem_temppath::TempPath;
+use diem_types::transaction::TransactionPayload;
+use ol::swarm::harness::{persona_index, resolve_address, SwarmHarness, POLL_INTERVAL_SECS};
+use ol_types::config::{parse_toml, AppCfg};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+use tower::{
+    commit_proof::commit_proof_tx, next_proof::get_next_proof_from_chain, proof::mine_once,
+};
+use txs::{
+    submit_tx::{maybe_submit, TxError},
+    tx_params::TxParams,
+};
+
+/// State of a polled check
+pub enum Poll {
+    /// the check passed
+    Done,
+    /// not yet, with what was observed
+    Pending(String),
+}
+
+/// A swarm under test
+pub struct E2eSwarm {
+    /// the running swarm
+    pub swarm: SwarmHarness,
+    /// removes the swarm directory, dropped after the swarm
+    _swarm_dir: Option<TempPath>,
+}
+
+impl E2eSwarm {
+    /// start a swarm with the binaries of SOURCE_PATH, or of this workspace.
+    /// The swarm lives in a temp dir, unless E2E_SWARM_PATH is set.
+    pub fn start(num_nodes: usize) -> Result<E2eSwarm, Error> {
+        E2eSwarm::start_from(source_path()?, num_nodes)
+    }
+
+    /// start a swarm with the binaries, and so the genesis stdlib, of the given source tree
+    pub fn start_from(source_path: PathBuf, num_nodes: usize) -> Result<E2eSwarm, Error> {
+        // the tools skip their prompts in test mode, which is read from the environment of the
+        // whole test process, so it is not set here
+        if env::var("NODE_ENV").as_deref() != Ok("test") || env::var("TEST").is_err() {
+            bail!("the end to end tests need NODE_ENV=test and TEST=y in the environment");
+        }
+
+        let (swarm_path, swarm_dir) = match env::var("E2E_SWARM_PATH") {
+            Ok(p) => (PathBuf::from(p), None),
+            Err(_) => {
+                let temp = TempPath::new();
+                (temp.path().to_owned(), Some(temp))
+            }
+        };
+        let swarm = SwarmHarness::start(source_path, swarm_path, num_nodes)?;
+        Ok(E2eSwarm {
+            swarm,
+            _swarm_dir: swarm_dir,
+        })
+    }
+
+    /// tx params of a persona with a node in the swarm
+    pub fn tx_params(&self, persona: &str) -> Result<TxParams, Error> {
+        persona_index(persona, self.swarm.num_nodes())?;
+        TxParams::get_tx_params_from_swarm(self.swarm.swarm_path.clone(), persona.to_owned(), false)
+    }
+
+    /// 0L.toml of a persona with a node in the swarm
+    pub fn persona_cfg(&self, persona: &str) -> Result<AppCfg, Error> {
+        let idx = persona_index(persona, self.swarm.num_nodes())?;
+        let mut cfg = parse_toml(Some(
+            self.swarm.swarm_path.join(idx.to_string()).join("0L.toml"),
+        ))?;
+        // swarm configs are all written for alice
+        cfg.profile.account = resolve_address(persona)?;
+        Ok(cfg)
+    }
+
+    /// sign a transaction as a persona, and wait for it to execute
+    pub fn submit(
+        &self,
+        persona: &str,
+        what: &str,
+        script: TransactionPayload,
+    ) -> Result<(), Error> {
+        let tx_params = self.tx_params(persona)?;
+        match maybe_submit(script, &tx_params, None) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(self
+                .tx_failure(&format!("{} by {}", what, persona), e)
+                .into()),
+        }
+    }
+
+    /// mine and commit proofs as a persona, returns the tower height on chain
+    pub fn mine(&self, persona: &str, proofs: u64) -> Result<u64, Error> {
+        let mut cfg = self.persona_cfg(persona)?;
+        let tx_params = self.tx_params(persona)?;
+        for _ in 0..proofs {
+            let next = get_next_proof_from_chain(
+                &mut cfg,
+                self.swarm.client()?,
+                Some(self.swarm.swarm_path.clone()),
+            )?;
+            let height = next.next_height;
+            let proof = mine_once(&cfg, next)?;
+            if let Err(e) = commit_proof_tx(&tx_params, proof) {
+                return Err(self
+                    .tx_failure(&format!("commit proof {} by {}", height, persona), e)
+                    .into());
+            }
+        }
+        self.swarm.tower_height(cfg.profile.account)
+    }
+
+    /// poll a check until it passes, or fail with what it last saw.
+    /// Errors while polling are observed, not fatal, since nodes can be busy at epoch boundaries.
+    pub fn wait_until<F>(&self, check: &str, timeout: Duration, mut poll: F) -> Result<(), Error>
+    where
+        F: FnMut() -> Result<Poll, Error>,
+    {
+        let started = Instant::now();
+        loop {
+            let observed = match poll() {
+                Ok(Poll::Done) => return Ok(()),
+                Ok(Poll::Pending(o)) => o,
+                Err(e) => format!("error: {:?}", e),
+            };
+            if started.elapsed() > timeout {
+                return Err(self.failure(check, started.elapsed(), observed).into());
+            }
+            thread::sleep(Duration::from_secs(POLL_INTERVAL_SECS));
+        }
+    }
+
+    /// fail with the state of the swarm if a one-off check did not pass
+    pub fn ensure(&self, check: &str, passed: bool, observed: String) -> Result<(), Error> {
+        if passed {
+            Ok(())
+        } else {
+            Err(self.failure(check, Duration::default(), observed).into())
+        }
+    }
+
+    /// a failure with the current state of the swarm
+    pub fn failure(&self, check: &str, elapsed: Duration, last_observed: String) -> CheckFailure {
+        CheckFailure {
+            check: check.to_owned(),
+            elapsed,
+            last_observed,
+            epoch: self.swarm.epoch().ok(),
+            abort_code: None,
+            location: None,
+            log_tails: tail_logs(&self.swarm.swarm_path, LOG_TAIL_LINES),
+        }
+    }
+
+    fn tx_failure(&self, check: &str, e: TxError) -> CheckFailure {
+        let last_observed = match (&e.err, &e.tx_view) {
+            (Some(err), _) => format!("{:?}", err),
+            (None, Some(view)) => format!("{:?}", view.vm_status),
+            (None, None) => "transaction failed without a message".to_owned(),
+        };
+        CheckFailure {
+            abort_code: e.abort_code,
+            location: e.location,
+            ..self.failure(check, Duration::default(), last_observed)
+        }
+    }
+}
+
+/// the source tree to take binaries from: SOURCE_PATH, or this workspace
+pub fn source_path() -> Result<PathBuf, Error> {
+    let path = match env::var("SOURCE_PATH") {
+        Ok(p) => PathBuf::from(p),
+        Err(_) => Path::new(env!("CARGO_MANIFEST_DIR")).join("../.."),
+    };
+    Ok(path.canonicalize()?)
+}
+
+/// checkout of the previous release, PREV_SOURCE_PATH, with its node built
+pub fn prev_release_path() -> Result<PathBuf, Error> {
+    let path = match env::var("PREV_SOURCE_PATH") {
+        Ok(p) => PathBuf::from(p),
+        Err(_) => bail!(
+            "PREV_SOURCE_PATH is not set. Check out the release in production (PREV_VERSION) and \
+            build its node with `cargo build -p diem-node`, see ol/documentation/core-devs/ci.md"
+        ),
+    };
+    if !path.join("target/debug/diem-node").exists() {
+        bail!(
+            "no diem-node built in the previous release {:?}, run `cargo build -p diem-node` there",
+            path
+        );
+    }
+    Ok(path.canonicalize()?)
+}
+
+/// ol/fixtures
+pub fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../fixtures")
+}
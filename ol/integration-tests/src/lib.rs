@@ -0,0 +1,13 @@
+//! End to end tests of the 0L tools against a local swarm.
+//!
+//! The tests need a built node, and start their own swarm. The tools they drive in process
+//! skip their prompts only in test mode, so the environment has to be set up front:
+//!  cargo build -p diem-node
+//!  NODE_ENV=test TEST=y cargo test -p ol-integration-tests -- --ignored --test-threads=1
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs)]
+
+pub mod checks;
+pub mod diagnostics;
+pub mod harness;
@@ -0,0 +1,91 @@
+//! Autopay from alice to bob's community wallet, formerly test-autopay.mk
+
+use anyhow::Error;
+use diem_transaction_builder::stdlib as transaction_builder;
+use ol::swarm::harness::{resolve_address, EPOCH_TIMEOUT_SECS};
+use ol_integration_tests::{
+    checks::{autopay_payees, is_community},
+    harness::{fixtures_dir, E2eSwarm, Poll},
+};
+use ol_types::pay_instruction::PayInstruction;
+use std::time::Duration;
+use txs::commands::autopay_batch_cmd::process_instructions;
+
+/// seconds for the instructions to be on chain
+const INSTRUCTION_TIMEOUT_SECS: u64 = 60;
+
+/// set bob as a community wallet, send alice's batch, and wait for bob to be paid
+fn autopay_to_community(batch_file: &str) -> Result<(), Error> {
+    let e2e = E2eSwarm::start(2)?;
+    let client = e2e.swarm.client()?;
+    let alice = resolve_address("alice")?;
+    let bob = resolve_address("bob")?;
+
+    e2e.submit(
+        "bob",
+        "set community wallet",
+        transaction_builder::encode_set_wallet_type_script_function(1),
+    )?;
+    e2e.ensure(
+        "bob is a community wallet",
+        is_community(&client, bob)?,
+        format!("{} has no unfrozen community wallet", bob),
+    )?;
+    let bob_start = e2e.swarm.balance(bob)?.unwrap_or_default();
+
+    let instructions = PayInstruction::parse_autopay_instructions(
+        &fixtures_dir().join("autopay").join(batch_file),
+        Some(e2e.swarm.epoch()?),
+        Some(0),
+    )?;
+    for (i, script) in process_instructions(instructions).into_iter().enumerate() {
+        e2e.submit("alice", &format!("autopay instruction {}", i), script)?;
+    }
+
+    e2e.wait_until(
+        "bob is an autopay payee of alice",
+        Duration::from_secs(INSTRUCTION_TIMEOUT_SECS),
+        || {
+            let payees = autopay_payees(&client, alice)?;
+            if payees.contains(&bob) {
+                Ok(Poll::Done)
+            } else {
+                Ok(Poll::Pending(format!("payees of alice: {:?}", payees)))
+            }
+        },
+    )?;
+    // payments are made at the epoch boundary
+    e2e.wait_until(
+        "bob's balance increases",
+        Duration::from_secs(EPOCH_TIMEOUT_SECS * 2),
+        || {
+            let balance = e2e.swarm.balance(bob)?.unwrap_or_default();
+            if balance > bob_start {
+                Ok(Poll::Done)
+            } else {
+                Ok(Poll::Pending(format!(
+                    "bob's balance is {}, was {}",
+                    balance, bob_start
+                )))
+            }
+        },
+    )
+}
+
+#[test]
+#[ignore]
+fn autopay_percent_of_balance() -> Result<(), Error> {
+    autopay_to_community("alice.autopay_batch.json")
+}
+
+#[test]
+#[ignore]
+fn autopay_fixed_once() -> Result<(), Error> {
+    autopay_to_community("alice.fixed_once.autopay_batch.json")
+}
+
+#[test]
+#[ignore]
+fn autopay_all_types() -> Result<(), Error> {
+    autopay_to_community("all.autopay_batch.json")
+}
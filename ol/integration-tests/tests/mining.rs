@@ -0,0 +1,40 @@
+//! Towers mined and committed through the tower library, formerly test-mining.mk and test-mining-epochs.mk
+
+use anyhow::Error;
+use ol::swarm::harness::resolve_address;
+use ol_integration_tests::harness::E2eSwarm;
+
+/// the height test-mining.mk waited for
+const TOWER_HEIGHT: u64 = 10;
+
+#[test]
+#[ignore]
+fn mining() -> Result<(), Error> {
+    let e2e = E2eSwarm::start(2)?;
+    let height = e2e.mine("alice", TOWER_HEIGHT)?;
+    e2e.ensure(
+        "alice's tower reaches the height",
+        height >= TOWER_HEIGHT,
+        format!("tower height {}, expected {}", height, TOWER_HEIGHT),
+    )
+}
+
+#[test]
+#[ignore]
+fn mining_across_epochs() -> Result<(), Error> {
+    let e2e = E2eSwarm::start(4)?;
+    let alice = resolve_address("alice")?;
+    let before = e2e.mine("alice", 2)?;
+
+    let epoch = e2e.swarm.epoch()?;
+    e2e.swarm.wait_for_epoch(epoch + 1)?;
+    let after = e2e.mine("alice", 2)?;
+    e2e.ensure(
+        "alice's tower grows after the epoch boundary",
+        after >= before + 2,
+        format!(
+            "tower of {} was {} in epoch {}, and is {} after it",
+            alice, before, epoch, after
+        ),
+    )
+}
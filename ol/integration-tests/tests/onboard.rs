@@ -0,0 +1,89 @@
+//! Eve onboarded by alice, formerly test-onboard.mk and test-onboard-user.mk
+
+use anyhow::Error;
+use diem_temppath::TempPath;
+use diem_transaction_builder::stdlib as transaction_builder;
+use ol::swarm::harness::resolve_address;
+use ol_integration_tests::{
+    checks::{account_state, has_tower},
+    harness::{E2eSwarm, Poll},
+};
+use ol_keys::wallet::get_account_from_mnem;
+use ol_types::{account::ValConfigs, fixtures};
+use onboard::commands::wizard_val_cmd::write_account_json;
+use std::{fs, path::PathBuf, time::Duration};
+use txs::commands::create_validator_cmd::create_validator_script_function;
+
+/// seconds for a new account to show up
+const ONBOARD_TIMEOUT_SECS: u64 = 60;
+
+/// write eve's account.json like `onboard val` does. It is made fresh, since the txs signed in it expire.
+fn eve_account_json(home: &PathBuf) -> Result<PathBuf, Error> {
+    let mut cfg = fixtures::get_persona_toml_configs("eve");
+    cfg.workspace.node_home = home.clone();
+    fs::create_dir_all(cfg.get_block_dir())?;
+    fs::copy(
+        fixtures::get_persona_block_zero_path("eve", "test"),
+        cfg.get_block_dir().join("proof_0.json"),
+    )?;
+    let (_, _, wallet) = get_account_from_mnem(fixtures::get_persona_mnem("eve"))?;
+    write_account_json(&Some(home.clone()), wallet, Some(cfg), None, None);
+    Ok(home.join("account.json"))
+}
+
+#[test]
+#[ignore]
+fn onboard_validator() -> Result<(), Error> {
+    let e2e = E2eSwarm::start(2)?;
+    let client = e2e.swarm.client()?;
+    let eve = resolve_address("eve")?;
+
+    let home = TempPath::new();
+    home.create_as_dir()?;
+    let account_json = eve_account_json(&home.path().to_owned())?;
+    let eve_configs = ValConfigs::get_init_data(&account_json)?;
+    e2e.submit(
+        "alice",
+        "create validator eve",
+        create_validator_script_function(&eve_configs)?,
+    )?;
+
+    e2e.wait_until(
+        "eve's account is created with a tower",
+        Duration::from_secs(ONBOARD_TIMEOUT_SECS),
+        || {
+            if has_tower(&client, eve)? {
+                Ok(Poll::Done)
+            } else {
+                Ok(Poll::Pending(format!("no tower state for {}", eve)))
+            }
+        },
+    )
+}
+
+#[test]
+#[ignore]
+fn onboard_user() -> Result<(), Error> {
+    let e2e = E2eSwarm::start(2)?;
+    let client = e2e.swarm.client()?;
+    let (auth_key, eve, _) = get_account_from_mnem(fixtures::get_persona_mnem("eve"))?;
+
+    e2e.submit(
+        "alice",
+        "create account eve",
+        transaction_builder::encode_create_user_by_coin_tx_script_function(
+            eve,
+            auth_key.prefix().to_vec(),
+            1,
+        ),
+    )?;
+
+    e2e.wait_until(
+        "eve's user account is created",
+        Duration::from_secs(ONBOARD_TIMEOUT_SECS),
+        || match account_state(&client, eve)? {
+            Some(_) => Ok(Poll::Done),
+            None => Ok(Poll::Pending(format!("no account state for {}", eve))),
+        },
+    )
+}
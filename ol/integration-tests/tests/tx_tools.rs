@@ -0,0 +1,49 @@
+//! Community wallet payment proposed by bob, formerly test-tx-tools.mk
+
+use anyhow::Error;
+use diem_transaction_builder::stdlib as transaction_builder;
+use ol::swarm::harness::resolve_address;
+use ol_integration_tests::{
+    checks::proposed_community_transfers,
+    harness::{E2eSwarm, Poll},
+};
+use std::time::Duration;
+
+/// seconds for the proposal to be on chain
+const PROPOSAL_TIMEOUT_SECS: u64 = 60;
+
+#[test]
+#[ignore]
+fn community_payment_proposal() -> Result<(), Error> {
+    let e2e = E2eSwarm::start(2)?;
+    let client = e2e.swarm.client()?;
+    let carol = resolve_address("carol")?;
+
+    e2e.submit(
+        "bob",
+        "set community wallet",
+        transaction_builder::encode_set_wallet_type_script_function(1),
+    )?;
+    let proposed = proposed_community_transfers(&client)?;
+    e2e.submit(
+        "bob",
+        "community payment to carol",
+        transaction_builder::encode_community_transfer_script_function(carol, 1, b"hello".to_vec()),
+    )?;
+
+    e2e.wait_until(
+        "the payment is proposed, pending vetos",
+        Duration::from_secs(PROPOSAL_TIMEOUT_SECS),
+        || {
+            let now = proposed_community_transfers(&client)?;
+            if now > proposed {
+                Ok(Poll::Done)
+            } else {
+                Ok(Poll::Pending(format!(
+                    "{} proposed community transfers, {} before",
+                    now, proposed
+                )))
+            }
+        },
+    )
+}
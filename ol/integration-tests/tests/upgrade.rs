@@ -0,0 +1,58 @@
+//! Stdlib upgrade through the oracle, formerly test-upgrade.mk
+//!
+//! The swarm runs the previous release, checked out and built in PREV_SOURCE_PATH, and upgrades
+//! to the payload built from this tree, or taken from STDLIB_BIN. The test fails without the
+//! previous release, upgrading a tree to itself proves nothing.
+
+use anyhow::Error;
+use diem_transaction_builder::stdlib as transaction_builder;
+use ol::{mgmt::stdlib_attest::build_upgrade_payload, swarm::harness::EPOCH_TIMEOUT_SECS};
+use ol_integration_tests::{
+    checks::upgrade_records,
+    harness::{prev_release_path, source_path, E2eSwarm, Poll},
+};
+use std::{env, path::PathBuf, time::Duration};
+use txs::commands::oracle_upgrade_cmd::oracle_tx_script;
+
+/// transactions that must go through on the upgraded stdlib
+const DEMO_TXS: u64 = 10;
+
+#[test]
+#[ignore]
+fn upgrade() -> Result<(), Error> {
+    let prev_release = prev_release_path()?;
+    let payload = match env::var("STDLIB_BIN") {
+        Ok(p) => PathBuf::from(p),
+        Err(_) => build_upgrade_payload(&source_path()?)?,
+    };
+    let e2e = E2eSwarm::start_from(prev_release, 2)?;
+    let client = e2e.swarm.client()?;
+    let upgrades = upgrade_records(&client)?;
+
+    for persona in e2e.swarm.personas() {
+        e2e.submit(&persona, "upgrade vote", oracle_tx_script(&payload))?;
+    }
+
+    // the upgrade is applied in the epoch after consensus
+    e2e.wait_until(
+        "the stdlib upgrade is published",
+        Duration::from_secs(EPOCH_TIMEOUT_SECS * 3),
+        || {
+            let now = upgrade_records(&client)?;
+            if now > upgrades {
+                Ok(Poll::Done)
+            } else {
+                Ok(Poll::Pending(format!("{} upgrades recorded on chain", now)))
+            }
+        },
+    )?;
+
+    for i in 0..DEMO_TXS {
+        e2e.submit(
+            "alice",
+            &format!("demo tx {} after the upgrade", i),
+            transaction_builder::encode_demo_e2e_script_function(42),
+        )?;
+    }
+    Ok(())
+}
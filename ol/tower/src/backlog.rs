@@ -8,6 +8,7 @@ use anyhow::{anyhow, bail, Error, Result};
 
 use cli::diem_client::DiemClient;
 use diem_logger::prelude::*;
+use ol::node::node::Node;
 use ol_types::block::VDFProof;
 use ol_types::config::AppCfg;
 use txs::submit_tx::{eval_tx_status, TxError};
@@ -21,9 +22,11 @@ use crate::{tower_errors, EPOCH_MINING_THRES_UPPER};
 /// Submit a backlog of blocks that may have been mined while network is offline.
 /// Likely not more than 1.
 pub fn process_backlog(config: &AppCfg, tx_params: &TxParams) -> Result<(), TxError> {
+    let client = DiemClient::new(tx_params.url.clone(), tx_params.waypoint)?;
+    let node = Node::new(client, config, false);
     // Getting remote miner state
     // there may not be any onchain state.
-    let (remote_height, proofs_in_epoch) = get_remote_tower_height(tx_params)?;
+    let (remote_height, proofs_in_epoch) = get_remote_tower_height(&node.client, tx_params)?;
 
     info!("Remote tower height: {}", remote_height);
     info!("Proofs already submitted in epoch: {}", proofs_in_epoch);
@@ -56,6 +59,21 @@ pub fn process_backlog(config: &AppCfg, tx_params: &TxParams) -> Result<(), TxEr
         } else {
             EPOCH_MINING_THRES_UPPER
         };
+        // fullnodes are paid per proof above threshold, up to the cap, so spread the proofs over the epoch
+        let remaining_in_epoch = match fullnode_pace(&node, tx_params) {
+            Ok(Some(allowed)) if allowed < remaining_in_epoch => {
+                info!("Backlog: fullnode pacing, {} proofs allowed now", allowed);
+                allowed
+            }
+            Ok(_) => remaining_in_epoch,
+            Err(e) => {
+                warn!(
+                    "Backlog: could not get the fullnode pacing, not pacing. Message: {}",
+                    e
+                );
+                remaining_in_epoch
+            }
+        };
         let mut submitted_now = 1u64;
 
         info!("Backlog: resubmitting missing proofs. Remaining in epoch: {}, already submitted in this backlog: {}", remaining_in_epoch, submitted_now);
@@ -77,7 +95,7 @@ pub fn process_backlog(config: &AppCfg, tx_params: &TxParams) -> Result<(), TxEr
                 Ok(_) => {}
                 Err(e) => {
                     warn!(
-                        "WARN: could not fetch TX status, aborting. Message: {:?} ",
+                        "WARN: could not fetch TX status, aborting. Message: {} ",
                         &e
                     );
                     // evaluate type of error and maybe garbage collect
@@ -104,9 +122,10 @@ pub fn submit_proof_by_number(
     tx_params: &TxParams,
     proof_to_submit: u64,
 ) -> Result<(), TxError> {
+    let client = DiemClient::new(tx_params.url.clone(), tx_params.waypoint)?;
     // Getting remote miner state
     // there may not be any onchain state.
-    let (remote_height, _proofs_in_epoch) = get_remote_tower_height(tx_params)?;
+    let (remote_height, _proofs_in_epoch) = get_remote_tower_height(&client, tx_params)?;
 
     info!("Remote tower height: {}", remote_height);
     // Getting local state height
@@ -162,9 +181,11 @@ pub fn submit_proof_by_number(
 
 /// display the user's tower backlog
 pub fn show_backlog(config: &AppCfg, tx_params: &TxParams) -> Result<(), TxError> {
+    let client = DiemClient::new(tx_params.url.clone(), tx_params.waypoint)?;
+    let node = Node::new(client, config, false);
     // Getting remote miner state
     // there may not be any onchain state.
-    let (remote_height, _proofs_in_epoch) = get_remote_tower_height(tx_params)?;
+    let (remote_height, _proofs_in_epoch) = get_remote_tower_height(&node.client, tx_params)?;
 
     println!("Remote tower height: {}", remote_height);
     // Getting local state height
@@ -176,12 +197,29 @@ pub fn show_backlog(config: &AppCfg, tx_params: &TxParams) -> Result<(), TxError
     // } else {
     // println!("Local tower height: 0");
     // }
+    match fullnode_pace(&node, tx_params) {
+        Ok(Some(allowed)) => println!("Fullnode pacing: {} proofs can be submitted now", allowed),
+        Ok(None) => {}
+        Err(e) => println!("WARN: could not get the fullnode pacing, message: {}", e),
+    }
     Ok(())
 }
 
+/// proofs a fullnode may submit now to keep pace with the epoch, none for validators
+fn fullnode_pace(node: &Node, tx_params: &TxParams) -> Result<Option<u64>, Error> {
+    let view = node.get_fullnode_view(tx_params.owner_address)?;
+    Ok(if view.is_validator {
+        None
+    } else {
+        Some(view.paced_allowance())
+    })
+}
+
 /// returns remote tower height and current proofs in epoch
-pub fn get_remote_tower_height(tx_params: &TxParams) -> Result<(i64, i64), Error> {
-    let client = DiemClient::new(tx_params.url.clone(), tx_params.waypoint)?;
+pub fn get_remote_tower_height(
+    client: &DiemClient,
+    tx_params: &TxParams,
+) -> Result<(i64, i64), Error> {
     info!(
         "Fetching remote tower height: {}, {}",
         tx_params.url.clone(),
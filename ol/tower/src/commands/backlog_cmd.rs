@@ -85,7 +85,7 @@ impl Runnable for BacklogCmd {
             match backlog::submit_proof_by_number(&cfg, &tx_params, specific_proof) {
                 Ok(()) => {}
                 Err(e) => {
-                    println!("WARN: Unable to submit proof: {:?}", e);
+                    println!("WARN: Unable to submit proof: {}", e);
                 }
             }
 
@@ -96,7 +96,7 @@ impl Runnable for BacklogCmd {
             match backlog::process_backlog(&cfg, &tx_params) {
                 Ok(()) => {}
                 Err(e) => {
-                    println!("WARN: Unable to submit backlog: {:?}", e);
+                    println!("WARN: Unable to submit backlog: {}", e);
                 }
             }
 
@@ -108,7 +108,7 @@ impl Runnable for BacklogCmd {
             match backlog::show_backlog(&cfg, &tx_params) {
                 Ok(()) => {}
                 Err(e) => {
-                    println!("WARN: Unable to list backlog: {:?}", e);
+                    println!("WARN: Unable to list backlog: {}", e);
                 }
             }
         }
@@ -92,7 +92,7 @@ impl Runnable for StartCmd {
             match backlog::process_backlog(&cfg, &tx_params) {
                 Ok(()) => status_ok!("Backlog:", "backlog committed to chain"),
                 Err(e) => {
-                    println!("WARN: Failed processing backlog: {:?}", e);
+                    println!("WARN: Failed processing backlog: {}", e);
                 }
             }
         }
@@ -0,0 +1,57 @@
+//! Generate the catalog of Move abort codes from the framework sources
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+#[path = "src/abort_parse.rs"]
+mod abort_parse;
+
+const MOVE_SOURCES: &[&str] = &[
+    "../../language/diem-framework/modules",
+    "../../language/move-stdlib/modules",
+];
+
+fn main() {
+    let manifest = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let mut entries = vec![];
+    for dir in MOVE_SOURCES {
+        let dir = manifest.join(dir);
+        println!("cargo:rerun-if-changed={}", dir.display());
+        let mut files = vec![];
+        move_files(&dir, &mut files);
+        files.sort();
+        for f in files {
+            let source = fs::read_to_string(&f).unwrap();
+            entries.extend(abort_parse::parse_source(&source));
+        }
+    }
+
+    let mut out = String::from("&[\n");
+    for e in entries {
+        out.push_str(&format!(
+            "    ({:?}, {}, {:?}, {:?}, {:?}),\n",
+            e.module, e.code, e.name, e.category, e.explanation
+        ));
+    }
+    out.push_str("]\n");
+
+    let dest = PathBuf::from(env::var("OUT_DIR").unwrap()).join("abort_catalog.rs");
+    fs::write(dest, out).unwrap();
+}
+
+fn move_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let read = match fs::read_dir(dir) {
+        Ok(r) => r,
+        Err(_) => return,
+    };
+    for entry in read.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            move_files(&path, files);
+        } else if path.extension().map_or(false, |e| e == "move") {
+            files.push(path);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! Explain Move abort codes of failed transactions, from a catalog built from the Move sources
+
+use crate::submit_tx::TxError;
+use diem_json_rpc_types::views::VMStatusView;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// module, code, constant name, `Errors::` category and explanation, see build.rs
+static CATALOG: &[(&str, u64, &str, &str, &str)] =
+    include!(concat!(env!("OUT_DIR"), "/abort_catalog.rs"));
+
+/// A failed transaction, explained
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct AbortReport {
+    /// Move module which aborted
+    pub module: Option<String>,
+    /// abort code, or the code set by txs::submit_tx
+    pub abort_code: Option<u64>,
+    /// name of the error constant
+    pub name: Option<String>,
+    /// `Errors::` category of the abort
+    pub category: Option<String>,
+    /// what went wrong
+    pub explanation: String,
+    /// what to do about it
+    pub fix: Option<String>,
+    /// status of the transaction, if it was executed
+    pub vm_status: Option<String>,
+}
+
+impl AbortReport {
+    /// as json, for scripts
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for AbortReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.module, self.abort_code) {
+            (Some(m), Some(c)) => write!(f, "{} aborted with {}", m, c)?,
+            (None, Some(c)) => write!(f, "error {}", c)?,
+            _ => write!(f, "error")?,
+        }
+        if let Some(n) = &self.name {
+            write!(f, " ({})", n)?;
+        }
+        write!(f, ": {}", self.explanation)?;
+        if let Some(fix) = &self.fix {
+            write!(f, "\n  suggested fix: {}", fix)?;
+        }
+        Ok(())
+    }
+}
+
+/// Explain an abort code. The location is a module id, e.g. `00000000000000000000000000000001::TowerState`, or a module name
+pub fn explain_abort(location: &str, code: u64) -> AbortReport {
+    let module = location.rsplit("::").next().unwrap_or(location);
+    let entry = CATALOG.iter().find(|e| e.0 == module && e.1 == code);
+    let (name, category, explanation) = match entry {
+        Some(e) => (e.2, e.3, e.4),
+        None => (
+            "",
+            "",
+            "no such abort code in the Move sources of this release",
+        ),
+    };
+    AbortReport {
+        module: Some(module.to_string()),
+        abort_code: Some(code),
+        name: some_str(name),
+        category: some_str(category),
+        explanation: explanation.to_string(),
+        fix: suggested_fix(module, code, category).map(|s| s.to_string()),
+        vm_status: None,
+    }
+}
+
+/// Explain the error of a txs or tower transaction
+pub fn report(tx_err: &TxError) -> AbortReport {
+    let vm_status = tx_err.tx_view.as_ref().map(|tv| tv.vm_status.to_string());
+    let message = || match &tx_err.err {
+        Some(e) => e.to_string(),
+        None => "unknown error".to_string(),
+    };
+    match (tx_err.abort_code, &tx_err.location) {
+        (Some(code), Some(location)) => AbortReport {
+            vm_status,
+            ..explain_abort(location, code)
+        },
+        // set by txs::submit_tx before the transaction was sent
+        (Some(404), None) => AbortReport {
+            abort_code: Some(404),
+            explanation: message(),
+            fix: Some("check the node is running, and the upstream url in 0L.toml".to_string()),
+            ..AbortReport::default()
+        },
+        (Some(1004), None) => AbortReport {
+            explanation: message(),
+            ..explain_abort("DiemAccount", 1004)
+        },
+        _ => {
+            let status = tx_err.tx_view.as_ref().map(|tv| &tv.vm_status);
+            let fix = match status {
+                Some(VMStatusView::OutOfGas) => {
+                    Some("fund the account, or raise max_gas_unit_for_tx in 0L.toml".to_string())
+                }
+                _ => None,
+            };
+            AbortReport {
+                explanation: message(),
+                fix,
+                vm_status,
+                ..AbortReport::default()
+            }
+        }
+    }
+}
+
+/// The whole catalog, e.g. to dump as json
+pub fn catalog() -> Vec<AbortReport> {
+    CATALOG.iter().map(|e| explain_abort(e.0, e.1)).collect()
+}
+
+fn some_str(s: &str) -> Option<String> {
+    if s.is_empty() {
+        None
+    } else {
+        Some(s.to_string())
+    }
+}
+
+/// fixes of the common errors, else of the category
+fn suggested_fix(module: &str, code: u64, category: &str) -> Option<&'static str> {
+    let fix = match (module, code) {
+        ("DiemAccount", 1001) => {
+            "the signing key is not the account's auth key, check the mnemonic"
+        }
+        ("DiemAccount", 1002) => {
+            "a transaction with this sequence number was already executed, wait and resend"
+        }
+        ("DiemAccount", 1004) => {
+            "the account is not on chain, someone must send it coins or `txs create-account` first"
+        }
+        ("DiemAccount", 1005) => {
+            "fund the account, or lower the gas price and max gas units in 0L.toml"
+        }
+        ("DiemAccount", 1006) => "the transaction expired before execution, send it again",
+        ("DiemAccount", 1007) => "the node is on another chain, check the upstream url in 0L.toml",
+        ("DiemAccount", 120126) => {
+            "community wallets can only pay by proposing a transfer with `txs community-pay`"
+        }
+        ("DiemAccount", 120128) => "slow wallets can only transfer their unlocked coins",
+        ("TowerState", 130102) => {
+            "the proof has the wrong difficulty for this chain, remove it and mine again"
+        }
+        ("TowerState", 130108) => "the proof limit of this epoch is reached, submit next epoch",
+        ("TowerState", 130109) => {
+            "the proof does not continue the tower on chain, resubmit from the last proof on chain"
+        }
+        ("TowerState", 130110) => "the proof does not verify, remove it and mine again",
+        ("TransferScripts", 0) => {
+            "only community wallets propose transfers, see `txs wallet --community`"
+        }
+        ("Wallet", 24) => {
+            "only community wallets propose transfers, and validators in the set veto them"
+        }
+        ("WalletScripts", 231011) => {
+            "check the transfer is still pending with `txs community list`"
+        }
+        _ => match category {
+            "requires_role" | "requires_address" => {
+                "sign with an account which has the role, e.g. a validator or operator"
+            }
+            "not_published" => "the account is missing a resource, it may need to be set up first",
+            "already_published" => "the resource is already set up, this transaction is not needed",
+            "limit_exceeded" => "lower the amount, or wait until the limit resets",
+            _ => return None,
+        },
+    };
+    Some(fix)
+}
+
+#[test]
+fn test_explain_abort() {
+    let r = explain_abort("00000000000000000000000000000001::TowerState", 130108);
+    assert_eq!(r.module.as_deref(), Some("TowerState"));
+    assert_eq!(r.category.as_deref(), Some("invalid_state"));
+    assert!(r.fix.unwrap().contains("next epoch"));
+
+    let r = explain_abort("DiemAccount", 120117);
+    assert_eq!(r.name.as_deref(), Some("EPAYEE_DOES_NOT_EXIST"));
+    assert!(r.explanation.contains("does not exist"));
+
+    // script codes are only unique with their module
+    let r = explain_abort("TransferScripts", 0);
+    assert!(r.fix.unwrap().contains("community wallets"));
+
+    let r = explain_abort("WalletScripts", 231011);
+    assert_eq!(r.name.as_deref(), Some("ETRANSFER_NOT_PROPOSED"));
+    assert!(r.fix.unwrap().contains("community list"));
+
+    let r = explain_abort("TowerState", 999);
+    assert_eq!(r.name, None);
+    assert_eq!(r.fix, None);
+}
@@ -0,0 +1,388 @@
+//! Scan Move sources for abort codes. Std only, it is also compiled by build.rs
+
+use std::collections::BTreeMap;
+
+/// An abort code found in a Move module
+#[derive(Clone, Debug, PartialEq)]
+pub struct AbortEntry {
+    /// module which aborts
+    pub module: String,
+    /// abort code, 0L codes have no category bits
+    pub code: u64,
+    /// name of the error constant, empty for literal codes
+    pub name: String,
+    /// `Errors::` category, empty for bare codes
+    pub category: String,
+    /// doc comment of the constant, comment of the assert, or the condition
+    pub explanation: String,
+}
+
+/// Find every `assert` and `abort` of the modules in a Move source file
+pub fn parse_source(source: &str) -> Vec<AbortEntry> {
+    let code = strip_comments(source);
+    let lines: Vec<&str> = source.lines().collect();
+    let mut entries: Vec<AbortEntry> = vec![];
+
+    for (module, start, end) in modules(&code) {
+        let body = &code[start..end];
+        let consts = constants(body, &lines, line_of(&code, start));
+
+        let mut pos = 0;
+        while let Some((at, is_assert)) = next_abort(body, pos) {
+            pos = at + 1;
+            let (cond, expr) = if is_assert {
+                let open = at + "assert".len();
+                let args = match balanced(body, open) {
+                    Some(a) => a,
+                    None => continue,
+                };
+                match split_last_arg(&args) {
+                    Some(s) => s,
+                    None => continue,
+                }
+            } else {
+                let rest = &body[at + "abort".len()..];
+                let expr = rest
+                    .split(|c| c == ';' || c == '}')
+                    .next()
+                    .unwrap_or_default();
+                (String::new(), expr.trim().to_string())
+            };
+
+            let (category, inner) = unwrap_category(&expr);
+            let value = match eval(&inner, &consts) {
+                Some(v) => v,
+                None => continue,
+            };
+            let name = if consts.contains_key(inner.trim()) {
+                inner.trim().to_string()
+            } else {
+                String::new()
+            };
+
+            let explanation = match consts.get(&name) {
+                Some((_, doc)) if !doc.is_empty() => doc.clone(),
+                _ => {
+                    let comment = comment_above(&lines, line_of(&code, start + at));
+                    if !comment.is_empty() {
+                        comment
+                    } else if !name.is_empty() {
+                        humanize(&name)
+                    } else if !cond.is_empty() {
+                        format!("requires {}", squash(&cond))
+                    } else {
+                        String::new()
+                    }
+                }
+            };
+
+            let exists = entries
+                .iter()
+                .any(|e| e.module == module && e.code == value);
+            if !exists {
+                entries.push(AbortEntry {
+                    module: module.clone(),
+                    code: value,
+                    name,
+                    category,
+                    explanation,
+                });
+            }
+        }
+
+        // constants which are only used by the VM, e.g. the prologue codes
+        for (name, (value, doc)) in consts.iter() {
+            if !name.starts_with("PROLOGUE_E") {
+                continue;
+            }
+            if entries
+                .iter()
+                .any(|e| e.module == module && e.code == *value)
+            {
+                continue;
+            }
+            entries.push(AbortEntry {
+                module: module.clone(),
+                code: *value,
+                name: name.clone(),
+                category: String::new(),
+                explanation: if doc.is_empty() {
+                    humanize(name)
+                } else {
+                    doc.clone()
+                },
+            });
+        }
+    }
+    entries
+}
+
+/// blank out comments, keeping offsets and line breaks
+fn strip_comments(source: &str) -> String {
+    source
+        .lines()
+        .map(|l| match l.find("//") {
+            Some(i) => format!("{}{}", &l[..i], " ".repeat(l.len() - i)),
+            None => l.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn line_of(code: &str, offset: usize) -> usize {
+    code[..offset].matches('\n').count()
+}
+
+/// name, start and end of each module or script module
+fn modules(code: &str) -> Vec<(String, usize, usize)> {
+    let mut found = vec![];
+    let mut pos = 0;
+    while let Some(i) = find_word(code, "module", pos) {
+        pos = i + 1;
+        let header = &code[i + "module".len()..];
+        let brace = match header.find('{') {
+            Some(b) => b,
+            None => break,
+        };
+        let name = header[..brace].trim();
+        // e.g. `module 0x1::Foo`
+        let name = name.rsplit("::").next().unwrap_or_default().to_string();
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            continue;
+        }
+        let start = i + "module".len() + brace;
+        let end = match balanced_span(code, start, '{', '}') {
+            Some(e) => e,
+            None => code.len(),
+        };
+        found.push((name, start, end));
+        pos = end;
+    }
+    found
+}
+
+/// `const NAME: u64 = value;` with its `///` doc
+fn constants(body: &str, lines: &[&str], first_line: usize) -> BTreeMap<String, (u64, String)> {
+    let mut consts = BTreeMap::new();
+    let mut pos = 0;
+    while let Some(i) = find_word(body, "const", pos) {
+        pos = i + 1;
+        let rest = &body[i + "const".len()..];
+        let decl = match rest.find(';') {
+            Some(e) => &rest[..e],
+            None => break,
+        };
+        let (name_ty, value) = match decl.split_once('=') {
+            Some(s) => s,
+            None => continue,
+        };
+        let (name, ty) = match name_ty.split_once(':') {
+            Some(s) => s,
+            None => continue,
+        };
+        if ty.trim() != "u64" {
+            continue;
+        }
+        if let Some(v) = eval(value, &consts) {
+            let line = first_line + line_of(body, i);
+            consts.insert(name.trim().to_string(), (v, doc_above(lines, line)));
+        }
+    }
+    consts
+}
+
+/// offset of the next `assert(` or `abort`, and whether it is an assert
+fn next_abort(body: &str, pos: usize) -> Option<(usize, bool)> {
+    // skip spec `assert` statements, which take no parentheses
+    let mut assert = find_word(body, "assert", pos);
+    while let Some(i) = assert {
+        if body[i + "assert".len()..].trim_start().starts_with('(') {
+            break;
+        }
+        assert = find_word(body, "assert", i + 1);
+    }
+    let abort = find_word(body, "abort", pos);
+    match (assert, abort) {
+        (Some(a), Some(b)) if b < a => Some((b, false)),
+        (Some(a), _) => Some((a, true)),
+        (None, Some(b)) => Some((b, false)),
+        (None, None) => None,
+    }
+}
+
+/// find a word which is not part of an identifier, e.g. `assert` but not `assert_vm`
+fn find_word(s: &str, word: &str, from: usize) -> Option<usize> {
+    let mut pos = from;
+    while let Some(i) = s.get(pos..)?.find(word) {
+        let at = pos + i;
+        let before = s[..at].chars().last();
+        let after = s[at + word.len()..].chars().next();
+        let is_ident =
+            |c: Option<char>| c.map_or(false, |c| c.is_alphanumeric() || c == '_' || c == ':');
+        if !is_ident(before) && !is_ident(after) {
+            return Some(at);
+        }
+        pos = at + word.len();
+    }
+    None
+}
+
+/// the text inside the parentheses which open at or after `from`
+fn balanced(s: &str, from: usize) -> Option<String> {
+    let open = from + s[from..].find('(')?;
+    let close = balanced_span(s, open, '(', ')')?;
+    Some(s[open + 1..close - 1].to_string())
+}
+
+/// offset after the delimiter closing the one at `open`
+fn balanced_span(s: &str, open: usize, l: char, r: char) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s[open..].char_indices() {
+        if c == l {
+            depth += 1;
+        } else if c == r {
+            depth -= 1;
+            if depth == 0 {
+                return Some(open + i + 1);
+            }
+        }
+    }
+    None
+}
+
+/// split the arguments of an assert into the condition and the code
+fn split_last_arg(args: &str) -> Option<(String, String)> {
+    let mut depth = 0;
+    let mut last = None;
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' | '{' | '[' => depth += 1,
+            ')' | '}' | ']' => depth -= 1,
+            ',' if depth == 0 => last = Some(i),
+            _ => {}
+        }
+    }
+    let i = last?;
+    Some((
+        args[..i].trim().to_string(),
+        args[i + 1..].trim().to_string(),
+    ))
+}
+
+/// `Errors::invalid_state(X)` is (invalid_state, X)
+fn unwrap_category(expr: &str) -> (String, String) {
+    let expr = expr.trim();
+    if let Some(rest) = expr.strip_prefix("Errors::") {
+        if let Some(open) = rest.find('(') {
+            if let Some(inner) = balanced(rest, open) {
+                return (rest[..open].trim().to_string(), inner.trim().to_string());
+            }
+        }
+    }
+    (String::new(), expr.to_string())
+}
+
+/// evaluate a sum of literals and constants
+fn eval(expr: &str, consts: &BTreeMap<String, (u64, String)>) -> Option<u64> {
+    let mut total: u64 = 0;
+    for term in expr.split('+') {
+        let term = term.trim().trim_start_matches('(').trim_end_matches(')');
+        // Move has no octal, `023` is 23
+        let value = match term.parse::<u64>() {
+            Ok(v) => v,
+            Err(_) => consts.get(term)?.0,
+        };
+        total = total.checked_add(value)?;
+    }
+    Some(total)
+}
+
+/// the `///` lines directly above a line
+fn doc_above(lines: &[&str], line: usize) -> String {
+    let mut doc = vec![];
+    let mut i = line;
+    while i > 0 {
+        i -= 1;
+        // a `///// 0L /////` banner is not a doc
+        if lines[i].trim().starts_with("////") {
+            break;
+        }
+        match lines[i].trim().strip_prefix("///") {
+            Some(d) => doc.push(clean_comment(d)),
+            None => break,
+        }
+    }
+    doc.retain(|d| !d.is_empty());
+    doc.reverse();
+    doc.join(" ")
+}
+
+/// the comment on the line above an assert, if it is a whole line comment
+fn comment_above(lines: &[&str], line: usize) -> String {
+    if line == 0 {
+        return String::new();
+    }
+    match lines[line - 1].trim().strip_prefix("//") {
+        Some(c) => clean_comment(c).to_string(),
+        None => String::new(),
+    }
+}
+
+/// drop slashes, and tags like `[PCA1]:`
+fn clean_comment(c: &str) -> &str {
+    let c = c.trim_matches(|c: char| c == '/' || c.is_whitespace());
+    match c.strip_prefix('[').and_then(|r| r.split_once("]:")) {
+        Some((_, rest)) => rest.trim(),
+        None => c,
+    }
+}
+
+/// `PROLOGUE_EACCOUNT_DNE` is "account dne"
+fn humanize(name: &str) -> String {
+    let name = name.strip_prefix("PROLOGUE_").unwrap_or(name);
+    let name = name.strip_prefix('E').unwrap_or(name);
+    name.to_lowercase().replace('_', " ")
+}
+
+fn squash(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[test]
+fn test_parse_source() {
+    let source = r#"
+address 0x1 {
+module Foo {
+    use 0x1::Errors;
+    const ERR_PREFIX: u64 = 023;
+    /// The payee does not exist
+    const EPAYEE: u64 = 010017;
+    const PROLOGUE_EACCOUNT_DNE: u64 = 1004;
+
+    fun f(a: u64) {
+        assert(a > 0, Errors::not_published(EPAYEE));
+        // too many proofs
+        assert(
+            a < 10,
+            Errors::invalid_state(130108)
+        );
+        assert(a != 3, Errors::requires_role(ERR_PREFIX + 001));
+        // assert(a != 4, 44);
+        CoreAddresses::assert_vm(a);
+        if (a == 5) abort 7;
+    }
+}
+}
+"#;
+    let entries = parse_source(source);
+    let find = |code| entries.iter().find(|e| e.code == code).unwrap();
+    assert_eq!(entries.len(), 5);
+    assert_eq!(find(10017).name, "EPAYEE");
+    assert_eq!(find(10017).category, "not_published");
+    assert_eq!(find(10017).explanation, "The payee does not exist");
+    assert_eq!(find(130108).explanation, "too many proofs");
+    assert_eq!(find(24).explanation, "requires a != 3");
+    assert_eq!(find(7).category, "");
+    assert_eq!(find(1004).explanation, "account dne");
+    assert!(entries.iter().all(|e| e.module == "Foo"));
+}
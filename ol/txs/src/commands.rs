@@ -14,27 +14,30 @@ pub mod autopay_batch_cmd;
 pub mod burn_pref_cmd;
 pub mod community_pay_cmd;
 pub mod create_account_cmd;
+pub mod create_validator_cmd;
 pub mod demo_cmd;
+pub mod oracle_upgrade_cmd;
 pub mod transfer_cmd;
 pub mod val_config_cmd;
 pub mod wallet_cmd;
 
+mod abort_code_cmd;
 mod authkey_cmd;
 mod autopay_cmd;
-mod create_validator_cmd;
-mod oracle_upgrade_cmd;
+mod community_cmd;
 mod relay_cmd;
 mod valset_cmd;
 mod version_cmd;
 mod vouch_cmd;
 
 use self::{
-    authkey_cmd::AuthkeyCmd, autopay_batch_cmd::AutopayBatchCmd, autopay_cmd::AutopayCmd,
-    burn_pref_cmd::BurnPrefCmd, community_pay_cmd::CommunityPayCmd,
-    create_account_cmd::CreateAccountCmd, create_validator_cmd::CreateValidatorCmd,
-    demo_cmd::DemoCmd, oracle_upgrade_cmd::OracleUpgradeCmd, relay_cmd::RelayCmd,
-    transfer_cmd::TransferCmd, val_config_cmd::ValConfigCmd, valset_cmd::ValSetCmd,
-    version_cmd::VersionCmd, vouch_cmd::VouchCmd, wallet_cmd::WalletCmd,
+    abort_code_cmd::AbortCodeCmd, authkey_cmd::AuthkeyCmd, autopay_batch_cmd::AutopayBatchCmd,
+    autopay_cmd::AutopayCmd, burn_pref_cmd::BurnPrefCmd, community_cmd::CommunityCmd,
+    community_pay_cmd::CommunityPayCmd, create_account_cmd::CreateAccountCmd,
+    create_validator_cmd::CreateValidatorCmd, demo_cmd::DemoCmd,
+    oracle_upgrade_cmd::OracleUpgradeCmd, relay_cmd::RelayCmd, transfer_cmd::TransferCmd,
+    val_config_cmd::ValConfigCmd, valset_cmd::ValSetCmd, version_cmd::VersionCmd,
+    vouch_cmd::VouchCmd, wallet_cmd::WalletCmd,
 };
 use crate::config::AppCfg;
 use crate::entrypoint;
@@ -61,6 +64,10 @@ pub enum TxsCmd {
     #[options(help = "create a community wallet payment proposal")]
     CommunityPay(CommunityPayCmd),
 
+    /// Community wallet transfers, and vetos
+    #[options(help = "list, show or veto community wallet transfers")]
+    Community(CommunityCmd),
+
     /// The `oracle-upgrade` subcommand
     #[options(help = "submit an oracle transaction to upgrade stdlib")]
     OracleUpgrade(OracleUpgradeCmd),
@@ -90,6 +97,10 @@ pub enum TxsCmd {
     #[options(help = "submit a saved transaction from file")]
     Relay(RelayCmd),
 
+    /// The `abort-code` subcommand
+    #[options(help = "explain the abort code of a failed transaction")]
+    AbortCode(AbortCodeCmd),
+
     /// The `valset` subcommand
     #[options(help = "join or leave the validator universe, i.e. candidate for validator set")]
     ValSet(ValSetCmd),
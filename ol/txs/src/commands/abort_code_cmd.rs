@@ -0,0 +1,53 @@
+//! `abort-code` subcommand
+
+use crate::abort_codes::{catalog, explain_abort};
+use abscissa_core::{Command, Options, Runnable};
+use std::process::exit;
+
+/// `abort-code` subcommand
+#[derive(Command, Debug, Default, Options)]
+pub struct AbortCodeCmd {
+    #[options(free, help = "module or location of the abort, and the abort code")]
+    args: Vec<String>,
+    #[options(help = "print as json")]
+    json: bool,
+    #[options(help = "print every known abort code")]
+    all: bool,
+}
+
+impl Runnable for AbortCodeCmd {
+    fn run(&self) {
+        if self.all {
+            let all = catalog();
+            if self.json {
+                println!("{}", serde_json::to_string_pretty(&all).unwrap());
+            } else {
+                for r in all.iter() {
+                    println!("{}", r);
+                }
+            }
+            return;
+        }
+
+        let (location, code) = match (self.args.get(0), self.args.get(1)) {
+            (Some(l), Some(c)) => match c.parse::<u64>() {
+                Ok(c) => (l, c),
+                Err(e) => {
+                    println!("ERROR: could not parse abort code {}, message: {}", c, e);
+                    exit(1);
+                }
+            },
+            _ => {
+                println!("usage: txs abort-code <module> <code>, e.g. TowerState 130108");
+                exit(1);
+            }
+        };
+
+        let report = explain_abort(location, code);
+        if self.json {
+            println!("{}", report.to_json());
+        } else {
+            println!("{}", report);
+        }
+    }
+}
@@ -35,7 +35,7 @@ impl Runnable for AutopayCmd {
         match maybe_submit(script, &tx_params, entry_args.save_path) {
             Err(e) => {
                 println!(
-                    "ERROR: could not submit autopay enable transaction, message: \n{:?}",
+                    "ERROR: could not submit autopay enable transaction, message: \n{}",
                     &e
                 );
                 exit(1);
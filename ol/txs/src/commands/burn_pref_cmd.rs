@@ -40,7 +40,7 @@ impl Runnable for BurnPrefCmd {
             }
             Err(e) => {
                 println!(
-                    "ERROR: could not submit burn preferences transaction, message: \n{:?}",
+                    "ERROR: could not submit burn preferences transaction, message: \n{}",
                     &e
                 );
                 exit(1);
@@ -0,0 +1,207 @@
+//! `community` subcommand
+
+use crate::{
+    entrypoint,
+    submit_tx::{maybe_submit, query_client_wrapper, tx_params_wrapper},
+};
+use abscissa_core::{Command, Options, Runnable};
+use anyhow::{bail, Error};
+use cli::diem_client::DiemClient;
+use diem_transaction_builder::stdlib as transaction_builder;
+use diem_types::{account_address::AccountAddress, account_state::AccountState};
+use ol_types::{
+    config::TxType,
+    wallet::{CommunityTransfersResource, TimedTransfer, TransferStatus},
+};
+use std::{convert::TryFrom, process::exit};
+
+/// `community` subcommand, community wallet transfers
+#[derive(Command, Debug, Default, Options)]
+pub struct CommunityCmd {
+    #[options(command)]
+    cmd: Option<CommunitySubcmd>,
+}
+
+/// what to do with the transfers
+#[derive(Debug, Options)]
+pub enum CommunitySubcmd {
+    /// list transfers
+    #[options(help = "list pending community wallet transfers")]
+    List(ListOpts),
+    /// show one transfer
+    #[options(help = "show the timeline and veto tally of a transfer")]
+    Show(UidOpts),
+    /// veto one transfer
+    #[options(help = "veto a pending transfer, for validators in the set")]
+    Veto(UidOpts),
+}
+
+/// options of `community list`
+#[derive(Debug, Default, Options)]
+pub struct ListOpts {
+    #[options(help = "also list paid and rejected transfers")]
+    all: bool,
+}
+
+/// options of `community show` and `community veto`
+#[derive(Debug, Default, Options)]
+pub struct UidOpts {
+    #[options(free, help = "uid of the transfer")]
+    uid: u64,
+}
+
+impl Runnable for CommunityCmd {
+    fn run(&self) {
+        let _entry_args = entrypoint::get_args();
+
+        let cmd = match &self.cmd {
+            Some(c) => c,
+            None => {
+                println!("no subcommand passed. Did you mean `community list`");
+                exit(1);
+            }
+        };
+
+        // listing needs no keys, only the veto is signed
+        let (transfers, epoch) = query_client_wrapper()
+            .and_then(|client| get_community_transfers(&client))
+            .unwrap_or_else(|e| {
+                println!(
+                    "ERROR: could not read community transfers, message: {:?}",
+                    e
+                );
+                exit(1);
+            });
+
+        match cmd {
+            CommunitySubcmd::List(opts) => {
+                println!(
+                    "epoch {}, {} transfers proposed",
+                    epoch,
+                    transfers.proposed.len()
+                );
+                for t in transfers.proposed.iter() {
+                    print_row(t, TransferStatus::Proposed, epoch);
+                }
+                if opts.all {
+                    for t in transfers.approved.iter() {
+                        print_row(t, TransferStatus::Approved, epoch);
+                    }
+                    for t in transfers.rejected.iter() {
+                        print_row(t, TransferStatus::Rejected, epoch);
+                    }
+                }
+            }
+            CommunitySubcmd::Show(opts) => match transfers.find(opts.uid) {
+                Some((status, t)) => print_timeline(t, status, epoch),
+                None => {
+                    println!("no community transfer with uid {}", opts.uid);
+                    exit(1);
+                }
+            },
+            CommunitySubcmd::Veto(opts) => {
+                let tx_params = tx_params_wrapper(TxType::Mgmt).unwrap_or_else(|e| {
+                    println!("ERROR: could not get the transaction params: {}", e);
+                    exit(1);
+                });
+                match transfers.find(opts.uid) {
+                    Some((TransferStatus::Proposed, t)) => {
+                        // Wallet::veto does not dedupe the list
+                        if t.veto.list.contains(&tx_params.signer_address) {
+                            println!(
+                                "{} already vetoed transfer {}",
+                                tx_params.signer_address, opts.uid
+                            );
+                            exit(1);
+                        }
+                    }
+                    Some((status, _)) => {
+                        println!("transfer {} is {:?}, it cannot be vetoed", opts.uid, status);
+                        exit(1);
+                    }
+                    None => {
+                        println!("no community transfer with uid {}", opts.uid);
+                        exit(1);
+                    }
+                }
+
+                let script = transaction_builder::encode_veto_script_function(opts.uid);
+                match maybe_submit(script, &tx_params, None) {
+                    Ok(_) => println!(
+                        "Success: vetoed transfer {}, its payment is delayed by one epoch",
+                        opts.uid
+                    ),
+                    Err(e) => {
+                        println!("ERROR: could not veto the transfer: {}", e);
+                        exit(1);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// the community transfers at 0x0, and the current epoch
+pub fn get_community_transfers(
+    client: &DiemClient,
+) -> Result<(CommunityTransfersResource, u64), Error> {
+    let (blob, _version) = client.get_account_state_blob(&AccountAddress::ZERO)?;
+    let state = match blob {
+        Some(b) => AccountState::try_from(&b)?,
+        None => bail!("no state found at 0x0"),
+    };
+    let epoch = match state.get_configuration_resource()? {
+        Some(c) => c.epoch(),
+        None => bail!("no configuration resource found at 0x0"),
+    };
+    match state.get_resource::<CommunityTransfersResource>()? {
+        Some(t) => Ok((t, epoch)),
+        None => bail!("community transfers are not initialized on this chain"),
+    }
+}
+
+fn print_row(t: &TimedTransfer, status: TransferStatus, epoch: u64) {
+    let when = match status {
+        TransferStatus::Proposed => format!("pays in {} epochs", t.epochs_to_maturity(epoch)),
+        _ => format!("{:?}", status),
+    };
+    println!(
+        "#{} {} -> {}, {}, {}, vetos: {}, memo: {}",
+        t.uid,
+        t.payer,
+        t.payee,
+        t.value,
+        when,
+        t.veto.list.len(),
+        t.memo()
+    );
+}
+
+fn print_timeline(t: &TimedTransfer, status: TransferStatus, epoch: u64) {
+    println!("transfer #{}: {:?}", t.uid, status);
+    println!("  payer:   {}", t.payer);
+    println!("  payee:   {}", t.payee);
+    println!("  value:   {}", t.value);
+    println!("  memo:    {}", t.memo());
+    println!("  current epoch: {}", epoch);
+    if status == TransferStatus::Proposed {
+        println!(
+            "  paid at the start of epoch {}, in {} epochs, unless vetoed",
+            t.expire_epoch,
+            t.epochs_to_maturity(epoch)
+        );
+        println!("  each new veto delays the payment by one epoch");
+    } else {
+        println!("  expire epoch: {}", t.expire_epoch);
+    }
+    // the tally is only refreshed when a veto is submitted
+    println!(
+        "  vetos: {}, voting power {} of {} needed to reject",
+        t.veto.list.len(),
+        t.veto.count,
+        t.veto.threshold
+    );
+    for v in t.veto.list.iter() {
+        println!("    {}", v);
+    }
+}
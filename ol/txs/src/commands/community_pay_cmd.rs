@@ -52,19 +52,7 @@ impl Runnable for CommunityPayCmd {
                 self.destination_account
             ),
             Err(e) => {
-                println!("ERROR: could not create community transfer proposal:");
-                if let Some(loc) = &e.location {
-                    if loc.contains("TransferScripts") {
-                        match e.abort_code {
-                            Some(0) => println!("this account is not a community wallet\n"),
-                            Some(1) => println!("destination account is not a slow wallet\n"),
-                            _ => println!("misc error, could not propose the tx: {:?}\n", &e),
-                        }
-                    } else {
-                        println!("misc error, could not propose the tx: {:?}\n", &e)
-                    }
-                };
-
+                println!("ERROR: could not create community transfer proposal: {}", e);
                 exit(1);
             }
         }
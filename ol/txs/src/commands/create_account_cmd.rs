@@ -42,7 +42,7 @@ impl Runnable for CreateAccountCmd {
         match create_from_auth_and_coin(authkey, self.coins, tx_params, entry_args.save_path) {
             Ok(_) => println!("Success: Account created for authkey: {}", authkey),
             Err(e) => {
-                println!("ERROR: could not create account, message: {:?}", &e);
+                println!("ERROR: could not create account, message: {}", &e);
                 exit(1);
             }
         }
@@ -58,6 +58,7 @@ pub fn create_validator_script_function(
     ))
 }
 
+/// fetch the account.json served by a node
 pub fn account_from_url(url: &mut Url, path: &PathBuf) -> PathBuf {
     url.set_port(Some(3030)).unwrap();
     let url_string = url.join("account.json").unwrap();
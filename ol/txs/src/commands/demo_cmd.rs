@@ -29,7 +29,7 @@ impl Runnable for DemoCmd {
             }
             Err(e) => {
                 println!(
-                    "ERROR: could not submit demo transaction, message: \n{:?}",
+                    "ERROR: could not submit demo transaction, message: \n{}",
                     &e
                 );
                 exit(1);
@@ -75,7 +75,7 @@ impl Runnable for OracleUpgradeCmd {
         match maybe_submit(script, &tx_params, entry_args.save_path) {
             Err(e) => {
                 println!(
-                    "ERROR: could not submit oracle transaction, message: \n{:?}",
+                    "ERROR: could not submit oracle transaction, message: \n{}",
                     &e
                 );
                 exit(1);
@@ -85,6 +85,7 @@ impl Runnable for OracleUpgradeCmd {
     }
 }
 
+/// vote for an upgrade with the stdlib payload
 pub fn oracle_tx_script(upgrade_file_path: &PathBuf) -> TransactionPayload {
     let mut file = fs::File::open(upgrade_file_path).expect("file should open read only");
     let mut buffer = Vec::new();
@@ -95,6 +96,7 @@ pub fn oracle_tx_script(upgrade_file_path: &PathBuf) -> TransactionPayload {
     transaction_builder::encode_ol_oracle_tx_script_function(id, buffer)
 }
 
+/// vote for an upgrade with the hash of the stdlib payload
 pub fn oracle_hash_tx_script(upgrade_hash: Vec<u8>) -> TransactionPayload {
     let id = 2; // upgrade with hash is oracle #2
     transaction_builder::encode_ol_oracle_tx_script_function(id, upgrade_hash)
@@ -4,7 +4,10 @@
 
 use crate::{
     entrypoint,
+    epoch::get_epoch,
+    prelude::app_config,
     submit_tx::{maybe_submit, tx_params_wrapper, TxError},
+    transfer_queue::TransferQueue,
     tx_params::TxParams,
 };
 use abscissa_core::{Command, Options, Runnable};
@@ -12,8 +15,16 @@ use abscissa_core::{Command, Options, Runnable};
 use diem_json_rpc_types::views::TransactionView;
 use diem_transaction_builder::stdlib as transaction_builder;
 use diem_types::account_address::AccountAddress;
+use ol::node::{
+    node::Node,
+    slow_wallet::{SlowWalletView, COIN_SCALING_FACTOR},
+};
 use ol_types::config::TxType;
 use std::{path::PathBuf, process::exit};
+
+/// epochs of the unlock schedule printed when a transfer is refused
+const SCHEDULE_EPOCHS_SHOWN: u64 = 10;
+
 /// `CreateAccount` subcommand
 #[derive(Command, Debug, Default, Options)]
 pub struct TransferCmd {
@@ -21,11 +32,32 @@ pub struct TransferCmd {
     destination_account: String,
     #[options(short = "c", help = "the amount of coins to send to new user")]
     coins: u64,
+    #[options(
+        no_short,
+        help = "slow wallets: queue the transfer across epochs as the coins unlock"
+    )]
+    split: bool,
+    #[options(no_short, help = "send the queued transfers which are unlocked")]
+    queued: bool,
 }
 
 impl Runnable for TransferCmd {
     fn run(&self) {
         let entry_args = entrypoint::get_args();
+        let tx_params = match tx_params_wrapper(TxType::Mgmt) {
+            Ok(t) => t,
+            Err(e) => {
+                println!("ERROR: could not get the tx params, message: {}", e);
+                exit(1);
+            }
+        };
+        let node_home = app_config().workspace.node_home.clone();
+
+        if self.queued {
+            send_queued(&tx_params, &node_home, entry_args.save_path);
+            return;
+        }
+
         let destination = match self.destination_account.parse::<AccountAddress>() {
             Ok(a) => a,
             Err(e) => {
@@ -37,14 +69,62 @@ impl Runnable for TransferCmd {
                 exit(1);
             }
         };
-        let tx_params = tx_params_wrapper(TxType::Mgmt).unwrap();
+
+        // slow wallets can only send what has unlocked, otherwise the tx aborts
+        let node = Node::default_from_cfg(app_config().clone(), entry_args.swarm_path.clone());
+        match node.get_slow_wallet(tx_params.signer_address) {
+            Ok(Some(wallet)) if !wallet.can_transfer(self.coins) => {
+                let epoch = get_epoch(&tx_params);
+                let per_epoch = node.get_epoch_unlock().unwrap_or_else(|e| {
+                    println!("ERROR: could not get the unlock per epoch, message: {}", e);
+                    exit(1);
+                });
+                print_unlock_schedule(&wallet, self.coins, epoch, per_epoch);
+
+                if !self.split {
+                    println!("ERROR: the transfer exceeds the unlocked balance of this slow wallet and would abort. Use --split to queue it across epochs.");
+                    exit(1);
+                }
+                let plan = wallet
+                    .plan_split(self.coins, epoch, per_epoch)
+                    .unwrap_or_else(|e| {
+                        println!("ERROR: cannot split the transfer, message: {}", e);
+                        exit(1);
+                    });
+                let mut queue = TransferQueue::read_json(&node_home).unwrap_or_else(|e| {
+                    println!("ERROR: could not read the transfer queue, message: {}", e);
+                    exit(1);
+                });
+                queue.push_tranches(tx_params.signer_address, destination, &plan);
+                queue.write_json(&node_home).unwrap_or_else(|e| {
+                    println!("ERROR: could not save the transfer queue, message: {}", e);
+                    exit(1);
+                });
+                for t in plan.iter() {
+                    println!("queued {} coins for epoch {}", t.coins, t.epoch);
+                }
+                println!(
+                    "Run `txs transfer --queued` in each epoch to send the unlocked tranches."
+                );
+
+                // the first tranche may already be unlocked
+                send_queued(&tx_params, &node_home, entry_args.save_path);
+                return;
+            }
+            Err(e) => println!(
+                "WARN: could not check the slow wallet state, sending anyway. Message: {}",
+                e
+            ),
+            _ => {}
+        }
+
         match balance_transfer(destination, self.coins, tx_params, entry_args.save_path) {
             Ok(_) => println!(
                 "Success: Balance transfer posted: {}",
                 self.destination_account
             ),
             Err(e) => {
-                println!("ERROR: execute balance transfer message: {:?}", &e);
+                println!("ERROR: execute balance transfer message: {}", &e);
                 exit(1);
             }
         }
@@ -63,3 +143,65 @@ pub fn balance_transfer(
 
     maybe_submit(script, &tx_params, save_path)
 }
+
+/// send the queued transfers due in the current epoch, failed ones go back to the queue
+fn send_queued(tx_params: &TxParams, node_home: &PathBuf, save_path: Option<PathBuf>) {
+    let epoch = get_epoch(tx_params);
+    let mut queue = TransferQueue::read_json(node_home).unwrap_or_else(|e| {
+        println!("ERROR: could not read the transfer queue, message: {}", e);
+        exit(1);
+    });
+    let sender = tx_params.signer_address;
+    let due = queue.take_due(sender, epoch);
+    if due.is_empty() {
+        println!(
+            "No queued transfers of {} are unlocked in epoch {}, {} pending.",
+            sender,
+            epoch,
+            queue.pending(sender).len()
+        );
+        return;
+    }
+
+    for t in due {
+        let script =
+            transaction_builder::encode_balance_transfer_script_function(t.destination, t.coins);
+        match maybe_submit(script, tx_params, save_path.clone()) {
+            Ok(_) => println!(
+                "Success: queued transfer of {} coins posted: {}",
+                t.coins, t.destination
+            ),
+            Err(e) => {
+                println!(
+                    "ERROR: queued transfer of {} coins to {} failed, kept in queue. Message: {}",
+                    t.coins, t.destination, e
+                );
+                queue.requeue(sender, t);
+            }
+        }
+    }
+    queue.write_json(node_home).unwrap_or_else(|e| {
+        println!("ERROR: could not save the transfer queue, message: {}", e);
+    });
+}
+
+/// print the unlocked amount by epoch, until the coins can be sent
+fn print_unlock_schedule(wallet: &SlowWalletView, coins: u64, epoch: u64, per_epoch: u64) {
+    println!(
+        "Slow wallet: unlocked {}, balance {}, transfer {}",
+        wallet.unlocked / COIN_SCALING_FACTOR,
+        wallet.balance / COIN_SCALING_FACTOR,
+        coins
+    );
+    println!("Projected unlock schedule:");
+    for s in wallet.unlock_schedule(epoch, per_epoch, SCHEDULE_EPOCHS_SHOWN) {
+        println!(
+            "  epoch {}: {} unlocked",
+            s.epoch,
+            s.unlocked / COIN_SCALING_FACTOR
+        );
+        if s.unlocked > coins.saturating_mul(COIN_SCALING_FACTOR) {
+            break;
+        }
+    }
+}
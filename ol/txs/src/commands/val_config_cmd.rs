@@ -67,7 +67,7 @@ impl Runnable for ValConfigCmd {
                 println!("{:?}", &r);
             }
             Err(e) => {
-                println!("ERROR: could not update on-chain ip address: {:?}", &e);
+                println!("ERROR: could not update on-chain ip address: {}", &e);
                 exit(1);
             }
         }
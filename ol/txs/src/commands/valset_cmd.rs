@@ -47,7 +47,7 @@ impl Runnable for ValSetCmd {
         ) {
             Err(e) => {
                 println!(
-                    "ERROR: could not submit validator-set transaction, message: \n{:?}",
+                    "ERROR: could not submit validator-set transaction, message: \n{}",
                     &e
                 );
                 exit(1);
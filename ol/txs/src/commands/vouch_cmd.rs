@@ -66,7 +66,7 @@ impl Runnable for VouchCmd {
             }
             Err(e) => {
                 println!(
-                    "ERROR: could not submit vouch transaction, message: \n{:?}",
+                    "ERROR: could not submit vouch transaction, message: \n{}",
                     &e
                 );
                 exit(1);
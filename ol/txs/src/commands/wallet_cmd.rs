@@ -46,7 +46,7 @@ impl Runnable for WalletCmd {
             Ok(_) => println!("Success: wallet type set"),
             Err(e) => {
                 println!(
-                    "ERROR: could not submit wallet type transaction, message: \n{:?}",
+                    "ERROR: could not submit wallet type transaction, message: \n{}",
                     &e
                 );
                 exit(1);
@@ -15,6 +15,8 @@
     unused_qualifications
 )]
 
+pub mod abort_codes;
+pub mod abort_parse;
 pub mod application;
 pub mod commands;
 pub mod config;
@@ -26,4 +28,5 @@ pub mod relay;
 pub mod save_tx;
 pub mod sign_tx;
 pub mod submit_tx;
+pub mod transfer_queue;
 pub mod tx_params;
@@ -1,11 +1,12 @@
 //! Txs App submit_tx module
 #![forbid(unsafe_code)]
 use crate::{
+    abort_codes,
     entrypoint::{self, EntryPointTxsCmd},
     prelude::app_config,
     save_tx::save_tx,
     sign_tx::sign_tx,
-    tx_params::TxParams,
+    tx_params::{what_url, TxParams},
 };
 use anyhow::{anyhow, Error};
 use cli::{diem_client::DiemClient, AccountData, AccountStatus};
@@ -21,6 +22,7 @@ use diem_types::{
 use ol_types::{self, config::TxType};
 
 use std::{
+    fmt,
     io::{stdout, Write},
     path::PathBuf,
     thread, time,
@@ -54,23 +56,8 @@ use std::{
 //     pub chain_id: ChainId,
 // }
 
-// REFERENCE:
-// DiemAccount.move defines the following prologue errors
-
-// const PROLOGUE_EACCOUNT_FROZEN: u64 = 1000;
-// const PROLOGUE_EINVALID_ACCOUNT_AUTH_KEY: u64 = 1001;
-// const PROLOGUE_ESEQUENCE_NUMBER_TOO_OLD: u64 = 1002;
-// const PROLOGUE_ESEQUENCE_NUMBER_TOO_NEW: u64 = 1003;
+// DiemAccount.move, the other prologue codes are explained by abort_codes.rs
 const PROLOGUE_EACCOUNT_DNE: u64 = 1004;
-// const PROLOGUE_ECANT_PAY_GAS_DEPOSIT: u64 = 1005;
-// const PROLOGUE_ETRANSACTION_EXPIRED: u64 = 1006;
-// const PROLOGUE_EBAD_CHAIN_ID: u64 = 1007;
-// const PROLOGUE_ESCRIPT_NOT_ALLOWED: u64 = 1008;
-// const PROLOGUE_EMODULE_NOT_ALLOWED: u64 = 1009;
-// const PROLOGUE_EINVALID_WRITESET_SENDER: u64 = 1010;
-// const PROLOGUE_ESEQUENCE_NUMBER_TOO_BIG: u64 = 1011;
-// const PROLOGUE_EBAD_TRANSACTION_FEE_CURRENCY: u64 = 1012;
-// const PROLOGUE_ESECONDARY_KEYS_ADDRESSES_COUNT_MISMATCH: u64 = 1013;
 
 #[derive(Debug)]
 /// a transaction error type specific to ol txs
@@ -85,6 +72,12 @@ pub struct TxError {
     pub abort_code: Option<u64>,
 }
 
+impl fmt::Display for TxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", abort_codes::report(self))
+    }
+}
+
 impl From<Error> for TxError {
     fn from(e: Error) -> Self {
         TxError {
@@ -261,6 +254,36 @@ pub fn tx_params_wrapper(tx_type: TxType) -> Result<TxParams, Error> {
     )
 }
 
+/// Client for queries, to the node the tx params would use. The mnemonic is not needed.
+pub fn query_client_wrapper() -> Result<DiemClient, Error> {
+    let EntryPointTxsCmd {
+        url,
+        waypoint,
+        swarm_path,
+        use_first_url,
+        ..
+    } = entrypoint::get_args();
+    let config = app_config().clone();
+    let (url, waypoint) = match swarm_path {
+        Some(s) => {
+            let (swarm_url, swarm_waypoint) = ol_types::config::get_swarm_rpc_url(s);
+            (swarm_url, waypoint.unwrap_or(swarm_waypoint))
+        }
+        None => {
+            let url = match url {
+                Some(u) => u,
+                None => what_url(&config, use_first_url)?,
+            };
+            let waypoint = match waypoint {
+                Some(w) => w,
+                None => config.get_waypoint(None)?,
+            };
+            (url, waypoint)
+        }
+    };
+    DiemClient::new(url, waypoint)
+}
+
 // // TODO: This could just be the constructor.
 // /// tx_parameters format
 // pub fn tx_params(
@@ -481,13 +504,14 @@ pub fn eval_tx_status(result: TransactionView) -> Result<TransactionView, TxErro
                 "Transaction failed, rejected with status: {:?}",
                 result.vm_status
             );
-            println!("{}", &msg);
-            Err(TxError {
+            let e = TxError {
                 err: Some(Error::msg(msg)),
                 tx_view: Some(result.clone()),
                 location: Some(location.to_string()),
                 abort_code: Some(*abort_code),
-            })
+            };
+            println!("Transaction failed, {}", &e);
+            Err(e)
         }
         _ => {
             let msg = format!("Rejected with code: {:?}", result.vm_status);
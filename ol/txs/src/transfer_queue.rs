@@ -0,0 +1,123 @@
+//! `transfer_queue` slow wallet transfers waiting for their coins to unlock
+
+use anyhow::{anyhow, Error};
+use diem_types::account_address::AccountAddress;
+use ol::node::slow_wallet::TransferTranche;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{ErrorKind, Write},
+    path::PathBuf,
+};
+
+/// filename of the queue, in node_home
+pub const TRANSFER_QUEUE_NAME: &str = "transfer_queue.json";
+
+/// A transfer to send once its epoch is reached
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct QueuedTransfer {
+    /// recipient
+    pub destination: AccountAddress,
+    /// coins, unscaled
+    pub coins: u64,
+    /// first epoch in which the coins are unlocked
+    pub epoch: u64,
+}
+
+/// Queued transfers, persisted in node_home.
+/// Several accounts may share a node_home, so the transfers are kept by sender.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct TransferQueue {
+    /// transfers of each sender, in the order they were queued
+    pub by_sender: BTreeMap<AccountAddress, Vec<QueuedTransfer>>,
+}
+
+impl TransferQueue {
+    /// read the queue, or start an empty one if there is no file yet.
+    /// A file which doesn't parse is an error, not an empty queue, so it is not overwritten.
+    pub fn read_json(node_home: &PathBuf) -> Result<TransferQueue, Error> {
+        let path = node_home.join(TRANSFER_QUEUE_NAME);
+        match fs::File::open(&path) {
+            Ok(file) => serde_json::from_reader(file)
+                .map_err(|e| anyhow!("could not parse {}: {}", path.display(), e)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(TransferQueue::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// write the queue
+    pub fn write_json(&self, node_home: &PathBuf) -> Result<(), Error> {
+        let mut file = File::create(node_home.join(TRANSFER_QUEUE_NAME))?;
+        file.write_all(&serde_json::to_vec_pretty(&self)?)?;
+        Ok(())
+    }
+
+    /// queue the tranches of a split transfer
+    pub fn push_tranches(
+        &mut self,
+        sender: AccountAddress,
+        destination: AccountAddress,
+        tranches: &[TransferTranche],
+    ) {
+        let transfers = self.by_sender.entry(sender).or_default();
+        for t in tranches {
+            transfers.push(QueuedTransfer {
+                destination,
+                coins: t.coins,
+                epoch: t.epoch,
+            });
+        }
+    }
+
+    /// put back a transfer which could not be sent
+    pub fn requeue(&mut self, sender: AccountAddress, transfer: QueuedTransfer) {
+        self.by_sender.entry(sender).or_default().push(transfer);
+    }
+
+    /// transfers of the sender still waiting
+    pub fn pending(&self, sender: AccountAddress) -> &[QueuedTransfer] {
+        self.by_sender.get(&sender).map_or(&[], |t| t.as_slice())
+    }
+
+    /// remove and return the transfers of the sender due at the epoch
+    pub fn take_due(&mut self, sender: AccountAddress, epoch: u64) -> Vec<QueuedTransfer> {
+        let transfers = match self.by_sender.get_mut(&sender) {
+            Some(t) => t,
+            None => return vec![],
+        };
+        let (due, later) = transfers.drain(..).partition(|t| t.epoch <= epoch);
+        *transfers = later;
+        if transfers.is_empty() {
+            self.by_sender.remove(&sender);
+        }
+        due
+    }
+}
+
+#[test]
+fn test_take_due() {
+    let sender = AccountAddress::new([1; AccountAddress::LENGTH]);
+    let other = AccountAddress::new([2; AccountAddress::LENGTH]);
+    let mut q = TransferQueue::default();
+    q.push_tranches(
+        sender,
+        AccountAddress::ZERO,
+        &[
+            TransferTranche {
+                epoch: 10,
+                coins: 5,
+            },
+            TransferTranche {
+                epoch: 11,
+                coins: 7,
+            },
+        ],
+    );
+    assert!(q.take_due(other, 10).is_empty());
+    let due = q.take_due(sender, 10);
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].coins, 5);
+    assert_eq!(q.pending(sender).len(), 1);
+    assert_eq!(q.pending(sender)[0].epoch, 11);
+}
@@ -12,15 +12,12 @@ use once_cell::sync::Lazy;
 use reqwest::{blocking::Client, Url};
 use serde::{Deserialize, Serialize};
 use serde_json::{self, json};
-use std::{
-    fs::{self, File},
-    io::{Read, Write},
-    net::Ipv4Addr,
-    path::PathBuf,
-    str::FromStr,
-};
+use std::{fs, io::Write, net::Ipv4Addr, path::PathBuf, str::FromStr};
 
-use crate::dialogue::{what_home, what_ip, what_statement, what_vfn_ip};
+use crate::{
+    config_migration::{migrate_file, CURRENT_SCHEMA_VERSION},
+    dialogue::{what_home, what_ip, what_statement, what_vfn_ip},
+};
 
 const BASE_WAYPOINT: &str = "0:683185844ef67e5c8eeaa158e635de2a4c574ce7bbb7f41f787d38db2d623ae2";
 
@@ -50,6 +47,9 @@ pub static IS_TEST: Lazy<bool> = Lazy::new(|| {
 #[derive(Clone, Debug, Deserialize, Serialize)]
 // #[serde(deny_unknown_fields)]
 pub struct AppCfg {
+    /// Schema version of the file, see `config_migration`
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u64,
     /// Workspace config
     pub workspace: Workspace,
     /// User Profile
@@ -60,16 +60,14 @@ pub struct AppCfg {
     pub tx_configs: TxConfigs,
 }
 
-/// Get a AppCfg object from toml file
+fn default_schema_version() -> u64 {
+    CURRENT_SCHEMA_VERSION
+}
+
+/// Get a AppCfg object from toml file, files of older versions are migrated in place
 pub fn parse_toml(path: Option<PathBuf>) -> Result<AppCfg, Error> {
     let cfg_path = path.unwrap_or(dirs::home_dir().unwrap().join(".0L").join("0L.toml"));
-
-    let mut toml_buf = "".to_string();
-    let mut file = File::open(&cfg_path)?;
-    file.read_to_string(&mut toml_buf)?;
-
-    let cfg: AppCfg = toml::from_str(&toml_buf)?;
-    Ok(cfg)
+    Ok(migrate_file(&cfg_path, false)?.cfg)
 }
 
 /// Get a AppCfg object from toml file
@@ -263,6 +261,7 @@ impl AppCfg {
         // let waypoint = config.base.waypoint.waypoint();
 
         let mut cfg = AppCfg {
+            schema_version: CURRENT_SCHEMA_VERSION,
             workspace: Workspace::default(),
             profile: Profile::default(),
             chain_info: ChainInfo::default(),
@@ -306,6 +305,7 @@ impl AppCfg {
 impl Default for AppCfg {
     fn default() -> Self {
         Self {
+            schema_version: CURRENT_SCHEMA_VERSION,
             workspace: Workspace::default(),
             profile: Profile::default(),
             chain_info: ChainInfo::default(),
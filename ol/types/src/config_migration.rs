@@ -0,0 +1,349 @@
+//! Versioned schema of 0L.toml, and the migrations from each historical version.
+//!
+//! Each released layout is frozen as its own struct, and migrates to the next one with a `From` impl.
+//! To change `AppCfg`: freeze the current layout as `AppCfgVn`, bump `CURRENT_SCHEMA_VERSION`, and add the step to `migrate_str`.
+
+use crate::config::{AppCfg, ChainInfo, Profile, TxConfigs, TxCost, Workspace};
+use anyhow::{bail, Error};
+use diem_types::{chain_id::NamedChain, waypoint::Waypoint};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// schema version of `AppCfg`
+///  0: v4.2.8
+///  1: v4.3.0
+pub const CURRENT_SCHEMA_VERSION: u64 = 1;
+
+/// 0L.toml as written by v4.2.8
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AppCfgV0 {
+    /// workspace, without the db path
+    pub workspace: WorkspaceV0,
+    /// profile, unchanged since
+    pub profile: Profile,
+    /// chain info, without the base epoch
+    pub chain_info: ChainInfoV0,
+    /// tx configs, before the costs per tx type
+    #[serde(default)]
+    pub tx_configs: TxConfigsV0,
+}
+
+/// `Workspace` of v4.2.8
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WorkspaceV0 {
+    /// home directory of the diem node
+    pub node_home: PathBuf,
+    /// source code, for developers
+    pub source_path: Option<PathBuf>,
+    /// directory of the vdf proofs
+    pub block_dir: String,
+    /// staged stdlib for upgrades
+    pub stdlib_bin_path: Option<PathBuf>,
+}
+
+/// `ChainInfo` of v4.2.8
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ChainInfoV0 {
+    /// chain the node is on
+    pub chain_id: NamedChain,
+    /// waypoint the node started syncing from
+    pub base_waypoint: Option<Waypoint>,
+}
+
+/// `TxConfigs` of v4.2.8
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct TxConfigsV0 {
+    /// became the baseline cost
+    pub miner_txs: Option<TxCost>,
+    /// became the management txs cost
+    pub management_txs: Option<TxCost>,
+}
+
+/// v4.2.8 to v4.3.0, the same changes `ol-util` used to patch in
+impl From<AppCfgV0> for AppCfg {
+    fn from(v0: AppCfgV0) -> Self {
+        let defaults = TxConfigs::default();
+        let management_txs_cost = v0.tx_configs.management_txs.map(|mut c| {
+            // the default was lowered from 1_000_000
+            c.max_gas_unit_for_tx = 100_000;
+            c
+        });
+        AppCfg {
+            schema_version: 1,
+            workspace: Workspace {
+                db_path: v0.workspace.node_home.join("db"),
+                node_home: v0.workspace.node_home,
+                source_path: v0.workspace.source_path,
+                block_dir: v0.workspace.block_dir,
+                stdlib_bin_path: v0.workspace.stdlib_bin_path,
+            },
+            profile: v0.profile,
+            chain_info: ChainInfo {
+                chain_id: v0.chain_info.chain_id,
+                base_epoch: Some(0),
+                base_waypoint: v0.chain_info.base_waypoint,
+            },
+            tx_configs: TxConfigs {
+                baseline_cost: v0.tx_configs.miner_txs.unwrap_or(defaults.baseline_cost),
+                management_txs_cost: management_txs_cost.or(defaults.management_txs_cost),
+                ..defaults
+            },
+        }
+    }
+}
+
+/// keys of v4.2.8 which were renamed, rather than dropped, by the migration to v4.3.0
+const V0_RENAMED_KEYS: &[&str] = &["tx_configs.miner_txs", "tx_configs.management_txs"];
+
+/// keys only written by v4.3.0 and later
+const V1_ONLY_KEYS: &[&str] = &[
+    "workspace.db_path",
+    "chain_info.base_epoch",
+    "tx_configs.baseline_cost",
+    "tx_configs.critical_txs_cost",
+    "tx_configs.management_txs_cost",
+    "tx_configs.miner_txs_cost",
+    "tx_configs.cheap_txs_cost",
+];
+
+/// Outcome of migrating a config file
+#[derive(Clone, Debug)]
+pub struct Migration {
+    /// the config, at the current schema
+    pub cfg: AppCfg,
+    /// schema version of the file, None if its layout matches no single version
+    pub from_version: Option<u64>,
+    /// lines removed from and added to the file, as `- ` and `+ `
+    pub diff: Vec<String>,
+    /// keys of the file the config has no field for, as dotted paths
+    pub dropped: Vec<String>,
+    /// backup of the original file, if it was rewritten
+    pub backup: Option<PathBuf>,
+}
+
+/// schema version of a 0L.toml. Files before versioning are recognized by the keys only one
+/// version writes. None if the file has keys of both versions, or of neither.
+pub fn detect_schema_version(value: &toml::Value) -> Option<u64> {
+    if let Some(v) = value.get("schema_version").and_then(|v| v.as_integer()) {
+        return Some(v as u64);
+    }
+    let has_any = |keys: &[&str]| keys.iter().any(|k| get_path(value, k).is_some());
+    match (has_any(V0_RENAMED_KEYS), has_any(V1_ONLY_KEYS)) {
+        (true, false) => Some(0),
+        (false, true) => Some(1),
+        _ => None,
+    }
+}
+
+/// parse a 0L.toml of any known version into the current `AppCfg`.
+/// A file of ambiguous version is read with the current layout.
+pub fn migrate_str(toml_str: &str) -> Result<(AppCfg, Option<u64>), Error> {
+    let value: toml::Value = toml::from_str(toml_str)?;
+    let from_version = detect_schema_version(&value);
+    let mut cfg: AppCfg = match from_version {
+        Some(0) => toml::from_str::<AppCfgV0>(toml_str)?.into(),
+        Some(1) | None => toml::from_str(toml_str)?,
+        Some(v) => bail!(
+            "0L.toml has schema version {}, this build only knows up to {}. Upgrade the 0L tools.",
+            v,
+            CURRENT_SCHEMA_VERSION
+        ),
+    };
+    cfg.schema_version = CURRENT_SCHEMA_VERSION;
+    Ok((cfg, from_version))
+}
+
+/// migrate a 0L.toml to the current schema. The original is backed up before it is rewritten.
+/// With dry_run the file is left untouched, and the diff shows what would change.
+/// A file of ambiguous version is never rewritten, it needs a `schema_version` to be migrated.
+pub fn migrate_file(path: &Path, dry_run: bool) -> Result<Migration, Error> {
+    let original = fs::read_to_string(path)?;
+    let (cfg, from_version) = migrate_str(&original)?;
+    let migrated = toml::to_string(&cfg)?;
+    let mut migration = Migration {
+        cfg,
+        from_version,
+        diff: vec![],
+        dropped: dropped_keys(
+            &toml::from_str::<toml::Value>(&original)?,
+            &toml::from_str::<toml::Value>(&migrated)?,
+            if from_version == Some(0) {
+                V0_RENAMED_KEYS
+            } else {
+                &[]
+            },
+        ),
+        backup: None,
+    };
+    for key in migration.dropped.iter() {
+        println!(
+            "WARN: {:?} has no setting for `{}`, it is ignored",
+            path, key
+        );
+    }
+    let from_version = match from_version {
+        Some(CURRENT_SCHEMA_VERSION) => return Ok(migration),
+        Some(v) => v,
+        None => {
+            println!(
+                "WARN: cannot tell the schema version of {:?}, it has keys of several versions or none. \
+                It is read with schema {} and left unchanged, set `schema_version` to migrate it.",
+                path, CURRENT_SCHEMA_VERSION
+            );
+            return Ok(migration);
+        }
+    };
+
+    migration.diff = line_diff(&original, &migrated);
+    if dry_run {
+        return Ok(migration);
+    }
+
+    let backup = backup_file(path, from_version)?;
+    let mut file = File::create(path)?;
+    file.write_all(migrated.as_bytes())?;
+    println!(
+        "migrated {:?} from schema {} to {}, backup saved to {:?}",
+        path, from_version, CURRENT_SCHEMA_VERSION, &backup
+    );
+    migration.backup = Some(backup);
+    Ok(migration)
+}
+
+/// value at a dotted path of tables
+fn get_path<'a>(value: &'a toml::Value, path: &str) -> Option<&'a toml::Value> {
+    path.split('.').try_fold(value, |v, key| v.get(key))
+}
+
+/// dotted paths of the keys of the original file missing from the migrated one, the keys the
+/// migration renamed excepted
+fn dropped_keys(original: &toml::Value, migrated: &toml::Value, renamed: &[&str]) -> Vec<String> {
+    fn walk(
+        v: &toml::Value,
+        prefix: &str,
+        migrated: &toml::Value,
+        renamed: &[&str],
+        out: &mut Vec<String>,
+    ) {
+        let table = match v.as_table() {
+            Some(t) => t,
+            None => return,
+        };
+        for (key, child) in table {
+            let path = if prefix.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", prefix, key)
+            };
+            if renamed.contains(&path.as_str()) {
+                continue;
+            }
+            match get_path(migrated, &path) {
+                Some(_) => walk(child, &path, migrated, renamed, out),
+                None => out.push(path),
+            }
+        }
+    }
+    let mut out = vec![];
+    walk(original, "", migrated, renamed, &mut out);
+    out
+}
+
+/// copy the file to `<file>.bak.v<version>.<unix secs>`
+pub fn backup_file(path: &Path, version: u64) -> Result<PathBuf, Error> {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".bak.v{}.{}", version, secs));
+    let backup = PathBuf::from(name);
+    fs::copy(path, &backup)?;
+    Ok(backup)
+}
+
+/// lines only in the old text, then lines only in the new one
+fn line_diff(old: &str, new: &str) -> Vec<String> {
+    let old_lines: Vec<&str> = old.lines().map(|l| l.trim()).collect();
+    let new_lines: Vec<&str> = new.lines().map(|l| l.trim()).collect();
+    let removed = old_lines
+        .iter()
+        .filter(|l| !l.is_empty() && !new_lines.contains(l))
+        .map(|l| format!("- {}", l));
+    let added = new_lines
+        .iter()
+        .filter(|l| !l.is_empty() && !old_lines.contains(l))
+        .map(|l| format!("+ {}", l));
+    removed.chain(added).collect()
+}
+
+/// historical configs and their expected migrations, in ol/fixtures/configs/history
+#[test]
+fn test_golden_migrations() {
+    let history = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .join("fixtures/configs/history");
+    // the partial file has a stale v4.2.8 key next to the v4.3.0 ones
+    let goldens = [
+        ("v0_4.2.8", Some(0)),
+        ("v1_4.3.0", Some(1)),
+        ("v1_4.3.0_partial", None),
+    ];
+    for (name, version) in goldens.iter() {
+        let input = fs::read_to_string(history.join(format!("{}.toml", name))).unwrap();
+        let golden = fs::read_to_string(history.join(format!("{}.golden.toml", name))).unwrap();
+
+        let (cfg, from_version) = migrate_str(&input).unwrap();
+        assert_eq!(from_version, *version, "schema version of {}", name);
+        let migrated: toml::Value = toml::from_str(&toml::to_string(&cfg).unwrap()).unwrap();
+        let expected: toml::Value = toml::from_str(&golden).unwrap();
+        assert_eq!(migrated, expected, "migration of {}", name);
+
+        // migrating again is a no-op
+        let (_, again) = migrate_str(&toml::to_string(&cfg).unwrap()).unwrap();
+        assert_eq!(again, Some(CURRENT_SCHEMA_VERSION));
+    }
+    assert!(migrate_str("schema_version = 99").is_err());
+}
+
+#[test]
+fn test_dropped_keys() {
+    let original: toml::Value = toml::from_str(
+        r#"
+        [tx_configs.miner_txs]
+        user_tx_timeout = 5000
+        [tx_configs.stale]
+        user_tx_timeout = 5000
+        [workspace]
+        node_home = "/root/.0L/"
+        old_key = 1
+        "#,
+    )
+    .unwrap();
+    let migrated: toml::Value = toml::from_str(
+        r#"
+        [tx_configs.baseline_cost]
+        user_tx_timeout = 5000
+        [workspace]
+        node_home = "/root/.0L/"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        dropped_keys(&original, &migrated, V0_RENAMED_KEYS),
+        vec!["tx_configs.stale", "workspace.old_key"]
+    );
+    assert_eq!(
+        dropped_keys(&original, &migrated, &[]),
+        vec![
+            "tx_configs.miner_txs",
+            "tx_configs.stale",
+            "workspace.old_key"
+        ]
+    );
+    assert_eq!(detect_schema_version(&original), Some(0));
+    assert_eq!(detect_schema_version(&migrated), Some(1));
+}
@@ -7,7 +7,7 @@ use move_core_types::{
     ident_str,
     identifier::IdentStr,
     language_storage::{ResourceKey, StructTag},
-    move_resource::MoveStructType,
+    move_resource::{MoveResource, MoveStructType},
 };
 use serde::{Deserialize, Serialize};
 
@@ -58,3 +58,43 @@ impl FullnodeCounterResource {
         bcs::from_bytes(bytes).map_err(Into::into)
     }
 }
+
+/// The global proof counts in `TowerState::TowerCounter`, at 0x0
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TowerCounterResource {
+    ///
+    pub lifetime_proofs: u64,
+    ///
+    pub lifetime_validator_proofs: u64,
+    ///
+    pub lifetime_fullnode_proofs: u64,
+    ///
+    pub proofs_in_epoch: u64,
+    ///
+    pub validator_proofs_in_epoch: u64,
+    ///
+    pub fullnode_proofs_in_epoch: u64,
+    ///
+    pub validator_proofs_in_epoch_above_thresh: u64,
+    /// proofs the fullnode subsidy of the epoch is split by
+    pub fullnode_proofs_in_epoch_above_thresh: u64,
+}
+
+impl MoveStructType for TowerCounterResource {
+    const MODULE_NAME: &'static IdentStr = ident_str!("TowerState");
+    const STRUCT_NAME: &'static IdentStr = ident_str!("TowerCounter");
+}
+
+impl MoveResource for TowerCounterResource {}
+
+impl TowerCounterResource {
+    ///
+    pub fn resource_path() -> Vec<u8> {
+        AccessPath::resource_access_vec(TowerCounterResource::struct_tag())
+    }
+
+    ///
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self> {
+        bcs::from_bytes(bytes).map_err(Into::into)
+    }
+}
@@ -13,6 +13,7 @@ pub mod account;
 pub mod autopay;
 pub mod block;
 pub mod config;
+pub mod config_migration;
 pub mod dialogue;
 pub mod epoch_timer;
 pub mod fixtures;
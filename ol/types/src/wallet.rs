@@ -7,7 +7,7 @@ use move_core_types::{
     ident_str,
     identifier::IdentStr,
     language_storage::{ResourceKey, StructTag},
-    move_resource::MoveStructType,
+    move_resource::{MoveResource, MoveStructType},
 };
 use serde::{Deserialize, Serialize};
 
@@ -86,3 +86,94 @@ impl SlowWalletResource {
         bcs::from_bytes(bytes).map_err(Into::into)
     }
 }
+
+/// Status of a community wallet transfer, the list it is in
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum TransferStatus {
+    /// pending, may be vetoed until its epoch
+    Proposed,
+    /// paid at the epoch boundary
+    Approved,
+    /// vetoed by the validator set
+    Rejected,
+}
+
+/// Veto tally of a transfer, updated on each veto
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Veto {
+    /// validators which vetoed
+    pub list: Vec<AccountAddress>,
+    /// voting power of the vetos, at the last veto
+    pub count: u64,
+    /// voting power needed to reject, at the last veto
+    pub threshold: u64,
+}
+
+/// A community wallet transfer, paid at the start of `expire_epoch` unless vetoed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimedTransfer {
+    /// id of the transfer
+    pub uid: u64,
+    /// epoch the transfer is paid at
+    pub expire_epoch: u64,
+    /// community wallet
+    pub payer: AccountAddress,
+    /// recipient, a slow wallet
+    pub payee: AccountAddress,
+    /// amount, scaled
+    pub value: u64,
+    /// memo of the proposal
+    pub description: Vec<u8>,
+    /// veto tally
+    pub veto: Veto,
+}
+
+impl TimedTransfer {
+    /// memo of the proposal as text
+    pub fn memo(&self) -> String {
+        String::from_utf8_lossy(&self.description).to_string()
+    }
+
+    /// epochs left before the transfer is paid
+    pub fn epochs_to_maturity(&self, current_epoch: u64) -> u64 {
+        self.expire_epoch.saturating_sub(current_epoch)
+    }
+}
+
+/// Struct that represents the CommunityTransfers resource, at 0x0
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommunityTransfersResource {
+    /// pending transfers
+    pub proposed: Vec<TimedTransfer>,
+    /// paid transfers
+    pub approved: Vec<TimedTransfer>,
+    /// vetoed transfers
+    pub rejected: Vec<TimedTransfer>,
+    /// last uid issued
+    pub max_uid: u64,
+}
+
+impl MoveStructType for CommunityTransfersResource {
+    const MODULE_NAME: &'static IdentStr = ident_str!("Wallet");
+    const STRUCT_NAME: &'static IdentStr = ident_str!("CommunityTransfers");
+}
+impl MoveResource for CommunityTransfersResource {}
+
+impl CommunityTransfersResource {
+    ///
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self> {
+        bcs::from_bytes(bytes).map_err(Into::into)
+    }
+
+    /// find a transfer in any of the lists
+    pub fn find(&self, uid: u64) -> Option<(TransferStatus, &TimedTransfer)> {
+        let lists = [
+            (TransferStatus::Proposed, &self.proposed),
+            (TransferStatus::Approved, &self.approved),
+            (TransferStatus::Rejected, &self.rejected),
+        ];
+        lists
+            .iter()
+            .find_map(|(status, list)| list.iter().find(|t| t.uid == uid).map(|t| (*status, t)))
+    }
+}
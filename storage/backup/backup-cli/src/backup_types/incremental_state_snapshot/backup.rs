@@ -0,0 +1,283 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    backup_types::incremental_state_snapshot::manifest::{
+        AnyStateSnapshotBackup, IncrementalStateSnapshotBackup, IncrementalStateSnapshotChunk,
+    },
+    metadata::Metadata,
+    storage::{BackupHandleRef, BackupStorage, FileHandle, ShellSafeName},
+    utils::{
+        backup_service_client::BackupServiceClient, read_record_bytes::ReadRecordBytes,
+        should_cut_chunk, storage_ext::BackupStorageExt, GlobalBackupOpt,
+    },
+};
+use anyhow::{anyhow, ensure, Result};
+use bytes::Bytes;
+use diem_crypto::HashValue;
+use diem_logger::prelude::*;
+use diem_types::{
+    account_state_blob::AccountStateBlob, ledger_info::LedgerInfoWithSignatures,
+    proof::TransactionInfoWithProof, transaction::Version,
+};
+use once_cell::sync::Lazy;
+use std::{convert::TryInto, str::FromStr, sync::Arc};
+use structopt::StructOpt;
+use tokio::io::AsyncWriteExt;
+
+#[derive(StructOpt)]
+pub struct IncrementalStateSnapshotBackupOpt {
+    #[structopt(
+        long = "state-version",
+        help = "Version at which a state snapshot to be taken."
+    )]
+    pub version: Version,
+    #[structopt(
+        long = "base-state-manifest",
+        help = "Manifest of the state snapshot, full or incremental, to take this one on top of."
+    )]
+    pub base_manifest: FileHandle,
+}
+
+pub struct IncrementalStateSnapshotBackupController {
+    version: Version,
+    base_manifest: FileHandle,
+    max_chunk_size: usize,
+    client: Arc<BackupServiceClient>,
+    storage: Arc<dyn BackupStorage>,
+}
+
+impl IncrementalStateSnapshotBackupController {
+    pub fn new(
+        opt: IncrementalStateSnapshotBackupOpt,
+        global_opt: GlobalBackupOpt,
+        client: Arc<BackupServiceClient>,
+        storage: Arc<dyn BackupStorage>,
+    ) -> Self {
+        Self {
+            version: opt.version,
+            base_manifest: opt.base_manifest,
+            max_chunk_size: global_opt.max_chunk_size,
+            client,
+            storage,
+        }
+    }
+
+    pub async fn run(self) -> Result<FileHandle> {
+        info!(
+            "Incremental state snapshot backup started, for version {}, on top of {}.",
+            self.version, self.base_manifest,
+        );
+        let ret = self
+            .run_impl()
+            .await
+            .map_err(|e| anyhow!("Incremental state snapshot backup failed: {}", e))?;
+        info!(
+            "Incremental state snapshot backup succeeded. Manifest: {}",
+            ret
+        );
+        Ok(ret)
+    }
+
+    async fn run_impl(self) -> Result<FileHandle> {
+        let base = match self.storage.load_json_file(&self.base_manifest).await? {
+            AnyStateSnapshotBackup::Full(base) => BaseChain {
+                version: base.version,
+                snapshot_version: base.version,
+                snapshot: self.base_manifest.clone(),
+                increments: vec![],
+            },
+            AnyStateSnapshotBackup::Incremental(base) => {
+                let mut increments = base.base_increments;
+                increments.push(self.base_manifest.clone());
+                BaseChain {
+                    version: base.version,
+                    snapshot_version: base.base_snapshot_version,
+                    snapshot: base.base_snapshot,
+                    increments,
+                }
+            }
+        };
+        ensure!(
+            base.version < self.version,
+            "Base state snapshot at version {} is not older than version {}.",
+            base.version,
+            self.version,
+        );
+
+        let backup_handle = self
+            .storage
+            .create_backup_with_random_suffix(&self.backup_name())
+            .await?;
+
+        let mut chunks = vec![];
+        let mut chunk_bytes = vec![];
+        let mut chunk_first_idx: usize = 0;
+        let mut chunk_first_key = HashValue::zero();
+        let mut prev_key = HashValue::zero();
+        let mut current_idx: usize = 0;
+
+        let mut state_delta_file = self
+            .client
+            .get_state_delta(base.version, self.version)
+            .await?;
+        while let Some(record_bytes) = state_delta_file.read_record_bytes().await? {
+            let key = Self::parse_key(&record_bytes)?;
+            if chunk_bytes.is_empty() {
+                chunk_first_idx = current_idx;
+                chunk_first_key = key;
+            } else if should_cut_chunk(&chunk_bytes, &record_bytes, self.max_chunk_size) {
+                let chunk = self
+                    .write_chunk(
+                        &backup_handle,
+                        &chunk_bytes,
+                        chunk_first_idx,
+                        current_idx - 1,
+                        chunk_first_key,
+                        prev_key,
+                    )
+                    .await?;
+                chunks.push(chunk);
+                chunk_bytes = vec![];
+                chunk_first_idx = current_idx;
+                chunk_first_key = key;
+            }
+
+            chunk_bytes.extend(&(record_bytes.len() as u32).to_be_bytes());
+            chunk_bytes.extend(&record_bytes);
+            prev_key = key;
+            current_idx += 1;
+        }
+
+        // Unlike a full state snapshot, this can be empty if nothing changed.
+        if !chunk_bytes.is_empty() {
+            let chunk = self
+                .write_chunk(
+                    &backup_handle,
+                    &chunk_bytes,
+                    chunk_first_idx,
+                    current_idx - 1,
+                    chunk_first_key,
+                    prev_key,
+                )
+                .await?;
+            chunks.push(chunk);
+        }
+
+        self.write_manifest(&backup_handle, base, chunks).await
+    }
+}
+
+/// The chain of state snapshots an incremental state snapshot is based on.
+struct BaseChain {
+    /// Version of the last snapshot in the chain, which the new one is on top of.
+    version: Version,
+    /// Version of the full state snapshot at the start of the chain.
+    snapshot_version: Version,
+    /// Manifest of the full state snapshot at the start of the chain.
+    snapshot: FileHandle,
+    /// Manifests of the incremental state snapshots in the chain.
+    increments: Vec<FileHandle>,
+}
+
+impl IncrementalStateSnapshotBackupController {
+    fn backup_name(&self) -> String {
+        format!("incremental_state_ver_{}", self.version)
+    }
+
+    fn manifest_name() -> &'static ShellSafeName {
+        static NAME: Lazy<ShellSafeName> =
+            Lazy::new(|| ShellSafeName::from_str("incremental_state.manifest").unwrap());
+        &NAME
+    }
+
+    fn proof_name() -> &'static ShellSafeName {
+        static NAME: Lazy<ShellSafeName> =
+            Lazy::new(|| ShellSafeName::from_str("incremental_state.proof").unwrap());
+        &NAME
+    }
+
+    fn chunk_name(first_idx: usize) -> ShellSafeName {
+        format!("{}-.chunk", first_idx).try_into().unwrap()
+    }
+
+    fn parse_key(record: &Bytes) -> Result<HashValue> {
+        let (key, _): (HashValue, AccountStateBlob) = bcs::from_bytes(record)?;
+        Ok(key)
+    }
+
+    async fn write_chunk(
+        &self,
+        backup_handle: &BackupHandleRef,
+        chunk_bytes: &[u8],
+        first_idx: usize,
+        last_idx: usize,
+        first_key: HashValue,
+        last_key: HashValue,
+    ) -> Result<IncrementalStateSnapshotChunk> {
+        let (chunk_handle, mut chunk_file) = self
+            .storage
+            .create_for_write(backup_handle, &Self::chunk_name(first_idx))
+            .await?;
+        chunk_file.write_all(&chunk_bytes).await?;
+        chunk_file.shutdown().await?;
+
+        Ok(IncrementalStateSnapshotChunk {
+            first_idx,
+            last_idx,
+            first_key,
+            last_key,
+            blobs: chunk_handle,
+        })
+    }
+
+    async fn write_manifest(
+        &self,
+        backup_handle: &BackupHandleRef,
+        base: BaseChain,
+        chunks: Vec<IncrementalStateSnapshotChunk>,
+    ) -> Result<FileHandle> {
+        let proof_bytes = self.client.get_state_root_proof(self.version).await?;
+        let (txn_info, _): (TransactionInfoWithProof, LedgerInfoWithSignatures) =
+            bcs::from_bytes(&proof_bytes)?;
+
+        let (proof_handle, mut proof_file) = self
+            .storage
+            .create_for_write(&backup_handle, Self::proof_name())
+            .await?;
+        proof_file.write_all(&proof_bytes).await?;
+        proof_file.shutdown().await?;
+
+        let manifest = IncrementalStateSnapshotBackup {
+            version: self.version,
+            root_hash: txn_info.transaction_info().state_root_hash(),
+            base_version: base.version,
+            base_snapshot_version: base.snapshot_version,
+            base_snapshot: base.snapshot,
+            base_increments: base.increments,
+            chunks,
+            proof: proof_handle,
+        };
+
+        let (manifest_handle, mut manifest_file) = self
+            .storage
+            .create_for_write(&backup_handle, Self::manifest_name())
+            .await?;
+        manifest_file
+            .write_all(&serde_json::to_vec(&manifest)?)
+            .await?;
+        manifest_file.shutdown().await?;
+
+        let metadata = Metadata::new_incremental_state_snapshot_backup(
+            self.version,
+            manifest.base_version,
+            manifest.base_snapshot_version,
+            manifest_handle.clone(),
+        );
+        self.storage
+            .save_metadata_line(&metadata.name(), &metadata.to_text_line()?)
+            .await?;
+
+        Ok(manifest_handle)
+    }
+}
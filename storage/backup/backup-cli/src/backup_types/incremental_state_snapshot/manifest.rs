@@ -0,0 +1,64 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{backup_types::state_snapshot::manifest::StateSnapshotBackup, storage::FileHandle};
+use diem_crypto::HashValue;
+use diem_types::transaction::Version;
+use serde::{Deserialize, Serialize};
+
+/// A chunk of an incremental state snapshot manifest, representing the changed accounts in the
+/// key range [`first_key`, `last_key`] (right side inclusive).
+#[derive(Deserialize, Serialize)]
+pub struct IncrementalStateSnapshotChunk {
+    /// index of the first account in this chunk over all changed accounts.
+    pub first_idx: usize,
+    /// index of the last account in this chunk over all changed accounts.
+    pub last_idx: usize,
+    /// key of the first account in this chunk.
+    pub first_key: HashValue,
+    /// key of the last account in this chunk.
+    pub last_key: HashValue,
+    /// Repeated `len(record) + record` where `record` is BCS serialized tuple
+    /// `(key, account_state_blob)`
+    pub blobs: FileHandle,
+}
+
+/// Incremental state snapshot backup manifest, representing the accounts changed between
+/// `base_version` and `version`. Applied on top of the state snapshot at `base_version`, it gives
+/// a complete state view at `version`.
+///
+/// There are no range proofs for the chunks, since the accounts in them are not continuous in the
+/// state tree. Instead, the state at `version` merged from all the bases and this is verified as a
+/// whole against `root_hash`, and the range proofs to restore it in chunks are computed from it.
+#[derive(Deserialize, Serialize)]
+pub struct IncrementalStateSnapshotBackup {
+    /// Version at which this state snapshot is taken.
+    pub version: Version,
+    /// Hash of the state tree root.
+    pub root_hash: HashValue,
+    /// Version of the state snapshot this is based on.
+    pub base_version: Version,
+    /// Version of the (full) `StateSnapshotBackup` at the start of the chain of bases.
+    pub base_snapshot_version: Version,
+    /// Manifest of the (full) `StateSnapshotBackup` at the start of the chain of bases.
+    pub base_snapshot: FileHandle,
+    /// Manifests of the `IncrementalStateSnapshotBackup`s between `base_snapshot` and this, in
+    /// version order; the last one is at `base_version`. Empty if this is based on
+    /// `base_snapshot` directly.
+    pub base_increments: Vec<FileHandle>,
+    /// The changed account blobs in chunks.
+    pub chunks: Vec<IncrementalStateSnapshotChunk>,
+    /// BCS serialized
+    /// `Tuple(TransactionInfoWithProof, LedgerInfoWithSignatures)`, the same as
+    /// `StateSnapshotBackup::proof`.
+    pub proof: FileHandle,
+}
+
+/// Either kind of state snapshot manifest, told apart by the fields present, for an incremental
+/// state snapshot to be taken on top of either.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(crate) enum AnyStateSnapshotBackup {
+    Incremental(IncrementalStateSnapshotBackup),
+    Full(StateSnapshotBackup),
+}
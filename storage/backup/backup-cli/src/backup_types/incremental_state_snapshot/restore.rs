@@ -0,0 +1,479 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    backup_types::{
+        epoch_ending::restore::EpochHistory,
+        incremental_state_snapshot::manifest::IncrementalStateSnapshotBackup,
+        state_snapshot::manifest::StateSnapshotBackup,
+    },
+    metrics::{
+        restore::{
+            STATE_SNAPSHOT_LEAF_INDEX, STATE_SNAPSHOT_TARGET_LEAF_INDEX, STATE_SNAPSHOT_VERSION,
+        },
+        verify::{
+            VERIFY_STATE_SNAPSHOT_LEAF_INDEX, VERIFY_STATE_SNAPSHOT_TARGET_LEAF_INDEX,
+            VERIFY_STATE_SNAPSHOT_VERSION,
+        },
+    },
+    storage::{BackupStorage, FileHandle},
+    utils::{
+        read_record_bytes::ReadRecordBytes, storage_ext::BackupStorageExt, GlobalRestoreOptions,
+        RestoreRunMode,
+    },
+};
+use anyhow::{anyhow, ensure, Result};
+use diem_crypto::{
+    hash::{CryptoHash, SPARSE_MERKLE_PLACEHOLDER_HASH},
+    HashValue,
+};
+use diem_logger::prelude::*;
+use diem_types::{
+    account_state_blob::AccountStateBlob,
+    ledger_info::LedgerInfoWithSignatures,
+    proof::{
+        SparseMerkleInternalNode, SparseMerkleLeafNode, SparseMerkleRangeProof,
+        TransactionInfoWithProof,
+    },
+    transaction::Version,
+};
+use std::{cmp::min, collections::VecDeque, sync::Arc};
+use structopt::StructOpt;
+use tokio::io::AsyncRead;
+
+/// Accounts added to the tree at a time.
+#[cfg(not(test))]
+const RESTORE_CHUNK_SIZE: usize = 10_000;
+#[cfg(test)]
+const RESTORE_CHUNK_SIZE: usize = 3;
+
+#[derive(StructOpt)]
+pub struct IncrementalStateSnapshotRestoreOpt {
+    #[structopt(long = "incremental-state-manifest")]
+    pub manifest_handle: FileHandle,
+    #[structopt(long = "state-into-version")]
+    pub version: Version,
+}
+
+/// Restores the state at the version of an incremental state snapshot, by merging the accounts in
+/// the full state snapshot at the start of its chain of bases with the changed accounts in each of
+/// the incremental ones, later ones winning. All of them are in key order, so the merge is
+/// streamed. It's done twice: first the leaf hashes are folded into the tree, to check the merged
+/// state against the root hash and compute the range proof of each chunk, then the accounts are
+/// added to the tree chunk by chunk with those proofs. Besides the proofs, which grow with the
+/// number of chunks, the memory used doesn't depend on the size of the state.
+pub struct IncrementalStateSnapshotRestoreController {
+    storage: Arc<dyn BackupStorage>,
+    run_mode: Arc<RestoreRunMode>,
+    /// State snapshot restores to this version.
+    version: Version,
+    manifest_handle: FileHandle,
+    /// Global "target_version" for the entire restore process, if `version` is newer than this,
+    /// nothing will be done, otherwise, this has no effect.
+    target_version: Version,
+    epoch_history: Option<Arc<EpochHistory>>,
+}
+
+impl IncrementalStateSnapshotRestoreController {
+    pub fn new(
+        opt: IncrementalStateSnapshotRestoreOpt,
+        global_opt: GlobalRestoreOptions,
+        storage: Arc<dyn BackupStorage>,
+        epoch_history: Option<Arc<EpochHistory>>,
+    ) -> Self {
+        Self {
+            storage,
+            run_mode: global_opt.run_mode,
+            version: opt.version,
+            manifest_handle: opt.manifest_handle,
+            target_version: global_opt.target_version,
+            epoch_history,
+        }
+    }
+
+    pub async fn run(self) -> Result<()> {
+        let name = self.name();
+        info!("{} started. Manifest: {}", name, self.manifest_handle);
+        self.run_impl()
+            .await
+            .map_err(|e| anyhow!("{} failed: {}", name, e))?;
+        info!("{} succeeded.", name);
+        Ok(())
+    }
+}
+
+impl IncrementalStateSnapshotRestoreController {
+    fn name(&self) -> String {
+        format!("incremental state snapshot {}", self.run_mode.name())
+    }
+
+    async fn run_impl(self) -> Result<()> {
+        if self.version > self.target_version {
+            warn!(
+                "Trying to restore state snapshot to version {}, which is newer than the target version {}, skipping.",
+                self.version,
+                self.target_version,
+            );
+            return Ok(());
+        }
+
+        let manifest: IncrementalStateSnapshotBackup =
+            self.storage.load_json_file(&self.manifest_handle).await?;
+        let (txn_info_with_proof, li): (TransactionInfoWithProof, LedgerInfoWithSignatures) =
+            self.storage.load_bcs_file(&manifest.proof).await?;
+        txn_info_with_proof.verify(li.ledger_info(), manifest.version)?;
+        ensure!(
+            txn_info_with_proof.transaction_info().state_root_hash() == manifest.root_hash,
+            "Root hash mismatch with that in proof. root hash: {}, expected: {}",
+            manifest.root_hash,
+            txn_info_with_proof.transaction_info().state_root_hash(),
+        );
+        if let Some(epoch_history) = self.epoch_history.as_ref() {
+            epoch_history.verify_ledger_info(&li)?;
+        }
+
+        let (ver_gauge, tgt_leaf_idx, leaf_idx) = if self.run_mode.is_verify() {
+            (
+                &VERIFY_STATE_SNAPSHOT_VERSION,
+                &VERIFY_STATE_SNAPSHOT_TARGET_LEAF_INDEX,
+                &VERIFY_STATE_SNAPSHOT_LEAF_INDEX,
+            )
+        } else {
+            (
+                &STATE_SNAPSHOT_VERSION,
+                &STATE_SNAPSHOT_TARGET_LEAF_INDEX,
+                &STATE_SNAPSHOT_LEAF_INDEX,
+            )
+        };
+        ver_gauge.set(self.version as i64);
+
+        let chain = self.load_chain(&manifest).await?;
+
+        // The last key of each chunk.
+        let mut last_keys = Vec::new();
+        let mut num_leaves = 0;
+        let mut tree = TreeHasher::default();
+        let mut accounts = MergedAccounts::new(&self.storage, &chain).await?;
+        while let Some((key, blob)) = accounts.next().await? {
+            tree.add_leaf(key, SparseMerkleLeafNode::new(key, blob.hash()).hash());
+            num_leaves += 1;
+            if num_leaves % RESTORE_CHUNK_SIZE == 0 {
+                tree.mark_last_leaf();
+                last_keys.push(key);
+            }
+        }
+        ensure!(num_leaves > 0, "State is empty.");
+        if num_leaves % RESTORE_CHUNK_SIZE != 0 {
+            tree.mark_last_leaf();
+            last_keys.push(tree.last_key().expect("State is not empty."));
+        }
+        tgt_leaf_idx.set(num_leaves as i64 - 1);
+
+        let (root_hash, right_siblings) = tree.finish();
+        ensure!(
+            root_hash == manifest.root_hash,
+            "Merged state root hash mismatch. root hash: {}, expected: {}",
+            root_hash,
+            manifest.root_hash,
+        );
+
+        let mut receiver = self
+            .run_mode
+            .get_state_restore_receiver(self.version, manifest.root_hash)?;
+        let mut accounts = MergedAccounts::new(&self.storage, &chain).await?;
+        let chunks = last_keys.into_iter().zip(right_siblings).enumerate();
+        for (chunk_idx, (last_key, siblings)) in chunks {
+            let first_idx = chunk_idx * RESTORE_CHUNK_SIZE;
+            let last_idx = min(first_idx + RESTORE_CHUNK_SIZE, num_leaves) - 1;
+            let mut chunk = Vec::with_capacity(last_idx + 1 - first_idx);
+            for _ in first_idx..=last_idx {
+                chunk.push(
+                    accounts
+                        .next()
+                        .await?
+                        .ok_or_else(|| anyhow!("State changed between reads, less accounts."))?,
+                );
+            }
+            // Any other change fails the verification of the chunk against its proof.
+            let key = chunk.last().expect("Chunks are not empty.").0;
+            ensure!(
+                key == last_key,
+                "State changed between reads, account {} at index {}, expecting {}.",
+                key,
+                last_idx,
+                last_key,
+            );
+            receiver.add_chunk(chunk, SparseMerkleRangeProof::new(siblings))?;
+            leaf_idx.set(last_idx as i64);
+        }
+
+        receiver.finish()?;
+        Ok(())
+    }
+
+    /// Loads the chain of bases of `manifest`, checking it is consistent with `manifest`. Returns
+    /// the chunks of the full state snapshot, then those of each incremental one in order, ending
+    /// with `manifest`.
+    async fn load_chain(
+        &self,
+        manifest: &IncrementalStateSnapshotBackup,
+    ) -> Result<Vec<Vec<FileHandle>>> {
+        let base: StateSnapshotBackup =
+            self.storage.load_json_file(&manifest.base_snapshot).await?;
+        ensure!(
+            base.version == manifest.base_snapshot_version,
+            "Base state snapshot is at version {}, expecting {}.",
+            base.version,
+            manifest.base_snapshot_version,
+        );
+        let mut chain = vec![base.chunks.into_iter().map(|c| c.blobs).collect()];
+
+        let mut version = base.version;
+        for increment_handle in &manifest.base_increments {
+            let increment: IncrementalStateSnapshotBackup =
+                self.storage.load_json_file(increment_handle).await?;
+            ensure!(
+                increment.base_version == version,
+                "Incremental state snapshot at version {} is based on version {}, expecting {}.",
+                increment.version,
+                increment.base_version,
+                version,
+            );
+            chain.push(increment.chunks.into_iter().map(|c| c.blobs).collect());
+            version = increment.version;
+        }
+
+        ensure!(
+            manifest.base_version == version,
+            "Incremental state snapshot at version {} is based on version {}, but the chain of \
+            bases ends at version {}.",
+            manifest.version,
+            manifest.base_version,
+            version,
+        );
+        chain.push(manifest.chunks.iter().map(|c| c.blobs.clone()).collect());
+
+        Ok(chain)
+    }
+}
+
+/// The accounts in the chunks of one state snapshot, read one at a time, in key order.
+struct AccountStream {
+    storage: Arc<dyn BackupStorage>,
+    chunks: VecDeque<FileHandle>,
+    file: Option<Box<dyn AsyncRead + Send + Unpin>>,
+    prev_key: Option<HashValue>,
+}
+
+impl AccountStream {
+    fn new(storage: Arc<dyn BackupStorage>, chunks: Vec<FileHandle>) -> Self {
+        Self {
+            storage,
+            chunks: chunks.into(),
+            file: None,
+            prev_key: None,
+        }
+    }
+
+    async fn next(&mut self) -> Result<Option<(HashValue, AccountStateBlob)>> {
+        loop {
+            if let Some(file) = self.file.as_mut() {
+                if let Some(record_bytes) = file.read_record_bytes().await? {
+                    let (key, blob): (HashValue, AccountStateBlob) =
+                        bcs::from_bytes(&record_bytes)?;
+                    ensure!(
+                        self.prev_key.map_or(true, |prev_key| prev_key < key),
+                        "Account {} is not after {:?} in the state snapshot.",
+                        key,
+                        self.prev_key,
+                    );
+                    self.prev_key = Some(key);
+                    return Ok(Some((key, blob)));
+                }
+                self.file = None;
+            }
+            match self.chunks.pop_front() {
+                Some(chunk) => self.file = Some(self.storage.open_for_read(&chunk).await?),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+/// The accounts of a chain of state snapshots merged in key order, each taken from the last
+/// snapshot it is in.
+struct MergedAccounts {
+    streams: Vec<AccountStream>,
+    /// The next account of each stream.
+    heads: Vec<Option<(HashValue, AccountStateBlob)>>,
+}
+
+impl MergedAccounts {
+    async fn new(storage: &Arc<dyn BackupStorage>, chain: &[Vec<FileHandle>]) -> Result<Self> {
+        let mut streams = Vec::with_capacity(chain.len());
+        let mut heads = Vec::with_capacity(chain.len());
+        for chunks in chain {
+            let mut stream = AccountStream::new(Arc::clone(storage), chunks.clone());
+            heads.push(stream.next().await?);
+            streams.push(stream);
+        }
+        Ok(Self { streams, heads })
+    }
+
+    async fn next(&mut self) -> Result<Option<(HashValue, AccountStateBlob)>> {
+        let key = match self.heads.iter().flatten().map(|(key, _)| *key).min() {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        let mut account = None;
+        for (stream, head) in self.streams.iter_mut().zip(self.heads.iter_mut()) {
+            if head.as_ref().map_or(false, |(k, _)| *k == key) {
+                account = head.take();
+                *head = stream.next().await?;
+            }
+        }
+        Ok(account)
+    }
+}
+
+/// A subtree whose parent isn't built yet.
+struct Subtree {
+    /// A key in the subtree, all of them share the path to its root.
+    key: HashValue,
+    /// Depth of the root of the subtree, so far for a single leaf, which can be at any depth
+    /// below the last node with other leaves.
+    depth: usize,
+    hash: HashValue,
+    is_leaf: bool,
+    /// The marked leaves in the subtree, still collecting their right siblings.
+    marked: Vec<usize>,
+}
+
+/// Computes the root hash of a sparse Merkle tree from its leaves, added in key order, and the
+/// right siblings of the marked ones, as a `SparseMerkleRangeProof` has them. Only the subtrees on
+/// the path of the last leaf are kept, at most one per level.
+#[derive(Default)]
+pub(crate) struct TreeHasher {
+    /// The subtrees left of the path of the last leaf and the last leaf, in key order, by strictly
+    /// increasing depth but for the last two which can be siblings.
+    subtrees: Vec<Subtree>,
+    /// The right siblings of each marked leaf, bottom first.
+    right_siblings: Vec<Vec<HashValue>>,
+}
+
+impl TreeHasher {
+    /// Adds the leaf of `key`, which must be after the previous one.
+    pub(crate) fn add_leaf(&mut self, key: HashValue, hash: HashValue) {
+        let depth = match self.last_key() {
+            Some(last_key) => {
+                assert!(last_key < key, "Leaves are not in key order.");
+                // The leaves part at the node above, the previous one is the last in the left
+                // subtree.
+                let depth = last_key.common_prefix_bits_len(key) + 1;
+                self.fold(depth);
+                let left = self.subtrees.last_mut().expect("Folded to a subtree.");
+                if left.depth < depth {
+                    left.depth = depth;
+                }
+                depth
+            }
+            None => 0,
+        };
+        self.subtrees.push(Subtree {
+            key,
+            depth,
+            hash,
+            is_leaf: true,
+            marked: vec![],
+        });
+    }
+
+    /// Key of the last leaf added.
+    pub(crate) fn last_key(&self) -> Option<HashValue> {
+        self.subtrees.last().map(|subtree| subtree.key)
+    }
+
+    /// Marks the last leaf added, its right siblings are returned by `finish` after those of the
+    /// leaves marked before.
+    pub(crate) fn mark_last_leaf(&mut self) {
+        let leaf = self.subtrees.last_mut().expect("No leaf added.");
+        leaf.marked.push(self.right_siblings.len());
+        self.right_siblings.push(vec![]);
+    }
+
+    /// Returns the root hash, and the right siblings of each marked leaf in the order they were
+    /// marked.
+    pub(crate) fn finish(mut self) -> (HashValue, Vec<Vec<HashValue>>) {
+        if self.subtrees.is_empty() {
+            return (*SPARSE_MERKLE_PLACEHOLDER_HASH, self.right_siblings);
+        }
+        self.fold(0);
+        let root = self.subtrees.pop().expect("Folded to a subtree.");
+        assert!(self.subtrees.is_empty());
+        (root.hash, self.right_siblings)
+    }
+
+    /// Builds the subtrees deeper than `depth` into the one at `depth` holding the last leaf.
+    fn fold(&mut self, depth: usize) {
+        loop {
+            let right = match self.subtrees.pop() {
+                Some(subtree) if subtree.depth > depth => subtree,
+                Some(subtree) => {
+                    self.subtrees.push(subtree);
+                    return;
+                }
+                None => return,
+            };
+            let parent = match self.subtrees.pop() {
+                Some(left) if left.depth == right.depth => {
+                    for idx in &left.marked {
+                        self.right_siblings[*idx].push(right.hash);
+                    }
+                    let mut marked = left.marked;
+                    marked.extend(right.marked);
+                    Subtree {
+                        key: left.key,
+                        depth: left.depth - 1,
+                        hash: SparseMerkleInternalNode::new(left.hash, right.hash).hash(),
+                        is_leaf: false,
+                        marked,
+                    }
+                }
+                left => {
+                    self.subtrees.extend(left);
+                    let (depth, hash) = if right.is_leaf {
+                        // A leaf alone in a subtree is its root.
+                        (depth, right.hash)
+                    } else if right.key.bit(right.depth - 1) {
+                        (
+                            right.depth - 1,
+                            SparseMerkleInternalNode::new(
+                                *SPARSE_MERKLE_PLACEHOLDER_HASH,
+                                right.hash,
+                            )
+                            .hash(),
+                        )
+                    } else {
+                        for idx in &right.marked {
+                            self.right_siblings[*idx].push(*SPARSE_MERKLE_PLACEHOLDER_HASH);
+                        }
+                        (
+                            right.depth - 1,
+                            SparseMerkleInternalNode::new(
+                                right.hash,
+                                *SPARSE_MERKLE_PLACEHOLDER_HASH,
+                            )
+                            .hash(),
+                        )
+                    };
+                    Subtree {
+                        depth,
+                        hash,
+                        ..right
+                    }
+                }
+            };
+            self.subtrees.push(parent);
+        }
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    backup_types::{
+        incremental_state_snapshot::{
+            backup::{IncrementalStateSnapshotBackupController, IncrementalStateSnapshotBackupOpt},
+            manifest::IncrementalStateSnapshotBackup,
+            restore::{
+                IncrementalStateSnapshotRestoreController, IncrementalStateSnapshotRestoreOpt,
+                TreeHasher,
+            },
+        },
+        state_snapshot::backup::{StateSnapshotBackupController, StateSnapshotBackupOpt},
+    },
+    storage::{local_fs::LocalFs, BackupStorage},
+    utils::{
+        backup_service_client::BackupServiceClient,
+        storage_ext::BackupStorageExt,
+        test_utils::{start_local_backup_service, tmp_db_with_random_content},
+        ConcurrentDownloadsOpt, GlobalBackupOpt, GlobalRestoreOpt, RocksdbOpt, TrustedWaypointOpt,
+    },
+};
+use diem_config::config::RocksdbConfig;
+use diem_crypto::{
+    hash::{CryptoHash, SPARSE_MERKLE_PLACEHOLDER_HASH},
+    HashValue,
+};
+use diem_temppath::TempPath;
+use diem_types::{proof::SparseMerkleInternalNode, transaction::PRE_GENESIS_VERSION};
+use diemdb::DiemDB;
+use proptest::{collection::btree_set, prelude::*};
+use std::{convert::TryInto, sync::Arc};
+use storage_interface::DbReader;
+use tokio::time::Duration;
+
+#[test]
+fn end_to_end() {
+    let (_src_db_dir, src_db, _blocks) = tmp_db_with_random_content();
+    let tgt_db_dir = TempPath::new();
+    tgt_db_dir.create_as_dir().unwrap();
+    let backup_dir = TempPath::new();
+    backup_dir.create_as_dir().unwrap();
+    let store: Arc<dyn BackupStorage> = Arc::new(LocalFs::new(backup_dir.path().to_path_buf()));
+
+    let latest_tree_state = src_db.get_latest_tree_state().unwrap();
+    let version = latest_tree_state.num_transactions - 1;
+    let state_root_hash = latest_tree_state.account_state_root_hash;
+
+    let (rt, port) = start_local_backup_service(src_db);
+    let client = Arc::new(BackupServiceClient::new(format!(
+        "http://localhost:{}",
+        port
+    )));
+    let global_opt = GlobalBackupOpt {
+        max_chunk_size: 500,
+    };
+
+    // A full state snapshot at version 0, then a chain of incremental ones on top of it.
+    let mut manifest_handle = rt
+        .block_on(
+            StateSnapshotBackupController::new(
+                StateSnapshotBackupOpt { version: 0 },
+                global_opt.clone(),
+                Arc::clone(&client),
+                Arc::clone(&store),
+            )
+            .run(),
+        )
+        .unwrap();
+    let mut incremental_versions = vec![version / 2, version];
+    incremental_versions.retain(|v| *v > 0);
+    incremental_versions.dedup();
+    for incremental_version in &incremental_versions {
+        manifest_handle = rt
+            .block_on(
+                IncrementalStateSnapshotBackupController::new(
+                    IncrementalStateSnapshotBackupOpt {
+                        version: *incremental_version,
+                        base_manifest: manifest_handle,
+                    },
+                    global_opt.clone(),
+                    Arc::clone(&client),
+                    Arc::clone(&store),
+                )
+                .run(),
+            )
+            .unwrap();
+    }
+    if incremental_versions.is_empty() {
+        // Only the genesis transaction, nothing to take an incremental state snapshot of.
+        rt.shutdown_timeout(Duration::from_secs(1));
+        return;
+    }
+
+    let manifest: IncrementalStateSnapshotBackup =
+        rt.block_on(store.load_json_file(&manifest_handle)).unwrap();
+    assert_eq!(manifest.version, version);
+    assert_eq!(manifest.base_snapshot_version, 0);
+    assert_eq!(
+        manifest.base_increments.len(),
+        incremental_versions.len() - 1
+    );
+
+    rt.block_on(
+        IncrementalStateSnapshotRestoreController::new(
+            IncrementalStateSnapshotRestoreOpt {
+                manifest_handle,
+                version: PRE_GENESIS_VERSION,
+            },
+            GlobalRestoreOpt {
+                dry_run: false,
+                db_dir: Some(tgt_db_dir.path().to_path_buf()),
+                target_version: None, // max
+                trusted_waypoints: TrustedWaypointOpt::default(),
+                rocksdb_opt: RocksdbOpt::default(),
+                concurernt_downloads: ConcurrentDownloadsOpt::default(),
+            }
+            .try_into()
+            .unwrap(),
+            store,
+            None, /* epoch_history */
+        )
+        .run(),
+    )
+    .unwrap();
+
+    let tgt_db = DiemDB::open(
+        &tgt_db_dir,
+        true, /* read_only */
+        None, /* pruner */
+        RocksdbConfig::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        tgt_db
+            .get_latest_tree_state()
+            .unwrap()
+            .account_state_root_hash,
+        state_root_hash,
+    );
+
+    rt.shutdown_timeout(Duration::from_secs(1));
+}
+
+/// Computes the hash of the subtree at `depth` holding `leaves`, the sorted keys and leaf hashes
+/// starting from the `offset`-th leaf of the tree, and pushes the right siblings of each leaf in
+/// `last_idxs` to the same entry of `right_siblings`, by splitting the leaves at each level.
+fn subtree_hash(
+    leaves: &[(HashValue, HashValue)],
+    depth: usize,
+    offset: usize,
+    last_idxs: &[usize],
+    right_siblings: &mut [Vec<HashValue>],
+) -> HashValue {
+    match leaves.len() {
+        0 => return *SPARSE_MERKLE_PLACEHOLDER_HASH,
+        1 => return leaves[0].1,
+        _ => (),
+    }
+    let split = leaves
+        .iter()
+        .position(|(key, _)| key.bit(depth))
+        .unwrap_or(leaves.len());
+    let num_left = last_idxs
+        .iter()
+        .take_while(|idx| **idx < offset + split)
+        .count();
+    let (left_idxs, right_idxs) = last_idxs.split_at(num_left);
+    let (left_siblings, right_siblings) = right_siblings.split_at_mut(num_left);
+
+    let left_hash = subtree_hash(
+        &leaves[..split],
+        depth + 1,
+        offset,
+        left_idxs,
+        left_siblings,
+    );
+    let right_hash = subtree_hash(
+        &leaves[split..],
+        depth + 1,
+        offset + split,
+        right_idxs,
+        right_siblings,
+    );
+    for siblings in left_siblings.iter_mut() {
+        siblings.push(right_hash);
+    }
+    SparseMerkleInternalNode::new(left_hash, right_hash).hash()
+}
+
+proptest! {
+    #[test]
+    fn tree_hasher(
+        // Keys differing only in the first and the last byte share long paths.
+        key_bytes in btree_set(any::<(u8, u8)>(), 1..200),
+        chunk_size in 1usize..20,
+    ) {
+        let leaves = key_bytes
+            .into_iter()
+            .map(|(first, last)| {
+                let mut key = [0u8; HashValue::LENGTH];
+                key[0] = first;
+                key[HashValue::LENGTH - 1] = last;
+                let key = HashValue::new(key);
+                (key, HashValue::sha3_256_of(key.as_ref()))
+            })
+            .collect::<Vec<_>>();
+        let last_idxs = (0..leaves.len())
+            .filter(|idx| (idx + 1) % chunk_size == 0 || idx + 1 == leaves.len())
+            .collect::<Vec<_>>();
+
+        let mut expected_siblings = vec![vec![]; last_idxs.len()];
+        let expected_root = subtree_hash(&leaves, 0, 0, &last_idxs, &mut expected_siblings);
+
+        let mut tree = TreeHasher::default();
+        let mut last_idxs = last_idxs.iter().peekable();
+        for (idx, (key, hash)) in leaves.iter().enumerate() {
+            tree.add_leaf(*key, *hash);
+            if last_idxs.next_if_eq(&&idx).is_some() {
+                tree.mark_last_leaf();
+            }
+        }
+        let (root, siblings) = tree.finish();
+        prop_assert_eq!(root, expected_root);
+        prop_assert_eq!(siblings, expected_siblings);
+    }
+}
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 pub mod epoch_ending;
+pub mod incremental_state_snapshot;
 pub mod state_snapshot;
 pub mod transaction;
 
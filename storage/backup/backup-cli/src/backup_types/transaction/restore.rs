@@ -7,7 +7,9 @@ use crate::{
         transaction::manifest::{TransactionBackup, TransactionChunk},
     },
     metrics::{
-        restore::{TRANSACTION_REPLAY_VERSION, TRANSACTION_SAVE_VERSION},
+        restore::{
+            TRANSACTION_REPLAY_VERSION, TRANSACTION_SAVE_VERSION, TRANSACTION_VERIFIED_VERSION,
+        },
         verify::VERIFY_TRANSACTION_VERSION,
     },
     storage::{BackupStorage, FileHandle},
@@ -27,7 +29,7 @@ use diem_types::{
 use diem_vm::DiemVM;
 use executor::Executor;
 use executor_types::TransactionReplayer;
-use futures::StreamExt;
+use futures::{future, StreamExt, TryStreamExt};
 use std::{
     cmp::{max, min},
     sync::Arc,
@@ -49,23 +51,12 @@ pub struct TransactionRestoreOpt {
     pub replay_from_version: Option<Version>,
 }
 
-impl TransactionRestoreOpt {
-    pub fn replay_from_version(&self) -> Version {
-        self.replay_from_version.unwrap_or(Version::max_value())
-    }
-}
-
+/// Restores the transactions in a single backup, see `TransactionRestoreBatchController`.
 pub struct TransactionRestoreController {
-    storage: Arc<dyn BackupStorage>,
-    run_mode: Arc<RestoreRunMode>,
-    manifest_handle: FileHandle,
-    target_version: Version,
-    replay_from_version: Version,
-    epoch_history: Option<Arc<EpochHistory>>,
-    state: State,
+    inner: TransactionRestoreBatchController,
 }
 
-struct LoadedChunk {
+pub(crate) struct LoadedChunk {
     pub manifest: TransactionChunk,
     pub txns: Vec<Transaction>,
     pub txn_infos: Vec<TransactionInfo>,
@@ -75,7 +66,7 @@ struct LoadedChunk {
 }
 
 impl LoadedChunk {
-    async fn load(
+    pub(crate) async fn load(
         manifest: TransactionChunk,
         storage: &Arc<dyn BackupStorage>,
         epoch_history: Option<&Arc<EpochHistory>>,
@@ -141,58 +132,181 @@ impl TransactionRestoreController {
         epoch_history: Option<Arc<EpochHistory>>,
     ) -> Self {
         Self {
-            storage,
-            run_mode: global_opt.run_mode,
-            replay_from_version: opt.replay_from_version(),
-            manifest_handle: opt.manifest_handle,
-            target_version: global_opt.target_version,
-            epoch_history,
-            state: State::default(),
+            inner: TransactionRestoreBatchController::new(
+                global_opt,
+                storage,
+                vec![opt.manifest_handle],
+                opt.replay_from_version,
+                epoch_history,
+            ),
         }
     }
 
-    pub async fn preheat(mut self) -> PreheatedTransactionRestore {
-        PreheatedTransactionRestore {
-            preheat_result: self.preheat_impl().await,
-            controller: self,
+    pub async fn run(self) -> Result<()> {
+        self.inner.run().await
+    }
+}
+
+/// Takes a series of transaction backup manifests, and restores the transactions in them as a
+/// pipeline: chunks are loaded and verified against their ledger infos in parallel, across
+/// backups, while the verified ones are saved or replayed strictly in version order.
+pub struct TransactionRestoreBatchController {
+    global_opt: GlobalRestoreOptions,
+    storage: Arc<dyn BackupStorage>,
+    manifest_handles: Vec<FileHandle>,
+    replay_from_version: Option<Version>,
+    epoch_history: Option<Arc<EpochHistory>>,
+}
+
+impl TransactionRestoreBatchController {
+    pub fn new(
+        global_opt: GlobalRestoreOptions,
+        storage: Arc<dyn BackupStorage>,
+        manifest_handles: Vec<FileHandle>,
+        replay_from_version: Option<Version>,
+        epoch_history: Option<Arc<EpochHistory>>,
+    ) -> Self {
+        Self {
+            global_opt,
+            storage,
+            manifest_handles,
+            replay_from_version,
+            epoch_history,
         }
     }
 
     pub async fn run(self) -> Result<()> {
-        self.preheat().await.run().await
+        let name = format!("transaction {}", self.global_opt.run_mode.name());
+        info!("{} started. Backups: {}", name, self.manifest_handles.len());
+        self.run_impl()
+            .await
+            .map_err(|e| anyhow!("{} failed: {}", name, e))?;
+        info!("{} succeeded.", name);
+        Ok(())
     }
 }
 
-impl TransactionRestoreController {
-    fn name(&self) -> String {
-        format!("transaction {}", self.run_mode.name())
-    }
+impl TransactionRestoreBatchController {
+    async fn run_impl(self) -> Result<()> {
+        let timer = std::time::Instant::now();
+        let target_version = self.global_opt.target_version;
+        let concurrent_downloads = self.global_opt.concurrent_downloads;
+
+        let storage = Arc::clone(&self.storage);
+        let chunk_manifests = futures::stream::iter(self.manifest_handles)
+            .map(move |hdl| {
+                let storage = Arc::clone(&storage);
+                async move {
+                    let manifest: TransactionBackup = storage.load_json_file(&hdl).await?;
+                    manifest.verify()?;
+                    if target_version < manifest.last_version {
+                        warn!(
+                            "Transactions newer than target version {} ignored.",
+                            target_version,
+                        )
+                    }
+                    Result::<_>::Ok(futures::stream::iter(
+                        manifest.chunks.into_iter().map(Result::<_>::Ok),
+                    ))
+                }
+            })
+            .buffered_x(concurrent_downloads, concurrent_downloads)
+            .try_flatten()
+            .try_filter(move |chunk_manifest| {
+                future::ready(chunk_manifest.first_version <= target_version)
+            });
+
+        let storage = Arc::clone(&self.storage);
+        let epoch_history = self.epoch_history.clone();
+        let mut loaded_chunks = chunk_manifests
+            .map(move |chunk_manifest: Result<TransactionChunk>| {
+                let storage = Arc::clone(&storage);
+                let epoch_history = epoch_history.clone();
+                async move {
+                    // Use `spawn()` so it's (most likely) off the main thread.
+                    // There's a lot of deserialization / verification happening that's CPU
+                    // intensive, hence the `spawn()`
+                    tokio::spawn(async move {
+                        let chunk =
+                            LoadedChunk::load(chunk_manifest?, &storage, epoch_history.as_ref())
+                                .await?;
+                        // Chunks are loaded concurrently, they may finish out of order.
+                        let verified = min(target_version, chunk.manifest.last_version) as i64;
+                        if verified > TRANSACTION_VERIFIED_VERSION.get() {
+                            TRANSACTION_VERIFIED_VERSION.set(verified);
+                        }
+                        Result::<_>::Ok(chunk)
+                    })
+                    .await
+                    .expect("Failed to spawn task.")
+                }
+            })
+            .buffered_x(
+                // more buffer here because the load of a chunk is heavy and variates more in
+                // execution time
+                concurrent_downloads * 3,
+                concurrent_downloads, /* concurrency */
+            );
 
-    async fn preheat_impl(&mut self) -> Result<TransactionRestorePreheatData> {
-        let manifest: TransactionBackup =
-            self.storage.load_json_file(&self.manifest_handle).await?;
-        manifest.verify()?;
+        let mut applier = ChunkApplier {
+            run_mode: Arc::clone(&self.global_opt.run_mode),
+            target_version,
+            replay_from_version: self.replay_from_version.unwrap_or_else(Version::max_value),
+            next_version: None,
+            frozen_subtree_confirmed: false,
+            transaction_replayer: None,
+        };
+        let mut num_applied: u64 = 0;
+        while let Some(chunk) = loaded_chunks.next().await {
+            let chunk = chunk?;
+            let num_txns = min(target_version + 1, chunk.manifest.last_version + 1)
+                - chunk.manifest.first_version;
+            // Chunks keep being loaded and verified in the background while this one is applied.
+            applier = tokio::task::spawn_blocking(move || -> Result<ChunkApplier> {
+                applier.apply(chunk)?;
+                Ok(applier)
+            })
+            .await??;
+            num_applied += num_txns;
+            debug!(
+                "Accumulative TPS: {:.0}",
+                num_applied as f64 / timer.elapsed().as_secs_f64()
+            );
+        }
 
-        let mut loaded_chunks = Vec::new();
-        for chunk_manifest in &manifest.chunks {
-            if chunk_manifest.first_version > self.target_version {
-                break;
-            }
+        Ok(())
+    }
+}
 
-            loaded_chunks.push(
-                LoadedChunk::load(
-                    chunk_manifest.clone(),
-                    &self.storage,
-                    self.epoch_history.as_ref(),
-                )
-                .await?,
-            )
+/// Saves or replays loaded chunks one after another. A chunk depends on the ones before it being
+/// applied, since transactions are replayed on top of the state they leave behind.
+struct ChunkApplier {
+    run_mode: Arc<RestoreRunMode>,
+    target_version: Version,
+    replay_from_version: Version,
+    /// First version of the chunk expected next, `None` before the first chunk.
+    next_version: Option<Version>,
+    frozen_subtree_confirmed: bool,
+    transaction_replayer: Option<Executor<DiemVM>>,
+}
+
+impl ChunkApplier {
+    fn apply(&mut self, chunk: LoadedChunk) -> Result<()> {
+        if let Some(next_version) = self.next_version {
+            ensure!(
+                chunk.manifest.first_version == next_version,
+                "Transaction chunks not continuous, expecting version {}, got {}.",
+                next_version,
+                chunk.manifest.first_version,
+            );
         }
+        self.maybe_save_frozen_subtrees(&chunk)?;
 
-        Ok(TransactionRestorePreheatData {
-            manifest,
-            loaded_chunks,
-        })
+        let last = min(self.target_version, chunk.manifest.last_version);
+        let first_to_replay = max(chunk.manifest.first_version, self.replay_from_version);
+        self.next_version = Some(chunk.manifest.last_version + 1);
+
+        self.maybe_restore_transactions(chunk, last, first_to_replay)
     }
 
     fn maybe_restore_transactions(
@@ -255,12 +369,12 @@ impl TransactionRestoreController {
 
     fn maybe_save_frozen_subtrees(&mut self, chunk: &LoadedChunk) -> Result<()> {
         if let RestoreRunMode::Restore { restore_handler } = self.run_mode.as_ref() {
-            if !self.state.frozen_subtree_confirmed {
+            if !self.frozen_subtree_confirmed {
                 restore_handler.confirm_or_save_frozen_subtrees(
                     chunk.manifest.first_version,
                     chunk.range_proof.left_siblings(),
                 )?;
-                self.state.frozen_subtree_confirmed = true;
+                self.frozen_subtree_confirmed = true;
             }
         }
 
@@ -268,20 +382,19 @@ impl TransactionRestoreController {
     }
 
     fn transaction_replayer(&mut self, first_version: Version) -> Result<&mut Executor<DiemVM>> {
-        if self.state.transaction_replayer.is_none() {
+        if self.transaction_replayer.is_none() {
             if let RestoreRunMode::Restore { restore_handler } = self.run_mode.as_ref() {
                 let replayer = Executor::new_on_unbootstrapped_db(
                     DbReaderWriter::from_arc(Arc::clone(&restore_handler.diemdb)),
                     restore_handler.get_tree_state(first_version)?,
                 );
-                self.state.transaction_replayer = Some(replayer);
+                self.transaction_replayer = Some(replayer);
             } else {
                 bail!("Trying to construct TransactionReplayer under Verify mode.");
             }
         } else {
             assert_eq!(
-                self.state
-                    .transaction_replayer
+                self.transaction_replayer
                     .as_ref()
                     .unwrap()
                     .expecting_version(),
@@ -289,153 +402,6 @@ impl TransactionRestoreController {
             );
         }
 
-        Ok(self.state.transaction_replayer.as_mut().unwrap())
-    }
-}
-
-#[derive(Default)]
-struct State {
-    frozen_subtree_confirmed: bool,
-    transaction_replayer: Option<Executor<DiemVM>>,
-}
-
-struct TransactionRestorePreheatData {
-    manifest: TransactionBackup,
-    loaded_chunks: Vec<LoadedChunk>,
-}
-
-pub struct PreheatedTransactionRestore {
-    controller: TransactionRestoreController,
-    preheat_result: Result<TransactionRestorePreheatData>,
-}
-
-impl PreheatedTransactionRestore {
-    pub async fn run(self) -> Result<()> {
-        let name = self.controller.name();
-        info!(
-            "{} started. Manifest: {}",
-            name, self.controller.manifest_handle
-        );
-        let res = self
-            .run_impl()
-            .await
-            .map_err(|e| anyhow!("{} failed: {}", name, e))?;
-        info!("{} succeeded.", name);
-        Ok(res)
-    }
-
-    pub fn get_last_version(&self) -> Result<Version> {
-        Ok(min(
-            self.preheat_result
-                .as_ref()
-                .map_err(|e| anyhow!("Preheat failed: {}", e))?
-                .manifest
-                .last_version,
-            self.controller.target_version,
-        ))
-    }
-}
-
-impl PreheatedTransactionRestore {
-    async fn run_impl(mut self) -> Result<()> {
-        let preheat_data = self
-            .preheat_result
-            .map_err(|e| anyhow!("Preheat failed: {}", e))?;
-
-        for chunk in preheat_data.loaded_chunks {
-            self.controller.maybe_save_frozen_subtrees(&chunk)?;
-
-            let last = min(self.controller.target_version, chunk.manifest.last_version);
-            let first_to_replay = max(
-                chunk.manifest.first_version,
-                self.controller.replay_from_version,
-            );
-
-            self.controller
-                .maybe_restore_transactions(chunk, last, first_to_replay)?;
-        }
-
-        if self.controller.target_version < preheat_data.manifest.last_version {
-            warn!(
-                "Transactions newer than target version {} ignored.",
-                self.controller.target_version,
-            )
-        }
-
-        Ok(())
-    }
-}
-
-/// Takes a series of transaction backup manifests, preheat in parallel, then execute in order.
-pub struct TransactionRestoreBatchController {
-    global_opt: GlobalRestoreOptions,
-    storage: Arc<dyn BackupStorage>,
-    manifest_handles: Vec<FileHandle>,
-    replay_from_version: Option<Version>,
-    epoch_history: Option<Arc<EpochHistory>>,
-}
-
-impl TransactionRestoreBatchController {
-    pub fn new(
-        global_opt: GlobalRestoreOptions,
-        storage: Arc<dyn BackupStorage>,
-        manifest_handles: Vec<FileHandle>,
-        replay_from_version: Option<Version>,
-        epoch_history: Option<Arc<EpochHistory>>,
-    ) -> Self {
-        Self {
-            global_opt,
-            storage,
-            manifest_handles,
-            replay_from_version,
-            epoch_history,
-        }
-    }
-
-    pub async fn run(self) -> Result<()> {
-        let timer = std::time::Instant::now();
-
-        let futs_iter = self.manifest_handles.clone().into_iter().map(|hdl| {
-            let _global_opt = self.global_opt.clone();
-            let _epoch_history = self.epoch_history.clone();
-            let _storage = self.storage.clone();
-            let _replay_from_version = self.replay_from_version;
-            async move {
-                // Use `spawn()` so it's (most likely) off the main thread.
-                // There's a lot of deserialization / verification happening that's CPU
-                // intensive, hence the `spawn()`
-                tokio::spawn(
-                    TransactionRestoreController::new(
-                        TransactionRestoreOpt {
-                            manifest_handle: hdl,
-                            replay_from_version: _replay_from_version,
-                        },
-                        _global_opt,
-                        _storage,
-                        _epoch_history,
-                    )
-                    .preheat(),
-                )
-                .await
-                .expect("Failed to spawn task.")
-            }
-        });
-
-        let mut futs_stream = futures::stream::iter(futs_iter).buffered_x(
-            // more buffer here because the preheat of txns is heavy and variates more in execution
-            // time
-            self.global_opt.concurrent_downloads * 3,
-            self.global_opt.concurrent_downloads, /* concurrency */
-        );
-        while let Some(preheated_txn_restore) = futs_stream.next().await {
-            let v = preheated_txn_restore.get_last_version();
-            preheated_txn_restore.run().await?;
-            debug!(
-                "Accumulative TPS: {:.0}",
-                (v? + 1) as f64 / timer.elapsed().as_secs_f64()
-            );
-        }
-
-        Ok(())
+        Ok(self.transaction_replayer.as_mut().unwrap())
     }
 }
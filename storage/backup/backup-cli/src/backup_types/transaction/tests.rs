@@ -4,22 +4,25 @@
 use crate::{
     backup_types::transaction::{
         backup::{TransactionBackupController, TransactionBackupOpt},
-        restore::{TransactionRestoreController, TransactionRestoreOpt},
+        restore::{
+            TransactionRestoreBatchController, TransactionRestoreController, TransactionRestoreOpt,
+        },
     },
-    storage::{local_fs::LocalFs, BackupStorage},
+    storage::{local_fs::LocalFs, BackupStorage, FileHandle},
     utils::{
         backup_service_client::BackupServiceClient,
         test_utils::{start_local_backup_service, tmp_db_with_random_content},
-        ConcurrentDownloadsOpt, GlobalBackupOpt, GlobalRestoreOpt, RocksdbOpt, TrustedWaypointOpt,
+        ConcurrentDownloadsOpt, GlobalBackupOpt, GlobalRestoreOpt, GlobalRestoreOptions,
+        RocksdbOpt, TrustedWaypointOpt,
     },
 };
 use diem_config::config::RocksdbConfig;
 use diem_temppath::TempPath;
-use diem_types::transaction::Version;
+use diem_types::transaction::{Transaction, Version};
 use diemdb::DiemDB;
 use std::{convert::TryInto, mem::size_of, sync::Arc};
 use storage_interface::DbReader;
-use tokio::time::Duration;
+use tokio::{runtime::Runtime, time::Duration};
 
 #[test]
 fn end_to_end() {
@@ -45,32 +48,20 @@ fn end_to_end() {
         .flatten()
         .map(|txn_to_commit| txn_to_commit.transaction())
         .collect::<Vec<_>>();
-    let max_chunk_size = txns
-        .iter()
-        .map(|t| bcs::to_bytes(t).unwrap().len())
-        .max()
-        .unwrap() // biggest txn
-        + 115 // size of a serialized TransactionInfo
-        + size_of::<u32>(); // record len header
+    let max_chunk_size = max_chunk_size(&txns);
     let first_ver_to_backup = (total_txns / 4) as Version;
     let num_txns_to_backup = total_txns - first_ver_to_backup as usize;
     let target_version = first_ver_to_backup + total_txns as Version / 2;
     let num_txns_to_restore = (target_version - first_ver_to_backup + 1) as usize;
 
-    let manifest_handle = rt
-        .block_on(
-            TransactionBackupController::new(
-                TransactionBackupOpt {
-                    start_version: first_ver_to_backup,
-                    num_transactions: num_txns_to_backup,
-                },
-                GlobalBackupOpt { max_chunk_size },
-                client,
-                Arc::clone(&store),
-            )
-            .run(),
-        )
-        .unwrap();
+    let manifest_handle = backup_transactions(
+        &rt,
+        &client,
+        &store,
+        first_ver_to_backup,
+        num_txns_to_backup,
+        max_chunk_size,
+    );
 
     rt.block_on(
         TransactionRestoreController::new(
@@ -147,3 +138,189 @@ fn end_to_end() {
 
     rt.shutdown_timeout(Duration::from_secs(1));
 }
+
+/// Large enough for any transaction of `txns` to fit in a chunk.
+fn max_chunk_size(txns: &[&Transaction]) -> usize {
+    txns.iter()
+        .map(|t| bcs::to_bytes(t).unwrap().len())
+        .max()
+        .unwrap() // biggest txn
+        + 115 // size of a serialized TransactionInfo
+        + size_of::<u32>() // record len header
+}
+
+fn backup_transactions(
+    rt: &Runtime,
+    client: &Arc<BackupServiceClient>,
+    store: &Arc<dyn BackupStorage>,
+    start_version: Version,
+    num_transactions: usize,
+    max_chunk_size: usize,
+) -> FileHandle {
+    rt.block_on(
+        TransactionBackupController::new(
+            TransactionBackupOpt {
+                start_version,
+                num_transactions,
+            },
+            GlobalBackupOpt { max_chunk_size },
+            Arc::clone(client),
+            Arc::clone(store),
+        )
+        .run(),
+    )
+    .unwrap()
+}
+
+fn restore_opt(tgt_db_dir: &TempPath) -> GlobalRestoreOptions {
+    GlobalRestoreOpt {
+        dry_run: false,
+        db_dir: Some(tgt_db_dir.path().to_path_buf()),
+        target_version: None, // max
+        trusted_waypoints: TrustedWaypointOpt::default(),
+        rocksdb_opt: RocksdbOpt::default(),
+        concurernt_downloads: ConcurrentDownloadsOpt::default(),
+    }
+    .try_into()
+    .unwrap()
+}
+
+#[test]
+fn multiple_backups() {
+    let (_src_db_dir, src_db, blocks) = tmp_db_with_random_content();
+    let tgt_db_dir = TempPath::new();
+    tgt_db_dir.create_as_dir().unwrap();
+    let backup_dir = TempPath::new();
+    backup_dir.create_as_dir().unwrap();
+    let store: Arc<dyn BackupStorage> = Arc::new(LocalFs::new(backup_dir.path().to_path_buf()));
+
+    let (rt, port) = start_local_backup_service(src_db);
+    let client = Arc::new(BackupServiceClient::new(format!(
+        "http://localhost:{}",
+        port
+    )));
+
+    let txns = blocks
+        .iter()
+        .map(|(txns, _li)| txns)
+        .flatten()
+        .map(|txn_to_commit| txn_to_commit.transaction())
+        .collect::<Vec<_>>();
+    let max_chunk_size = max_chunk_size(&txns);
+
+    // Three backups, each starting where the previous one ends, the chunks of one being loaded
+    // while those of the previous one are applied.
+    let boundaries = vec![0, txns.len() / 3, txns.len() * 2 / 3, txns.len()];
+    let manifest_handles = boundaries
+        .windows(2)
+        .filter(|w| w[0] < w[1])
+        .map(|w| {
+            backup_transactions(
+                &rt,
+                &client,
+                &store,
+                w[0] as Version,
+                w[1] - w[0],
+                max_chunk_size,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    rt.block_on(
+        TransactionRestoreBatchController::new(
+            restore_opt(&tgt_db_dir),
+            store,
+            manifest_handles,
+            None, /* replay_from_version */
+            None, /* epoch_history */
+        )
+        .run(),
+    )
+    .unwrap();
+
+    let tgt_db = DiemDB::open(
+        &tgt_db_dir,
+        true, /* read_only */
+        None, /* pruner */
+        RocksdbConfig::default(),
+    )
+    .unwrap();
+    let latest_version = txns.len() as Version - 1;
+    assert_eq!(
+        tgt_db
+            .get_latest_transaction_info_option()
+            .unwrap()
+            .unwrap()
+            .0,
+        latest_version,
+    );
+    assert_eq!(
+        tgt_db
+            .get_transactions(
+                0,
+                txns.len() as u64,
+                latest_version,
+                false, /* fetch_events */
+            )
+            .unwrap()
+            .transactions,
+        txns.into_iter().cloned().collect::<Vec<_>>()
+    );
+
+    rt.shutdown_timeout(Duration::from_secs(1));
+}
+
+#[test]
+fn gap_between_backups() {
+    let (_src_db_dir, src_db, blocks) = tmp_db_with_random_content();
+    let tgt_db_dir = TempPath::new();
+    tgt_db_dir.create_as_dir().unwrap();
+    let backup_dir = TempPath::new();
+    backup_dir.create_as_dir().unwrap();
+    let store: Arc<dyn BackupStorage> = Arc::new(LocalFs::new(backup_dir.path().to_path_buf()));
+
+    let (rt, port) = start_local_backup_service(src_db);
+    let client = Arc::new(BackupServiceClient::new(format!(
+        "http://localhost:{}",
+        port
+    )));
+
+    let txns = blocks
+        .iter()
+        .map(|(txns, _li)| txns)
+        .flatten()
+        .map(|txn_to_commit| txn_to_commit.transaction())
+        .collect::<Vec<_>>();
+    if txns.len() < 3 {
+        // Not enough transactions to leave one out between two backups.
+        rt.shutdown_timeout(Duration::from_secs(1));
+        return;
+    }
+    let max_chunk_size = max_chunk_size(&txns);
+
+    // The transaction at version 1 is in neither backup.
+    let manifest_handles = vec![
+        backup_transactions(&rt, &client, &store, 0, 1, max_chunk_size),
+        backup_transactions(&rt, &client, &store, 2, txns.len() - 2, max_chunk_size),
+    ];
+
+    let err = rt
+        .block_on(
+            TransactionRestoreBatchController::new(
+                restore_opt(&tgt_db_dir),
+                store,
+                manifest_handles,
+                None, /* replay_from_version */
+                None, /* epoch_history */
+            )
+            .run(),
+        )
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("chunks not continuous"),
+        "unexpected error: {}",
+        err
+    );
+
+    rt.shutdown_timeout(Duration::from_secs(1));
+}
@@ -0,0 +1,55 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Result;
+use backup_cli::{
+    coordinators::query::{QueryCoordinator, QueryCoordinatorOpt},
+    metadata::cache::MetadataCacheOpt,
+    storage::StorageOpt,
+    utils::{ConcurrentDownloadsOpt, TrustedWaypointOpt},
+};
+use diem_logger::{prelude::*, Level, Logger};
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+struct Opt {
+    #[structopt(flatten)]
+    query_opt: QueryCoordinatorOpt,
+    #[structopt(flatten)]
+    metadata_cache_opt: MetadataCacheOpt,
+    #[structopt(flatten)]
+    trusted_waypoints_opt: TrustedWaypointOpt,
+    #[structopt(subcommand)]
+    storage: StorageOpt,
+    #[structopt(flatten)]
+    concurrent_downloads: ConcurrentDownloadsOpt,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    main_impl().await.map_err(|e| {
+        error!("main_impl() failed: {}", e);
+        e
+    })
+}
+
+async fn main_impl() -> Result<()> {
+    Logger::new().level(Level::Info).read_env().init();
+
+    let opt = Opt::from_args();
+    let results = QueryCoordinator::new(
+        opt.query_opt,
+        opt.storage.init_storage().await?,
+        opt.metadata_cache_opt,
+        opt.trusted_waypoints_opt,
+        opt.concurrent_downloads.get(),
+    )
+    .run()
+    .await?;
+
+    // One JSON object per line on stdout, logs go to stderr.
+    for result in results {
+        println!("{}", serde_json::to_string(&result)?);
+    }
+    Ok(())
+}
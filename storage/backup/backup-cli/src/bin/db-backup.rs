@@ -9,10 +9,16 @@ use structopt::StructOpt;
 use backup_cli::{
     backup_types::{
         epoch_ending::backup::{EpochEndingBackupController, EpochEndingBackupOpt},
+        incremental_state_snapshot::backup::{
+            IncrementalStateSnapshotBackupController, IncrementalStateSnapshotBackupOpt,
+        },
         state_snapshot::backup::{StateSnapshotBackupController, StateSnapshotBackupOpt},
         transaction::backup::{TransactionBackupController, TransactionBackupOpt},
     },
-    coordinators::backup::{BackupCoordinator, BackupCoordinatorOpt},
+    coordinators::{
+        backup::{BackupCoordinator, BackupCoordinatorOpt},
+        compact::{CompactCoordinator, CompactCoordinatorOpt},
+    },
     metadata::{cache, cache::MetadataCacheOpt},
     storage::StorageOpt,
     utils::{
@@ -95,6 +101,12 @@ enum BackupType {
         #[structopt(subcommand)]
         storage: StorageOpt,
     },
+    IncrementalStateSnapshot {
+        #[structopt(flatten)]
+        opt: IncrementalStateSnapshotBackupOpt,
+        #[structopt(subcommand)]
+        storage: StorageOpt,
+    },
     Transaction {
         #[structopt(flatten)]
         opt: TransactionBackupOpt,
@@ -107,6 +119,10 @@ enum BackupType {
 enum CoordinatorCommand {
     #[structopt(about = "Run the coordinator.")]
     Run(CoordinatorRunOpt),
+    #[structopt(
+        about = "Compact the metadata files, deleting old backups if a retention policy is given."
+    )]
+    Compact(CoordinatorCompactOpt),
 }
 
 #[derive(StructOpt)]
@@ -124,6 +140,15 @@ struct CoordinatorRunOpt {
     storage: StorageOpt,
 }
 
+#[derive(StructOpt)]
+struct CoordinatorCompactOpt {
+    #[structopt(flatten)]
+    coordinator: CompactCoordinatorOpt,
+
+    #[structopt(subcommand)]
+    storage: StorageOpt,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     main_impl().await.map_err(|e| {
@@ -141,7 +166,7 @@ async fn main_impl() -> Result<()> {
         Command::OneShot(one_shot_cmd) => match one_shot_cmd {
             OneShotCommand::Query(typ) => match typ {
                 OneShotQueryType::NodeState(opt) => {
-                    let client = BackupServiceClient::new_with_opt(opt.client);
+                    let client = BackupServiceClient::new_with_opt(opt.client)?;
                     if let Some(db_state) = client.get_db_state().await? {
                         println!("{}", db_state)
                     } else {
@@ -159,7 +184,7 @@ async fn main_impl() -> Result<()> {
                 }
             },
             OneShotCommand::Backup(opt) => {
-                let client = Arc::new(BackupServiceClient::new_with_opt(opt.client));
+                let client = Arc::new(BackupServiceClient::new_with_opt(opt.client)?);
                 let global_opt = opt.global;
 
                 match opt.backup_type {
@@ -183,6 +208,16 @@ async fn main_impl() -> Result<()> {
                         .run()
                         .await?;
                     }
+                    BackupType::IncrementalStateSnapshot { opt, storage } => {
+                        IncrementalStateSnapshotBackupController::new(
+                            opt,
+                            global_opt,
+                            client,
+                            storage.init_storage().await?,
+                        )
+                        .run()
+                        .await?;
+                    }
                     BackupType::Transaction { opt, storage } => {
                         TransactionBackupController::new(
                             opt,
@@ -201,12 +236,17 @@ async fn main_impl() -> Result<()> {
                 BackupCoordinator::new(
                     opt.coordinator,
                     opt.global,
-                    Arc::new(BackupServiceClient::new_with_opt(opt.client)),
+                    Arc::new(BackupServiceClient::new_with_opt(opt.client)?),
                     opt.storage.init_storage().await?,
                 )
                 .run()
                 .await?;
             }
+            CoordinatorCommand::Compact(opt) => {
+                CompactCoordinator::new(opt.coordinator, opt.storage.init_storage().await?)
+                    .run()
+                    .await?;
+            }
         },
     }
     Ok(())
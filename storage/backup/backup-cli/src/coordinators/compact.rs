@@ -0,0 +1,195 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    backup_types::{
+        incremental_state_snapshot::manifest::IncrementalStateSnapshotBackup,
+        state_snapshot::manifest::StateSnapshotBackup, transaction::manifest::TransactionBackup,
+    },
+    metadata::{
+        cache::LoadMetadataLines,
+        retention::{DeletionPlan, RetentionPolicyOpt},
+        view::MetadataView,
+    },
+    metrics::compact::{
+        COMPACT_COORDINATOR_FAIL_TS, COMPACT_COORDINATOR_START_TS, COMPACT_COORDINATOR_SUCC_TS,
+        COMPACT_DELETED_FILES, COMPACT_METADATA_FILES,
+    },
+    storage::{BackupStorage, FileHandleRef, ShellSafeName},
+    utils::{error_notes::ErrorNotes, storage_ext::BackupStorageExt, unix_timestamp_sec},
+};
+use anyhow::{ensure, Result};
+use diem_logger::prelude::*;
+use std::{convert::TryInto, sync::Arc};
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub struct CompactCoordinatorOpt {
+    #[structopt(flatten)]
+    pub retention: RetentionPolicyOpt,
+    #[structopt(
+        long,
+        default_value = "10000",
+        help = "Maximum number of metadata entries in one compacted metadata file."
+    )]
+    pub metadata_lines_per_file: usize,
+    #[structopt(
+        long,
+        help = "Only print what would be compacted and deleted, without changing the storage."
+    )]
+    pub dry_run: bool,
+}
+
+/// Merges all metadata files into a few compacted ones, dropping the metadata of the backups the
+/// retention policy deletes. The new files are saved before the old ones are moved away, and
+/// backup files are deleted only after no metadata refers to them, so an interrupted run leaves
+/// duplicated metadata or orphaned files behind, but never metadata pointing to missing files.
+pub struct CompactCoordinator {
+    storage: Arc<dyn BackupStorage>,
+    retention: RetentionPolicyOpt,
+    metadata_lines_per_file: usize,
+    dry_run: bool,
+}
+
+impl CompactCoordinator {
+    pub fn new(opt: CompactCoordinatorOpt, storage: Arc<dyn BackupStorage>) -> Self {
+        Self {
+            storage,
+            retention: opt.retention,
+            metadata_lines_per_file: opt.metadata_lines_per_file,
+            dry_run: opt.dry_run,
+        }
+    }
+
+    pub async fn run(self) -> Result<()> {
+        info!("Compact coordinator started.");
+        COMPACT_COORDINATOR_START_TS.set(unix_timestamp_sec());
+
+        let ret = self.run_impl().await;
+
+        if let Err(e) = &ret {
+            error!(
+                error = ?e,
+                "Compact coordinator failed."
+            );
+            COMPACT_COORDINATOR_FAIL_TS.set(unix_timestamp_sec());
+        } else {
+            info!("Compact coordinator exiting with success.");
+            COMPACT_COORDINATOR_SUCC_TS.set(unix_timestamp_sec());
+        }
+
+        ret
+    }
+
+    async fn run_impl(self) -> Result<()> {
+        ensure!(
+            self.metadata_lines_per_file > 0,
+            "--metadata-lines-per-file must be greater than 0."
+        );
+
+        // Read the metadata files directly instead of through the cache, to know exactly which
+        // files the compacted ones replace.
+        let metadata_files = self.storage.list_metadata_files().await?;
+        let mut metadata_vec = Vec::new();
+        for file_handle in &metadata_files {
+            metadata_vec.extend(
+                self.storage
+                    .open_for_read(file_handle)
+                    .await?
+                    .load_metadata_lines()
+                    .await
+                    .err_notes(file_handle)?,
+            );
+        }
+        let view: MetadataView = metadata_vec.into();
+
+        let (kept, plan) = self.retention.plan(&view)?;
+        let lines = kept
+            .to_metadata_vec()
+            .iter()
+            .map(|m| m.to_text_line())
+            .collect::<Result<Vec<_>>>()?;
+        let num_compacted_files =
+            (lines.len() + self.metadata_lines_per_file - 1) / self.metadata_lines_per_file;
+        info!(
+            num_metadata_files = metadata_files.len(),
+            num_compacted_files = num_compacted_files,
+            "Compaction planned, to delete {}.",
+            plan,
+        );
+        if self.dry_run {
+            println!(
+                "Would compact {} metadata files into {}, and delete {}.",
+                metadata_files.len(),
+                num_compacted_files,
+                plan,
+            );
+            return Ok(());
+        }
+        if plan.is_empty() && metadata_files.len() <= num_compacted_files {
+            info!("Nothing to compact.");
+            return Ok(());
+        }
+
+        let timestamp = unix_timestamp_sec();
+        for (i, chunk) in lines.chunks(self.metadata_lines_per_file).enumerate() {
+            let name: ShellSafeName = format!("compacted_{}_{}.meta", timestamp, i).try_into()?;
+            self.storage.save_metadata_lines(&name, chunk).await?;
+        }
+        for file_handle in &metadata_files {
+            self.storage.backup_metadata_file(file_handle).await?;
+        }
+        COMPACT_METADATA_FILES.set(metadata_files.len() as i64);
+
+        self.delete_backups(&plan).await
+    }
+
+    async fn delete_backups(&self, plan: &DeletionPlan) -> Result<()> {
+        COMPACT_DELETED_FILES.set(0);
+        // Incremental state snapshots first, they are based on the full ones.
+        for backup in &plan.incremental_state_snapshot_backups {
+            let manifest: IncrementalStateSnapshotBackup =
+                self.storage.load_json_file(&backup.manifest).await?;
+            for chunk in &manifest.chunks {
+                self.delete_file(&chunk.blobs).await?;
+            }
+            self.delete_file(&manifest.proof).await?;
+            self.delete_file(&backup.manifest).await?;
+            info!(
+                version = backup.version,
+                "Incremental state snapshot deleted."
+            );
+        }
+        for backup in &plan.state_snapshot_backups {
+            let manifest: StateSnapshotBackup =
+                self.storage.load_json_file(&backup.manifest).await?;
+            for chunk in &manifest.chunks {
+                self.delete_file(&chunk.blobs).await?;
+                self.delete_file(&chunk.proof).await?;
+            }
+            self.delete_file(&manifest.proof).await?;
+            self.delete_file(&backup.manifest).await?;
+            info!(version = backup.version, "State snapshot deleted.");
+        }
+        for backup in &plan.transaction_backups {
+            let manifest: TransactionBackup = self.storage.load_json_file(&backup.manifest).await?;
+            for chunk in &manifest.chunks {
+                self.delete_file(&chunk.transactions).await?;
+                self.delete_file(&chunk.proof).await?;
+            }
+            self.delete_file(&backup.manifest).await?;
+            info!(
+                first_version = backup.first_version,
+                last_version = backup.last_version,
+                "Transaction backup deleted."
+            );
+        }
+        Ok(())
+    }
+
+    async fn delete_file(&self, file_handle: &FileHandleRef) -> Result<()> {
+        self.storage.delete_file(file_handle).await?;
+        COMPACT_DELETED_FILES.inc();
+        Ok(())
+    }
+}
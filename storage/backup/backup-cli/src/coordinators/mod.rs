@@ -2,5 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 pub mod backup;
+pub mod compact;
+pub mod query;
 pub mod restore;
 pub mod verify;
@@ -0,0 +1,442 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    backup_types::{
+        epoch_ending::restore::{EpochHistory, EpochHistoryRestoreController},
+        state_snapshot::manifest::StateSnapshotBackup,
+        transaction::{manifest::TransactionBackup, restore::LoadedChunk},
+    },
+    metadata,
+    metadata::{cache::MetadataCacheOpt, StateSnapshotBackupMeta, TransactionBackupMeta},
+    storage::BackupStorage,
+    utils::{
+        read_record_bytes::ReadRecordBytes, storage_ext::BackupStorageExt, GlobalRestoreOptions,
+        RestoreRunMode, TrustedWaypointOpt,
+    },
+};
+use anyhow::{anyhow, bail, ensure, Result};
+use diem_crypto::HashValue;
+use diem_infallible::RwLock;
+use diem_logger::prelude::*;
+use diem_state_view::StateView;
+use diem_types::{
+    access_path::AccessPath,
+    account_address::{AccountAddress, HashAccountAddress},
+    account_state::AccountState,
+    account_state_blob::AccountStateBlob,
+    ledger_info::LedgerInfoWithSignatures,
+    proof::{SparseMerkleProof, TransactionInfoWithProof},
+    transaction::{Transaction, TransactionInfo, TransactionStatus, Version},
+    write_set::WriteSet,
+};
+use diem_vm::{DiemVM, VMExecutor};
+use executor::process_write_set;
+use scratchpad::{AccountStatus, ProofRead, SparseMerkleTree};
+use serde::Serialize;
+use std::{
+    cmp::min,
+    collections::{hash_map, HashMap},
+    convert::TryFrom,
+    sync::Arc,
+};
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub struct QueryCoordinatorOpt {
+    #[structopt(long, help = "Version to query the account states at.")]
+    pub version: Version,
+    #[structopt(long = "address", help = "(multiple) Address of an account to query.")]
+    pub addresses: Vec<AccountAddress>,
+}
+
+/// Answers account state queries at an arbitrary version from the backups alone: the nearest
+/// state snapshot at or before the version is loaded into an in-memory `SparseMerkleTree`, and
+/// the transactions after it are replayed on top, checking the state root against the backed up
+/// `TransactionInfo` after each one.
+pub struct QueryCoordinator {
+    storage: Arc<dyn BackupStorage>,
+    metadata_cache_opt: MetadataCacheOpt,
+    trusted_waypoints_opt: TrustedWaypointOpt,
+    concurrent_downloads: usize,
+    version: Version,
+    addresses: Vec<AccountAddress>,
+}
+
+/// State of one account at a version, with a proof against the state root at that version.
+#[derive(Serialize)]
+pub struct AccountStateQueryResult {
+    pub address: AccountAddress,
+    pub version: Version,
+    pub state_root_hash: HashValue,
+    /// Hex encoded `AccountStateBlob`, `None` if the account doesn't exist.
+    pub blob: Option<String>,
+    /// Hex encoded BCS bytes of the `SparseMerkleProof`.
+    pub proof: String,
+}
+
+impl QueryCoordinator {
+    pub fn new(
+        opt: QueryCoordinatorOpt,
+        storage: Arc<dyn BackupStorage>,
+        metadata_cache_opt: MetadataCacheOpt,
+        trusted_waypoints_opt: TrustedWaypointOpt,
+        concurrent_downloads: usize,
+    ) -> Self {
+        Self {
+            storage,
+            metadata_cache_opt,
+            trusted_waypoints_opt,
+            concurrent_downloads,
+            version: opt.version,
+            addresses: opt.addresses,
+        }
+    }
+
+    pub async fn run(self) -> Result<Vec<AccountStateQueryResult>> {
+        info!("Query coordinator started.");
+        let ret = self.run_impl().await;
+        if let Err(e) = &ret {
+            error!(
+                error = ?e,
+                "Query coordinator failed."
+            );
+        } else {
+            info!("Query coordinator exiting with success.");
+        }
+        ret
+    }
+
+    async fn run_impl(self) -> Result<Vec<AccountStateQueryResult>> {
+        let metadata_view = metadata::cache::sync_and_load(
+            &self.metadata_cache_opt,
+            Arc::clone(&self.storage),
+            self.concurrent_downloads,
+        )
+        .await?;
+        let state_snapshot = metadata_view.select_state_snapshot(self.version)?;
+        let transactions = metadata_view.select_transaction_backups(self.version)?;
+        // Ledger infos in the transaction backups can be from epochs after the queried version.
+        let epoch_endings = metadata_view.select_epoch_ending_backups(Version::max_value())?;
+
+        let global_opt = GlobalRestoreOptions {
+            target_version: Version::max_value(),
+            trusted_waypoints: Arc::new(self.trusted_waypoints_opt.verify()?),
+            run_mode: Arc::new(RestoreRunMode::Verify),
+            concurrent_downloads: self.concurrent_downloads,
+        };
+        let epoch_history = EpochHistoryRestoreController::new(
+            epoch_endings
+                .into_iter()
+                .map(|backup| backup.manifest)
+                .collect(),
+            global_opt,
+            Arc::clone(&self.storage),
+        )
+        .run()
+        .await?;
+
+        let mut state = match state_snapshot {
+            Some(backup) => self.load_state_snapshot(backup, &epoch_history).await?,
+            None => {
+                warn!("No state snapshot before the queried version, replaying from genesis.");
+                InMemoryState::default()
+            }
+        };
+        self.replay_transactions(&mut state, transactions, Arc::new(epoch_history))
+            .await?;
+
+        self.addresses
+            .iter()
+            .map(|address| state.query(*address))
+            .collect()
+    }
+
+    async fn load_state_snapshot(
+        &self,
+        backup: StateSnapshotBackupMeta,
+        epoch_history: &EpochHistory,
+    ) -> Result<InMemoryState> {
+        info!(version = backup.version, "Loading state snapshot.");
+        let manifest: StateSnapshotBackup = self.storage.load_json_file(&backup.manifest).await?;
+        let (txn_info_with_proof, li): (TransactionInfoWithProof, LedgerInfoWithSignatures) =
+            self.storage.load_bcs_file(&manifest.proof).await?;
+        txn_info_with_proof.verify(li.ledger_info(), manifest.version)?;
+        ensure!(
+            txn_info_with_proof.transaction_info().state_root_hash() == manifest.root_hash,
+            "Root hash mismatch with that in proof. root hash: {}, expected: {}",
+            manifest.root_hash,
+            txn_info_with_proof.transaction_info().state_root_hash(),
+        );
+        epoch_history.verify_ledger_info(&li)?;
+
+        // The chunk proofs are not checked, since the root hash of the whole tree is.
+        let mut blobs = HashMap::new();
+        for chunk in manifest.chunks {
+            let mut file = self.storage.open_for_read(&chunk.blobs).await?;
+            while let Some(record_bytes) = file.read_record_bytes().await? {
+                let (key, blob): (HashValue, AccountStateBlob) = bcs::from_bytes(&record_bytes)?;
+                blobs.insert(key, blob);
+            }
+        }
+        let state = InMemoryState::new(manifest.version + 1, blobs)?;
+        ensure!(
+            state.smt.root_hash() == manifest.root_hash,
+            "State snapshot root hash mismatch. root hash: {}, expected: {}",
+            state.smt.root_hash(),
+            manifest.root_hash,
+        );
+
+        Ok(state)
+    }
+
+    async fn replay_transactions(
+        &self,
+        state: &mut InMemoryState,
+        backups: Vec<TransactionBackupMeta>,
+        epoch_history: Arc<EpochHistory>,
+    ) -> Result<()> {
+        for backup in backups {
+            if backup.last_version < state.next_version {
+                continue;
+            }
+            let manifest: TransactionBackup = self.storage.load_json_file(&backup.manifest).await?;
+            manifest.verify()?;
+
+            for chunk in manifest.chunks {
+                if state.next_version > self.version {
+                    break;
+                }
+                if chunk.last_version < state.next_version {
+                    continue;
+                }
+                ensure!(
+                    chunk.first_version <= state.next_version,
+                    "Transaction backups start at version {}, expecting version {}.",
+                    chunk.first_version,
+                    state.next_version,
+                );
+
+                let chunk = LoadedChunk::load(chunk, &self.storage, Some(&epoch_history)).await?;
+                let skip = (state.next_version - chunk.manifest.first_version) as usize;
+                let take = (min(chunk.manifest.last_version, self.version) + 1 - state.next_version)
+                    as usize;
+                state.replay(
+                    chunk.txns.into_iter().skip(skip).take(take).collect(),
+                    chunk.txn_infos.into_iter().skip(skip).take(take).collect(),
+                )?;
+                info!(version = state.next_version - 1, "Transactions replayed.");
+            }
+        }
+
+        ensure!(
+            state.next_version > self.version,
+            "Transaction backups end before version {}, short of the queried version {}.",
+            state.next_version,
+            self.version,
+        );
+        Ok(())
+    }
+}
+
+/// Versions replayed before the tree is rebuilt from the account states, so the nodes of the
+/// versions in between, which every new tree keeps alive through its base tree, are dropped.
+const REBUILD_TREE_INTERVAL: Version = 10_000;
+
+/// Account states in memory, after applying all transactions before `next_version`.
+#[derive(Default)]
+struct InMemoryState {
+    next_version: Version,
+    smt: SparseMerkleTree<AccountStateBlob>,
+    /// The leaves of `smt`, to rebuild it from.
+    blobs: HashMap<HashValue, AccountStateBlob>,
+}
+
+impl InMemoryState {
+    fn new(next_version: Version, blobs: HashMap<HashValue, AccountStateBlob>) -> Result<Self> {
+        Ok(Self {
+            next_version,
+            smt: Self::build_tree(&blobs)?,
+            blobs,
+        })
+    }
+
+    fn build_tree(
+        blobs: &HashMap<HashValue, AccountStateBlob>,
+    ) -> Result<SparseMerkleTree<AccountStateBlob>> {
+        SparseMerkleTree::default()
+            .batch_update(
+                blobs.iter().map(|(k, v)| (*k, v)).collect(),
+                &InMemoryProofReader,
+            )
+            .map_err(|e| anyhow!("Failed to build the state tree: {:?}", e))
+    }
+
+    /// Replaces the tree with one without history, after checking it has the same root.
+    fn rebuild_tree(&mut self) -> Result<()> {
+        let smt = Self::build_tree(&self.blobs)?;
+        ensure!(
+            smt.root_hash() == self.smt.root_hash(),
+            "Rebuilt state tree root hash mismatch at version {}. root hash: {}, expected: {}",
+            self.next_version,
+            smt.root_hash(),
+            self.smt.root_hash(),
+        );
+        self.smt = smt;
+        Ok(())
+    }
+
+    fn replay(
+        &mut self,
+        mut txns: Vec<Transaction>,
+        txn_infos: Vec<TransactionInfo>,
+    ) -> Result<()> {
+        let mut txn_infos = txn_infos.into_iter();
+        while !txns.is_empty() {
+            let outputs = DiemVM::execute_block(
+                txns.clone(),
+                &InMemoryStateView::new(&self.smt, self.next_version == 0),
+            )?;
+
+            // Transactions after a reconfiguration come back as `Retry` and are executed again
+            // in the next round.
+            let mut num_executed = 0;
+            for (txn, output) in txns.iter().zip(outputs) {
+                let status = match output.status() {
+                    TransactionStatus::Keep(status) => status.clone(),
+                    TransactionStatus::Retry => break,
+                    TransactionStatus::Discard(status) => bail!(
+                        "The transaction at version {}, got the status of 'Discard': {:?}",
+                        self.next_version,
+                        status,
+                    ),
+                };
+                let txn_info = txn_infos
+                    .next()
+                    .ok_or_else(|| anyhow!("Less TransactionInfos than transactions."))?;
+                ensure!(
+                    &status == txn_info.status(),
+                    "Status mismatch at version {}. status: {:?}, expected: {:?}",
+                    self.next_version,
+                    status,
+                    txn_info.status(),
+                );
+
+                let (write_set, _events) = output.into();
+                self.apply_write_set(txn, write_set)?;
+                ensure!(
+                    self.smt.root_hash() == txn_info.state_root_hash(),
+                    "State root hash mismatch at version {}. root hash: {}, expected: {}",
+                    self.next_version,
+                    self.smt.root_hash(),
+                    txn_info.state_root_hash(),
+                );
+                self.next_version += 1;
+                num_executed += 1;
+                if self.next_version % REBUILD_TREE_INTERVAL == 0 {
+                    self.rebuild_tree()?;
+                }
+            }
+            ensure!(
+                num_executed > 0,
+                "No progress executing the transaction at version {}.",
+                self.next_version,
+            );
+            txns.drain(..num_executed);
+        }
+
+        Ok(())
+    }
+
+    fn apply_write_set(&mut self, txn: &Transaction, write_set: WriteSet) -> Result<()> {
+        let mut account_to_state = HashMap::new();
+        for (access_path, _write_op) in write_set.iter() {
+            if let hash_map::Entry::Vacant(entry) = account_to_state.entry(access_path.address) {
+                entry.insert(match self.smt.get(access_path.address.hash()) {
+                    AccountStatus::ExistsInScratchPad(blob) => AccountState::try_from(&blob)?,
+                    _ => AccountState::default(),
+                });
+            }
+        }
+
+        let updates = process_write_set(txn, &mut account_to_state, write_set)?
+            .into_iter()
+            .map(|(address, blob)| (address.hash(), blob))
+            .collect::<Vec<_>>();
+        self.smt = self
+            .smt
+            .batch_update(
+                updates.iter().map(|(k, v)| (*k, v)).collect(),
+                &InMemoryProofReader,
+            )
+            .map_err(|e| anyhow!("Failed to update state at {}: {:?}", self.next_version, e))?;
+        self.blobs.extend(updates);
+        Ok(())
+    }
+
+    fn query(&self, address: AccountAddress) -> Result<AccountStateQueryResult> {
+        let (blob, proof): (_, SparseMerkleProof<AccountStateBlob>) = self
+            .smt
+            .get_with_proof(address.hash())
+            .ok_or_else(|| anyhow!("State of account {} is not in memory.", address))?;
+
+        Ok(AccountStateQueryResult {
+            address,
+            version: self.next_version - 1,
+            state_root_hash: self.smt.root_hash(),
+            blob: blob.map(hex::encode),
+            proof: hex::encode(bcs::to_bytes(&proof)?),
+        })
+    }
+}
+
+/// The whole tree is built in memory from empty, so no proof is ever needed.
+struct InMemoryProofReader;
+
+impl ProofRead<AccountStateBlob> for InMemoryProofReader {
+    fn get_proof(&self, _key: HashValue) -> Option<&SparseMerkleProof<AccountStateBlob>> {
+        None
+    }
+}
+
+struct InMemoryStateView<'a> {
+    smt: &'a SparseMerkleTree<AccountStateBlob>,
+    is_genesis: bool,
+    account_to_state_cache: RwLock<HashMap<AccountAddress, AccountState>>,
+}
+
+impl<'a> InMemoryStateView<'a> {
+    fn new(smt: &'a SparseMerkleTree<AccountStateBlob>, is_genesis: bool) -> Self {
+        Self {
+            smt,
+            is_genesis,
+            account_to_state_cache: RwLock::new(HashMap::new()),
+        }
+    }
+}
+
+impl<'a> StateView for InMemoryStateView<'a> {
+    fn get(&self, access_path: &AccessPath) -> Result<Option<Vec<u8>>> {
+        let address = access_path.address;
+        if let Some(account_state) = self.account_to_state_cache.read().get(&address) {
+            return Ok(account_state.get(&access_path.path).cloned());
+        }
+
+        let account_state = match self.smt.get(address.hash()) {
+            AccountStatus::ExistsInScratchPad(blob) => AccountState::try_from(&blob)?,
+            AccountStatus::DoesNotExist => AccountState::default(),
+            AccountStatus::ExistsInDB | AccountStatus::Unknown => {
+                bail!("State of account {} is not in memory.", address)
+            }
+        };
+        let ret = account_state.get(&access_path.path).cloned();
+        self.account_to_state_cache
+            .write()
+            .insert(address, account_state);
+        Ok(ret)
+    }
+
+    fn is_genesis(&self) -> bool {
+        self.is_genesis
+    }
+}
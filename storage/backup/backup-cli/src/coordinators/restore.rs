@@ -4,6 +4,9 @@
 use crate::{
     backup_types::{
         epoch_ending::restore::EpochHistoryRestoreController,
+        incremental_state_snapshot::restore::{
+            IncrementalStateSnapshotRestoreController, IncrementalStateSnapshotRestoreOpt,
+        },
         state_snapshot::restore::{StateSnapshotRestoreController, StateSnapshotRestoreOpt},
         transaction::restore::TransactionRestoreBatchController,
     },
@@ -15,10 +18,10 @@ use crate::{
     storage::BackupStorage,
     utils::{unix_timestamp_sec, GlobalRestoreOptions, RestoreRunMode},
 };
-use anyhow::{bail, Result};
+use anyhow::{bail, ensure, Result};
 use diem_logger::prelude::*;
 use diem_types::transaction::Version;
-use std::sync::Arc;
+use std::{cmp::max, sync::Arc};
 use structopt::StructOpt;
 
 #[derive(StructOpt)]
@@ -84,15 +87,21 @@ impl RestoreCoordinator {
         let transactions = metadata_view.select_transaction_backups(self.target_version())?;
         let actual_target_version = self.get_actual_target_version(&transactions)?;
         let epoch_endings = metadata_view.select_epoch_ending_backups(actual_target_version)?;
-        let state_snapshot = if self.replay_all {
-            None
+        let (state_snapshot, incremental_state_snapshot) = if self.replay_all {
+            (None, None)
         } else {
-            metadata_view.select_state_snapshot(actual_target_version)?
-        };
-        let replay_transactions_from_version = match &state_snapshot {
-            Some(b) => b.version + 1,
-            None => 0,
+            // Use an incremental state snapshot if it's newer than the latest full one.
+            let full = metadata_view.select_state_snapshot(actual_target_version)?;
+            match metadata_view.select_incremental_state_snapshot(actual_target_version)? {
+                Some(i) if full.as_ref().map_or(true, |f| f.version < i.version) => (None, Some(i)),
+                _ => (full, None),
+            }
         };
+        let replay_transactions_from_version = state_snapshot
+            .as_ref()
+            .map(|b| b.version)
+            .or_else(|| incremental_state_snapshot.as_ref().map(|b| b.version))
+            .map_or(0, |v| v + 1);
         COORDINATOR_TARGET_VERSION.set(actual_target_version as i64);
         info!("Planned to restore to version {}.", actual_target_version);
         let txn_resume_point = match self.global_opt.run_mode.as_ref() {
@@ -110,6 +119,16 @@ impl RestoreCoordinator {
                 txn_resume_point
             );
         }
+        if let Some(b) = transactions.first() {
+            // Older transaction backups can be deleted by a retention policy.
+            ensure!(
+                b.first_version <= max(replay_transactions_from_version, txn_resume_point),
+                "Transaction backups start at version {}, restoring requires a state snapshot \
+                at or after version {}, without --replay-all.",
+                b.first_version,
+                b.first_version - 1,
+            );
+        }
 
         let epoch_history = Arc::new(
             EpochHistoryRestoreController::new(
@@ -137,6 +156,19 @@ impl RestoreCoordinator {
             .run()
             .await?;
         }
+        if let Some(backup) = incremental_state_snapshot {
+            IncrementalStateSnapshotRestoreController::new(
+                IncrementalStateSnapshotRestoreOpt {
+                    manifest_handle: backup.manifest,
+                    version: backup.version,
+                },
+                self.global_opt.clone(),
+                Arc::clone(&self.storage),
+                Some(Arc::clone(&epoch_history)),
+            )
+            .run()
+            .await?;
+        }
 
         let txn_manifests = transactions
             .into_iter()
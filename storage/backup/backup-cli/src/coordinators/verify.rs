@@ -4,6 +4,9 @@
 use crate::{
     backup_types::{
         epoch_ending::restore::EpochHistoryRestoreController,
+        incremental_state_snapshot::restore::{
+            IncrementalStateSnapshotRestoreController, IncrementalStateSnapshotRestoreOpt,
+        },
         state_snapshot::restore::{StateSnapshotRestoreController, StateSnapshotRestoreOpt},
         transaction::restore::TransactionRestoreBatchController,
     },
@@ -71,6 +74,8 @@ impl VerifyCoordinator {
         .await?;
         let ver_max = Version::max_value();
         let state_snapshot = metadata_view.select_state_snapshot(ver_max)?;
+        let incremental_state_snapshot =
+            metadata_view.select_incremental_state_snapshot(ver_max)?;
         let transactions = metadata_view.select_transaction_backups(ver_max)?;
         let epoch_endings = metadata_view.select_epoch_ending_backups(ver_max)?;
 
@@ -107,6 +112,19 @@ impl VerifyCoordinator {
             .run()
             .await?;
         }
+        if let Some(backup) = incremental_state_snapshot {
+            IncrementalStateSnapshotRestoreController::new(
+                IncrementalStateSnapshotRestoreOpt {
+                    manifest_handle: backup.manifest,
+                    version: backup.version,
+                },
+                global_opt.clone(),
+                Arc::clone(&self.storage),
+                Some(Arc::clone(&epoch_history)),
+            )
+            .run()
+            .await?;
+        }
 
         let txn_manifests = transactions.into_iter().map(|b| b.manifest).collect();
         TransactionRestoreBatchController::new(
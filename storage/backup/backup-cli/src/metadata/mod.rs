@@ -2,8 +2,12 @@
 // SPDX-License-Identifier: Apache-2.0
 
 pub mod cache;
+pub mod retention;
 pub mod view;
 
+#[cfg(test)]
+mod tests;
+
 use crate::storage::{FileHandle, ShellSafeName, TextLine};
 use anyhow::Result;
 use diem_types::transaction::Version;
@@ -14,6 +18,7 @@ use std::convert::TryInto;
 #[allow(clippy::enum_variant_names)] // to introduce: BackupperId, etc
 pub(crate) enum Metadata {
     EpochEndingBackup(EpochEndingBackupMeta),
+    IncrementalStateSnapshotBackup(IncrementalStateSnapshotBackupMeta),
     StateSnapshotBackup(StateSnapshotBackupMeta),
     TransactionBackup(TransactionBackupMeta),
 }
@@ -35,6 +40,20 @@ impl Metadata {
         })
     }
 
+    pub fn new_incremental_state_snapshot_backup(
+        version: Version,
+        base_version: Version,
+        base_snapshot_version: Version,
+        manifest: FileHandle,
+    ) -> Self {
+        Self::IncrementalStateSnapshotBackup(IncrementalStateSnapshotBackupMeta {
+            version,
+            base_version,
+            base_snapshot_version,
+            manifest,
+        })
+    }
+
     pub fn new_state_snapshot_backup(version: Version, manifest: FileHandle) -> Self {
         Self::StateSnapshotBackup(StateSnapshotBackupMeta { version, manifest })
     }
@@ -56,6 +75,9 @@ impl Metadata {
             Self::EpochEndingBackup(e) => {
                 format!("epoch_ending_{}-{}.meta", e.first_epoch, e.last_epoch)
             }
+            Self::IncrementalStateSnapshotBackup(s) => {
+                format!("incremental_state_snapshot_ver_{}.meta", s.version)
+            }
             Self::StateSnapshotBackup(s) => format!("state_snapshot_ver_{}.meta", s.version),
             Self::TransactionBackup(t) => {
                 format!("transaction_{}-{}.meta", t.first_version, t.last_version,)
@@ -79,6 +101,15 @@ pub struct EpochEndingBackupMeta {
     pub manifest: FileHandle,
 }
 
+#[derive(Clone, Deserialize, Serialize, Eq, PartialEq, Ord, PartialOrd)]
+pub struct IncrementalStateSnapshotBackupMeta {
+    pub version: Version,
+    pub base_version: Version,
+    /// Version of the full state snapshot at the start of the chain of bases.
+    pub base_snapshot_version: Version,
+    pub manifest: FileHandle,
+}
+
 #[derive(Clone, Deserialize, Serialize, Eq, PartialEq, Ord, PartialOrd)]
 pub struct StateSnapshotBackupMeta {
     pub version: Version,
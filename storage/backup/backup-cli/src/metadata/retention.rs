@@ -0,0 +1,143 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::metadata::{
+    view::MetadataView, IncrementalStateSnapshotBackupMeta, Metadata, StateSnapshotBackupMeta,
+    TransactionBackupMeta,
+};
+use anyhow::{anyhow, ensure, Result};
+use diem_types::transaction::Version;
+use itertools::Itertools;
+use std::fmt;
+use structopt::StructOpt;
+
+#[derive(Clone, Default, StructOpt)]
+pub struct RetentionPolicyOpt {
+    #[structopt(
+        long,
+        help = "Keep the latest N state snapshots and delete the older ones, together with the \
+        incremental state snapshots based on them. Epoch ending backups are always kept."
+    )]
+    pub keep_last_state_snapshots: Option<usize>,
+    #[structopt(
+        long,
+        help = "Delete the transaction backups before the kept state snapshot at this version. \
+        Defaults to the oldest kept state snapshot if --keep-last-state-snapshots is set, \
+        otherwise all transaction backups are kept."
+    )]
+    pub keep_transactions_since_snapshot: Option<Version>,
+}
+
+impl RetentionPolicyOpt {
+    /// Splits the backups into the ones to keep and the ones to delete, making sure what's kept
+    /// can still be restored.
+    pub fn plan(&self, view: &MetadataView) -> Result<(MetadataView, DeletionPlan)> {
+        let snapshots = view.state_snapshot_backups();
+        let num_deleted_snapshots = match self.keep_last_state_snapshots {
+            Some(n) => {
+                ensure!(n > 0, "--keep-last-state-snapshots must be greater than 0.");
+                snapshots.len().saturating_sub(n)
+            }
+            None => 0,
+        };
+        let (deleted_snapshots, kept_snapshots) = snapshots.split_at(num_deleted_snapshots);
+
+        let keep_transactions_since = match self.keep_transactions_since_snapshot {
+            Some(version) => {
+                ensure!(
+                    kept_snapshots.iter().any(|s| s.version == version),
+                    "No kept state snapshot at version {}.",
+                    version,
+                );
+                Some(version)
+            }
+            None => self
+                .keep_last_state_snapshots
+                .and_then(|_| kept_snapshots.first().map(|s| s.version)),
+        };
+        // Incremental state snapshots can't be restored without the full one they are based on.
+        let deleted_incremental_snapshots = view
+            .incremental_state_snapshot_backups()
+            .iter()
+            .filter(|i| {
+                deleted_snapshots
+                    .iter()
+                    .any(|s| s.version == i.base_snapshot_version)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        let deleted_transactions = view
+            .transaction_backups()
+            .iter()
+            .filter(|t| keep_transactions_since.map_or(false, |v| t.last_version < v))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let plan = DeletionPlan {
+            state_snapshot_backups: deleted_snapshots.to_vec(),
+            incremental_state_snapshot_backups: deleted_incremental_snapshots,
+            transaction_backups: deleted_transactions,
+        };
+        let kept: MetadataView = view
+            .to_metadata_vec()
+            .into_iter()
+            .filter(|m| match m {
+                Metadata::EpochEndingBackup(_) => true,
+                Metadata::StateSnapshotBackup(s) => !plan.state_snapshot_backups.contains(s),
+                Metadata::IncrementalStateSnapshotBackup(s) => {
+                    !plan.incremental_state_snapshot_backups.contains(s)
+                }
+                Metadata::TransactionBackup(t) => !plan.transaction_backups.contains(t),
+            })
+            .collect::<Vec<_>>()
+            .into();
+        if !plan.is_empty() {
+            kept.check_restorable().map_err(|e| {
+                anyhow!(
+                    "Refusing to delete {}, what's left can't be restored: {}",
+                    plan,
+                    e
+                )
+            })?;
+        }
+
+        Ok((kept, plan))
+    }
+}
+
+/// Backups a retention policy decided to delete.
+pub struct DeletionPlan {
+    pub state_snapshot_backups: Vec<StateSnapshotBackupMeta>,
+    pub incremental_state_snapshot_backups: Vec<IncrementalStateSnapshotBackupMeta>,
+    pub transaction_backups: Vec<TransactionBackupMeta>,
+}
+
+impl DeletionPlan {
+    pub fn is_empty(&self) -> bool {
+        self.state_snapshot_backups.is_empty()
+            && self.incremental_state_snapshot_backups.is_empty()
+            && self.transaction_backups.is_empty()
+    }
+}
+
+impl fmt::Display for DeletionPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "state snapshots at versions [{}], incremental state snapshots at versions [{}], \
+            transaction backups of versions [{}]",
+            self.state_snapshot_backups
+                .iter()
+                .map(|s| s.version)
+                .join(", "),
+            self.incremental_state_snapshot_backups
+                .iter()
+                .map(|s| s.version)
+                .join(", "),
+            self.transaction_backups
+                .iter()
+                .map(|t| format!("{}-{}", t.first_version, t.last_version))
+                .join(", "),
+        )
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::metadata::{retention::RetentionPolicyOpt, view::MetadataView, Metadata};
+
+fn test_view() -> MetadataView {
+    vec![
+        Metadata::new_epoch_ending_backup(0, 9, 0, 999, "epochs".to_string()),
+        Metadata::new_state_snapshot_backup(99, "snapshot_99".to_string()),
+        Metadata::new_state_snapshot_backup(499, "snapshot_499".to_string()),
+        Metadata::new_state_snapshot_backup(899, "snapshot_899".to_string()),
+        Metadata::new_transaction_backup(0, 299, "txn_0".to_string()),
+        Metadata::new_transaction_backup(300, 599, "txn_300".to_string()),
+        Metadata::new_transaction_backup(600, 999, "txn_600".to_string()),
+        // duplicated by an interrupted compaction
+        Metadata::new_transaction_backup(600, 999, "txn_600".to_string()),
+    ]
+    .into()
+}
+
+#[test]
+fn test_no_retention_policy() {
+    let view = test_view();
+    let (kept, plan) = RetentionPolicyOpt::default().plan(&view).unwrap();
+    assert!(plan.is_empty());
+    assert_eq!(kept.to_metadata_vec().len(), 7);
+}
+
+#[test]
+fn test_keep_last_state_snapshots() {
+    let view = test_view();
+    let (kept, plan) = RetentionPolicyOpt {
+        keep_last_state_snapshots: Some(2),
+        keep_transactions_since_snapshot: None,
+    }
+    .plan(&view)
+    .unwrap();
+
+    assert_eq!(
+        plan.state_snapshot_backups
+            .iter()
+            .map(|s| s.version)
+            .collect::<Vec<_>>(),
+        vec![99]
+    );
+    assert_eq!(
+        plan.transaction_backups
+            .iter()
+            .map(|t| t.first_version)
+            .collect::<Vec<_>>(),
+        vec![0]
+    );
+    kept.check_restorable().unwrap();
+    assert_eq!(kept.select_transaction_backups(1000).unwrap().len(), 2);
+    assert_eq!(
+        kept.select_state_snapshot(1000).unwrap().unwrap().version,
+        899
+    );
+}
+
+#[test]
+fn test_keep_transactions_since_snapshot() {
+    let view = test_view();
+    let (kept, plan) = RetentionPolicyOpt {
+        keep_last_state_snapshots: Some(1),
+        keep_transactions_since_snapshot: Some(899),
+    }
+    .plan(&view)
+    .unwrap();
+    assert_eq!(plan.state_snapshot_backups.len(), 2);
+    assert_eq!(plan.transaction_backups.len(), 2);
+    kept.check_restorable().unwrap();
+
+    // The state snapshot at 499 is kept, but transactions after it are not.
+    match (RetentionPolicyOpt {
+        keep_last_state_snapshots: Some(2),
+        keep_transactions_since_snapshot: Some(899),
+    })
+    .plan(&view)
+    {
+        Ok(_) => panic!("Should refuse to leave unrestorable backups."),
+        Err(e) => assert!(e.to_string().contains("can't be restored")),
+    }
+
+    // Not a kept state snapshot.
+    assert!(RetentionPolicyOpt {
+        keep_last_state_snapshots: Some(1),
+        keep_transactions_since_snapshot: Some(499),
+    }
+    .plan(&view)
+    .is_err());
+}
+
+#[test]
+fn test_incremental_state_snapshots() {
+    let mut metadata_vec = test_view().to_metadata_vec();
+    metadata_vec.extend(vec![
+        Metadata::new_incremental_state_snapshot_backup(199, 99, 99, "incr_199".to_string()),
+        Metadata::new_incremental_state_snapshot_backup(299, 199, 99, "incr_299".to_string()),
+        Metadata::new_incremental_state_snapshot_backup(599, 499, 499, "incr_599".to_string()),
+    ]);
+    let view: MetadataView = metadata_vec.into();
+    view.check_restorable().unwrap();
+
+    assert_eq!(
+        view.select_incremental_state_snapshot(550)
+            .unwrap()
+            .unwrap()
+            .version,
+        299
+    );
+    assert_eq!(
+        view.select_incremental_state_snapshot_base(700).unwrap(),
+        Some((599, "incr_599".to_string()))
+    );
+    assert_eq!(
+        view.select_incremental_state_snapshot_base(950).unwrap(),
+        Some((899, "snapshot_899".to_string()))
+    );
+
+    // The incremental state snapshots based on a deleted one are deleted as well.
+    let (kept, plan) = RetentionPolicyOpt {
+        keep_last_state_snapshots: Some(2),
+        keep_transactions_since_snapshot: None,
+    }
+    .plan(&view)
+    .unwrap();
+    assert_eq!(
+        plan.incremental_state_snapshot_backups
+            .iter()
+            .map(|s| s.version)
+            .collect::<Vec<_>>(),
+        vec![199, 299]
+    );
+    kept.check_restorable().unwrap();
+    assert_eq!(
+        kept.select_incremental_state_snapshot(1000)
+            .unwrap()
+            .unwrap()
+            .version,
+        599
+    );
+}
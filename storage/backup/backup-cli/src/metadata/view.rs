@@ -1,8 +1,12 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::metadata::{
-    EpochEndingBackupMeta, Metadata, StateSnapshotBackupMeta, TransactionBackupMeta,
+use crate::{
+    metadata::{
+        EpochEndingBackupMeta, IncrementalStateSnapshotBackupMeta, Metadata,
+        StateSnapshotBackupMeta, TransactionBackupMeta,
+    },
+    storage::FileHandle,
 };
 use anyhow::{anyhow, ensure, Result};
 use diem_types::transaction::Version;
@@ -12,6 +16,7 @@ use std::{fmt, str::FromStr};
 pub struct MetadataView {
     epoch_ending_backups: Vec<EpochEndingBackupMeta>,
     state_snapshot_backups: Vec<StateSnapshotBackupMeta>,
+    incremental_state_snapshot_backups: Vec<IncrementalStateSnapshotBackupMeta>,
     transaction_backups: Vec<TransactionBackupMeta>,
 }
 
@@ -47,28 +52,73 @@ impl MetadataView {
             .map(Clone::clone))
     }
 
+    /// Selects the latest incremental state snapshot at or before `target_version`, among the
+    /// ones whose full base state snapshot is still there.
+    pub fn select_incremental_state_snapshot(
+        &self,
+        target_version: Version,
+    ) -> Result<Option<IncrementalStateSnapshotBackupMeta>> {
+        Ok(self
+            .incremental_state_snapshot_backups
+            .iter()
+            .sorted()
+            .rev()
+            .filter(|m| self.has_state_snapshot_at(m.base_snapshot_version))
+            .find(|m| m.version <= target_version)
+            .map(Clone::clone))
+    }
+
+    /// Selects the latest state snapshot, full or incremental, before `version`, for a new
+    /// incremental state snapshot at `version` to be based on. Returns its version and manifest.
+    pub fn select_incremental_state_snapshot_base(
+        &self,
+        version: Version,
+    ) -> Result<Option<(Version, FileHandle)>> {
+        let target_version = match version.checked_sub(1) {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let full = self.select_state_snapshot(target_version)?;
+        let incremental = self.select_incremental_state_snapshot(target_version)?;
+        Ok(match (full, incremental) {
+            (Some(f), Some(i)) if i.version > f.version => Some((i.version, i.manifest)),
+            (Some(f), _) => Some((f.version, f.manifest)),
+            (None, Some(i)) => Some((i.version, i.manifest)),
+            (None, None) => None,
+        })
+    }
+
+    fn has_state_snapshot_at(&self, version: Version) -> bool {
+        self.state_snapshot_backups
+            .iter()
+            .any(|s| s.version == version)
+    }
+
     pub fn select_transaction_backups(
         &self,
         target_version: Version,
     ) -> Result<Vec<TransactionBackupMeta>> {
         // This can be more flexible, but for now we assume and check backups are continuous in
-        // range (which is always true when we backup from a single backup coordinator)
-        let mut next_ver = 0;
+        // range (which is always true when we backup from a single backup coordinator). They
+        // start after genesis if a retention policy deleted the older ones.
+        let mut next_ver = None;
         let mut res = Vec::new();
         for backup in self.transaction_backups.iter().sorted() {
             if backup.first_version > target_version {
                 break;
             }
-            ensure!(
-                backup.first_version == next_ver,
-                "Transactioon backup ranges not continuous, expecting version {}, got {}.",
-                next_ver,
-                backup.first_version,
-            );
+            if let Some(next_ver) = next_ver {
+                ensure!(
+                    backup.first_version == next_ver,
+                    "Transactioon backup ranges not continuous, expecting version {}, got {}.",
+                    next_ver,
+                    backup.first_version,
+                );
+            }
 
             res.push(backup.clone());
 
-            next_ver = backup.last_version + 1;
+            next_ver = Some(backup.last_version + 1);
         }
 
         Ok(res)
@@ -100,25 +150,112 @@ impl MetadataView {
 
         Ok(res)
     }
+
+    /// Checks the backups are continuous, and that every state snapshot can be restored, i.e.
+    /// transaction backups are not missing after it.
+    pub fn check_restorable(&self) -> Result<()> {
+        let ver_max = Version::max_value();
+        self.select_epoch_ending_backups(ver_max)?;
+        let transactions = self.select_transaction_backups(ver_max)?;
+        if let Some(first) = transactions.first() {
+            ensure!(
+                first.first_version == 0 || !self.state_snapshot_backups.is_empty(),
+                "Transaction backups start at version {} but there's no state snapshot.",
+                first.first_version,
+            );
+            for snapshot in &self.state_snapshot_backups {
+                ensure!(
+                    first.first_version <= snapshot.version + 1,
+                    "Transaction backups start at version {}, can't replay them on top of the \
+                    state snapshot at version {}.",
+                    first.first_version,
+                    snapshot.version,
+                );
+            }
+        }
+        for snapshot in &self.incremental_state_snapshot_backups {
+            ensure!(
+                self.has_state_snapshot_at(snapshot.base_snapshot_version),
+                "Incremental state snapshot at version {} is based on the state snapshot at \
+                version {}, which is missing.",
+                snapshot.version,
+                snapshot.base_snapshot_version,
+            );
+        }
+        Ok(())
+    }
+
+    pub fn state_snapshot_backups(&self) -> &[StateSnapshotBackupMeta] {
+        &self.state_snapshot_backups
+    }
+
+    pub fn incremental_state_snapshot_backups(&self) -> &[IncrementalStateSnapshotBackupMeta] {
+        &self.incremental_state_snapshot_backups
+    }
+
+    pub fn transaction_backups(&self) -> &[TransactionBackupMeta] {
+        &self.transaction_backups
+    }
+
+    /// All the metadata entries, to be saved again in compacted metadata files.
+    pub(crate) fn to_metadata_vec(&self) -> Vec<Metadata> {
+        self.epoch_ending_backups
+            .iter()
+            .cloned()
+            .map(Metadata::EpochEndingBackup)
+            .chain(
+                self.state_snapshot_backups
+                    .iter()
+                    .cloned()
+                    .map(Metadata::StateSnapshotBackup),
+            )
+            .chain(
+                self.incremental_state_snapshot_backups
+                    .iter()
+                    .cloned()
+                    .map(Metadata::IncrementalStateSnapshotBackup),
+            )
+            .chain(
+                self.transaction_backups
+                    .iter()
+                    .cloned()
+                    .map(Metadata::TransactionBackup),
+            )
+            .collect()
+    }
 }
 
 impl From<Vec<Metadata>> for MetadataView {
     fn from(metadata_vec: Vec<Metadata>) -> Self {
         let mut epoch_ending_backups = Vec::new();
         let mut state_snapshot_backups = Vec::new();
+        let mut incremental_state_snapshot_backups = Vec::new();
         let mut transaction_backups = Vec::new();
 
         for meta in metadata_vec {
             match meta {
                 Metadata::EpochEndingBackup(e) => epoch_ending_backups.push(e),
                 Metadata::StateSnapshotBackup(s) => state_snapshot_backups.push(s),
+                Metadata::IncrementalStateSnapshotBackup(s) => {
+                    incremental_state_snapshot_backups.push(s)
+                }
                 Metadata::TransactionBackup(t) => transaction_backups.push(t),
             }
         }
+        // The same entry can be in multiple metadata files if a compaction was interrupted.
+        epoch_ending_backups.sort();
+        epoch_ending_backups.dedup();
+        state_snapshot_backups.sort();
+        state_snapshot_backups.dedup();
+        incremental_state_snapshot_backups.sort();
+        incremental_state_snapshot_backups.dedup();
+        transaction_backups.sort();
+        transaction_backups.dedup();
 
         Self {
             epoch_ending_backups,
             state_snapshot_backups,
+            incremental_state_snapshot_backups,
             transaction_backups,
         }
     }
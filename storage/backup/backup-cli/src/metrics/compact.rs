@@ -0,0 +1,45 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use diem_secure_push_metrics::{register_int_gauge, IntGauge};
+use once_cell::sync::Lazy;
+
+pub static COMPACT_COORDINATOR_START_TS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "diem_db_backup_compact_coordinator_start_timestamp_s",
+        "Timestamp when the compact coordinator starts."
+    )
+    .unwrap()
+});
+
+pub static COMPACT_COORDINATOR_SUCC_TS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "diem_db_backup_compact_coordinator_succeed_timestamp_s",
+        "Timestamp when the compact coordinator succeeds."
+    )
+    .unwrap()
+});
+
+pub static COMPACT_COORDINATOR_FAIL_TS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "diem_db_backup_compact_coordinator_fail_timestamp_s",
+        "Timestamp when the compact coordinator fails."
+    )
+    .unwrap()
+});
+
+pub static COMPACT_METADATA_FILES: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "diem_db_backup_compact_metadata_files",
+        "Number of metadata files compacted in the last run."
+    )
+    .unwrap()
+});
+
+pub static COMPACT_DELETED_FILES: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "diem_db_backup_compact_deleted_files",
+        "Number of backup files deleted by the retention policy in the last run."
+    )
+    .unwrap()
+});
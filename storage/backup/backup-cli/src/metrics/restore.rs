@@ -60,6 +60,14 @@ pub static TRANSACTION_SAVE_VERSION: Lazy<IntGauge> = Lazy::new(|| {
     .unwrap()
 });
 
+pub static TRANSACTION_VERIFIED_VERSION: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "diem_db_restore_transaction_verified_version",
+        "Highest version of the transactions loaded and verified, ahead of those being applied."
+    )
+    .unwrap()
+});
+
 pub static TRANSACTION_REPLAY_VERSION: Lazy<IntGauge> = Lazy::new(|| {
     register_int_gauge!(
         "diem_db_restore_transaction_replay_version",
@@ -59,14 +59,24 @@ pub struct Commands {
     ///     $FILE_NAME
     /// expected stdout to stream out bytes of the file.
     pub open_for_read: String,
-    /// Command line to save a line of metadata
+    /// Command line to save a line of metadata, also used to save multiple lines to a new file
+    /// when compacting metadata.
     /// input env vars:
     ///     $FILE_NAME
-    /// stdin will be fed with a line of text with a trailing newline.
+    /// stdin will be fed with lines of text, each with a trailing newline.
     pub save_metadata_line: String,
     /// Command line to list all existing metadata file handles.
     /// expected stdout to stream out lines of file handles.
     pub list_metadata_files: String,
+    /// Optional, command line to move a compacted metadata file out of the ones listed by
+    /// `list_metadata_files`. Required to compact metadata.
+    /// input env vars:
+    ///     $FILE_HANDLE
+    pub backup_metadata_file: Option<String>,
+    /// Optional, command line to delete a file. Required to enforce retention policies.
+    /// input env vars:
+    ///     $FILE_HANDLE
+    pub delete_file: Option<String>,
 }
 
 #[derive(Clone, Default, Deserialize)]
@@ -10,12 +10,12 @@ use crate::{
     storage::{BackupStorage, ShellSafeName, TextLine},
     utils::{error_notes::ErrorNotes, path_exists, PathToString},
 };
-use anyhow::Result;
+use anyhow::{anyhow, ensure, Result};
 use async_trait::async_trait;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use structopt::StructOpt;
 use tokio::{
-    fs::{create_dir, create_dir_all, read_dir, OpenOptions},
+    fs::{create_dir, create_dir_all, read_dir, remove_file, rename, OpenOptions},
     io::{AsyncRead, AsyncWrite, AsyncWriteExt},
 };
 
@@ -37,6 +37,7 @@ pub struct LocalFs {
 
 impl LocalFs {
     const METADATA_DIR: &'static str = "metadata";
+    const METADATA_BACKUP_DIR: &'static str = "metadata_backup";
 
     pub fn new(dir: PathBuf) -> Self {
         Self { dir }
@@ -92,21 +93,8 @@ impl BackupStorage for LocalFs {
     }
 
     async fn save_metadata_line(&self, name: &ShellSafeName, content: &TextLine) -> Result<()> {
-        let dir = self.metadata_dir();
-        create_dir_all(&dir).await.err_notes(name)?; // in case not yet created
-
-        let path = dir.join(name.as_ref());
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create_new(true)
-            .open(&path)
-            .await
-            .err_notes(&path)?;
-        file.write_all(content.as_ref().as_bytes())
+        self.save_metadata_lines(name, std::slice::from_ref(content))
             .await
-            .err_notes(&path)?;
-
-        Ok(())
     }
 
     async fn list_metadata_files(&self) -> Result<Vec<FileHandle>> {
@@ -122,4 +110,56 @@ impl BackupStorage for LocalFs {
         }
         Ok(res)
     }
+
+    async fn save_metadata_lines(&self, name: &ShellSafeName, lines: &[TextLine]) -> Result<()> {
+        let dir = self.metadata_dir();
+        create_dir_all(&dir).await.err_notes(name)?; // in case not yet created
+
+        let path = dir.join(name.as_ref());
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .await
+            .err_notes(&path)?;
+        for line in lines {
+            file.write_all(line.as_ref().as_bytes())
+                .await
+                .err_notes(&path)?;
+        }
+        file.shutdown().await.err_notes(&path)?;
+
+        Ok(())
+    }
+
+    async fn backup_metadata_file(&self, file_handle: &FileHandleRef) -> Result<()> {
+        let dir = self.dir.join(Self::METADATA_BACKUP_DIR);
+        create_dir_all(&dir).await.err_notes(&dir)?; // in case not yet created
+
+        let name = Path::new(file_handle)
+            .file_name()
+            .ok_or_else(|| anyhow!("Bad metadata file handle: {}", file_handle))?;
+        let from = self.dir.join(file_handle);
+        let to = dir.join(name);
+        rename(&from, &to).await.err_notes((&from, &to))?;
+
+        Ok(())
+    }
+
+    async fn delete_file(&self, file_handle: &FileHandleRef) -> Result<()> {
+        // Only ever delete inside the backup dir.
+        let rel_path = Path::new(file_handle);
+        ensure!(
+            rel_path.components().next().is_some()
+                && rel_path
+                    .components()
+                    .all(|c| matches!(c, Component::Normal(_))),
+            "Bad file handle to delete: {}",
+            file_handle
+        );
+        let path = self.dir.join(rel_path);
+        remove_file(&path).await.err_notes(&path)?;
+
+        Ok(())
+    }
 }
@@ -3,8 +3,8 @@
 
 use super::*;
 use crate::storage::test_util::{
-    arb_backups, arb_metadata_files, test_save_and_list_metadata_files_impl,
-    test_write_and_read_impl,
+    arb_backups, arb_metadata_files, test_compact_metadata_files_impl,
+    test_save_and_list_metadata_files_impl, test_write_and_read_impl,
 };
 use diem_temppath::TempPath;
 use proptest::prelude::*;
@@ -36,4 +36,35 @@ proptest! {
         let rt = Runtime::new().unwrap();
         rt.block_on(test_save_and_list_metadata_files_impl(Box::new(store), input));
     }
+
+    #[test]
+    fn test_compact_metadata_files(
+        input in arb_metadata_files(),
+    ) {
+        let tmpdir = TempPath::new();
+        tmpdir.create_as_dir().unwrap();
+        let store = LocalFs::new(tmpdir.path().to_path_buf());
+
+        let rt = Runtime::new().unwrap();
+        rt.block_on(test_compact_metadata_files_impl(Box::new(store), input));
+    }
+}
+
+#[test]
+fn test_delete_file_outside_dir_rejected() {
+    let tmpdir = TempPath::new();
+    tmpdir.create_as_dir().unwrap();
+    let store = LocalFs::new(tmpdir.path().join("backups"));
+    std::fs::create_dir(tmpdir.path().join("backups")).unwrap();
+    let outside = tmpdir.path().join("outside");
+    std::fs::write(&outside, b"keep").unwrap();
+    std::fs::write(tmpdir.path().join("backups/file"), b"delete").unwrap();
+
+    let rt = Runtime::new().unwrap();
+    for handle in &["../outside", outside.to_str().unwrap(), "", "./file"] {
+        assert!(rt.block_on(store.delete_file(handle)).is_err());
+    }
+    assert!(outside.exists());
+    rt.block_on(store.delete_file("file")).unwrap();
+    assert!(!tmpdir.path().join("backups/file").exists());
 }
@@ -3,6 +3,8 @@
 
 pub mod command_adapter;
 pub mod local_fs;
+pub mod s3;
+pub mod sealed;
 
 #[cfg(test)]
 mod test_util;
@@ -12,6 +14,8 @@ mod tests;
 use crate::storage::{
     command_adapter::{CommandAdapter, CommandAdapterOpt},
     local_fs::{LocalFs, LocalFsOpt},
+    s3::{S3Opt, S3},
+    sealed::{SealOpt, SealedStorage},
 };
 use anyhow::{ensure, Result};
 use async_trait::async_trait;
@@ -167,21 +171,54 @@ pub trait BackupStorage: Send + Sync {
     ///   2. But the cache does expect the content stays the same for a file handle, so when
     /// reorganising metadata files, give them new unique names.
     async fn list_metadata_files(&self) -> Result<Vec<FileHandle>>;
+    /// Asks to save multiple metadata entries into one new metadata file, used when compacting
+    /// metadata. Same as `save_metadata_line`, the name is expected to be new.
+    async fn save_metadata_lines(&self, name: &ShellSafeName, lines: &[TextLine]) -> Result<()>;
+    /// Moves a metadata file out of the ones returned by `list_metadata_files`, once its content is
+    /// saved in a compacted file. The file is kept elsewhere instead of deleted, so a bad
+    /// compaction can be reverted by hand.
+    async fn backup_metadata_file(&self, file_handle: &FileHandleRef) -> Result<()>;
+    /// Deletes a file created by `create_for_write`, used to enforce retention policies. The
+    /// metadata referring to the file must have been compacted away before this is called.
+    async fn delete_file(&self, file_handle: &FileHandleRef) -> Result<()>;
 }
 
 #[derive(StructOpt)]
 pub enum StorageOpt {
     #[structopt(about = "Select the LocalFs backup store.")]
-    LocalFs(LocalFsOpt),
+    LocalFs {
+        #[structopt(flatten)]
+        opt: LocalFsOpt,
+        #[structopt(flatten)]
+        seal: SealOpt,
+    },
     #[structopt(about = "Select the CommandAdapter backup store.")]
-    CommandAdapter(CommandAdapterOpt),
+    CommandAdapter {
+        #[structopt(flatten)]
+        opt: CommandAdapterOpt,
+        #[structopt(flatten)]
+        seal: SealOpt,
+    },
+    #[structopt(about = "Select the native S3 compatible backup store.")]
+    S3 {
+        #[structopt(flatten)]
+        opt: S3Opt,
+        #[structopt(flatten)]
+        seal: SealOpt,
+    },
 }
 
 impl StorageOpt {
+    /// Files and metadata lines are always read through `SealedStorage`, so sealed backups are
+    /// opened whether or not new ones are sealed.
     pub async fn init_storage(self) -> Result<Arc<dyn BackupStorage>> {
-        Ok(match self {
-            StorageOpt::LocalFs(opt) => Arc::new(LocalFs::new_with_opt(opt)),
-            StorageOpt::CommandAdapter(opt) => Arc::new(CommandAdapter::new_with_opt(opt).await?),
-        })
+        let (inner, seal): (Arc<dyn BackupStorage>, _) = match self {
+            StorageOpt::LocalFs { opt, seal } => (Arc::new(LocalFs::new_with_opt(opt)), seal),
+            StorageOpt::CommandAdapter { opt, seal } => {
+                (Arc::new(CommandAdapter::new_with_opt(opt).await?), seal)
+            }
+            StorageOpt::S3 { opt, seal } => (Arc::new(S3::new_with_opt(opt)?), seal),
+        };
+        Ok(Arc::new(SealedStorage::new_with_opt(inner, seal)?))
     }
 }
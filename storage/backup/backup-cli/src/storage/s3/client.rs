@@ -0,0 +1,643 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A minimal client of the S3 REST API, covering only what `S3` needs. Requests are signed with
+//! AWS Signature Version 4 and addressed path-style, i.e. "{endpoint}/{bucket}/{key}", which S3
+//! compatible services like MinIO support as well.
+
+use anyhow::{anyhow, bail, ensure, Result};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use diem_logger::prelude::*;
+use futures::Future;
+use hmac::{Hmac, Mac, NewMac};
+use rand::Rng;
+use reqwest::{header::HeaderMap, Method, StatusCode, Url};
+use sha2::{Digest, Sha256};
+use std::{cmp::min, collections::BTreeMap, fmt, time::Duration};
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+const MIN_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_BACKOFF: Duration = Duration::from_secs(20);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+/// An error response from the service.
+#[derive(Debug)]
+pub struct S3Error {
+    pub status: StatusCode,
+    pub code: String,
+    pub message: String,
+}
+
+impl S3Error {
+    /// Throttling and server side errors are worth retrying.
+    fn is_transient(&self) -> bool {
+        self.status.is_server_error() || self.status == StatusCode::TOO_MANY_REQUESTS
+    }
+}
+
+impl fmt::Display for S3Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "S3 error {} {}: {}",
+            self.status, self.code, self.message
+        )
+    }
+}
+
+impl std::error::Error for S3Error {}
+
+/// A byte range of an object, with the size and ETag of the whole object.
+pub struct ObjectRange {
+    pub data: Bytes,
+    pub object_size: u64,
+    pub etag: Option<String>,
+}
+
+pub struct S3Client {
+    http: reqwest::Client,
+    endpoint: Url,
+    bucket: String,
+    region: String,
+    credentials: Credentials,
+    max_retries: usize,
+}
+
+impl S3Client {
+    pub fn new(
+        endpoint: &str,
+        bucket: String,
+        region: String,
+        credentials: Credentials,
+        max_retries: usize,
+        request_timeout: Duration,
+    ) -> Result<Self> {
+        let endpoint = Url::parse(endpoint)?;
+        ensure!(
+            endpoint.host_str().is_some() && endpoint.path() == "/",
+            "S3 endpoint must be like \"https://host[:port]\", got {}.",
+            endpoint,
+        );
+        // A stalled connection times out, which is retried like other transient errors.
+        let http = reqwest::Client::builder()
+            .no_proxy()
+            .use_rustls_tls()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(request_timeout)
+            .build()?;
+        Ok(Self {
+            http,
+            endpoint,
+            bucket,
+            region,
+            credentials,
+            max_retries,
+        })
+    }
+
+    pub async fn put_object(&self, key: &str, body: Bytes) -> Result<()> {
+        let body = &body;
+        self.retry("PutObject", key, || async move {
+            self.send(Method::PUT, key, &[], HeaderMap::new(), body.clone())
+                .await
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Starts a multipart upload, returning the upload ID.
+    ///
+    /// Unlike the other requests this one isn't idempotent, a retry after a request timed out or
+    /// lost its response could leave the upload it did create behind, never completed nor aborted.
+    /// So it's only retried when rejected by the service.
+    pub async fn create_multipart_upload(&self, key: &str) -> Result<String> {
+        let res = self
+            .retry_when(
+                "CreateMultipartUpload",
+                key,
+                is_transient_rejection,
+                || async move {
+                    let res = self
+                        .send(
+                            Method::POST,
+                            key,
+                            &[("uploads", "")],
+                            HeaderMap::new(),
+                            Bytes::new(),
+                        )
+                        .await?;
+                    Ok(res.text().await?)
+                },
+            )
+            .await?;
+        xml_values(&res, "UploadId")
+            .pop()
+            .ok_or_else(|| anyhow!("No UploadId in response: {}", res))
+    }
+
+    /// Uploads a part, returning its ETag. Part numbers start from 1.
+    pub async fn upload_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: usize,
+        body: Bytes,
+    ) -> Result<String> {
+        let part_number = part_number.to_string();
+        let query = [
+            ("partNumber", part_number.as_str()),
+            ("uploadId", upload_id),
+        ];
+        let (query, body) = (&query, &body);
+        let res = self
+            .retry("UploadPart", key, || async move {
+                self.send(Method::PUT, key, query, HeaderMap::new(), body.clone())
+                    .await
+            })
+            .await?;
+        Ok(res
+            .headers()
+            .get("ETag")
+            .ok_or_else(|| anyhow!("No ETag in UploadPart response."))?
+            .to_str()?
+            .to_string())
+    }
+
+    pub async fn complete_multipart_upload(
+        &self,
+        key: &str,
+        upload_id: &str,
+        etags: &[String],
+    ) -> Result<()> {
+        let parts = etags
+            .iter()
+            .enumerate()
+            .map(|(i, etag)| {
+                format!(
+                    "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+                    i + 1,
+                    xml_escape(etag)
+                )
+            })
+            .collect::<String>();
+        let body = Bytes::from(format!(
+            "<CompleteMultipartUpload>{}</CompleteMultipartUpload>",
+            parts
+        ));
+        let body = &body;
+        self.retry("CompleteMultipartUpload", key, || async move {
+            let res = self
+                .send(
+                    Method::POST,
+                    key,
+                    &[("uploadId", upload_id)],
+                    HeaderMap::new(),
+                    body.clone(),
+                )
+                .await?;
+            // Errors can come after the 200 OK status, in the body.
+            check_error_in_body(res).await
+        })
+        .await
+    }
+
+    pub async fn abort_multipart_upload(&self, key: &str, upload_id: &str) -> Result<()> {
+        self.retry("AbortMultipartUpload", key, || async move {
+            self.send(
+                Method::DELETE,
+                key,
+                &[("uploadId", upload_id)],
+                HeaderMap::new(),
+                Bytes::new(),
+            )
+            .await
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Gets `len` bytes from `offset`, or less if the object ends before that. If `if_match` is
+    /// given, fails if the object no longer has that ETag.
+    pub async fn get_range(
+        &self,
+        key: &str,
+        offset: u64,
+        len: u64,
+        if_match: Option<&str>,
+    ) -> Result<ObjectRange> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "Range",
+            format!("bytes={}-{}", offset, offset + len - 1).parse()?,
+        );
+        if let Some(etag) = if_match {
+            headers.insert("If-Match", etag.parse()?);
+        }
+
+        let headers = &headers;
+        self.retry("GetObject", key, || async move {
+            let res = match self
+                .send(Method::GET, key, &[], headers.clone(), Bytes::new())
+                .await
+            {
+                Ok(res) => res,
+                // S3 refuses any range of an empty object.
+                Err(e)
+                    if offset == 0 && status_of(&e) == Some(StatusCode::RANGE_NOT_SATISFIABLE) =>
+                {
+                    return Ok(ObjectRange {
+                        data: Bytes::new(),
+                        object_size: 0,
+                        etag: None,
+                    });
+                }
+                Err(e) => return Err(e),
+            };
+            let partial = res.status() == StatusCode::PARTIAL_CONTENT;
+            let content_range = res
+                .headers()
+                .get("Content-Range")
+                .map(|v| v.to_str().map(str::to_string))
+                .transpose()?;
+            let etag = res
+                .headers()
+                .get("ETag")
+                .map(|v| v.to_str().map(str::to_string))
+                .transpose()?;
+            let data = res.bytes().await?;
+
+            let object_size = if partial {
+                let content_range = content_range
+                    .ok_or_else(|| anyhow!("No Content-Range in partial GetObject response."))?;
+                parse_object_size(&content_range)?
+            } else {
+                // The whole object is returned when the range is not supported.
+                ensure!(offset == 0, "Range of object not supported.");
+                data.len() as u64
+            };
+            let expected_len = min(len, object_size.saturating_sub(offset));
+            ensure!(
+                data.len() as u64 == expected_len || (!partial && offset == 0),
+                "Expecting {} bytes from offset {}, got {}.",
+                expected_len,
+                offset,
+                data.len(),
+            );
+            Ok(ObjectRange {
+                data,
+                object_size,
+                etag,
+            })
+        })
+        .await
+    }
+
+    /// Lists keys starting with `prefix`, in ascending order.
+    pub async fn list_objects(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut keys = Vec::new();
+        let mut continuation_token: Option<String> = None;
+        loop {
+            let mut query = vec![("list-type", "2"), ("prefix", prefix)];
+            if let Some(token) = &continuation_token {
+                query.push(("continuation-token", token));
+            }
+            let query = &query;
+            let res = self
+                .retry("ListObjectsV2", prefix, || async move {
+                    let res = self
+                        .send(Method::GET, "", query, HeaderMap::new(), Bytes::new())
+                        .await?;
+                    Ok(res.text().await?)
+                })
+                .await?;
+
+            keys.extend(xml_values(&res, "Key"));
+            if xml_values(&res, "IsTruncated").first().map(String::as_str) != Some("true") {
+                break;
+            }
+            continuation_token = Some(
+                xml_values(&res, "NextContinuationToken")
+                    .pop()
+                    .ok_or_else(|| anyhow!("Truncated listing without continuation token."))?,
+            );
+        }
+        Ok(keys)
+    }
+
+    pub async fn copy_object(&self, from_key: &str, to_key: &str) -> Result<()> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-amz-copy-source",
+            format!("/{}/{}", self.bucket, uri_encode(from_key, false)).parse()?,
+        );
+        let headers = &headers;
+        self.retry("CopyObject", to_key, || async move {
+            let res = self
+                .send(Method::PUT, to_key, &[], headers.clone(), Bytes::new())
+                .await?;
+            // Errors can come after the 200 OK status, in the body.
+            check_error_in_body(res).await
+        })
+        .await
+    }
+
+    pub async fn delete_object(&self, key: &str) -> Result<()> {
+        self.retry("DeleteObject", key, || async move {
+            self.send(Method::DELETE, key, &[], HeaderMap::new(), Bytes::new())
+                .await
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Runs `f` until it succeeds, retrying transient errors with exponential backoff.
+    async fn retry<T, F, Fut>(&self, op: &str, key: &str, f: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.retry_when(op, key, is_transient, f).await
+    }
+
+    /// Runs `f` until it succeeds, retrying the errors `should_retry` accepts with exponential
+    /// backoff.
+    async fn retry_when<T, F, Fut>(
+        &self,
+        op: &str,
+        key: &str,
+        should_retry: fn(&anyhow::Error) -> bool,
+        mut f: F,
+    ) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut backoff = MIN_BACKOFF;
+        let mut attempt = 0;
+        loop {
+            match f().await {
+                Ok(res) => return Ok(res),
+                Err(e) if attempt < self.max_retries && should_retry(&e) => {
+                    attempt += 1;
+                    warn!(
+                        key = key,
+                        attempt = attempt,
+                        "S3 {} failed: {}. Retrying in {:?}.",
+                        op,
+                        e,
+                        backoff,
+                    );
+                    // Full jitter, so clients throttled together don't retry together.
+                    let delay = backoff.mul_f64(rand::thread_rng().gen_range(0.5..1.0));
+                    tokio::time::sleep(delay).await;
+                    backoff = min(backoff * 2, MAX_BACKOFF);
+                }
+                Err(e) => return Err(e.context(format!("S3 {} on {} failed.", op, key))),
+            }
+        }
+    }
+
+    /// Sends a signed request once, turning an error status into `S3Error`.
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        query: &[(&str, &str)],
+        mut headers: HeaderMap,
+        body: Bytes,
+    ) -> Result<reqwest::Response> {
+        let path = if key.is_empty() {
+            format!("/{}", self.bucket)
+        } else {
+            format!("/{}/{}", self.bucket, key)
+        };
+        let canonical_path = uri_encode(&path, false);
+        let canonical_query = canonical_query(query);
+        let mut url = self.endpoint.join(&canonical_path)?;
+        if !canonical_query.is_empty() {
+            url.set_query(Some(&canonical_query));
+        }
+
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().expect("Checked."), port),
+            None => url.host_str().expect("Checked.").to_string(),
+        };
+        let mut signed_headers = BTreeMap::new();
+        signed_headers.insert("host".to_string(), host);
+        for (name, value) in headers.iter() {
+            if name.as_str().starts_with("x-amz-") {
+                signed_headers.insert(name.to_string(), value.to_str()?.to_string());
+            }
+        }
+        let authorization = sign(
+            &self.credentials,
+            &self.region,
+            Utc::now(),
+            method.as_str(),
+            &canonical_path,
+            &canonical_query,
+            &mut signed_headers,
+            &hex::encode(Sha256::digest(&body)),
+        );
+        for (name, value) in signed_headers {
+            if name != "host" {
+                headers.insert(
+                    reqwest::header::HeaderName::from_bytes(name.as_bytes())?,
+                    value.parse()?,
+                );
+            }
+        }
+        headers.insert("Authorization", authorization.parse()?);
+
+        let res = self
+            .http
+            .request(method, url)
+            .headers(headers)
+            .body(body)
+            .send()
+            .await?;
+        if res.status().is_success() {
+            return Ok(res);
+        }
+        let status = res.status();
+        let text = res.text().await.unwrap_or_default();
+        Err(S3Error {
+            status,
+            code: xml_values(&text, "Code").pop().unwrap_or_default(),
+            message: xml_values(&text, "Message").pop().unwrap_or(text),
+        }
+        .into())
+    }
+}
+
+fn status_of(e: &anyhow::Error) -> Option<StatusCode> {
+    e.downcast_ref::<S3Error>().map(|e| e.status)
+}
+
+fn is_transient(e: &anyhow::Error) -> bool {
+    if let Some(e) = e.downcast_ref::<S3Error>() {
+        e.is_transient()
+    } else {
+        // Failed to connect, timed out, connection dropped while reading the body, etc.
+        e.downcast_ref::<reqwest::Error>().is_some()
+    }
+}
+
+/// A transient error the service answered with, so the request didn't take effect.
+fn is_transient_rejection(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<S3Error>()
+        .map_or(false, S3Error::is_transient)
+}
+
+async fn check_error_in_body(res: reqwest::Response) -> Result<()> {
+    let status = res.status();
+    let text = res.text().await?;
+    if text.contains("<Error>") {
+        return Err(S3Error {
+            // Treated as a server error, as AWS suggests.
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            code: xml_values(&text, "Code").pop().unwrap_or_default(),
+            message: xml_values(&text, "Message").pop().unwrap_or(text),
+        }
+        .into());
+    }
+    ensure!(status.is_success(), "Unexpected status {}.", status);
+    Ok(())
+}
+
+/// Parses the object size out of Content-Range, like "bytes 0-99/1234".
+fn parse_object_size(content_range: &str) -> Result<u64> {
+    match content_range.rsplit_once('/') {
+        Some((_, size)) if size != "*" => Ok(size.parse()?),
+        _ => bail!("Bad Content-Range: {}", content_range),
+    }
+}
+
+/// Computes the `Authorization` header of AWS Signature Version 4, adding the other headers it
+/// requires to `headers`, which hold all the headers to sign, keyed by lowercase names.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn sign(
+    credentials: &Credentials,
+    region: &str,
+    now: DateTime<Utc>,
+    method: &str,
+    canonical_path: &str,
+    canonical_query: &str,
+    headers: &mut BTreeMap<String, String>,
+    payload_hash: &str,
+) -> String {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    headers.insert("x-amz-date".to_string(), amz_date.clone());
+    headers.insert("x-amz-content-sha256".to_string(), payload_hash.to_string());
+    if let Some(token) = &credentials.session_token {
+        headers.insert("x-amz-security-token".to_string(), token.clone());
+    }
+
+    let canonical_headers = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect::<String>();
+    let signed_headers = headers.keys().cloned().collect::<Vec<_>>().join(";");
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method, canonical_path, canonical_query, canonical_headers, signed_headers, payload_hash
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let string_to_sign = format!(
+        "{}\n{}\n{}\n{}",
+        ALGORITHM,
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let signing_key = [region, "s3", "aws4_request"].iter().fold(
+        hmac_sha256(
+            format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+            date.as_bytes(),
+        ),
+        |key, part| hmac_sha256(&key, part.as_bytes()),
+    );
+    let signature = hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+
+    format!(
+        "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+        ALGORITHM, credentials.access_key_id, scope, signed_headers, signature
+    )
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_varkey(key).expect("HMAC takes keys of any size.");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encodes all but the unreserved characters, and '/' unless `encode_slash`.
+pub(crate) fn uri_encode(s: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn canonical_query(query: &[(&str, &str)]) -> String {
+    let mut pairs = query
+        .iter()
+        .map(|(k, v)| (uri_encode(k, true), uri_encode(v, true)))
+        .collect::<Vec<_>>();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Values of all `<tag>` elements in the XML, good enough for the flat responses of S3.
+pub(crate) fn xml_values(xml: &str, tag: &str) -> Vec<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut values = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        match rest.find(&close) {
+            Some(end) => {
+                values.push(xml_unescape(&rest[..end]));
+                rest = &rest[end + close.len()..];
+            }
+            None => break,
+        }
+    }
+    values
+}
+
+fn xml_unescape(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
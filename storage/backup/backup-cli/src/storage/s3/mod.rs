@@ -0,0 +1,441 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+mod client;
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    storage::{
+        s3::client::{Credentials, S3Client},
+        BackupHandle, BackupHandleRef, BackupStorage, FileHandle, FileHandleRef, ShellSafeName,
+        TextLine,
+    },
+    utils::error_notes::ErrorNotes,
+};
+use anyhow::{anyhow, ensure, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use diem_logger::prelude::*;
+use futures::{
+    future::{self, BoxFuture, FutureExt},
+    ready,
+    stream::{self, StreamExt, TryStreamExt},
+};
+use std::{
+    cmp::min,
+    io,
+    mem::take,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use structopt::StructOpt;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_util::io::StreamReader;
+
+/// S3 refuses parts smaller than this, except the last one.
+const MIN_PART_SIZE: usize = 5 << 20;
+
+#[derive(StructOpt)]
+pub struct S3Opt {
+    #[structopt(long = "bucket", help = "Bucket to hold backups.")]
+    pub bucket: String,
+    #[structopt(
+        long = "prefix",
+        default_value = "",
+        help = "Prefix of the keys of everything stored, like \"backup1/e1\"."
+    )]
+    pub prefix: String,
+    #[structopt(
+        long = "region",
+        default_value = "us-east-1",
+        help = "Region of the bucket."
+    )]
+    pub region: String,
+    #[structopt(
+        long = "endpoint",
+        help = "Endpoint of an S3 compatible service, like \"http://localhost:9000\". \
+        Defaults to AWS S3 in the region."
+    )]
+    pub endpoint: Option<String>,
+    #[structopt(
+        long = "access-key-id",
+        env = "AWS_ACCESS_KEY_ID",
+        hide_env_values = true
+    )]
+    pub access_key_id: String,
+    #[structopt(
+        long = "secret-access-key",
+        env = "AWS_SECRET_ACCESS_KEY",
+        hide_env_values = true
+    )]
+    pub secret_access_key: String,
+    #[structopt(
+        long = "session-token",
+        env = "AWS_SESSION_TOKEN",
+        hide_env_values = true
+    )]
+    pub session_token: Option<String>,
+    #[structopt(
+        long = "part-size",
+        default_value = "16777216",
+        help = "Files are uploaded and downloaded in parts of this many bytes, at least 5MiB."
+    )]
+    pub part_size: usize,
+    #[structopt(
+        long = "concurrent-part-downloads",
+        default_value = "4",
+        help = "Number of parts of a file downloaded in parallel."
+    )]
+    pub concurrent_part_downloads: usize,
+    #[structopt(
+        long = "max-retries",
+        default_value = "5",
+        help = "Number of times a failed request is retried, with exponential backoff."
+    )]
+    pub max_retries: usize,
+    #[structopt(
+        long = "request-timeout-secs",
+        default_value = "300",
+        help = "Seconds before a request, including its body, times out and is retried. \
+        Should be long enough to transfer a part."
+    )]
+    pub request_timeout_secs: u64,
+}
+
+/// A storage backend that stores everything in an S3 bucket, or that of an S3 compatible service,
+/// under a key prefix. Laid out the same as `LocalFs`, with the prefix in place of the directory.
+pub struct S3 {
+    client: Arc<S3Client>,
+    /// Prepended to file handles to get the keys, empty or ends with '/'.
+    prefix: String,
+    part_size: usize,
+    concurrent_part_downloads: usize,
+}
+
+impl S3 {
+    const METADATA_DIR: &'static str = "metadata";
+    const METADATA_BACKUP_DIR: &'static str = "metadata_backup";
+
+    fn new(
+        client: S3Client,
+        prefix: &str,
+        part_size: usize,
+        concurrent_part_downloads: usize,
+    ) -> Result<Self> {
+        ensure!(part_size > 0, "--part-size must be greater than 0.");
+        ensure!(
+            concurrent_part_downloads > 0,
+            "--concurrent-part-downloads must be greater than 0."
+        );
+        let prefix = prefix.trim_matches('/');
+        let prefix = if prefix.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", prefix)
+        };
+        Ok(Self {
+            client: Arc::new(client),
+            prefix,
+            part_size,
+            concurrent_part_downloads,
+        })
+    }
+
+    pub fn new_with_opt(opt: S3Opt) -> Result<Self> {
+        ensure!(
+            opt.part_size >= MIN_PART_SIZE,
+            "--part-size must be at least {}.",
+            MIN_PART_SIZE
+        );
+        let endpoint = opt
+            .endpoint
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", opt.region));
+        let credentials = Credentials {
+            access_key_id: opt.access_key_id,
+            secret_access_key: opt.secret_access_key,
+            session_token: opt.session_token,
+        };
+        let client = S3Client::new(
+            &endpoint,
+            opt.bucket,
+            opt.region,
+            credentials,
+            opt.max_retries,
+            Duration::from_secs(opt.request_timeout_secs),
+        )?;
+        Self::new(
+            client,
+            &opt.prefix,
+            opt.part_size,
+            opt.concurrent_part_downloads,
+        )
+    }
+
+    fn key(&self, file_handle: &FileHandleRef) -> String {
+        format!("{}{}", self.prefix, file_handle)
+    }
+
+    fn metadata_file_handle(name: &str) -> FileHandle {
+        format!("{}/{}", Self::METADATA_DIR, name)
+    }
+}
+
+#[async_trait]
+impl BackupStorage for S3 {
+    async fn create_backup(&self, name: &ShellSafeName) -> Result<BackupHandle> {
+        // There are no directories to create in a bucket.
+        Ok(name.to_string())
+    }
+
+    async fn create_for_write(
+        &self,
+        backup_handle: &BackupHandleRef,
+        name: &ShellSafeName,
+    ) -> Result<(FileHandle, Box<dyn AsyncWrite + Send + Unpin>)> {
+        let file_handle = format!("{}/{}", backup_handle, name.as_ref());
+        let writer = S3Writer::new(self.client.clone(), self.key(&file_handle), self.part_size);
+        Ok((file_handle, Box::new(writer)))
+    }
+
+    async fn open_for_read(
+        &self,
+        file_handle: &FileHandleRef,
+    ) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+        let key = self.key(file_handle);
+        let part_size = self.part_size as u64;
+        // The first part tells the object size, the rest are downloaded in parallel. They are
+        // asked to match the ETag of the first one, in case the object is overwritten meanwhile.
+        let first = self
+            .client
+            .get_range(&key, 0, part_size, None)
+            .await
+            .err_notes(&key)?;
+        let object_size = first.object_size;
+        let etag = first.etag;
+        let client = self.client.clone();
+        let rest = stream::iter((first.data.len() as u64..object_size).step_by(self.part_size))
+            .map(move |offset| {
+                let client = client.clone();
+                let key = key.clone();
+                let etag = etag.clone();
+                async move {
+                    let len = min(part_size, object_size - offset);
+                    client
+                        .get_range(&key, offset, len, etag.as_deref())
+                        .await
+                        .err_notes(&key)
+                        .map(|range| range.data)
+                }
+            })
+            .buffered(self.concurrent_part_downloads);
+        let parts = stream::once(future::ready(Ok(first.data)))
+            .chain(rest)
+            .map_err(to_io_error)
+            .boxed();
+        Ok(Box::new(StreamReader::new(parts)))
+    }
+
+    async fn save_metadata_line(&self, name: &ShellSafeName, content: &TextLine) -> Result<()> {
+        self.save_metadata_lines(name, std::slice::from_ref(content))
+            .await
+    }
+
+    async fn list_metadata_files(&self) -> Result<Vec<FileHandle>> {
+        let prefix = self.key(&Self::metadata_file_handle(""));
+        Ok(self
+            .client
+            .list_objects(&prefix)
+            .await
+            .err_notes(&prefix)?
+            .into_iter()
+            .filter_map(|key| key.strip_prefix(&self.prefix).map(str::to_string))
+            .collect())
+    }
+
+    async fn save_metadata_lines(&self, name: &ShellSafeName, lines: &[TextLine]) -> Result<()> {
+        let content = lines.iter().map(|l| l.as_ref()).collect::<String>();
+        let key = self.key(&Self::metadata_file_handle(name));
+        self.client
+            .put_object(&key, Bytes::from(content))
+            .await
+            .err_notes(&key)
+    }
+
+    async fn backup_metadata_file(&self, file_handle: &FileHandleRef) -> Result<()> {
+        let name = file_handle
+            .rsplit('/')
+            .next()
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| anyhow!("Bad metadata file handle: {}", file_handle))?;
+        let from = self.key(file_handle);
+        let to = self.key(&format!("{}/{}", Self::METADATA_BACKUP_DIR, name));
+        // No renaming in S3, copy and then delete.
+        self.client
+            .copy_object(&from, &to)
+            .await
+            .err_notes((&from, &to))?;
+        self.client.delete_object(&from).await.err_notes(&from)
+    }
+
+    async fn delete_file(&self, file_handle: &FileHandleRef) -> Result<()> {
+        let key = self.key(file_handle);
+        self.client.delete_object(&key).await.err_notes(&key)
+    }
+}
+
+/// Uploads what is written in parts of `part_size`, one part at a time while the next one is
+/// being buffered. A file not larger than a part is uploaded in a single request on shutdown,
+/// otherwise a multipart upload is completed on shutdown. Nothing is visible before that.
+struct S3Writer {
+    client: Arc<S3Client>,
+    key: String,
+    part_size: usize,
+    buf: Vec<u8>,
+    upload_id: Option<String>,
+    etags: Vec<String>,
+    /// Uploading a part, resolves to the upload ID and the ETag of the part.
+    uploading: Option<BoxFuture<'static, anyhow::Result<(String, String)>>>,
+    /// Uploading the last part and completing the upload.
+    finishing: Option<BoxFuture<'static, anyhow::Result<()>>>,
+    finished: bool,
+}
+
+impl S3Writer {
+    fn new(client: Arc<S3Client>, key: String, part_size: usize) -> Self {
+        Self {
+            client,
+            key,
+            part_size,
+            buf: Vec::new(),
+            upload_id: None,
+            etags: Vec::new(),
+            uploading: None,
+            finishing: None,
+            finished: false,
+        }
+    }
+
+    fn poll_uploading(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if let Some(uploading) = &mut self.uploading {
+            let res = ready!(uploading.poll_unpin(cx));
+            self.uploading = None;
+            let (upload_id, etag) = res.map_err(to_io_error)?;
+            self.upload_id = Some(upload_id);
+            self.etags.push(etag);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_uploading(&mut self) {
+        let client = self.client.clone();
+        let key = self.key.clone();
+        let upload_id = self.upload_id.clone();
+        let part_number = self.etags.len() + 1;
+        let data = Bytes::from(take(&mut self.buf));
+        self.uploading = Some(
+            async move {
+                let upload_id = match upload_id {
+                    Some(upload_id) => upload_id,
+                    None => client.create_multipart_upload(&key).await?,
+                };
+                let etag = client
+                    .upload_part(&key, &upload_id, part_number, data)
+                    .await?;
+                Ok((upload_id, etag))
+            }
+            .boxed(),
+        );
+    }
+
+    fn start_finishing(&mut self) {
+        let client = self.client.clone();
+        let key = self.key.clone();
+        let data = Bytes::from(take(&mut self.buf));
+        self.finishing = Some(match self.upload_id.take() {
+            None => async move { client.put_object(&key, data).await }.boxed(),
+            Some(upload_id) => {
+                let mut etags = take(&mut self.etags);
+                async move {
+                    let res = async {
+                        if !data.is_empty() {
+                            let part_number = etags.len() + 1;
+                            etags.push(
+                                client
+                                    .upload_part(&key, &upload_id, part_number, data)
+                                    .await?,
+                            );
+                        }
+                        client
+                            .complete_multipart_upload(&key, &upload_id, &etags)
+                            .await
+                    }
+                    .await;
+                    if res.is_err() {
+                        // Best effort, otherwise the uploaded parts are left to the lifecycle
+                        // rules of the bucket.
+                        if let Err(e) = client.abort_multipart_upload(&key, &upload_id).await {
+                            warn!(key = key, "Failed to abort multipart upload: {}", e);
+                        }
+                    }
+                    res
+                }
+                .boxed()
+            }
+        });
+    }
+}
+
+fn to_io_error(e: anyhow::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, format!("{:#}", e))
+}
+
+impl AsyncWrite for S3Writer {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        data: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.finishing.is_some() || this.finished {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Write after shutdown.",
+            )));
+        }
+        if this.buf.len() >= this.part_size {
+            ready!(this.poll_uploading(cx))?;
+            this.start_uploading();
+        }
+        let n = min(data.len(), this.part_size - this.buf.len());
+        this.buf.extend_from_slice(&data[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // Parts are uploaded as they fill up, the rest goes on shutdown.
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.finished {
+            return Poll::Ready(Ok(()));
+        }
+        ready!(this.poll_uploading(cx))?;
+        if this.finishing.is_none() {
+            this.start_finishing();
+        }
+        let res = ready!(this
+            .finishing
+            .as_mut()
+            .expect("Started above.")
+            .poll_unpin(cx));
+        this.finishing = None;
+        this.finished = true;
+        Poll::Ready(res.map_err(to_io_error))
+    }
+}
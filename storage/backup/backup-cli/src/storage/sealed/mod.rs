@@ -0,0 +1,646 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    storage::{
+        BackupHandle, BackupHandleRef, BackupStorage, FileHandle, FileHandleRef, ShellSafeName,
+        TextLine,
+    },
+    utils::error_notes::ErrorNotes,
+};
+use aes_gcm::{
+    aead::{generic_array::GenericArray, Aead, NewAead, Payload},
+    Aes256Gcm,
+};
+use anyhow::{anyhow, bail, ensure, Result};
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use diem_secure_storage::{KVStorage, Namespaced, OnDiskStorage};
+use futures::ready;
+use rand::RngCore;
+use std::{
+    cmp::min,
+    convert::TryInto,
+    fmt,
+    io::{self, Cursor, Read},
+    path::PathBuf,
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use structopt::StructOpt;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
+use tokio_util::{
+    codec::{Decoder, FramedRead},
+    io::StreamReader,
+};
+
+/// Length of the AES-256-GCM key.
+pub const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+/// Plaintext bytes sealed in one frame.
+const FRAME_SIZE: usize = 1 << 20;
+/// Sealed frames are at most a little larger than the plaintext, anything larger is corrupt.
+const MAX_SEALED_FRAME_SIZE: usize = 2 * FRAME_SIZE;
+const ZSTD_LEVEL: i32 = 3;
+/// Authenticated context of sealed metadata lines, which are not tied to a file.
+const METADATA_LINE_CONTEXT: &str = "metadata-line";
+
+pub type Key = [u8; KEY_LEN];
+
+/// How backup files and metadata lines are sealed. It is recorded in the file handles, hence in
+/// the manifests and metadata, so `db-restore` and `db-backup-verify` pick it up by themselves.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Scheme {
+    pub compress: bool,
+    pub encrypt: bool,
+}
+
+impl Scheme {
+    const ZSTD: &'static str = "zstd";
+    const AES_256_GCM: &'static str = "aes256gcm";
+
+    pub fn is_plain(&self) -> bool {
+        !self.compress && !self.encrypt
+    }
+}
+
+impl fmt::Display for Scheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if self.compress {
+            parts.push(Self::ZSTD);
+        }
+        if self.encrypt {
+            parts.push(Self::AES_256_GCM);
+        }
+        write!(f, "{}", parts.join("+"))
+    }
+}
+
+impl FromStr for Scheme {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut scheme = Scheme {
+            compress: false,
+            encrypt: false,
+        };
+        for part in s.split('+') {
+            match part {
+                Self::ZSTD => scheme.compress = true,
+                Self::AES_256_GCM => scheme.encrypt = true,
+                _ => bail!("Unknown backup sealing scheme: {}", s),
+            }
+        }
+        Ok(scheme)
+    }
+}
+
+#[derive(Clone, StructOpt)]
+pub struct SealOpt {
+    #[structopt(
+        long = "compress",
+        help = "Compress new backup files and metadata with zstd."
+    )]
+    pub compress: bool,
+    #[structopt(
+        long = "encrypt",
+        help = "Encrypt new backup files and metadata with AES-256-GCM. \
+        Requires --encryption-key-storage, which implies it."
+    )]
+    pub encrypt: bool,
+    #[structopt(
+        long = "encryption-key-storage",
+        parse(from_os_str),
+        help = "On-disk secure storage holding the hex encoded 32 bytes encryption key. \
+        Also required to restore or verify encrypted backups. With a key, only files and \
+        metadata encrypted with it are read."
+    )]
+    pub encryption_key_storage: Option<PathBuf>,
+    #[structopt(
+        long = "encryption-key-name",
+        default_value = "backup_encryption_key",
+        help = "Name of the encryption key in the secure storage."
+    )]
+    pub encryption_key_name: String,
+    #[structopt(
+        long = "encryption-key-namespace",
+        help = "Namespace of the encryption key in the secure storage, if any."
+    )]
+    pub encryption_key_namespace: Option<String>,
+}
+
+impl SealOpt {
+    fn load_key(&self) -> Result<Option<Key>> {
+        let path = match &self.encryption_key_storage {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        ensure!(path.exists(), "Secure storage {:?} not found.", path);
+        let storage = OnDiskStorage::new(path.clone());
+        let hex_key = match &self.encryption_key_namespace {
+            Some(namespace) => {
+                Namespaced::new(namespace, storage).get::<String>(&self.encryption_key_name)
+            }
+            None => storage.get::<String>(&self.encryption_key_name),
+        }
+        .map_err(|e| anyhow!("Failed to get the backup encryption key: {}", e))?
+        .value;
+        let key = hex::decode(hex_key.trim())?;
+        ensure!(
+            key.len() == KEY_LEN,
+            "Backup encryption key must be {} bytes, got {}.",
+            KEY_LEN,
+            key.len()
+        );
+        Ok(Some(key.as_slice().try_into()?))
+    }
+}
+
+/// A `BackupStorage` sealing the files and metadata lines it writes into another storage, and
+/// opening whatever sealed files and lines it reads from it. Plain ones are passed through,
+/// unless a key is configured, then anything not encrypted is refused.
+pub struct SealedStorage {
+    inner: Arc<dyn BackupStorage>,
+    /// Scheme of new files and metadata lines.
+    scheme: Scheme,
+    key: Option<Key>,
+    /// Refuse to read files and metadata lines which are not encrypted, so whoever can write to
+    /// the inner storage can't slip in plain ones.
+    require_sealed: bool,
+}
+
+impl SealedStorage {
+    /// Prefix of the handles of sealed files, followed by the scheme, ':' and the inner handle.
+    const FILE_PREFIX: &'static str = "sealed+";
+    /// Prefix of the metadata file handles, whose lines are opened when read.
+    const METADATA_PREFIX: &'static str = "sealed-metadata:";
+
+    /// With a key, only encrypted files and metadata lines are read, so new ones are always
+    /// encrypted as well.
+    pub fn new(
+        inner: Arc<dyn BackupStorage>,
+        mut scheme: Scheme,
+        key: Option<Key>,
+    ) -> Result<Self> {
+        scheme.encrypt |= key.is_some();
+        ensure!(
+            !scheme.encrypt || key.is_some(),
+            "Encrypting backups requires --encryption-key-storage."
+        );
+        Ok(Self {
+            inner,
+            scheme,
+            require_sealed: key.is_some(),
+            key,
+        })
+    }
+
+    pub fn new_with_opt(inner: Arc<dyn BackupStorage>, opt: SealOpt) -> Result<Self> {
+        let scheme = Scheme {
+            compress: opt.compress,
+            encrypt: opt.encrypt,
+        };
+        Self::new(inner, scheme, opt.load_key()?)
+    }
+
+    /// The sealer of a file or metadata line, whose frames are bound to the `context`.
+    fn sealer(&self, scheme: Scheme, context: &str) -> Result<Arc<Sealer>> {
+        ensure!(
+            scheme.encrypt || !self.require_sealed,
+            "Backup file or metadata line sealed with {} is not encrypted, refusing to read \
+            it with an encryption key configured.",
+            scheme
+        );
+        let cipher = if scheme.encrypt {
+            let key = self.key.as_ref().ok_or_else(|| {
+                anyhow!(
+                    "Backup sealed with {} requires --encryption-key-storage.",
+                    scheme
+                )
+            })?;
+            Some(Aes256Gcm::new(GenericArray::from_slice(key)))
+        } else {
+            None
+        };
+        Ok(Arc::new(Sealer {
+            scheme,
+            cipher,
+            context: context.as_bytes().to_vec(),
+        }))
+    }
+
+    /// Splits a sealed handle into its scheme and the inner handle.
+    fn parse_sealed(handle: &str) -> Result<Option<(Scheme, &str)>> {
+        match handle.strip_prefix(Self::FILE_PREFIX) {
+            Some(rest) => {
+                let (scheme, inner) = rest
+                    .split_once(':')
+                    .ok_or_else(|| anyhow!("Bad sealed file handle: {}", handle))?;
+                Ok(Some((scheme.parse()?, inner)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Refuses plain files and metadata lines, if required.
+    fn ensure_plain_allowed(&self, what: &str) -> Result<()> {
+        ensure!(
+            !self.require_sealed,
+            "{} is not sealed, refusing to read it with an encryption key configured.",
+            what
+        );
+        Ok(())
+    }
+
+    fn seal_line(&self, content: &TextLine) -> Result<TextLine> {
+        let line = content.as_ref().trim_end_matches('\n');
+        ensure!(
+            line.len() <= FRAME_SIZE,
+            "Metadata line too long to seal: {} bytes.",
+            line.len()
+        );
+        let frame = self
+            .sealer(self.scheme, METADATA_LINE_CONTEXT)?
+            .seal_frame(0, true, line.as_bytes())?;
+        TextLine::new(&format!(
+            "{}{}:{}",
+            Self::FILE_PREFIX,
+            self.scheme,
+            hex::encode(frame)
+        ))
+    }
+
+    fn open_line(&self, line: &str) -> Result<String> {
+        match Self::parse_sealed(line)? {
+            Some((scheme, sealed)) => {
+                let frame = hex::decode(sealed)?;
+                let mut codec = OpenCodec::new(self.sealer(scheme, METADATA_LINE_CONTEXT)?);
+                let plain = codec
+                    .decode_eof(&mut BytesMut::from(frame.as_slice()))?
+                    .ok_or_else(|| anyhow!("Empty sealed metadata line."))?;
+                Ok(String::from_utf8(plain.to_vec())?)
+            }
+            None => {
+                self.ensure_plain_allowed("Metadata line")?;
+                Ok(line.to_string())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl BackupStorage for SealedStorage {
+    async fn create_backup(&self, name: &ShellSafeName) -> Result<BackupHandle> {
+        self.inner.create_backup(name).await
+    }
+
+    async fn create_for_write(
+        &self,
+        backup_handle: &BackupHandleRef,
+        name: &ShellSafeName,
+    ) -> Result<(FileHandle, Box<dyn AsyncWrite + Send + Unpin>)> {
+        let (file_handle, file) = self.inner.create_for_write(backup_handle, name).await?;
+        if self.scheme.is_plain() {
+            return Ok((file_handle, file));
+        }
+        let sealed_handle = format!("{}{}:{}", Self::FILE_PREFIX, self.scheme, file_handle);
+        let writer = SealWriter::new(file, self.sealer(self.scheme, &file_handle)?);
+        Ok((sealed_handle, Box::new(writer)))
+    }
+
+    async fn open_for_read(
+        &self,
+        file_handle: &FileHandleRef,
+    ) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+        if let Some(metadata_handle) = file_handle.strip_prefix(Self::METADATA_PREFIX) {
+            let mut buf = String::new();
+            self.inner
+                .open_for_read(metadata_handle)
+                .await?
+                .read_to_string(&mut buf)
+                .await
+                .err_notes(metadata_handle)?;
+            let mut content = String::new();
+            for line in buf.lines() {
+                content.push_str(&self.open_line(line).err_notes(metadata_handle)?);
+                content.push('\n');
+            }
+            return Ok(Box::new(Cursor::new(content.into_bytes())));
+        }
+
+        match Self::parse_sealed(file_handle)? {
+            Some((scheme, inner_handle)) => {
+                let file = self.inner.open_for_read(inner_handle).await?;
+                let sealer = self.sealer(scheme, inner_handle)?;
+                let frames = FramedRead::new(file, OpenCodec::new(sealer));
+                Ok(Box::new(StreamReader::new(frames)))
+            }
+            None => {
+                self.ensure_plain_allowed(&format!("Backup file {}", file_handle))?;
+                self.inner.open_for_read(file_handle).await
+            }
+        }
+    }
+
+    async fn save_metadata_line(&self, name: &ShellSafeName, content: &TextLine) -> Result<()> {
+        if self.scheme.is_plain() {
+            self.inner.save_metadata_line(name, content).await
+        } else {
+            self.inner
+                .save_metadata_line(name, &self.seal_line(content)?)
+                .await
+        }
+    }
+
+    async fn list_metadata_files(&self) -> Result<Vec<FileHandle>> {
+        Ok(self
+            .inner
+            .list_metadata_files()
+            .await?
+            .into_iter()
+            .map(|h| format!("{}{}", Self::METADATA_PREFIX, h))
+            .collect())
+    }
+
+    async fn save_metadata_lines(&self, name: &ShellSafeName, lines: &[TextLine]) -> Result<()> {
+        if self.scheme.is_plain() {
+            self.inner.save_metadata_lines(name, lines).await
+        } else {
+            let sealed = lines
+                .iter()
+                .map(|line| self.seal_line(line))
+                .collect::<Result<Vec<_>>>()?;
+            self.inner.save_metadata_lines(name, &sealed).await
+        }
+    }
+
+    async fn backup_metadata_file(&self, file_handle: &FileHandleRef) -> Result<()> {
+        let inner_handle = file_handle
+            .strip_prefix(Self::METADATA_PREFIX)
+            .unwrap_or(file_handle);
+        self.inner.backup_metadata_file(inner_handle).await
+    }
+
+    async fn delete_file(&self, file_handle: &FileHandleRef) -> Result<()> {
+        match Self::parse_sealed(file_handle)? {
+            Some((_scheme, inner_handle)) => self.inner.delete_file(inner_handle).await,
+            None => self.inner.delete_file(file_handle).await,
+        }
+    }
+}
+
+/// Seals and opens frames, which are `len(u32) + is_last(u8) + payload`. The payload is the
+/// plaintext, compressed and then encrypted with a random nonce prepended, as the scheme says.
+/// The context, i.e. the inner file handle, the frame index and `is_last` are authenticated, so
+/// swapped, reordered or truncated files are caught.
+struct Sealer {
+    scheme: Scheme,
+    cipher: Option<Aes256Gcm>,
+    context: Vec<u8>,
+}
+
+impl Sealer {
+    fn aad(&self, index: u64, is_last: bool) -> Vec<u8> {
+        let mut aad = (self.context.len() as u64).to_be_bytes().to_vec();
+        aad.extend_from_slice(&self.context);
+        aad.extend_from_slice(&index.to_be_bytes());
+        aad.push(is_last as u8);
+        aad
+    }
+
+    fn seal_frame(&self, index: u64, is_last: bool, plain: &[u8]) -> Result<Vec<u8>> {
+        let mut payload = if self.scheme.compress {
+            zstd::encode_all(plain, ZSTD_LEVEL)?
+        } else {
+            plain.to_vec()
+        };
+        if let Some(cipher) = &self.cipher {
+            let mut nonce = [0u8; NONCE_LEN];
+            rand::thread_rng().fill_bytes(&mut nonce);
+            let aad = self.aad(index, is_last);
+            let ciphertext = cipher
+                .encrypt(
+                    GenericArray::from_slice(&nonce),
+                    Payload {
+                        msg: &payload,
+                        aad: &aad,
+                    },
+                )
+                .map_err(|_| anyhow!("Failed to encrypt backup frame {}.", index))?;
+            payload = nonce.to_vec();
+            payload.extend(ciphertext);
+        }
+
+        let mut frame = Vec::with_capacity(5 + payload.len());
+        frame.extend_from_slice(&(1 + payload.len() as u32).to_be_bytes());
+        frame.push(is_last as u8);
+        frame.extend(payload);
+        Ok(frame)
+    }
+
+    fn open_payload(&self, index: u64, is_last: bool, payload: &[u8]) -> Result<Vec<u8>> {
+        let payload = match &self.cipher {
+            Some(cipher) => {
+                ensure!(
+                    payload.len() >= NONCE_LEN,
+                    "Sealed frame {} too short.",
+                    index
+                );
+                let aad = self.aad(index, is_last);
+                cipher
+                    .decrypt(
+                        GenericArray::from_slice(&payload[..NONCE_LEN]),
+                        Payload {
+                            msg: &payload[NONCE_LEN..],
+                            aad: &aad,
+                        },
+                    )
+                    .map_err(|_| {
+                        anyhow!(
+                            "Failed to decrypt backup frame {}, wrong key or corrupt file.",
+                            index
+                        )
+                    })?
+            }
+            None => payload.to_vec(),
+        };
+        if self.scheme.compress {
+            // A frame never holds more than FRAME_SIZE plaintext, don't inflate beyond it.
+            let mut plain = Vec::new();
+            zstd::stream::read::Decoder::new(payload.as_slice())?
+                .take(FRAME_SIZE as u64 + 1)
+                .read_to_end(&mut plain)?;
+            ensure!(
+                plain.len() <= FRAME_SIZE,
+                "Sealed frame {} decompresses to more than {} bytes.",
+                index,
+                FRAME_SIZE
+            );
+            Ok(plain)
+        } else {
+            Ok(payload)
+        }
+    }
+}
+
+fn invalid_data(err: anyhow::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+/// Buffers written bytes into frames and writes them sealed to the inner file.
+struct SealWriter {
+    inner: Box<dyn AsyncWrite + Send + Unpin>,
+    sealer: Arc<Sealer>,
+    /// Plaintext of the frame being filled.
+    buf: Vec<u8>,
+    /// Sealed bytes not yet written to the inner file.
+    out: Vec<u8>,
+    out_pos: usize,
+    index: u64,
+    finished: bool,
+}
+
+impl SealWriter {
+    fn new(inner: Box<dyn AsyncWrite + Send + Unpin>, sealer: Arc<Sealer>) -> Self {
+        Self {
+            inner,
+            sealer,
+            buf: Vec::new(),
+            out: Vec::new(),
+            out_pos: 0,
+            index: 0,
+            finished: false,
+        }
+    }
+
+    fn seal(&mut self, is_last: bool) -> io::Result<()> {
+        self.out = self
+            .sealer
+            .seal_frame(self.index, is_last, &self.buf)
+            .map_err(invalid_data)?;
+        self.out_pos = 0;
+        self.buf.clear();
+        self.index += 1;
+        Ok(())
+    }
+
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.out_pos < self.out.len() {
+            let n = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.out[self.out_pos..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.out_pos += n;
+        }
+        self.out.clear();
+        self.out_pos = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for SealWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        data: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.finished {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Write after shutdown.",
+            )));
+        }
+        ready!(this.poll_drain(cx))?;
+        if this.buf.len() >= FRAME_SIZE {
+            this.seal(false)?;
+            ready!(this.poll_drain(cx))?;
+        }
+        let n = min(data.len(), FRAME_SIZE - this.buf.len());
+        this.buf.extend_from_slice(&data[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        if !this.finished {
+            this.seal(true)?;
+            this.finished = true;
+            ready!(this.poll_drain(cx))?;
+        }
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+/// Decodes sealed frames back to plaintext chunks.
+struct OpenCodec {
+    sealer: Arc<Sealer>,
+    index: u64,
+    finished: bool,
+}
+
+impl OpenCodec {
+    fn new(sealer: Arc<Sealer>) -> Self {
+        Self {
+            sealer,
+            index: 0,
+            finished: false,
+        }
+    }
+}
+
+impl Decoder for OpenCodec {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Bytes>> {
+        if src.len() < 4 {
+            return Ok(None);
+        }
+        let len = u32::from_be_bytes(src[..4].try_into().expect("Checked length.")) as usize;
+        if len == 0 || len > MAX_SEALED_FRAME_SIZE || self.finished {
+            return Err(invalid_data(anyhow!(
+                "Corrupt sealed backup file at frame {}.",
+                self.index
+            )));
+        }
+        if src.len() < 4 + len {
+            src.reserve(4 + len - src.len());
+            return Ok(None);
+        }
+
+        let frame = src.split_to(4 + len);
+        let is_last = frame[4] == 1;
+        let plain = self
+            .sealer
+            .open_payload(self.index, is_last, &frame[5..])
+            .map_err(invalid_data)?;
+        self.index += 1;
+        self.finished = is_last;
+        Ok(Some(Bytes::from(plain)))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> io::Result<Option<Bytes>> {
+        match self.decode(src)? {
+            Some(plain) => Ok(Some(plain)),
+            None if src.is_empty() && self.finished => Ok(None),
+            None => Err(invalid_data(anyhow!(
+                "Sealed backup file truncated at frame {}.",
+                self.index
+            ))),
+        }
+    }
+}
@@ -0,0 +1,239 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use crate::storage::{
+    local_fs::LocalFs,
+    test_util::{
+        arb_backups, arb_metadata_files, test_compact_metadata_files_impl,
+        test_save_and_list_metadata_files_impl,
+    },
+};
+use diem_temppath::TempPath;
+use proptest::prelude::*;
+use std::collections::HashMap;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    runtime::Runtime,
+};
+
+const KEY: Key = [7u8; KEY_LEN];
+const COMPRESS_AND_ENCRYPT: Scheme = Scheme {
+    compress: true,
+    encrypt: true,
+};
+
+fn new_store(tmpdir: &TempPath, scheme: Scheme) -> SealedStorage {
+    let inner = Arc::new(LocalFs::new(tmpdir.path().to_path_buf()));
+    SealedStorage::new(inner, scheme, Some(KEY)).unwrap()
+}
+
+async fn write_file(store: &SealedStorage, backup_name: &str, content: &[u8]) -> FileHandle {
+    let name: ShellSafeName = "file".parse().unwrap();
+    let backup_handle = store
+        .create_backup(&backup_name.parse().unwrap())
+        .await
+        .unwrap();
+    let (handle, mut file) = store.create_for_write(&backup_handle, &name).await.unwrap();
+    file.write_all(content).await.unwrap();
+    file.shutdown().await.unwrap();
+    handle
+}
+
+async fn read_file(store: &SealedStorage, handle: &FileHandleRef) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    store
+        .open_for_read(handle)
+        .await?
+        .read_to_end(&mut buf)
+        .await?;
+    Ok(buf)
+}
+
+async fn test_write_and_read_impl(
+    store: SealedStorage,
+    backups: HashMap<ShellSafeName, HashMap<ShellSafeName, Vec<u8>>>,
+) {
+    let mut handles = Vec::new();
+    for (backup_name, files) in &backups {
+        let backup_handle = store.create_backup(backup_name).await.unwrap();
+        for (name, content) in files {
+            let (handle, mut file) = store.create_for_write(&backup_handle, name).await.unwrap();
+            assert!(handle.starts_with("sealed+zstd+aes256gcm:"));
+            file.write_all(content).await.unwrap();
+            file.shutdown().await.unwrap();
+            handles.push((handle, content));
+        }
+    }
+
+    for (handle, content) in handles {
+        assert_eq!(&read_file(&store, &handle).await.unwrap(), content);
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(10))]
+
+    #[test]
+    fn test_write_and_read(
+        backups in arb_backups()
+    ) {
+        let tmpdir = TempPath::new();
+        tmpdir.create_as_dir().unwrap();
+        let store = new_store(&tmpdir, COMPRESS_AND_ENCRYPT);
+
+        let rt = Runtime::new().unwrap();
+        rt.block_on(test_write_and_read_impl(store, backups));
+    }
+
+    #[test]
+    fn test_save_list_metadata_files(
+        input in arb_metadata_files(),
+    ) {
+        let tmpdir = TempPath::new();
+        tmpdir.create_as_dir().unwrap();
+        let store = new_store(&tmpdir, COMPRESS_AND_ENCRYPT);
+
+        let rt = Runtime::new().unwrap();
+        rt.block_on(test_save_and_list_metadata_files_impl(Box::new(store), input));
+    }
+
+    #[test]
+    fn test_compact_metadata_files(
+        input in arb_metadata_files(),
+    ) {
+        let tmpdir = TempPath::new();
+        tmpdir.create_as_dir().unwrap();
+        let store = new_store(&tmpdir, COMPRESS_AND_ENCRYPT);
+
+        let rt = Runtime::new().unwrap();
+        rt.block_on(test_compact_metadata_files_impl(Box::new(store), input));
+    }
+}
+
+#[test]
+fn test_scheme_display_and_parse() {
+    for compress in &[false, true] {
+        for encrypt in &[false, true] {
+            let scheme = Scheme {
+                compress: *compress,
+                encrypt: *encrypt,
+            };
+            if !scheme.is_plain() {
+                assert_eq!(scheme.to_string().parse::<Scheme>().unwrap(), scheme);
+            }
+        }
+    }
+    assert!("zstd+rot13".parse::<Scheme>().is_err());
+}
+
+#[test]
+fn test_multiple_frames_and_plain_files() {
+    let tmpdir = TempPath::new();
+    tmpdir.create_as_dir().unwrap();
+    let rt = Runtime::new().unwrap();
+    let content: Vec<u8> = (0..FRAME_SIZE * 2 + 100).map(|i| (i % 251) as u8).collect();
+
+    // Files written before sealing was turned on stay readable without a key.
+    let plain_scheme = Scheme {
+        compress: false,
+        encrypt: false,
+    };
+    let inner = Arc::new(LocalFs::new(tmpdir.path().to_path_buf()));
+    let plain = SealedStorage::new(inner.clone(), plain_scheme, None).unwrap();
+    let plain_handle = rt.block_on(write_file(&plain, "plain", &content));
+    assert_eq!(
+        rt.block_on(read_file(&plain, &plain_handle)).unwrap(),
+        content
+    );
+    let compressed = SealedStorage::new(
+        inner,
+        Scheme {
+            compress: true,
+            encrypt: false,
+        },
+        None,
+    )
+    .unwrap();
+    let compressed_handle = rt.block_on(write_file(&compressed, "compressed", &content));
+
+    // With a key, new files are encrypted and files which are not are refused.
+    let sealed = new_store(&tmpdir, plain_scheme);
+    let sealed_handle = rt.block_on(write_file(&sealed, "sealed", &content));
+    assert!(sealed_handle.starts_with("sealed+aes256gcm:"));
+    assert_eq!(
+        rt.block_on(read_file(&sealed, &sealed_handle)).unwrap(),
+        content
+    );
+    assert!(rt.block_on(read_file(&sealed, &plain_handle)).is_err());
+    assert!(rt.block_on(read_file(&sealed, &compressed_handle)).is_err());
+}
+
+#[test]
+fn test_swapped_file_detected() {
+    let tmpdir = TempPath::new();
+    tmpdir.create_as_dir().unwrap();
+    let rt = Runtime::new().unwrap();
+    let store = new_store(&tmpdir, COMPRESS_AND_ENCRYPT);
+    let old = rt.block_on(write_file(&store, "old", b"old transaction chunk"));
+    let new = rt.block_on(write_file(&store, "new", b"new transaction chunk"));
+    let (_, old_inner) = SealedStorage::parse_sealed(&old).unwrap().unwrap();
+    let (_, new_inner) = SealedStorage::parse_sealed(&new).unwrap().unwrap();
+
+    // The frames are bound to their file, a copy of another one is caught.
+    std::fs::copy(tmpdir.path().join(old_inner), tmpdir.path().join(new_inner)).unwrap();
+    assert!(rt.block_on(read_file(&store, &new)).is_err());
+}
+
+#[test]
+fn test_decompression_bounded() {
+    let sealer = Sealer {
+        scheme: Scheme {
+            compress: true,
+            encrypt: false,
+        },
+        cipher: None,
+        context: vec![],
+    };
+    let bomb = zstd::encode_all(vec![0u8; FRAME_SIZE + 1].as_slice(), ZSTD_LEVEL).unwrap();
+    assert!(sealer.open_payload(0, true, &bomb).is_err());
+    let ok = zstd::encode_all(vec![0u8; FRAME_SIZE].as_slice(), ZSTD_LEVEL).unwrap();
+    assert_eq!(sealer.open_payload(0, true, &ok).unwrap().len(), FRAME_SIZE);
+}
+
+#[test]
+fn test_tampering_and_wrong_key_detected() {
+    let tmpdir = TempPath::new();
+    tmpdir.create_as_dir().unwrap();
+    let rt = Runtime::new().unwrap();
+    let store = new_store(
+        &tmpdir,
+        Scheme {
+            compress: false,
+            encrypt: true,
+        },
+    );
+    let handle = rt.block_on(write_file(&store, "backup", b"transaction chunk"));
+    let (_, inner_handle) = SealedStorage::parse_sealed(&handle).unwrap().unwrap();
+    let path = tmpdir.path().join(inner_handle);
+    let sealed = std::fs::read(&path).unwrap();
+
+    let wrong_key = SealedStorage::new(
+        Arc::new(LocalFs::new(tmpdir.path().to_path_buf())),
+        Scheme {
+            compress: false,
+            encrypt: false,
+        },
+        Some([8u8; KEY_LEN]),
+    )
+    .unwrap();
+    assert!(rt.block_on(read_file(&wrong_key, &handle)).is_err());
+
+    let mut flipped = sealed.clone();
+    *flipped.last_mut().unwrap() ^= 1;
+    std::fs::write(&path, &flipped).unwrap();
+    assert!(rt.block_on(read_file(&store, &handle)).is_err());
+
+    std::fs::write(&path, &sealed[..sealed.len() - 1]).unwrap();
+    assert!(rt.block_on(read_file(&store, &handle)).is_err());
+}
@@ -60,14 +60,7 @@ pub fn arb_backups(
     )
 }
 
-pub async fn test_save_and_list_metadata_files_impl(
-    store: Box<dyn BackupStorage>,
-    input: Vec<(ShellSafeName, TextLine)>,
-) {
-    for (name, content) in &input {
-        store.save_metadata_line(name, &content).await.unwrap();
-    }
-
+async fn read_metadata_lines(store: &dyn BackupStorage) -> Vec<TextLine> {
     let mut read_back = Vec::new();
     for file_handle in store.list_metadata_files().await.unwrap() {
         let mut buf = String::new();
@@ -87,6 +80,18 @@ pub async fn test_save_and_list_metadata_files_impl(
         )
     }
     read_back.sort();
+    read_back
+}
+
+pub async fn test_save_and_list_metadata_files_impl(
+    store: Box<dyn BackupStorage>,
+    input: Vec<(ShellSafeName, TextLine)>,
+) {
+    for (name, content) in &input {
+        store.save_metadata_line(name, &content).await.unwrap();
+    }
+
+    let read_back = read_metadata_lines(store.as_ref()).await;
 
     let expected = input
         .into_iter()
@@ -97,6 +102,32 @@ pub async fn test_save_and_list_metadata_files_impl(
     assert_eq!(read_back, expected)
 }
 
+pub async fn test_compact_metadata_files_impl(
+    store: Box<dyn BackupStorage>,
+    input: Vec<(ShellSafeName, TextLine)>,
+) {
+    for (name, content) in &input {
+        store.save_metadata_line(name, &content).await.unwrap();
+    }
+    let old_files = store.list_metadata_files().await.unwrap();
+    let lines = read_metadata_lines(store.as_ref()).await;
+
+    let compacted_name: ShellSafeName = (0..)
+        .map(|i| format!("compacted_{}.meta", i).parse().unwrap())
+        .find(|name| input.iter().all(|(n, _)| n != name))
+        .unwrap();
+    store
+        .save_metadata_lines(&compacted_name, &lines)
+        .await
+        .unwrap();
+    for file_handle in &old_files {
+        store.backup_metadata_file(file_handle).await.unwrap();
+    }
+
+    assert_eq!(store.list_metadata_files().await.unwrap().len(), 1);
+    assert_eq!(read_metadata_lines(store.as_ref()).await, lines);
+}
+
 pub fn arb_metadata_files() -> impl Strategy<Value = Vec<(ShellSafeName, TextLine)>> {
     hash_map(any::<ShellSafeName>(), any::<TextLine>(), 0..10)
         .prop_map(HashMap::into_iter)
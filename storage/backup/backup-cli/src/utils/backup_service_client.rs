@@ -2,11 +2,12 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::utils::error_notes::ErrorNotes;
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use diem_crypto::HashValue;
 use diem_types::transaction::Version;
 use diemdb::backup::backup_handler::DbState;
 use futures::TryStreamExt;
+use std::path::PathBuf;
 use structopt::StructOpt;
 use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio_util::compat::FuturesAsyncReadCompatExt;
@@ -19,23 +20,74 @@ pub struct BackupServiceClientOpt {
         help = "Backup service address."
     )]
     pub address: String,
+    #[structopt(
+        long = "backup-service-token",
+        env = "BACKUP_SERVICE_TOKEN",
+        hide_env_values = true,
+        help = "Token to authenticate to the backup service with, if it requires one."
+    )]
+    pub token: Option<String>,
+    #[structopt(
+        long = "backup-service-ca-cert",
+        parse(from_os_str),
+        help = "PEM file of the CA certificate to trust the backup service certificate by, \
+        besides the system ones."
+    )]
+    pub ca_cert: Option<PathBuf>,
+    #[structopt(
+        long = "backup-service-client-cert",
+        parse(from_os_str),
+        requires = "client-key",
+        help = "PEM file of the client certificate to present, if the backup service requires one."
+    )]
+    pub client_cert: Option<PathBuf>,
+    #[structopt(
+        long = "backup-service-client-key",
+        parse(from_os_str),
+        requires = "client-cert",
+        help = "PEM file of the private key of the client certificate."
+    )]
+    pub client_key: Option<PathBuf>,
 }
 
 pub struct BackupServiceClient {
     address: String,
+    token: Option<String>,
     client: reqwest::Client,
 }
 
 impl BackupServiceClient {
-    pub fn new_with_opt(opt: BackupServiceClientOpt) -> Self {
-        Self::new(opt.address)
+    pub fn new_with_opt(opt: BackupServiceClientOpt) -> Result<Self> {
+        let mut builder = reqwest::Client::builder().no_proxy().use_rustls_tls();
+        if let Some(ca_cert) = &opt.ca_cert {
+            let pem = std::fs::read(ca_cert).err_notes(ca_cert)?;
+            builder = builder.add_root_certificate(reqwest::Certificate::from_pem(&pem)?);
+        }
+        if let (Some(client_cert), Some(client_key)) = (&opt.client_cert, &opt.client_key) {
+            // rustls takes the certificate and the key in the same PEM.
+            let mut pem = std::fs::read(client_cert).err_notes(client_cert)?;
+            pem.extend(std::fs::read(client_key).err_notes(client_key)?);
+            builder = builder.identity(reqwest::Identity::from_pem(&pem)?);
+        }
+        ensure!(
+            opt.token.as_ref().map_or(true, |t| !t.is_empty()),
+            "Backup service token can't be empty."
+        );
+
+        Ok(Self {
+            address: opt.address,
+            token: opt.token,
+            client: builder.build()?,
+        })
     }
 
     pub fn new(address: String) -> Self {
         Self {
             address,
+            token: None,
             client: reqwest::Client::builder()
                 .no_proxy()
+                .use_rustls_tls()
                 .build()
                 .expect("Http client should build."),
         }
@@ -43,9 +95,11 @@ impl BackupServiceClient {
 
     async fn get(&self, path: &str) -> Result<impl AsyncRead> {
         let url = format!("{}/{}", self.address, path);
-        Ok(self
-            .client
-            .get(&url)
+        let mut request = self.client.get(&url);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        Ok(request
             .send()
             .await
             .err_notes(&url)?
@@ -76,6 +130,15 @@ impl BackupServiceClient {
         self.get(&format!("state_snapshot/{}", version)).await
     }
 
+    pub async fn get_state_delta(
+        &self,
+        base_version: Version,
+        version: Version,
+    ) -> Result<impl AsyncRead> {
+        self.get(&format!("state_delta/{}/{}", base_version, version))
+            .await
+    }
+
     pub async fn get_state_root_proof(&self, version: Version) -> Result<Vec<u8>> {
         let mut buf = Vec::new();
         self.get(&format!("state_root_proof/{}", version))
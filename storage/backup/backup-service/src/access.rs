@@ -0,0 +1,299 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Access control of the backup service: token authentication, per client limits on concurrent
+//! requests and bandwidth, and access logging.
+
+use bytes::Bytes;
+use diem_config::config::BackupServiceConfig;
+use diem_infallible::Mutex;
+use diem_logger::prelude::*;
+use diem_metrics::{register_int_counter_vec, IntCounterVec};
+use diem_rate_limiter::rate_limit::{SharedBucket, TokenBucketRateLimiter};
+use futures::{stream, StreamExt};
+use hyper::Body;
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use warp::{
+    http::StatusCode,
+    path::FullPath,
+    reject::{Reject, Rejection},
+    reply::{Reply, Response},
+    Filter,
+};
+
+static DENIED_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_backup_service_denied_requests",
+        "Requests the backup service denied.",
+        &["reason"]
+    )
+    .unwrap()
+});
+
+/// A bucket holds the bandwidth of one refill period, so one not used for that long is full again
+/// and can be forgotten without the client gaining anything.
+const BUCKET_REFILL_PERIOD: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub(crate) enum AccessDenied {
+    Unauthorized,
+    TooManyRequests,
+}
+
+impl Reject for AccessDenied {}
+
+impl AccessDenied {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+}
+
+pub(crate) struct AccessControl {
+    /// (client name, token) pairs.
+    auth_tokens: Vec<(String, String)>,
+    max_concurrent_requests_per_client: Option<usize>,
+    semaphores: Mutex<HashMap<String, Arc<Semaphore>>>,
+    bandwidth_limiter: Option<TokenBucketRateLimiter<String>>,
+    /// When the clients last finished a request, to forget their buckets once idle long enough.
+    idle_since: Mutex<HashMap<String, Instant>>,
+}
+
+impl AccessControl {
+    pub fn new(config: &BackupServiceConfig) -> Self {
+        Self {
+            auth_tokens: config
+                .auth_tokens
+                .iter()
+                .map(|(name, token)| (name.clone(), token.clone()))
+                .collect(),
+            max_concurrent_requests_per_client: config.max_concurrent_requests_per_client,
+            semaphores: Mutex::new(HashMap::new()),
+            bandwidth_limiter: config.max_bytes_per_sec_per_client.map(|rate| {
+                TokenBucketRateLimiter::new(
+                    "backup_service",
+                    String::new(),
+                    100, /* new_bucket_start_percentage */
+                    rate,
+                    rate,
+                    None, /* metrics */
+                )
+            }),
+            idle_since: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records that a request of `client` finished, and forgets the buckets of the clients idle
+    /// for longer than a refill period.
+    fn forget_idle_buckets(&self, client: &str) {
+        let limiter = match &self.bandwidth_limiter {
+            Some(limiter) => limiter,
+            None => return,
+        };
+        let now = Instant::now();
+        let mut idle_since = self.idle_since.lock();
+        idle_since.insert(client.to_string(), now);
+        idle_since.retain(|client, since| {
+            if now.duration_since(*since) < BUCKET_REFILL_PERIOD {
+                return true;
+            }
+            // Kept if a request took it again, which records the client again when finished.
+            limiter.try_garbage_collect_key(client);
+            false
+        });
+    }
+
+    /// Admits a request, yielding a `ClientGuard` to wrap the reply in, or rejects it with
+    /// `AccessDenied`.
+    pub fn admit(
+        self: Arc<Self>,
+    ) -> impl Filter<Extract = (ClientGuard,), Error = Rejection> + Clone {
+        warp::header::optional::<String>("authorization")
+            .and(warp::addr::remote())
+            .and(warp::path::full())
+            .and_then(
+                move |authorization: Option<String>, remote: Option<SocketAddr>, path: FullPath| {
+                    let access_control = Arc::clone(&self);
+                    async move {
+                        access_control
+                            .admit_impl(authorization, remote, path.as_str())
+                            .map_err(|e| {
+                                warn!(
+                                    remote = remote,
+                                    path = path.as_str(),
+                                    "Backup service request denied: {:?}",
+                                    e
+                                );
+                                DENIED_COUNTER
+                                    .with_label_values(&[&format!("{:?}", e)])
+                                    .inc();
+                                warp::reject::custom(e)
+                            })
+                    }
+                },
+            )
+    }
+
+    fn admit_impl(
+        self: Arc<Self>,
+        authorization: Option<String>,
+        remote: Option<SocketAddr>,
+        path: &str,
+    ) -> Result<ClientGuard, AccessDenied> {
+        let client = if self.auth_tokens.is_empty() {
+            remote.map_or_else(|| "unknown".to_string(), |addr| addr.ip().to_string())
+        } else {
+            let token = authorization
+                .as_deref()
+                .and_then(|a| a.strip_prefix("Bearer "))
+                .ok_or(AccessDenied::Unauthorized)?;
+            self.auth_tokens
+                .iter()
+                .find(|(_, t)| constant_time_eq(t.as_bytes(), token.as_bytes()))
+                .map(|(name, _)| name.clone())
+                .ok_or(AccessDenied::Unauthorized)?
+        };
+
+        let permit = match self.max_concurrent_requests_per_client {
+            Some(max) => {
+                let semaphore = Arc::clone(
+                    self.semaphores
+                        .lock()
+                        .entry(client.clone())
+                        .or_insert_with(|| Arc::new(Semaphore::new(max))),
+                );
+                Some(
+                    semaphore
+                        .try_acquire_owned()
+                        .map_err(|_| AccessDenied::TooManyRequests)?,
+                )
+            }
+            None => None,
+        };
+        let bucket = self
+            .bandwidth_limiter
+            .as_ref()
+            .map(|limiter| limiter.bucket(client.clone()));
+
+        Ok(ClientGuard {
+            access_control: self,
+            client,
+            path: path.to_string(),
+            start_time: Instant::now(),
+            status: None,
+            bytes_sent: 0,
+            finished: false,
+            permit,
+            bucket,
+        })
+    }
+}
+
+/// Holds what a request takes from the limits of its client until the response is sent, and
+/// logs the access when dropped.
+pub(crate) struct ClientGuard {
+    access_control: Arc<AccessControl>,
+    client: String,
+    path: String,
+    start_time: Instant,
+    status: Option<StatusCode>,
+    bytes_sent: usize,
+    /// Whether the response body was sent completely.
+    finished: bool,
+    permit: Option<OwnedSemaphorePermit>,
+    bucket: Option<SharedBucket>,
+}
+
+impl ClientGuard {
+    /// Wraps the body of the reply, for it to be sent within the bandwidth limit, and for the
+    /// guard to live until the body is sent.
+    pub fn wrap(mut self, reply: impl Reply) -> Response {
+        let mut response = reply.into_response();
+        self.status = Some(response.status());
+        let body = std::mem::take(response.body_mut());
+        *response.body_mut() = Body::wrap_stream(stream::unfold(
+            (body, self),
+            |(mut body, mut guard)| async move {
+                match body.next().await {
+                    Some(Ok(chunk)) => {
+                        guard.throttle(chunk.len()).await;
+                        guard.bytes_sent += chunk.len();
+                        Some((Ok::<Bytes, hyper::Error>(chunk), (body, guard)))
+                    }
+                    Some(Err(e)) => Some((Err(e), (body, guard))),
+                    None => {
+                        guard.finished = true;
+                        None
+                    }
+                }
+            },
+        ));
+        response
+    }
+
+    async fn throttle(&self, mut num_bytes: usize) {
+        let bucket = match &self.bucket {
+            Some(bucket) => bucket,
+            None => return,
+        };
+        while num_bytes > 0 {
+            let res = bucket.lock().acquire_tokens(num_bytes);
+            match res {
+                Ok(acquired) => num_bytes = num_bytes.saturating_sub(acquired),
+                Err(next_refill) => {
+                    tokio::time::sleep_until(tokio::time::Instant::from_std(next_refill)).await
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ClientGuard {
+    fn drop(&mut self) {
+        info!(
+            client = self.client,
+            path = self.path,
+            status = self.status.map(|s| s.as_u16()),
+            bytes_sent = self.bytes_sent,
+            finished = self.finished,
+            elapsed_ms = self.start_time.elapsed().as_millis() as u64,
+            "Backup service access."
+        );
+
+        // Forget the concurrency limits of the clients with nothing in flight, but the buckets
+        // only once full again, otherwise back to back requests would each get a full one.
+        self.permit.take();
+        if self.bucket.take().is_some() {
+            self.access_control.forget_idle_buckets(&self.client);
+        }
+        let mut semaphores = self.access_control.semaphores.lock();
+        if semaphores
+            .get(&self.client)
+            .map_or(false, |s| Arc::strong_count(s) <= 1)
+        {
+            semaphores.remove(&self.client);
+        }
+    }
+}
+
+/// Returns the status code for requests `AccessControl` denied, passes the other rejections on.
+pub(crate) async fn handle_access_denied(err: Rejection) -> Result<impl Reply, Rejection> {
+    match err.find::<AccessDenied>() {
+        Some(denied) => Ok(denied.status()),
+        None => Err(err),
+    }
+}
+
+/// Compares two byte strings in time not depending on where they differ.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
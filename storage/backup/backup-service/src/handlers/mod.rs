@@ -3,16 +3,21 @@
 
 mod utils;
 
-use crate::handlers::utils::{
-    handle_rejection, reply_with_async_channel_writer, reply_with_bcs_bytes,
-    send_size_prefixed_bcs_bytes, unwrap_or_500, LATENCY_HISTOGRAM,
+use crate::{
+    access::{handle_access_denied, AccessControl, ClientGuard},
+    handlers::utils::{
+        handle_rejection, reply_with_async_channel_writer, reply_with_bcs_bytes,
+        send_size_prefixed_bcs_bytes, unwrap_or_500, LATENCY_HISTOGRAM,
+    },
 };
 use diem_crypto::hash::HashValue;
 use diem_types::transaction::Version;
 use diemdb::backup::backup_handler::BackupHandler;
+use std::sync::Arc;
 use warp::{filters::BoxedFilter, reply::Reply, Filter};
 
 static DB_STATE: &str = "db_state";
+static STATE_DELTA: &str = "state_delta";
 static STATE_RANGE_PROOF: &str = "state_range_proof";
 static STATE_SNAPSHOT: &str = "state_snapshot";
 static STATE_ROOT_PROOF: &str = "state_root_proof";
@@ -20,7 +25,10 @@ static EPOCH_ENDING_LEDGER_INFOS: &str = "epoch_ending_ledger_infos";
 static TRANSACTIONS: &str = "transactions";
 static TRANSACTION_RANGE_PROOF: &str = "transaction_range_proof";
 
-pub(crate) fn get_routes(backup_handler: BackupHandler) -> BoxedFilter<(impl Reply,)> {
+pub(crate) fn get_routes(
+    backup_handler: BackupHandler,
+    access_control: Arc<AccessControl>,
+) -> BoxedFilter<(impl Reply,)> {
     // GET db_state
     let bh = backup_handler.clone();
     let db_state = warp::path::end()
@@ -50,6 +58,19 @@ pub(crate) fn get_routes(backup_handler: BackupHandler) -> BoxedFilter<(impl Rep
         })
         .recover(handle_rejection);
 
+    // GET state_delta/<base_version>/<version>
+    let bh = backup_handler.clone();
+    let state_delta = warp::path!(Version / Version)
+        .map(move |base_version, version| {
+            reply_with_async_channel_writer(&bh, STATE_DELTA, |bh, sender| {
+                send_size_prefixed_bcs_bytes(
+                    bh.get_account_delta_iter(base_version, version),
+                    sender,
+                )
+            })
+        })
+        .recover(handle_rejection);
+
     // GET state_root_proof/<version>
     let bh = backup_handler.clone();
     let state_root_proof = warp::path!(Version)
@@ -112,14 +133,18 @@ pub(crate) fn get_routes(backup_handler: BackupHandler) -> BoxedFilter<(impl Rep
         .and(warp::path(DB_STATE).and(db_state))
         .or(warp::path(STATE_RANGE_PROOF).and(state_range_proof))
         .or(warp::path(STATE_SNAPSHOT).and(state_snapshot))
+        .or(warp::path(STATE_DELTA).and(state_delta))
         .or(warp::path(STATE_ROOT_PROOF).and(state_root_proof))
         .or(warp::path(EPOCH_ENDING_LEDGER_INFOS).and(epoch_ending_ledger_infos))
         .or(warp::path(TRANSACTIONS).and(transactions))
         .or(warp::path(TRANSACTION_RANGE_PROOF).and(transaction_range_proof));
 
-    // Serve all routes for GET only.
+    // Serve all routes for GET only, to admitted clients only.
     warp::get()
+        .and(access_control.admit())
         .and(routes)
+        .map(|guard: ClientGuard, reply| guard.wrap(reply))
+        .recover(handle_access_denied)
         .with(warp::log::custom(|info| {
             let endpoint = info.path().split('/').nth(1).unwrap_or("-");
             LATENCY_HISTOGRAM
@@ -1,17 +1,31 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+mod access;
 mod handlers;
 
-use crate::handlers::get_routes;
+use crate::{access::AccessControl, handlers::get_routes};
+use diem_config::config::BackupServiceConfig;
 use diem_logger::prelude::*;
 use diemdb::DiemDB;
+use futures::future::Either;
 use std::{net::SocketAddr, sync::Arc};
 use tokio::runtime::{Builder, Runtime};
 
 pub fn start_backup_service(address: SocketAddr, db: Arc<DiemDB>) -> Runtime {
+    start_backup_service_with_config(address, &BackupServiceConfig::default(), db)
+}
+
+pub fn start_backup_service_with_config(
+    address: SocketAddr,
+    config: &BackupServiceConfig,
+    db: Arc<DiemDB>,
+) -> Runtime {
+    config
+        .validate()
+        .expect("[backup] invalid backup service config");
     let backup_handler = db.get_backup_handler();
-    let routes = get_routes(backup_handler);
+    let routes = get_routes(backup_handler, Arc::new(AccessControl::new(config)));
 
     let runtime = Builder::new_multi_thread()
         .thread_name("backup")
@@ -27,7 +41,27 @@ pub fn start_backup_service(address: SocketAddr, db: Arc<DiemDB>) -> Runtime {
     // Note: we need to enter the runtime context first to actually bind, since
     //       tokio TcpListener can only be bound inside a tokio context.
     let _guard = runtime.enter();
-    let server = warp::serve(routes).bind(address);
+    if let Some(local_address) = config.local_address {
+        // Validated to be a loopback address, so plain HTTP is fine.
+        runtime
+            .handle()
+            .spawn(warp::serve(routes.clone()).bind(local_address));
+    }
+    let server = match (&config.tls_cert_path, &config.tls_key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            let tls = warp::serve(routes)
+                .tls()
+                .cert_path(cert_path)
+                .key_path(key_path);
+            let tls = match &config.tls_client_ca_path {
+                None => tls,
+                Some(ca_path) => tls.client_auth_required_path(ca_path),
+            };
+            Either::Right(tls.bind(address))
+        }
+        // Validated above, the key comes with the certificate.
+        _ => Either::Left(warp::serve(routes).bind(address)),
+    };
     runtime.handle().spawn(server);
     info!("Backup service spawned.");
     runtime
@@ -38,9 +72,56 @@ mod tests {
     use super::*;
     use diem_config::utils::get_available_port;
     use diem_crypto::hash::HashValue;
+    use diem_proptest_helpers::ValueGenerator;
     use diem_temppath::TempPath;
+    use diemdb::test_helper::arb_blocks_to_commit;
     use reqwest::blocking::get;
-    use std::net::{IpAddr, Ipv4Addr};
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    fn tmp_db_with_random_content() -> (TempPath, Arc<DiemDB>) {
+        let tmpdir = TempPath::new();
+        let db = Arc::new(DiemDB::new_for_test(&tmpdir));
+        let mut cur_ver = 0;
+        for (txns_to_commit, ledger_info_with_sigs) in
+            ValueGenerator::new().generate(arb_blocks_to_commit())
+        {
+            db.save_transactions(&txns_to_commit, cur_ver, Some(&ledger_info_with_sigs))
+                .unwrap();
+            cur_ver += txns_to_commit.len() as u64;
+        }
+        (tmpdir, db)
+    }
+
+    /// Starts the service with `max_bytes_per_sec_per_client` set to half the size of the
+    /// response to `path`, so that the response takes at least one refill period to send.
+    fn start_throttled(
+        db: Arc<DiemDB>,
+        path: &str,
+        mut config: BackupServiceConfig,
+    ) -> (Runtime, String) {
+        let port = get_available_port();
+        let rt = start_backup_service(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port),
+            Arc::clone(&db),
+        );
+        let url = format!("http://127.0.0.1:{}/{}", port, path);
+        let len = get(&url).unwrap().bytes().unwrap().len();
+        assert!(len >= 2);
+        rt.shutdown_timeout(Duration::from_secs(1));
+
+        let port = get_available_port();
+        config.max_bytes_per_sec_per_client = Some(len / 2);
+        let rt = start_backup_service_with_config(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port),
+            &config,
+            db,
+        );
+        (rt, format!("http://127.0.0.1:{}/{}", port, path))
+    }
 
     /// 404 - endpoint not found
     /// 400 - params not provided or failed parsing
@@ -94,4 +175,87 @@ mod tests {
         assert_eq!(resp.content_length(), None);
         assert!(resp.bytes().is_err());
     }
+
+    #[test]
+    fn authentication() {
+        let tmpdir = TempPath::new();
+        let db = Arc::new(DiemDB::new_for_test(&tmpdir));
+        let port = get_available_port();
+        let mut config = BackupServiceConfig::default();
+        config
+            .auth_tokens
+            .insert("client".to_string(), "token".to_string());
+        let _rt = start_backup_service_with_config(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port),
+            &config,
+            db,
+        );
+        let url = format!("http://127.0.0.1:{}/state_root_proof/0", port);
+        let client = reqwest::blocking::Client::new();
+
+        // No token, or a wrong one.
+        let resp = get(&url).unwrap();
+        assert_eq!(resp.status(), 401);
+        let resp = client.get(&url).bearer_auth("wrong").send().unwrap();
+        assert_eq!(resp.status(), 401);
+
+        // Admitted, then the handler raises the usual error (non-bootstrapped DB).
+        let resp = client.get(&url).bearer_auth("token").send().unwrap();
+        assert_eq!(resp.status(), 500);
+        let resp = client
+            .get(&format!("http://127.0.0.1:{}/x", port))
+            .bearer_auth("token")
+            .send()
+            .unwrap();
+        assert_eq!(resp.status(), 404);
+    }
+
+    #[test]
+    fn throttling() {
+        let (_tmpdir, db) = tmp_db_with_random_content();
+        let (_rt, url) = start_throttled(db, "transactions/0/1", BackupServiceConfig::default());
+
+        // Half of the response comes from the initial bucket, the other half from a refill.
+        let start = Instant::now();
+        let resp = get(&url).unwrap();
+        assert_eq!(resp.status(), 200);
+        resp.bytes().unwrap();
+        assert!(start.elapsed() >= Duration::from_secs(1));
+
+        // The next request doesn't start with a full bucket again, the two responses take three
+        // refills in total.
+        let resp = get(&url).unwrap();
+        assert_eq!(resp.status(), 200);
+        resp.bytes().unwrap();
+        assert!(start.elapsed() >= Duration::from_secs(3));
+    }
+
+    #[test]
+    fn concurrency_limit() {
+        let (_tmpdir, db) = tmp_db_with_random_content();
+        let config = BackupServiceConfig {
+            max_concurrent_requests_per_client: Some(1),
+            ..Default::default()
+        };
+        let (_rt, url) = start_throttled(db, "transactions/0/1", config);
+
+        // The first request is in flight for at least one refill period, while the second one is
+        // denied.
+        let in_flight = {
+            let url = url.clone();
+            thread::spawn(move || {
+                let resp = get(&url).unwrap();
+                assert_eq!(resp.status(), 200);
+                resp.bytes().unwrap();
+            })
+        };
+        thread::sleep(Duration::from_millis(300));
+        let resp = get(&url).unwrap();
+        assert_eq!(resp.status(), 429);
+
+        // Admitted again once the first one is done.
+        in_flight.join().unwrap();
+        let resp = get(&url).unwrap();
+        assert_eq!(resp.status(), 200);
+    }
 }
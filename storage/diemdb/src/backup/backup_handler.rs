@@ -13,7 +13,9 @@ use crate::{
 };
 use anyhow::{anyhow, ensure, Result};
 use diem_crypto::hash::HashValue;
-use diem_jellyfish_merkle::iterator::JellyfishMerkleIterator;
+use diem_jellyfish_merkle::{
+    delta_iterator::JellyfishMerkleDeltaIterator, iterator::JellyfishMerkleIterator,
+};
 use diem_types::{
     account_state_blob::AccountStateBlob,
     contract_event::ContractEvent,
@@ -118,6 +120,27 @@ impl BackupHandler {
         Ok(Box::new(iterator))
     }
 
+    /// Gets an iterator which yields the accounts changed from `base_version` to `version`, in
+    /// key order. It can also yield some unchanged accounts.
+    pub fn get_account_delta_iter(
+        &self,
+        base_version: Version,
+        version: Version,
+    ) -> Result<Box<dyn Iterator<Item = Result<(HashValue, AccountStateBlob)>> + Send + Sync>> {
+        let iterator = JellyfishMerkleDeltaIterator::new(
+            Arc::clone(&self.state_store),
+            base_version,
+            version,
+        )?
+        .enumerate()
+        .map(move |(idx, res)| {
+            BACKUP_STATE_SNAPSHOT_VERSION.set(version as i64);
+            BACKUP_STATE_SNAPSHOT_LEAF_IDX.set(idx as i64);
+            res
+        });
+        Ok(Box::new(iterator))
+    }
+
     /// Gets the proof that proves a range of accounts.
     pub fn get_account_state_range_proof(
         &self,
@@ -0,0 +1,348 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module provides `ConsistencyChecker`, which checks that the data persisted in a `DiemDB`
+//! is internally consistent, e.g. after a disk incident.
+//!
+//! Every check is done against the latest `LedgerInfo`, whose signatures are in turn checked
+//! against the chain of epoch ending `LedgerInfo`s persisted in the same DB. So a DB passing the
+//! checks is at least consistent with itself, while whether it is on the right chain is left to
+//! be checked against a waypoint.
+
+use crate::{
+    event_store::EventStore,
+    ledger_store::LedgerStore,
+    pruner::ledger_pruner::get_first_txn_version,
+    schema::{epoch_by_version::EpochByVersionSchema, event_by_key::EventByKeySchema},
+    state_store::StateStore,
+    transaction_store::TransactionStore,
+};
+use anyhow::{ensure, format_err, Result};
+use diem_crypto::hash::{CryptoHash, EventAccumulatorHasher, SPARSE_MERKLE_PLACEHOLDER_HASH};
+use diem_jellyfish_merkle::{
+    node_type::{LeafNode, Node, NodeKey},
+    TreeReader,
+};
+use diem_types::{
+    account_state_blob::AccountStateBlob,
+    contract_event::ContractEvent,
+    ledger_info::{LedgerInfo, LedgerInfoWithSignatures},
+    proof::accumulator::InMemoryAccumulator,
+    transaction::{Transaction, Version},
+};
+use schemadb::DB;
+use std::{collections::HashMap, fmt, sync::Arc};
+
+/// `ConsistencyChecker` provides functionalities to check the integrity of a DiemDB.
+#[derive(Clone)]
+pub struct ConsistencyChecker {
+    db: Arc<DB>,
+    ledger_store: Arc<LedgerStore>,
+    transaction_store: Arc<TransactionStore>,
+    state_store: Arc<StateStore>,
+    event_store: Arc<EventStore>,
+}
+
+/// The first problem found by a check.
+#[derive(Debug)]
+pub struct Corruption {
+    /// The version of the corrupted transaction or `LedgerInfo`.
+    pub version: Version,
+    pub error: anyhow::Error,
+}
+
+impl fmt::Display for Corruption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "DB corruption at version {}: {}",
+            self.version, self.error
+        )
+    }
+}
+
+impl ConsistencyChecker {
+    pub(crate) fn new(
+        db: Arc<DB>,
+        ledger_store: Arc<LedgerStore>,
+        transaction_store: Arc<TransactionStore>,
+        state_store: Arc<StateStore>,
+        event_store: Arc<EventStore>,
+    ) -> Self {
+        Self {
+            db,
+            ledger_store,
+            transaction_store,
+            state_store,
+            event_store,
+        }
+    }
+
+    /// Gets the version of the first transaction kept, older ones can be pruned.
+    pub fn get_first_version(&self) -> Result<Version> {
+        get_first_txn_version(&self.db)
+    }
+
+    /// Gets the latest `LedgerInfo`, which the transactions are checked against.
+    pub fn get_latest_ledger_info(&self) -> Result<LedgerInfoWithSignatures> {
+        self.ledger_store.get_latest_ledger_info()
+    }
+
+    /// Checks the epoch ending `LedgerInfo`s and the latest one: each is signed by the validator
+    /// set the previous epoch ending one carries, matches the transaction accumulator, and is
+    /// indexed in `EpochByVersionSchema`.
+    pub fn check_ledger_infos(&self) -> Option<Corruption> {
+        let latest_li = match self.get_latest_ledger_info() {
+            Ok(li) => li,
+            Err(error) => {
+                return Some(Corruption { version: 0, error });
+            }
+        };
+        let latest_epoch = latest_li.ledger_info().next_block_epoch();
+
+        let mut prev_li: Option<LedgerInfoWithSignatures> = None;
+        let mut iter = match self
+            .ledger_store
+            .get_epoch_ending_ledger_info_iter(0, latest_epoch)
+        {
+            Ok(iter) => iter,
+            Err(error) => {
+                return Some(Corruption { version: 0, error });
+            }
+        };
+        for epoch in 0..latest_epoch {
+            let li = match iter.next().transpose().and_then(|li| {
+                li.ok_or_else(|| format_err!("Epoch ending LedgerInfo of epoch {} missing.", epoch))
+            }) {
+                Ok(li) => li,
+                Err(error) => {
+                    let version = prev_li.map_or(0, |li| li.ledger_info().version() + 1);
+                    return Some(Corruption { version, error });
+                }
+            };
+            let res = self.check_ledger_info(&li, prev_li.as_ref()).and_then(|_| {
+                let indexed_epoch = self
+                    .db
+                    .get::<EpochByVersionSchema>(&li.ledger_info().version())?;
+                ensure!(
+                    indexed_epoch == Some(epoch),
+                    "Epoch {} ending at this version is indexed as {:?}.",
+                    epoch,
+                    indexed_epoch,
+                );
+                Ok(())
+            });
+            if let Err(error) = res {
+                return Some(Corruption {
+                    version: li.ledger_info().version(),
+                    error,
+                });
+            }
+            prev_li = Some(li);
+        }
+
+        if latest_li.ledger_info().ends_epoch() {
+            // Already checked as the last epoch ending one.
+            return None;
+        }
+        self.check_ledger_info(&latest_li, prev_li.as_ref())
+            .err()
+            .map(|error| Corruption {
+                version: latest_li.ledger_info().version(),
+                error,
+            })
+    }
+
+    /// Checks the transactions in `[begin, end)`, stopping at the first corrupted one.
+    pub fn check_transactions(
+        &self,
+        begin: Version,
+        end: Version,
+        ledger_info: &LedgerInfo,
+        allow_pruned_state: bool,
+    ) -> Option<Corruption> {
+        (begin..end).find_map(|version| {
+            self.check_transaction(version, ledger_info, allow_pruned_state)
+                .err()
+                .map(|error| Corruption { version, error })
+        })
+    }
+
+    /// Checks the whole state tree at `version`: every node referenced exists and hashes to the
+    /// hash its parent holds, leaves are hashed from their values, up to the state root in the
+    /// `TransactionInfo`. This reads every node of the tree, so it is slow on a large state.
+    pub fn check_state_tree(
+        &self,
+        version: Version,
+        ledger_info: &LedgerInfo,
+    ) -> Option<Corruption> {
+        self.check_state_tree_impl(version, ledger_info)
+            .err()
+            .map(|error| Corruption { version, error })
+    }
+
+    fn check_state_tree_impl(&self, version: Version, ledger_info: &LedgerInfo) -> Result<()> {
+        let txn_info_with_proof = self
+            .ledger_store
+            .get_transaction_info_with_proof(version, ledger_info.version())?;
+        txn_info_with_proof.verify(ledger_info, version)?;
+
+        // Depth first, the stack holds the nodes to check with the hash and kind their parent
+        // expects.
+        let mut to_check = vec![(
+            NodeKey::new_empty_path(version),
+            txn_info_with_proof.transaction_info().state_root_hash(),
+            None,
+        )];
+        while let Some((node_key, expected_hash, expected_leaf)) = to_check.pop() {
+            let node: Node<AccountStateBlob> = self.state_store.get_node(&node_key)?;
+            if let Some(is_leaf) = expected_leaf {
+                ensure!(
+                    node.is_leaf() == is_leaf,
+                    "State tree node {:?} is referenced as a leaf: {}, but it is not.",
+                    node_key,
+                    is_leaf,
+                );
+            }
+            let hash = match node {
+                Node::Null => *SPARSE_MERKLE_PLACEHOLDER_HASH,
+                Node::Internal(internal_node) => {
+                    let hash = internal_node.hash();
+                    for (nibble, child) in HashMap::from(internal_node) {
+                        to_check.push((
+                            node_key.gen_child_node_key(child.version, nibble),
+                            child.hash,
+                            Some(child.is_leaf),
+                        ));
+                    }
+                    hash
+                }
+                // Hash the value again, rather than trusting the value hash stored with it.
+                Node::Leaf(leaf_node) => {
+                    LeafNode::new(leaf_node.account_key(), leaf_node.value().clone()).hash()
+                }
+            };
+            ensure!(
+                hash == expected_hash,
+                "State tree node {:?} hashes to {}, expected: {}",
+                node_key,
+                hash,
+                expected_hash,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn check_ledger_info(
+        &self,
+        li: &LedgerInfoWithSignatures,
+        prev_epoch_ending_li: Option<&LedgerInfoWithSignatures>,
+    ) -> Result<()> {
+        let ledger_info = li.ledger_info();
+        match prev_epoch_ending_li {
+            Some(prev) => prev
+                .ledger_info()
+                .next_epoch_state()
+                .ok_or_else(|| format_err!("Epoch ending LedgerInfo without next epoch state."))?
+                .verify(li)?,
+            // Nothing to verify the genesis LedgerInfo against, but a waypoint.
+            None => ensure!(
+                ledger_info.epoch() == 0,
+                "LedgerInfo of epoch {} without previous epoch ending LedgerInfo.",
+                ledger_info.epoch(),
+            ),
+        }
+        ensure!(
+            self.ledger_store.get_epoch(ledger_info.version())? == ledger_info.epoch(),
+            "Epoch of version doesn't match that in LedgerInfo: {}",
+            ledger_info,
+        );
+        let root_hash = self.ledger_store.get_root_hash(ledger_info.version())?;
+        ensure!(
+            root_hash == ledger_info.transaction_accumulator_hash(),
+            "Transaction accumulator root hash {} doesn't match that in LedgerInfo: {}",
+            root_hash,
+            ledger_info,
+        );
+        Ok(())
+    }
+
+    fn check_transaction(
+        &self,
+        version: Version,
+        ledger_info: &LedgerInfo,
+        allow_pruned_state: bool,
+    ) -> Result<()> {
+        // Transaction info, against the transaction accumulator.
+        let txn_info_with_proof = self
+            .ledger_store
+            .get_transaction_info_with_proof(version, ledger_info.version())?;
+        txn_info_with_proof.verify(ledger_info, version)?;
+        let txn_info = txn_info_with_proof.transaction_info();
+
+        let txn = self.transaction_store.get_transaction(version)?;
+        ensure!(
+            txn.hash() == txn_info.transaction_hash(),
+            "Transaction hash {} doesn't match that in TransactionInfo: {}",
+            txn.hash(),
+            txn_info,
+        );
+        if let Transaction::UserTransaction(signed_txn) = &txn {
+            let indexed_version = self.transaction_store.lookup_transaction_by_account(
+                signed_txn.sender(),
+                signed_txn.sequence_number(),
+                ledger_info.version(),
+            )?;
+            ensure!(
+                indexed_version == Some(version),
+                "Transaction by account index points to {:?}.",
+                indexed_version,
+            );
+        }
+
+        // State root, against the Jellyfish Merkle tree.
+        match self.state_store.get_root_hash_option(version)? {
+            Some(root_hash) => ensure!(
+                root_hash == txn_info.state_root_hash(),
+                "State tree root hash {} doesn't match that in TransactionInfo: {}",
+                root_hash,
+                txn_info,
+            ),
+            None => ensure!(allow_pruned_state, "State tree root is missing."),
+        }
+
+        // Events, against the event accumulator and indices.
+        let events = self.event_store.get_events_by_version(version)?;
+        let event_hashes = events.iter().map(ContractEvent::hash).collect::<Vec<_>>();
+        let event_root_hash =
+            InMemoryAccumulator::<EventAccumulatorHasher>::from_leaves(&event_hashes).root_hash();
+        ensure!(
+            event_root_hash == txn_info.event_root_hash(),
+            "Event root hash {} doesn't match that in TransactionInfo: {}",
+            event_root_hash,
+            txn_info,
+        );
+        for (idx, (event, event_hash)) in events.iter().zip(event_hashes).enumerate() {
+            let (_, proof) = self
+                .event_store
+                .get_event_with_proof_by_version_and_index(version, idx as u64)?;
+            proof.verify(txn_info.event_root_hash(), event_hash, idx as u64)?;
+
+            let indexed = self
+                .db
+                .get::<EventByKeySchema>(&(*event.key(), event.sequence_number()))?;
+            ensure!(
+                indexed == Some((version, idx as u64)),
+                "Event by key index of event {} points to {:?}.",
+                idx,
+                indexed,
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,78 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    schema::{jellyfish_merkle_node::JellyfishMerkleNodeSchema, transaction::TransactionSchema},
+    test_helper::arb_blocks_to_commit,
+    DiemDB,
+};
+use diem_jellyfish_merkle::node_type::{Node, NodeKey};
+use diem_temppath::TempPath;
+use diem_types::account_state_blob::AccountStateBlob;
+use proptest::prelude::*;
+use std::collections::HashMap;
+use storage_interface::DbWriter;
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(10))]
+
+    #[test]
+    fn test_consistency_check(input in arb_blocks_to_commit()) {
+        let tmp_dir = TempPath::new();
+        let db = DiemDB::new_for_test(&tmp_dir);
+
+        let mut cur_ver = 0;
+        for (txns_to_commit, ledger_info_with_sigs) in input.iter() {
+            db.save_transactions(&txns_to_commit, cur_ver, Some(ledger_info_with_sigs))
+                .unwrap();
+            cur_ver += txns_to_commit.len() as u64;
+        }
+        prop_assume!(cur_ver > 1);
+
+        let checker = db.get_consistency_checker();
+        prop_assert!(checker.check_ledger_infos().is_none());
+        let li = checker.get_latest_ledger_info().unwrap();
+        prop_assert_eq!(checker.get_first_version().unwrap(), 0);
+        prop_assert!(checker
+            .check_transactions(0, cur_ver, li.ledger_info(), false)
+            .is_none());
+
+        // Overwrite the last transaction with the first one.
+        let first_txn = db.transaction_store.get_transaction(0).unwrap();
+        let last_version = cur_ver - 1;
+        prop_assume!(db.transaction_store.get_transaction(last_version).unwrap() != first_txn);
+        db.db.put::<TransactionSchema>(&last_version, &first_txn).unwrap();
+
+        let corruption = checker
+            .check_transactions(0, cur_ver, li.ledger_info(), false)
+            .unwrap();
+        prop_assert_eq!(corruption.version, last_version);
+        prop_assert!(checker
+            .check_transactions(0, last_version, li.ledger_info(), false)
+            .is_none());
+
+        // Overwrite a node below the root of the latest state tree.
+        prop_assert!(checker
+            .check_state_tree(last_version, li.ledger_info())
+            .is_none());
+        let root_key = NodeKey::new_empty_path(last_version);
+        let root: Option<Node<AccountStateBlob>> =
+            db.db.get::<JellyfishMerkleNodeSchema>(&root_key).unwrap();
+        let (nibble, child) = match root {
+            Some(Node::Internal(internal_node)) => {
+                HashMap::from(internal_node).into_iter().next().unwrap()
+            }
+            _ => return Ok(()),
+        };
+        db.db
+            .put::<JellyfishMerkleNodeSchema>(
+                &root_key.gen_child_node_key(child.version, nibble),
+                &Node::new_null(),
+            )
+            .unwrap();
+        let corruption = checker
+            .check_state_tree(last_version, li.ledger_info())
+            .unwrap();
+        prop_assert_eq!(corruption.version, last_version);
+    }
+}
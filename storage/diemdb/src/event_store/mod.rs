@@ -34,17 +34,32 @@ use schemadb::{schema::ValueCodec, ReadOptions, SchemaIterator, DB};
 use std::{
     convert::{TryFrom, TryInto},
     iter::Peekable,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
 };
 
 #[derive(Debug)]
 pub(crate) struct EventStore {
     db: Arc<DB>,
+    /// The least readable version of the ledger pruner, if it is enabled.
+    ledger_least_readable_version: Option<Arc<AtomicU64>>,
 }
 
 impl EventStore {
-    pub fn new(db: Arc<DB>) -> Self {
-        Self { db }
+    pub fn new(db: Arc<DB>, ledger_least_readable_version: Option<Arc<AtomicU64>>) -> Self {
+        Self {
+            db,
+            ledger_least_readable_version,
+        }
+    }
+
+    /// Whether the ledger pruner has deleted any history.
+    fn ledger_pruned(&self) -> bool {
+        self.ledger_least_readable_version
+            .as_ref()
+            .map_or(false, |v| v.load(Ordering::Relaxed) > 0)
     }
 
     /// Get all of the events given a transaction version.
@@ -194,6 +209,11 @@ impl EventStore {
             if path != *event_key || ver > ledger_version {
                 break;
             }
+            // The ledger pruner removes the lowest sequence numbers, the caller checks for that.
+            // A gap is only allowed at the start, if the history before the event was pruned.
+            if result.is_empty() && seq > cur_seq && self.ledger_pruned() {
+                cur_seq = seq;
+            }
             ensure!(
                 seq == cur_seq,
                 "DB corrupt: Sequence number not continuous, expected: {}, actual: {}.",
@@ -214,7 +234,7 @@ impl EventStore {
         ledger_version: Version,
     ) -> Result<(Version, u64)> {
         let indices = self.lookup_events_by_key(event_key, seq_num, 1, ledger_version)?;
-        if indices.is_empty() {
+        if indices.first().map_or(true, |(seq, _, _)| *seq != seq_num) {
             return Err(DiemDbError::NotFound(format!(
                 "Event {} of seq num {}.",
                 event_key, seq_num
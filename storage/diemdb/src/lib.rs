@@ -16,6 +16,7 @@ pub mod diemsum;
 pub mod test_helper;
 
 pub mod backup;
+pub mod consistency_check;
 pub mod errors;
 pub mod metrics;
 pub mod schema;
@@ -39,6 +40,7 @@ pub use diemdb_test::test_save_blocks_impl;
 use crate::{
     backup::{backup_handler::BackupHandler, restore_handler::RestoreHandler},
     change_set::{ChangeSet, SealedChangeSet},
+    consistency_check::ConsistencyChecker,
     errors::DiemDbError,
     event_store::EventStore,
     ledger_counters::LedgerCounters,
@@ -49,7 +51,10 @@ use crate::{
         DIEM_STORAGE_NEXT_BLOCK_EPOCH, DIEM_STORAGE_OTHER_TIMERS_SECONDS,
         DIEM_STORAGE_ROCKSDB_PROPERTIES,
     },
-    pruner::Pruner,
+    pruner::{
+        ledger_pruner::{get_first_txn_version, LedgerPruner},
+        Pruner,
+    },
     schema::*,
     state_store::StateStore,
     system_store::SystemStore,
@@ -58,6 +63,10 @@ use crate::{
 use anyhow::{ensure, Result};
 use diem_config::config::RocksdbConfig;
 use diem_crypto::hash::{CryptoHash, HashValue, SPARSE_MERKLE_PLACEHOLDER_HASH};
+use diem_jellyfish_merkle::{
+    node_type::{Node, NodeKey},
+    TreeReader,
+};
 use diem_logger::prelude::*;
 use diem_types::{
     account_address::AccountAddress,
@@ -86,7 +95,7 @@ use std::{
     thread::{self, JoinHandle},
     time::{Duration, Instant},
 };
-use storage_interface::{DbReader, DbWriter, Order, StartupInfo, TreeState};
+use storage_interface::{DbReader, DbWriter, Order, PrunedError, StartupInfo, TreeState};
 
 const MAX_LIMIT: u64 = 1000;
 
@@ -209,6 +218,7 @@ pub struct DiemDB {
     system_store: SystemStore,
     rocksdb_property_reporter: RocksdbPropertyReporter,
     pruner: Option<Pruner>,
+    ledger_pruner: Option<LedgerPruner>,
 }
 
 impl DiemDB {
@@ -230,18 +240,25 @@ impl DiemDB {
         ]
     }
 
-    fn new_with_db(db: DB, prune_window: Option<u64>) -> Self {
+    fn new_with_db(db: DB, prune_window: Option<u64>, ledger_prune_window: Option<u64>) -> Self {
         let db = Arc::new(db);
+        let ledger_pruner = ledger_prune_window.map(|n| LedgerPruner::new(Arc::clone(&db), n));
 
         DiemDB {
             db: Arc::clone(&db),
-            event_store: Arc::new(EventStore::new(Arc::clone(&db))),
+            event_store: Arc::new(EventStore::new(
+                Arc::clone(&db),
+                ledger_pruner
+                    .as_ref()
+                    .map(LedgerPruner::least_readable_version_handle),
+            )),
             ledger_store: Arc::new(LedgerStore::new(Arc::clone(&db))),
             state_store: Arc::new(StateStore::new(Arc::clone(&db))),
             transaction_store: Arc::new(TransactionStore::new(Arc::clone(&db))),
             system_store: SystemStore::new(Arc::clone(&db)),
             rocksdb_property_reporter: RocksdbPropertyReporter::new(Arc::clone(&db)),
             pruner: prune_window.map(|n| Pruner::new(Arc::clone(&db), n)),
+            ledger_pruner,
         }
     }
 
@@ -250,11 +267,26 @@ impl DiemDB {
         readonly: bool,
         prune_window: Option<u64>,
         rocksdb_config: RocksdbConfig,
+    ) -> Result<Self> {
+        Self::open_with_ledger_pruner(db_root_path, readonly, prune_window, None, rocksdb_config)
+    }
+
+    /// Like `open`, also deleting the ledger history older than `ledger_prune_window` versions.
+    pub fn open_with_ledger_pruner<P: AsRef<Path> + Clone>(
+        db_root_path: P,
+        readonly: bool,
+        prune_window: Option<u64>,
+        ledger_prune_window: Option<u64>,
+        rocksdb_config: RocksdbConfig,
     ) -> Result<Self> {
         ensure!(
             prune_window.is_none() || !readonly,
             "Do not set prune_window when opening readonly.",
         );
+        ensure!(
+            ledger_prune_window.is_none() || !readonly,
+            "Do not set ledger_prune_window when opening readonly.",
+        );
 
         let path = db_root_path.as_ref().join("diemdb");
         let instant = Instant::now();
@@ -279,7 +311,7 @@ impl DiemDB {
             )?
         };
 
-        let ret = Self::new_with_db(db, prune_window);
+        let ret = Self::new_with_db(db, prune_window, ledger_prune_window);
         info!(
             path = path,
             time_ms = %instant.elapsed().as_millis(),
@@ -308,6 +340,7 @@ impl DiemDB {
                 &rocksdb_opts,
             )?,
             None, // prune_window
+            None, // ledger_prune_window
         ))
     }
 
@@ -396,6 +429,7 @@ impl DiemDB {
         ledger_version: Version,
         fetch_events: bool,
     ) -> Result<TransactionWithProof> {
+        self.error_if_ledger_pruned("Transaction", version)?;
         let proof = self
             .ledger_store
             .get_transaction_info_with_proof(version, ledger_version)?;
@@ -428,6 +462,28 @@ impl DiemDB {
         )
     }
 
+    // ============================== Consistency Check APIs ==============================
+
+    /// Gets an instance of `ConsistencyChecker` to check the integrity of the DB.
+    pub fn get_consistency_checker(&self) -> ConsistencyChecker {
+        ConsistencyChecker::new(
+            Arc::clone(&self.db),
+            Arc::clone(&self.ledger_store),
+            Arc::clone(&self.transaction_store),
+            Arc::clone(&self.state_store),
+            Arc::clone(&self.event_store),
+        )
+    }
+
+    /// Gets a node of the Jellyfish Merkle tree that stores the account states. Meant for
+    /// inspecting the DB, normal reads go through `DbReader`.
+    pub fn get_jellyfish_merkle_node(
+        &self,
+        node_key: &NodeKey,
+    ) -> Result<Option<Node<AccountStateBlob>>> {
+        self.state_store.get_node_option(node_key)
+    }
+
     // ================================== Private APIs ==================================
     fn get_events_with_proof_by_event_key(
         &self,
@@ -461,6 +517,17 @@ impl DiemDB {
             ledger_version,
         )?;
 
+        // The ledger pruner removes the lowest sequence numbers of the event key.
+        if let Some((seq_num, _, _)) = event_indices.first() {
+            if *seq_num > first_seq {
+                return Err(PrunedError {
+                    what: format!("Event {} of seq num {}", event_key, first_seq),
+                    first_version: self.get_first_txn_version()?,
+                }
+                .into());
+            }
+        }
+
         // When descending, it's possible that user is asking for something beyond the latest
         // sequence number, in which case we will consider it a bad request and return an empty
         // list.
@@ -592,6 +659,27 @@ impl DiemDB {
         if let Some(pruner) = self.pruner.as_ref() {
             pruner.wake(latest_version)
         }
+        if let Some(ledger_pruner) = self.ledger_pruner.as_ref() {
+            ledger_pruner.wake(latest_version)
+        }
+    }
+
+    /// Errors with `PrunedError` if the ledger history at `version` was pruned. Without the
+    /// ledger pruner nothing is pruned, and the first transaction isn't looked up.
+    fn error_if_ledger_pruned(&self, what: &str, version: Version) -> Result<()> {
+        let first_version = match self.ledger_pruner.as_ref() {
+            Some(ledger_pruner) => ledger_pruner.least_readable_version(),
+            None => return Ok(()),
+        };
+        if version < first_version {
+            Err(PrunedError {
+                what: format!("{} at version {}", what, version),
+                first_version,
+            }
+            .into())
+        } else {
+            Ok(())
+        }
     }
 }
 
@@ -638,8 +726,26 @@ impl DbReader for DiemDB {
         fetch_events: bool,
     ) -> Result<Option<TransactionWithProof>> {
         gauged_api("get_txn_by_account", || {
-            self.transaction_store
-                .lookup_transaction_by_account(address, seq_num, ledger_version)?
+            let version = self.transaction_store.lookup_transaction_by_account(
+                address,
+                seq_num,
+                ledger_version,
+            )?;
+            if version.is_none() {
+                // The ledger pruner removes the lowest sequence numbers of the account.
+                if let Some(first_seq_num) =
+                    self.transaction_store.get_first_sequence_number(address)?
+                {
+                    if seq_num < first_seq_num {
+                        return Err(PrunedError {
+                            what: format!("Transaction {} of account {}", seq_num, address),
+                            first_version: self.get_first_txn_version()?,
+                        }
+                        .into());
+                    }
+                }
+            }
+            version
                 .map(|version| {
                     self.get_transaction_with_proof(version, ledger_version, fetch_events)
                 })
@@ -664,6 +770,7 @@ impl DbReader for DiemDB {
             if start_version > ledger_version || limit == 0 {
                 return Ok(TransactionListWithProof::new_empty());
             }
+            self.error_if_ledger_pruned("Transaction", start_version)?;
 
             let limit = std::cmp::min(limit, ledger_version - start_version + 1);
 
@@ -821,6 +928,7 @@ impl DbReader for DiemDB {
                     latest_version
                 );
             }
+            self.error_if_ledger_pruned("Account state proof", version)?;
 
             let txn_info_with_proof = self
                 .ledger_store
@@ -915,6 +1023,15 @@ impl DbReader for DiemDB {
         })
     }
 
+    fn get_first_txn_version(&self) -> Result<Version> {
+        gauged_api("get_first_txn_version", || {
+            match self.ledger_pruner.as_ref() {
+                Some(ledger_pruner) => Ok(ledger_pruner.least_readable_version()),
+                None => get_first_txn_version(&self.db),
+            }
+        })
+    }
+
     fn get_accumulator_root_hash(&self, version: Version) -> Result<HashValue> {
         gauged_api("get_accumulator_root_hash", || {
             self.ledger_store.get_root_hash(version)
@@ -0,0 +1,282 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module provides `LedgerPruner` which deletes the ledger history (transactions, transaction
+//! infos and events, with their indices) older than a window, in the background like `Pruner`.
+//!
+//! The transaction accumulator is kept, so the retained transactions and the ledger infos stay
+//! provable. Write sets are not persisted by this DB, only their hashes in the transaction infos.
+
+use super::Command;
+use crate::{
+    metrics::{
+        DIEM_STORAGE_LEDGER_PRUNE_WINDOW, DIEM_STORAGE_OTHER_TIMERS_SECONDS,
+        DIEM_STORAGE_PRUNER_LEAST_READABLE_LEDGER_VERSION,
+    },
+    schema::{
+        event::EventSchema, event_accumulator::EventAccumulatorSchema,
+        event_by_key::EventByKeySchema, event_by_version::EventByVersionSchema,
+        transaction::TransactionSchema, transaction_by_account::TransactionByAccountSchema,
+        transaction_info::TransactionInfoSchema,
+    },
+};
+use anyhow::Result;
+use diem_infallible::Mutex;
+use diem_logger::prelude::*;
+use diem_types::{
+    proof::position::Position,
+    transaction::{Transaction, Version},
+};
+use schemadb::{ReadOptions, SchemaBatch, DB};
+use std::{
+    cmp::min,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{channel, Receiver, Sender},
+        Arc,
+    },
+    thread::JoinHandle,
+};
+
+/// The `LedgerPruner` is meant to be part of a `DiemDB` instance and runs in the background to
+/// prune old ledger history.
+#[derive(Debug)]
+pub(crate) struct LedgerPruner {
+    /// Other than the latest version, how many historical versions of the ledger to keep.
+    historical_versions_to_keep: u64,
+    /// The worker thread handle, it only becomes `None` after joined in `drop()`.
+    worker_thread: Option<JoinHandle<()>>,
+    /// The sender side of the channel talking to the worker thread.
+    command_sender: Mutex<Sender<Command>>,
+    /// Set by the worker thread, versions before it can no longer be accessed.
+    least_readable_version: Arc<AtomicU64>,
+}
+
+impl LedgerPruner {
+    /// Creates a worker thread that waits on a channel for pruning commands.
+    pub fn new(db: Arc<DB>, historical_versions_to_keep: u64) -> Self {
+        let (command_sender, command_receiver) = channel();
+
+        // Found before the worker starts, so that reads never see a pruned version as readable.
+        let least_readable_version = get_first_txn_version(&db).unwrap_or_else(|e| {
+            error!(
+                error = ?e,
+                "[ledger pruner] Error seeking the first transaction, assuming 0.",
+            );
+            0
+        });
+        info!(
+            least_readable_version = least_readable_version,
+            "[ledger pruner worker] initialized."
+        );
+        DIEM_STORAGE_LEDGER_PRUNE_WINDOW.set(historical_versions_to_keep as i64);
+        DIEM_STORAGE_PRUNER_LEAST_READABLE_LEDGER_VERSION.set(least_readable_version as i64);
+        let least_readable_version = Arc::new(AtomicU64::new(least_readable_version));
+        let least_readable_version_clone = Arc::clone(&least_readable_version);
+
+        let worker_thread = std::thread::Builder::new()
+            .name("diemdb_ledger_pruner".into())
+            .spawn(move || Worker::new(db, command_receiver, least_readable_version_clone).work())
+            .expect("Creating ledger pruner thread should succeed.");
+
+        Self {
+            historical_versions_to_keep,
+            worker_thread: Some(worker_thread),
+            command_sender: Mutex::new(command_sender),
+            least_readable_version,
+        }
+    }
+
+    /// The first version whose transaction, info and events are still readable.
+    pub fn least_readable_version(&self) -> Version {
+        self.least_readable_version.load(Ordering::Relaxed)
+    }
+
+    /// Shares the least readable version with the stores that tell pruned history apart from
+    /// missing data, without looking up the first transaction every time.
+    pub fn least_readable_version_handle(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.least_readable_version)
+    }
+
+    /// Sends pruning command to the worker thread when necessary.
+    pub fn wake(&self, latest_version: Version) {
+        if latest_version > self.historical_versions_to_keep {
+            let least_readable_version = latest_version - self.historical_versions_to_keep;
+            self.command_sender
+                .lock()
+                .send(Command::Prune {
+                    least_readable_version,
+                })
+                .expect("Receiver should not destruct prematurely.");
+        }
+    }
+}
+
+impl Drop for LedgerPruner {
+    fn drop(&mut self) {
+        self.command_sender
+            .lock()
+            .send(Command::Quit)
+            .expect("Receiver should not destruct.");
+        self.worker_thread
+            .take()
+            .expect("Worker thread must exist.")
+            .join()
+            .expect("Worker thread should join peacefully.");
+    }
+}
+
+struct Worker {
+    db: Arc<DB>,
+    command_receiver: Receiver<Command>,
+    target_least_readable_version: Version,
+    least_readable_version: Arc<AtomicU64>,
+    /// Indicates if there's NOT any pending work to do currently.
+    blocking_recv: bool,
+}
+
+impl Worker {
+    const MAX_VERSIONS_TO_PRUNE_PER_BATCH: u64 = 100;
+
+    fn new(
+        db: Arc<DB>,
+        command_receiver: Receiver<Command>,
+        least_readable_version: Arc<AtomicU64>,
+    ) -> Self {
+        Self {
+            db,
+            command_receiver,
+            target_least_readable_version: least_readable_version.load(Ordering::Relaxed),
+            least_readable_version,
+            blocking_recv: true,
+        }
+    }
+
+    fn work(mut self) {
+        while self.receive_commands() {
+            // Prune a small batch before receiving commands again, in case `Command::Quit` is
+            // received.
+            let least_readable_version = self.least_readable_version.load(Ordering::Relaxed);
+            let new_least_readable_version = min(
+                self.target_least_readable_version,
+                least_readable_version + Self::MAX_VERSIONS_TO_PRUNE_PER_BATCH,
+            );
+            if new_least_readable_version <= least_readable_version {
+                self.blocking_recv = true;
+                continue;
+            }
+
+            // Readers are told the batch is gone before it is deleted, so they get a clean
+            // `PrunedError` instead of missing data.
+            self.record_progress(new_least_readable_version);
+            match prune_ledger(&self.db, least_readable_version, new_least_readable_version) {
+                Ok(()) => {
+                    self.blocking_recv =
+                        new_least_readable_version == self.target_least_readable_version;
+                }
+                Err(e) => {
+                    error!(
+                        error = ?e,
+                        "Error pruning ledger history.",
+                    );
+                    self.record_progress(least_readable_version);
+                    // On error, stop retrying vigorously by making next recv() blocking.
+                    self.blocking_recv = true;
+                }
+            }
+        }
+    }
+
+    fn record_progress(&mut self, least_readable_version: Version) {
+        self.least_readable_version
+            .store(least_readable_version, Ordering::Relaxed);
+        DIEM_STORAGE_PRUNER_LEAST_READABLE_LEDGER_VERSION.set(least_readable_version as i64);
+    }
+
+    /// Receives all pending commands like `Pruner`'s worker, returns `false` on `Command::Quit`.
+    fn receive_commands(&mut self) -> bool {
+        loop {
+            let command = if self.blocking_recv {
+                self.command_receiver
+                    .recv()
+                    .expect("Sender should not destruct prematurely.")
+            } else {
+                match self.command_receiver.try_recv() {
+                    Ok(command) => command,
+                    Err(_) => return true,
+                }
+            };
+
+            match command {
+                Command::Quit => return false,
+                Command::Prune {
+                    least_readable_version,
+                } => {
+                    if least_readable_version > self.target_least_readable_version {
+                        self.target_least_readable_version = least_readable_version;
+                        self.blocking_recv = false;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The version of the first transaction in the DB, 0 if there is none.
+pub fn get_first_txn_version(db: &DB) -> Result<Version> {
+    let mut iter = db.iter::<TransactionSchema>(ReadOptions::default())?;
+    iter.seek_to_first();
+    Ok(iter.next().transpose()?.map_or(0, |(version, _)| version))
+}
+
+/// Deletes the transactions in `[begin, end)`, their infos, events and indices in one batch.
+pub fn prune_ledger(db: &DB, begin: Version, end: Version) -> Result<()> {
+    if begin >= end {
+        return Ok(());
+    }
+    let _timer = DIEM_STORAGE_OTHER_TIMERS_SECONDS
+        .with_label_values(&["ledger_pruner_commit"])
+        .start_timer();
+    let mut batch = SchemaBatch::new();
+
+    let mut iter = db.iter::<TransactionSchema>(ReadOptions::default())?;
+    iter.seek(&begin)?;
+    for res in iter {
+        let (version, txn) = res?;
+        if version >= end {
+            break;
+        }
+        if let Transaction::UserTransaction(signed_txn) = txn {
+            batch.delete::<TransactionByAccountSchema>(&(
+                signed_txn.sender(),
+                signed_txn.sequence_number(),
+            ))?;
+        }
+        batch.delete::<TransactionSchema>(&version)?;
+        batch.delete::<TransactionInfoSchema>(&version)?;
+    }
+
+    let mut iter = db.iter::<EventSchema>(ReadOptions::default())?;
+    iter.seek(&begin)?;
+    for res in iter {
+        let ((version, index), event) = res?;
+        if version >= end {
+            break;
+        }
+        batch.delete::<EventByKeySchema>(&(*event.key(), event.sequence_number()))?;
+        batch.delete::<EventByVersionSchema>(&(*event.key(), version, event.sequence_number()))?;
+        batch.delete::<EventSchema>(&(version, index))?;
+    }
+
+    let mut iter = db.iter::<EventAccumulatorSchema>(ReadOptions::default())?;
+    iter.seek(&(begin, Position::from_inorder_index(0)))?;
+    for res in iter {
+        let ((version, position), _) = res?;
+        if version >= end {
+            break;
+        }
+        batch.delete::<EventAccumulatorSchema>(&(version, position))?;
+    }
+
+    db.write_schemas(batch)
+}
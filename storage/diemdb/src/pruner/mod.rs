@@ -408,5 +408,6 @@ pub fn prune_state(
     }
 }
 
+pub(crate) mod ledger_pruner;
 #[cfg(test)]
 mod test;
@@ -1,12 +1,20 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use super::*;
-use crate::{change_set::ChangeSet, state_store::StateStore, DiemDB};
+use super::{
+    ledger_pruner::{get_first_txn_version, prune_ledger},
+    *,
+};
+use crate::{
+    change_set::ChangeSet, state_store::StateStore, test_helper::arb_blocks_to_commit, DiemDB,
+};
+use diem_config::config::RocksdbConfig;
 use diem_crypto::HashValue;
 use diem_temppath::TempPath;
 use diem_types::{account_address::AccountAddress, account_state_blob::AccountStateBlob};
+use proptest::prelude::*;
 use std::collections::HashMap;
+use storage_interface::{DbReader, DbWriter, PrunedError};
 
 fn put_account_state_set(
     db: &DB,
@@ -155,3 +163,96 @@ fn test_worker_quit_eagerly() {
         verify_state_in_store(state_store, address, Some(&value2), 2);
     }
 }
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(10))]
+
+    #[test]
+    fn test_ledger_pruner(input in arb_blocks_to_commit()) {
+        let tmp_dir = TempPath::new();
+        let db = DiemDB::new_for_test(&tmp_dir);
+
+        let mut cur_ver = 0;
+        for (txns_to_commit, ledger_info_with_sigs) in input.iter() {
+            db.save_transactions(&txns_to_commit, cur_ver, Some(ledger_info_with_sigs))
+                .unwrap();
+            cur_ver += txns_to_commit.len() as u64;
+        }
+        let latest_ledger_info = input.last().unwrap().1.ledger_info();
+        let ledger_version = cur_ver - 1;
+        let first_version = cur_ver / 2;
+
+        prune_ledger(&db.db, 0, first_version).unwrap();
+        prop_assert_eq!(get_first_txn_version(&db.db).unwrap(), first_version);
+
+        // Reopened with a ledger pruner, which never prunes more but knows what was pruned.
+        drop(db);
+        let db = DiemDB::open_with_ledger_pruner(
+            &tmp_dir,
+            false,          /* readonly */
+            None,           /* pruner */
+            Some(u64::MAX), /* ledger_pruner */
+            RocksdbConfig::default(),
+        )
+        .unwrap();
+        prop_assert_eq!(db.get_first_txn_version().unwrap(), first_version);
+
+        // The retained transactions are still provable by the latest ledger info.
+        let limit = cur_ver - first_version;
+        db.get_transactions(first_version, limit, ledger_version, true)
+            .unwrap()
+            .verify(latest_ledger_info, Some(first_version))
+            .unwrap();
+
+        if first_version > 0 {
+            let err = db
+                .get_transactions(0, limit, ledger_version, true)
+                .unwrap_err();
+            prop_assert_eq!(
+                err.downcast_ref::<PrunedError>().map(|e| e.first_version),
+                Some(first_version)
+            );
+        }
+
+        let txns = input.iter().flat_map(|(txns, _)| txns);
+        for (version, txn_to_commit) in txns.enumerate() {
+            let user_txn = match txn_to_commit.transaction().as_signed_user_txn() {
+                Ok(txn) => txn,
+                Err(_) => continue,
+            };
+            let res = db.get_txn_by_account(
+                user_txn.sender(),
+                user_txn.sequence_number(),
+                ledger_version,
+                true,
+            );
+            if (version as u64) < first_version {
+                // Pruned, unless no later transaction of the account is left to tell.
+                match res {
+                    Ok(txn) => prop_assert!(txn.is_none()),
+                    Err(e) => prop_assert!(e.downcast_ref::<PrunedError>().is_some()),
+                }
+            } else {
+                prop_assert_eq!(res.unwrap().unwrap().version, version as u64);
+            }
+
+            // The account states stay in the state tree, but not the transaction infos proving
+            // them.
+            let address = match txn_to_commit.account_states().keys().next() {
+                Some(address) => *address,
+                None => continue,
+            };
+            let res = db.get_account_state_with_proof(address, version as u64, ledger_version);
+            if (version as u64) < first_version {
+                prop_assert_eq!(
+                    res.unwrap_err().downcast_ref::<PrunedError>().map(|e| e.first_version),
+                    Some(first_version)
+                );
+            } else {
+                res.unwrap()
+                    .verify(latest_ledger_info, version as u64, address)
+                    .unwrap();
+            }
+        }
+    }
+}
@@ -14,7 +14,7 @@ use diem_types::{
     block_metadata::BlockMetadata,
     transaction::{Transaction, Version},
 };
-use schemadb::{SchemaIterator, DB};
+use schemadb::{ReadOptions, SchemaIterator, DB};
 use std::sync::Arc;
 
 #[derive(Debug)]
@@ -46,6 +46,18 @@ impl TransactionStore {
         Ok(None)
     }
 
+    /// Gets the lowest sequence number of the transactions of `address` kept in the DB.
+    pub fn get_first_sequence_number(&self, address: AccountAddress) -> Result<Option<u64>> {
+        let mut iter = self
+            .db
+            .iter::<TransactionByAccountSchema>(ReadOptions::default())?;
+        iter.seek(&(address, 0))?;
+        Ok(iter
+            .next()
+            .transpose()?
+            .and_then(|((sender, seq_num), _)| (sender == address).then(|| seq_num)))
+    }
+
     /// Get signed transaction given `version`
     pub fn get_transaction(&self, version: Version) -> Result<Transaction> {
         self.db
@@ -0,0 +1,149 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![forbid(unsafe_code)]
+
+use anyhow::{ensure, Result};
+use diem_config::config::RocksdbConfig;
+use diem_logger::info;
+use diem_types::transaction::Version;
+use diemdb::{consistency_check::Corruption, DiemDB};
+use rayon::prelude::*;
+use std::{
+    cmp::min,
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use structopt::StructOpt;
+
+/// Checks that a DiemDB is internally consistent, and reports the first corrupted version found.
+/// The DB is opened read only, so it can be run against the DB of a live node.
+#[derive(Debug, StructOpt)]
+struct Opt {
+    #[structopt(long, parse(from_os_str))]
+    db: PathBuf,
+
+    #[structopt(
+        long,
+        help = "First version to check. Defaults to the first transaction kept in the DB."
+    )]
+    start_version: Option<Version>,
+
+    #[structopt(
+        long,
+        help = "Last version to check. Defaults to the version of the latest LedgerInfo."
+    )]
+    end_version: Option<Version>,
+
+    #[structopt(
+        long,
+        default_value = "10000",
+        help = "Number of versions a worker checks at a time."
+    )]
+    range_size: u64,
+
+    #[structopt(long, help = "Number of workers. Defaults to the number of CPUs.")]
+    concurrency: Option<usize>,
+
+    #[structopt(
+        long,
+        help = "Don't report missing state tree roots, which are expected if the state pruner \
+        is on."
+    )]
+    allow_pruned_state: bool,
+
+    #[structopt(
+        long,
+        help = "Also walk the whole state tree at the last version checked and hash every node \
+        again, rather than only comparing the root hash. Slow on a large state."
+    )]
+    check_state_tree: bool,
+}
+
+fn main() {
+    ::diem_logger::DiemLogger::builder().build();
+
+    match check(Opt::from_args()) {
+        Ok(None) => println!("No corruption found."),
+        Ok(Some(corruption)) => {
+            println!("{}", corruption);
+            std::process::exit(1);
+        }
+        Err(e) => {
+            println!("Check failed: {}", e);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn check(opt: Opt) -> Result<Option<Corruption>> {
+    ensure!(opt.range_size > 0, "--range-size must be greater than 0.");
+    let db = DiemDB::open(
+        &opt.db,
+        true, /* readonly */
+        None, /* pruner */
+        RocksdbConfig::default(),
+    )?;
+    let checker = db.get_consistency_checker();
+
+    info!("Checking ledger infos.");
+    let ledger_info_corruption = checker.check_ledger_infos();
+
+    let li = checker.get_latest_ledger_info()?;
+    let start_version = match opt.start_version {
+        Some(version) => version,
+        None => checker.get_first_version()?,
+    };
+    let end_version = min(
+        opt.end_version.unwrap_or(Version::max_value()),
+        li.ledger_info().version(),
+    );
+    info!(
+        "Checking versions [{}, {}] against ledger info: {}",
+        start_version,
+        end_version,
+        li.ledger_info()
+    );
+
+    let ranges = (start_version..=end_version)
+        .step_by(opt.range_size as usize)
+        .map(|begin| (begin, min(begin + opt.range_size, end_version + 1)))
+        .collect::<Vec<_>>();
+    let num_checked = AtomicU64::new(0);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(opt.concurrency.unwrap_or(0))
+        .thread_name(|index| format!("db_check_{}", index))
+        .build()?;
+    let txn_corruption = pool.install(|| {
+        ranges
+            .par_iter()
+            .filter_map(|(begin, end)| {
+                let res = checker.check_transactions(
+                    *begin,
+                    *end,
+                    li.ledger_info(),
+                    opt.allow_pruned_state,
+                );
+                let checked = num_checked.fetch_add(end - begin, Ordering::Relaxed) + end - begin;
+                info!(
+                    "Checked versions [{}, {}), {} versions done.",
+                    begin, end, checked
+                );
+                res
+            })
+            .min_by_key(|corruption| corruption.version)
+    });
+
+    let state_tree_corruption = if opt.check_state_tree {
+        info!("Checking the state tree at version {}.", end_version);
+        checker.check_state_tree(end_version, li.ledger_info())
+    } else {
+        None
+    };
+
+    Ok(ledger_info_corruption
+        .into_iter()
+        .chain(txn_corruption)
+        .chain(state_tree_corruption)
+        .min_by_key(|corruption| corruption.version))
+}
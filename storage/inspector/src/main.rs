@@ -3,20 +3,31 @@
 
 #![forbid(unsafe_code)]
 
-use anyhow::Result;
+use anyhow::{bail, ensure, format_err, Result};
 use diem_config::config::RocksdbConfig;
-use diem_framework_releases::name_for_script;
+use diem_crypto::hash::{CryptoHash, HashValue};
+use diem_jellyfish_merkle::node_type::{Node, NodeKey};
 use diem_logger::info;
+use diem_nibble::Nibble;
 use diemdb::DiemDB;
-use std::path::PathBuf;
-use storage_interface::DbReader;
+use serde::Serialize;
+use serde_json::json;
+use std::{collections::BTreeMap, path::PathBuf};
+use storage_interface::{DbReader, Order};
 
 use diem_types::{
-    account_address::AccountAddress, account_config::AccountResource, account_state::AccountState,
+    account_address::{AccountAddress, HashAccountAddress},
+    account_config::diem_root_address,
+    account_state::AccountState,
+    account_state_blob::AccountStateBlob,
+    event::EventKey,
+    ledger_info::LedgerInfoWithSignatures,
+    transaction::Version,
 };
 use std::convert::TryFrom;
 use structopt::StructOpt;
 
+/// Inspects a DiemDB. Everything is printed as JSON, one value per line.
 #[derive(Debug, StructOpt)]
 struct Opt {
     #[structopt(long, parse(from_os_str))]
@@ -36,120 +47,451 @@ enum Command {
     PrintAccount {
         #[structopt(parse(try_from_str))]
         address: AccountAddress,
+        /// Defaults to the latest version.
+        #[structopt(long)]
+        version: Option<Version>,
     },
     #[structopt(name = "list-accounts")]
     ListAccounts,
+    /// Events emitted to an event key, by sequence number.
+    #[structopt(name = "list-events-by-key")]
+    ListEventsByKey {
+        #[structopt(parse(try_from_str))]
+        key: EventKey,
+        #[structopt(long, default_value = "0")]
+        start_seq_num: u64,
+        #[structopt(long, default_value = "100")]
+        limit: u64,
+    },
+    /// Events emitted by the transactions in [start_version, end_version).
+    #[structopt(name = "list-events-by-version")]
+    ListEventsByVersion {
+        start_version: Version,
+        end_version: Version,
+    },
+    /// Versions that changed the state of an account, latest first.
+    #[structopt(name = "print-account-history")]
+    PrintAccountHistory {
+        #[structopt(parse(try_from_str))]
+        address: AccountAddress,
+        /// Looks back from this version, defaults to the latest version.
+        #[structopt(long)]
+        version: Option<Version>,
+        #[structopt(long, default_value = "100")]
+        limit: usize,
+    },
+    /// Node of the state tree at a version, found by its nibble path in hex, e.g. "1f".
+    #[structopt(name = "print-jmt-node")]
+    PrintJMTNode {
+        version: Version,
+        #[structopt(default_value = "")]
+        nibble_path: String,
+    },
+    /// Account state with the sparse Merkle proof against the state root.
+    #[structopt(name = "print-account-proof")]
+    PrintAccountProof {
+        #[structopt(parse(try_from_str))]
+        address: AccountAddress,
+        /// Defaults to the latest version.
+        #[structopt(long)]
+        version: Option<Version>,
+    },
+    /// Epoch ending ledger infos of [start_epoch, end_epoch).
+    #[structopt(name = "list-ledger-infos")]
+    ListLedgerInfos {
+        #[structopt(long, default_value = "0")]
+        start_epoch: u64,
+        /// Defaults to the latest epoch.
+        #[structopt(long)]
+        end_epoch: Option<u64>,
+    },
+    /// Validator sets taking effect after the epochs in [start_epoch, end_epoch) end.
+    #[structopt(name = "list-validator-sets")]
+    ListValidatorSets {
+        #[structopt(long, default_value = "0")]
+        start_epoch: u64,
+        /// Defaults to the latest epoch.
+        #[structopt(long)]
+        end_epoch: Option<u64>,
+    },
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string(value)?);
+    Ok(())
 }
 
 /// Print out latest information stored in the DB.
 fn print_head(db: &DiemDB) -> Result<()> {
     let si = db
-        .get_startup_info()
-        .expect("Can't get startup info")
-        .expect("StartupInfo is empty, database is empty.");
+        .get_startup_info()?
+        .ok_or_else(|| format_err!("StartupInfo is empty, database is empty."))?;
 
     let version = si.latest_ledger_info.ledger_info().version();
-    info!("Version: {}", version);
+    let backup = db.get_backup_handler();
+    let num_accounts = backup.get_account_iter(version)?.count();
+    print_json(&json!({
+        "version": version,
+        "latest_ledger_info": si.latest_ledger_info,
+        "epoch_state": si.get_epoch_state(),
+        "num_accounts": num_accounts,
+    }))?;
 
-    info!(
-        "The latest ledger info: {}",
-        si.latest_ledger_info.ledger_info()
-    );
+    print_txn(db, version)
+}
 
-    info!("Signatures: {:?}", si.latest_ledger_info.signatures());
+fn print_txn(db: &DiemDB, version: u64) -> Result<()> {
+    let tx_list = db.get_transactions(version, 1, version, true /* fetch_events */)?;
+    let tx = tx_list
+        .transactions
+        .first()
+        .ok_or_else(|| format_err!("Got empty txn list."))?;
+    print_json(&json!({
+        "version": version,
+        "transaction": tx,
+        "info": tx_list.proof.transaction_infos().first(),
+        "events": tx_list.events.as_ref().and_then(|events| events.first()),
+    }))
+}
 
-    info!("Current EpochState: {}", si.get_epoch_state());
+fn print_account(db: &DiemDB, address: AccountAddress, version: Option<Version>) -> Result<()> {
+    let version = match version {
+        Some(version) => version,
+        None => db.get_latest_version()?,
+    };
+    let (blob, _proof) = db.get_account_state_with_proof_by_version(address, version)?;
+    print_json(&json!({
+        "address": address,
+        "version": version,
+        "resources": blob.as_ref().map(account_resources).transpose()?,
+    }))
+}
 
+/// Resources in an account, by the struct tag in hex encoded BCS bytes.
+fn account_resources(blob: &AccountStateBlob) -> Result<BTreeMap<String, String>> {
+    Ok(AccountState::try_from(blob)?
+        .get_resources()
+        .map(|(struct_tag, bytes)| (struct_tag.to_string(), hex::encode(bytes)))
+        .collect())
+}
+
+fn list_txns(db: &DiemDB) -> Result<()> {
+    let version = db.get_latest_version()?;
     let backup = db.get_backup_handler();
-    let iter = backup.get_account_iter(version)?;
-    let num_account_state = iter.count();
-    info!("Total Accounts: {}", num_account_state);
+    let iter = backup.get_transaction_iter(0, version as usize + 1)?;
+    for (v, res) in iter.enumerate() {
+        let (txn, _txn_info, _events) = res?;
+        print_json(&json!({
+            "version": v,
+            "transaction": txn,
+        }))?;
+    }
+    Ok(())
+}
 
-    print_txn(db, version);
+fn list_accounts(db: &DiemDB) -> Result<()> {
+    let version = db.get_latest_version()?;
+    let backup = db.get_backup_handler();
+    let mut num_account = 0;
+    for res in backup.get_account_iter(version)? {
+        let (key, blob) = res?;
+        let address = AccountState::try_from(&blob)?.get_account_address()?;
+        if address.is_some() {
+            num_account += 1;
+        }
+        print_json(&json!({
+            "key": key,
+            "address": address,
+        }))?;
+    }
+    info!("Total Accounts: {}", num_account);
+    Ok(())
+}
 
+fn list_events_by_key(db: &DiemDB, key: &EventKey, start_seq_num: u64, limit: u64) -> Result<()> {
+    for (version, event) in db.get_events(key, start_seq_num, Order::Ascending, limit)? {
+        print_json(&json!({
+            "version": version,
+            "event": event,
+        }))?;
+    }
     Ok(())
 }
 
-fn print_txn(db: &DiemDB, version: u64) {
-    let tx_list = db
-        .get_transactions(version, 1, version, false)
-        .expect("Unable to load latest TXN");
-    let tx = tx_list.transactions.first().expect("Got empty txn list.");
-    println!(
-        "Transaction {}: {}",
-        version,
-        tx.format_for_client(|bytes| name_for_script(bytes).unwrap())
+fn list_events_by_version(db: &DiemDB, start_version: Version, end_version: Version) -> Result<()> {
+    ensure!(
+        start_version <= end_version,
+        "Bad version range [{}, {})",
+        start_version,
+        end_version,
     );
+    let backup = db.get_backup_handler();
+    let iter =
+        backup.get_transaction_iter(start_version, (end_version - start_version) as usize)?;
+    for (version, res) in (start_version..).zip(iter) {
+        let (_txn, _txn_info, events) = res?;
+        for (index, event) in events.iter().enumerate() {
+            print_json(&json!({
+                "version": version,
+                "index": index,
+                "event": event,
+            }))?;
+        }
+    }
+    Ok(())
 }
 
-fn print_account(db: &DiemDB, addr: AccountAddress) {
-    let maybe_blob = db
-        .get_latest_account_state(addr)
-        .expect("Unable to read AccountState");
-    if let Some(blob) = maybe_blob {
-        match AccountResource::try_from(&blob) {
-            Ok(r) => {
-                println!("Account {}: {:?}", addr, r);
-            }
-            Err(e) => {
-                info!(
-                    "Account {} exists, but have no AccountResource: {}.",
-                    addr, e
-                );
+/// Walks down the state tree at `version` along `nibbles`, until they are all consumed or a node
+/// without the next child is reached. Returns that node with its key, and the number of nibbles
+/// consumed.
+fn walk_jmt(
+    db: &DiemDB,
+    version: Version,
+    nibbles: &[Nibble],
+) -> Result<(NodeKey, Node<AccountStateBlob>, usize)> {
+    let mut node_key = NodeKey::new_empty_path(version);
+    let mut depth = 0;
+    loop {
+        let node = db
+            .get_jellyfish_merkle_node(&node_key)?
+            .ok_or_else(|| format_err!("Missing state tree node {:?}, pruned?", node_key))?;
+        let next = match (&node, nibbles.get(depth)) {
+            (Node::Internal(internal_node), Some(nibble)) => internal_node
+                .child(*nibble)
+                .map(|child| node_key.gen_child_node_key(child.version, *nibble)),
+            _ => None,
+        };
+        match next {
+            Some(child_key) => {
+                node_key = child_key;
+                depth += 1;
             }
+            None => return Ok((node_key, node, depth)),
         }
-    } else {
-        info!("Account {} doesn't exists", addr);
     }
 }
 
-fn list_txns(db: &DiemDB) {
-    let version = db
-        .get_latest_version()
-        .expect("Unable to get latest version");
-    let backup = db.get_backup_handler();
-    let iter = backup
-        .get_transaction_iter(0, version as usize)
-        .expect("Unable to get txn iter");
-    for (v, tx) in iter.enumerate() {
-        println!(
-            "TXN {}: {}",
-            v,
-            tx.expect("Unable to read TX")
-                .0
-                .format_for_client(|bytes| name_for_script(bytes).unwrap())
+fn key_nibbles(key: HashValue) -> Vec<Nibble> {
+    key.iter()
+        .flat_map(|byte| vec![Nibble::from(byte >> 4), Nibble::from(byte & 0x0f)])
+        .collect()
+}
+
+/// Finds the leaf of an account in the state tree at `version`, returning the version the leaf
+/// was written at and the hash of the account state.
+fn find_account_leaf(
+    db: &DiemDB,
+    version: Version,
+    key: HashValue,
+) -> Result<Option<(Version, HashValue)>> {
+    let (node_key, node, _depth) = walk_jmt(db, version, &key_nibbles(key))?;
+    Ok(match node {
+        Node::Leaf(leaf) if leaf.account_key() == key => {
+            Some((node_key.version(), leaf.value().hash()))
+        }
+        _ => None,
+    })
+}
+
+fn print_account_history(
+    db: &DiemDB,
+    address: AccountAddress,
+    version: Option<Version>,
+    limit: usize,
+) -> Result<()> {
+    let key = address.hash();
+    let mut version = match version {
+        Some(version) => version,
+        None => db.get_latest_version()?,
+    };
+    let mut num_printed = 0;
+    // Hop back through the versions the leaf of the account was written at. A leaf is rewritten
+    // as well when an update to another account moves it, so only the versions changing the
+    // account state are printed.
+    while num_printed < limit {
+        let (leaf_version, state_hash) = match find_account_leaf(db, version, key)? {
+            Some(leaf) => leaf,
+            None => break,
+        };
+        let prev_state_hash = match leaf_version {
+            0 => None,
+            _ => find_account_leaf(db, leaf_version - 1, key)?.map(|(_, hash)| hash),
+        };
+        if prev_state_hash != Some(state_hash) {
+            print_json(&json!({
+                "version": leaf_version,
+                "state_hash": state_hash,
+                "created": prev_state_hash.is_none(),
+            }))?;
+            num_printed += 1;
+        }
+        if prev_state_hash.is_none() {
+            break;
+        }
+        version = leaf_version - 1;
+    }
+    Ok(())
+}
+
+fn print_jmt_node(db: &DiemDB, version: Version, nibble_path: &str) -> Result<()> {
+    let nibbles = nibble_path
+        .chars()
+        .map(|c| match c.to_digit(16) {
+            Some(n) => Ok(Nibble::from(n as u8)),
+            None => bail!("Invalid nibble path: {}", nibble_path),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let (node_key, node, depth) = walk_jmt(db, version, &nibbles)?;
+    ensure!(
+        depth == nibbles.len(),
+        "No node at the path, the tree ends at depth {}.",
+        depth,
+    );
+
+    let node_json = match &node {
+        Node::Null => json!({ "type": "null" }),
+        Node::Internal(internal_node) => json!({
+            "type": "internal",
+            "children": (0..16u8)
+                .filter_map(|n| {
+                    internal_node.child(Nibble::from(n)).map(|child| {
+                        json!({
+                            "nibble": format!("{:x}", n),
+                            "hash": child.hash,
+                            "version": child.version,
+                            "is_leaf": child.is_leaf,
+                        })
+                    })
+                })
+                .collect::<Vec<_>>(),
+        }),
+        Node::Leaf(leaf) => json!({
+            "type": "leaf",
+            "account_key": leaf.account_key(),
+            "value_hash": leaf.value().hash(),
+        }),
+    };
+    print_json(&json!({
+        "version": node_key.version(),
+        "hash": node.hash(),
+        "node": node_json,
+    }))
+}
+
+fn print_account_proof(
+    db: &DiemDB,
+    address: AccountAddress,
+    version: Option<Version>,
+) -> Result<()> {
+    let version = match version {
+        Some(version) => version,
+        None => db.get_latest_version()?,
+    };
+    let (blob, proof) = db.get_account_state_with_proof_by_version(address, version)?;
+    let state_root_hash = db
+        .get_jellyfish_merkle_node(&NodeKey::new_empty_path(version))?
+        .ok_or_else(|| format_err!("State tree root at version {} missing.", version))?
+        .hash();
+    proof.verify(state_root_hash, address.hash(), blob.as_ref())?;
+
+    print_json(&json!({
+        "address": address,
+        "version": version,
+        "state_root_hash": state_root_hash,
+        "blob": blob.map(hex::encode),
+        "proof": proof,
+    }))
+}
+
+fn for_each_ledger_info(
+    db: &DiemDB,
+    start_epoch: u64,
+    end_epoch: Option<u64>,
+    mut f: impl FnMut(LedgerInfoWithSignatures) -> Result<()>,
+) -> Result<()> {
+    let end_epoch = match end_epoch {
+        Some(epoch) => epoch,
+        None => db
+            .get_latest_ledger_info()?
+            .ledger_info()
+            .next_block_epoch(),
+    };
+    let mut epoch = start_epoch;
+    while epoch < end_epoch {
+        let proof = db.get_epoch_ending_ledger_infos(epoch, end_epoch)?;
+        ensure!(
+            !proof.ledger_info_with_sigs.is_empty(),
+            "No ledger info of epoch {}.",
+            epoch
         );
+        for li in proof.ledger_info_with_sigs {
+            epoch = li.ledger_info().epoch() + 1;
+            f(li)?;
+        }
     }
+    Ok(())
 }
 
-fn list_accounts(db: &DiemDB) {
-    let version = db
-        .get_latest_version()
-        .expect("Unable to get latest version");
-    let backup = db.get_backup_handler();
-    let iter = backup
-        .get_account_iter(version)
-        .expect("Unagle to get account iter");
-    let mut num_account = 0;
-    for res in iter {
-        match res {
-            Ok((_, blob)) => {
-                let accs = AccountState::try_from(&blob).expect("Failed to read AccountState");
-                let addr = accs
-                    .get_account_address()
-                    .expect("Could not get address from state");
-                match addr {
-                    Some(x) => {
-                        num_account += 1;
-                        println!("Address: {:?}", x);
-                    }
-                    None => println!("Skipping: No address for AccountState: {:?}", accs),
-                }
-            }
-            Err(x) => println!("Got err iterating through AccountStateBlobs {:?}", x),
+fn list_ledger_infos(db: &DiemDB, start_epoch: u64, end_epoch: Option<u64>) -> Result<()> {
+    for_each_ledger_info(db, start_epoch, end_epoch, |li| print_json(&li))
+}
+
+fn list_validator_sets(db: &DiemDB, start_epoch: u64, end_epoch: Option<u64>) -> Result<()> {
+    for_each_ledger_info(db, start_epoch, end_epoch, |li| {
+        let ledger_info = li.ledger_info();
+        // The full validator set (with network addresses) is read from the state, which can be
+        // pruned.
+        let validator_set = match db
+            .get_account_state_with_proof_by_version(diem_root_address(), ledger_info.version())
+        {
+            Ok((Some(blob), _proof)) => AccountState::try_from(&blob)?.get_validator_set()?,
+            Ok((None, _proof)) | Err(_) => None,
+        };
+        print_json(&json!({
+            "epoch": ledger_info.epoch() + 1,
+            "version": ledger_info.version(),
+            "epoch_state": ledger_info.next_epoch_state(),
+            "validator_set": validator_set,
+        }))
+    })
+}
+
+fn run_cmd(db: &DiemDB, cmd: Command) -> Result<()> {
+    match cmd {
+        Command::ListTXNs => list_txns(db),
+        Command::PrintTXN { version } => print_txn(db, version),
+        Command::PrintAccount { address, version } => print_account(db, address, version),
+        Command::ListAccounts => list_accounts(db),
+        Command::ListEventsByKey {
+            key,
+            start_seq_num,
+            limit,
+        } => list_events_by_key(db, &key, start_seq_num, limit),
+        Command::ListEventsByVersion {
+            start_version,
+            end_version,
+        } => list_events_by_version(db, start_version, end_version),
+        Command::PrintAccountHistory {
+            address,
+            version,
+            limit,
+        } => print_account_history(db, address, version, limit),
+        Command::PrintJMTNode {
+            version,
+            nibble_path,
+        } => print_jmt_node(db, version, &nibble_path),
+        Command::PrintAccountProof { address, version } => {
+            print_account_proof(db, address, version)
         }
+        Command::ListLedgerInfos {
+            start_epoch,
+            end_epoch,
+        } => list_ledger_infos(db, start_epoch, end_epoch),
+        Command::ListValidatorSets {
+            start_epoch,
+            end_epoch,
+        } => list_validator_sets(db, start_epoch, end_epoch),
     }
-    info!("Total Accounts: {}", num_account);
 }
 
 fn main() {
@@ -177,19 +519,9 @@ fn main() {
     info!("DB opened successfully.");
 
     if let Some(cmd) = opt.cmd {
-        match cmd {
-            Command::ListTXNs => {
-                list_txns(&db);
-            }
-            Command::PrintTXN { version } => {
-                print_txn(&db, version);
-            }
-            Command::PrintAccount { address } => {
-                print_account(&db, address);
-            }
-            Command::ListAccounts => {
-                list_accounts(&db);
-            }
+        if let Err(e) = run_cmd(&db, cmd) {
+            eprintln!("Error: {:?}", e);
+            std::process::exit(1);
         }
     } else {
         print_head(&db).expect("Unable to read information from DB");
@@ -0,0 +1,94 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    delta_iterator::JellyfishMerkleDeltaIterator, iterator::JellyfishMerkleIterator,
+    mock_tree_store::MockTreeStore, test_helper::ValueBlob, JellyfishMerkleTree,
+};
+use anyhow::Result;
+use diem_crypto::HashValue;
+use diem_types::transaction::Version;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{collections::BTreeMap, sync::Arc};
+
+#[test]
+fn test_delta_iterator() {
+    let db = Arc::new(MockTreeStore::default());
+    let tree = JellyfishMerkleTree::new(&*db);
+
+    let mut rng = StdRng::from_seed([1; 32]);
+    let mut keys = Vec::new();
+    let mut snapshots = Vec::new();
+    let mut btree = BTreeMap::new();
+    for version in 0..30 {
+        // Insert some new keys and update some existing ones at each version.
+        let mut value_set = BTreeMap::new();
+        for _ in 0..rng.gen_range(1..5) {
+            let key = if keys.is_empty() || rng.gen() {
+                let key = HashValue::random_with_rng(&mut rng);
+                keys.push(key);
+                key
+            } else {
+                keys[rng.gen_range(0..keys.len())]
+            };
+            let value = ValueBlob::from(rng.gen::<[u8; 8]>().to_vec());
+            value_set.insert(key, value);
+        }
+        btree.extend(value_set.clone());
+        let (_root_hash, batch) = tree
+            .put_value_set(value_set.into_iter().collect(), version)
+            .unwrap();
+        db.write_tree_update_batch(batch).unwrap();
+        snapshots.push(btree.clone());
+    }
+
+    for base_version in 0..30 {
+        for version in base_version + 1..30 {
+            run_test(
+                Arc::clone(&db),
+                &snapshots[base_version as usize],
+                &snapshots[version as usize],
+                base_version,
+                version,
+            );
+        }
+    }
+}
+
+fn run_test(
+    db: Arc<MockTreeStore<ValueBlob>>,
+    base: &BTreeMap<HashValue, ValueBlob>,
+    target: &BTreeMap<HashValue, ValueBlob>,
+    base_version: Version,
+    version: Version,
+) {
+    let delta = JellyfishMerkleDeltaIterator::new(Arc::clone(&db), base_version, version)
+        .unwrap()
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+
+    // Yielded in key order, and only things in the target version.
+    assert!(delta.windows(2).all(|w| w[0].0 < w[1].0));
+    assert!(delta.iter().all(|(k, v)| target.get(k) == Some(v)));
+    // Everything changed since the base version is included.
+    assert!(target
+        .iter()
+        .filter(|(k, v)| base.get(k) != Some(v))
+        .all(|(k, _)| delta.iter().any(|(key, _)| key == k)));
+    // Smaller than the whole tree, which the plain iterator gives.
+    let full = JellyfishMerkleIterator::new(Arc::clone(&db), version, HashValue::zero())
+        .unwrap()
+        .count();
+    assert!(delta.len() <= full);
+
+    let mut merged = base.clone();
+    merged.extend(delta);
+    assert_eq!(&merged, target);
+}
+
+#[test]
+fn test_delta_iterator_bad_versions() {
+    let db = Arc::new(MockTreeStore::<ValueBlob>::default());
+    assert!(JellyfishMerkleDeltaIterator::new(Arc::clone(&db), 1, 1).is_err());
+    assert!(JellyfishMerkleDeltaIterator::new(db, 2, 1).is_err());
+}
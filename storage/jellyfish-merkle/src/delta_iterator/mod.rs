@@ -0,0 +1,98 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements `JellyfishMerkleDeltaIterator`. Initialized with a base version and a
+//! version, the iterator generates the key-value pairs in this version of the tree that are new
+//! or updated since the base version, in key order.
+//!
+//! Every node in the tree records the version it was created at, and so does every child pointer
+//! in an internal node. A subtree whose root was created at or before the base version is
+//! identical to the one in the base version of the tree, so the depth first traversal only
+//! descends into the children created after the base version. The result may include some
+//! leaves that are not changed, but only moved to a new position by a later insertion nearby,
+//! which are harmless to apply on top of the base version.
+
+#[cfg(test)]
+mod delta_iterator_test;
+
+use crate::{
+    node_type::{Node, NodeKey},
+    TreeReader,
+};
+use anyhow::{ensure, Result};
+use diem_crypto::HashValue;
+use diem_nibble::Nibble;
+use diem_types::transaction::Version;
+use std::{marker::PhantomData, sync::Arc};
+
+/// The `JellyfishMerkleDeltaIterator` implementation.
+pub struct JellyfishMerkleDeltaIterator<R, V> {
+    /// The storage engine from which we can read nodes using node keys.
+    reader: Arc<R>,
+
+    /// Subtrees not updated after this version are skipped.
+    base_version: Version,
+
+    /// The nodes yet to visit, the next one to visit on the top.
+    stack: Vec<NodeKey>,
+
+    phantom_value: PhantomData<V>,
+}
+
+impl<R, V> JellyfishMerkleDeltaIterator<R, V>
+where
+    R: TreeReader<V>,
+    V: crate::Value,
+{
+    /// Constructs a new iterator over the changes from `base_version` to `version`.
+    pub fn new(reader: Arc<R>, base_version: Version, version: Version) -> Result<Self> {
+        ensure!(
+            base_version < version,
+            "Base version {} should be smaller than version {}.",
+            base_version,
+            version,
+        );
+
+        Ok(Self {
+            reader,
+            base_version,
+            stack: vec![NodeKey::new_empty_path(version)],
+            phantom_value: PhantomData,
+        })
+    }
+}
+
+impl<R, V> Iterator for JellyfishMerkleDeltaIterator<R, V>
+where
+    R: TreeReader<V>,
+    V: crate::Value,
+{
+    type Item = Result<(HashValue, V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node_key) = self.stack.pop() {
+            match self.reader.get_node(&node_key) {
+                Ok(Node::Internal(internal_node)) => {
+                    // Push the children from right to left, so the leftmost one is visited first.
+                    for i in (0..16u8).rev() {
+                        let nibble = Nibble::from(i);
+                        if let Some(child) = internal_node.child(nibble) {
+                            if child.version > self.base_version {
+                                self.stack
+                                    .push(node_key.gen_child_node_key(child.version, nibble));
+                            }
+                        }
+                    }
+                }
+                Ok(Node::Leaf(leaf_node)) => {
+                    return Some(Ok((leaf_node.account_key(), leaf_node.value().clone())));
+                }
+                // The tree is empty.
+                Ok(Node::Null) => (),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+
+        None
+    }
+}
@@ -670,6 +670,54 @@ where
         ret
     }
 
+    /// Queries a `key` in this `SparseMerkleTree`, together with a proof of its value or its
+    /// non-existence against `root_hash()`. Returns `None` if the nodes on the path are not all in
+    /// memory, or only the value hash of the leaf is.
+    pub fn get_with_proof(&self, key: HashValue) -> Option<(Option<V>, SparseMerkleProof<V>)> {
+        let mut cur = self.root_weak();
+        let mut bits = key.iter_bits();
+        let mut siblings = Vec::new();
+
+        loop {
+            if let SubTree::NonEmpty { root, .. } = &cur {
+                let node = root.get_node_if_in_mem()?;
+                if let Node::Internal(internal_node) = node.borrow() {
+                    let bit = bits.next().expect("Tree is deeper than the key.");
+                    let (next, sibling) =
+                        swap_if(internal_node.left.weak(), internal_node.right.weak(), bit);
+                    siblings.push(sibling.hash());
+                    cur = next;
+                    continue;
+                }
+            }
+            break;
+        }
+        // Siblings in a proof go from the bottom to the root.
+        siblings.reverse();
+
+        match cur {
+            SubTree::Empty => Some((None, SparseMerkleProof::new(None, siblings))),
+            SubTree::NonEmpty { root, .. } => match root.get_node_if_in_mem()?.borrow() {
+                Node::Internal(_) => {
+                    unreachable!("There is an internal node at the bottom of the tree.")
+                }
+                Node::Leaf(leaf_node) => {
+                    let leaf =
+                        SparseMerkleLeafNode::new(leaf_node.key, leaf_node.value.calc_hash());
+                    let value = if leaf_node.key == key {
+                        match &leaf_node.value {
+                            LeafValue::Value(value) => Some(value.clone()),
+                            LeafValue::ValueHash(_) => return None,
+                        }
+                    } else {
+                        None
+                    };
+                    Some((value, SparseMerkleProof::new(Some(leaf), siblings)))
+                }
+            },
+        }
+    }
+
     /// Constructs a new Sparse Merkle Tree by applying `updates`, which are considered to happen
     /// all at once. See `serial_update` and `batches_update` which take in multiple batches
     /// of updates and yields intermediate results.
@@ -396,6 +396,43 @@ fn test_drop() {
     // `Drop` implementation is not in place.
 }
 
+#[test]
+fn test_get_with_proof() {
+    let key1 = HashValue::from_slice(&[0; 32]).unwrap();
+    let key2 = update_byte(&key1, 0, 0b01000000);
+    let key3 = update_byte(&key1, 0, 0b00100000);
+    let key4 = update_byte(&key1, 0, 0b01100000);
+    let key5 = update_byte(&key1, 0, 0b10000000);
+    let value1: AccountStateBlob = vec![1].into();
+    let value2: AccountStateBlob = vec![2].into();
+    let value3: AccountStateBlob = vec![3].into();
+
+    let smt = SparseMerkleTree::default()
+        .batch_update(
+            vec![(key1, &value1), (key2, &value2), (key3, &value3)],
+            &ProofReader::default(),
+        )
+        .unwrap();
+    let root_hash = smt.root_hash();
+
+    for (key, value) in &[(key1, &value1), (key2, &value2), (key3, &value3)] {
+        let (found, proof) = smt.get_with_proof(*key).unwrap();
+        assert_eq!(found.as_ref(), Some(*value));
+        proof.verify(root_hash, *key, Some(*value)).unwrap();
+    }
+    // key4 ends at the leaf of key2, key5 at the empty right child of the root.
+    for key in &[key4, key5] {
+        let (found, proof) = smt.get_with_proof(*key).unwrap();
+        assert!(found.is_none());
+        proof.verify(root_hash, *key, None).unwrap();
+    }
+
+    // Nothing in memory.
+    assert!(SparseMerkleTree::new(root_hash)
+        .get_with_proof(key1)
+        .is_none());
+}
+
 proptest! {
     #[test]
     fn test_correctness( input in arb_smt_correctness_case() ) {
@@ -155,6 +155,14 @@ impl From<diem_secure_net::Error> for Error {
     }
 }
 
+/// Ledger history below `first_version` was deleted by the ledger pruner.
+#[derive(Debug, Error, PartialEq)]
+#[error("{what} is pruned, the ledger history starts at version {first_version}.")]
+pub struct PrunedError {
+    pub what: String,
+    pub first_version: Version,
+}
+
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub enum Order {
     Ascending,
@@ -322,6 +330,12 @@ pub trait DbReader: Send + Sync {
         unimplemented!()
     }
 
+    /// Gets the first version whose transaction, info and events are still readable. Versions
+    /// before it were removed by the ledger pruner, their accumulator nodes are kept.
+    fn get_first_txn_version(&self) -> Result<Version> {
+        Ok(0)
+    }
+
     /// Gets the transaction accumulator root hash at specified version.
     /// Caller must guarantee the version is not greater than the latest version.
     fn get_accumulator_root_hash(&self, _version: Version) -> Result<HashValue> {
@@ -147,6 +147,15 @@ impl DiemClient {
             .map(Response::into_inner)
     }
 
+    ///////// 0L ////////
+    /// Gets the number of peers the node is connected to
+    pub fn get_network_status(&self) -> Result<u64> {
+        self.client
+            .get_network_status()
+            .map_err(Into::into)
+            .map(Response::into_inner)
+    }
+
     /// Gets the currency info stored on-chain
     pub fn get_currency_info(&self) -> Result<Vec<views::CurrencyInfoView>> {
         self.client
@@ -225,6 +234,11 @@ impl DiemClient {
         Ok(self.client.url())
     }
 
+    /// latest version verified with a state proof
+    pub fn verified_version(&self) -> Version {
+        self.trusted_state.version()
+    }
+
     /// get any account state with client
     pub fn get_account_state(&self, address: AccountAddress) -> Result<AccountState, Error> {
         let (blob, _ver) = self.get_account_state_blob(&address)?;